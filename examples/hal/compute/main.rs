@@ -55,14 +55,16 @@ fn main() {
                     ty: pso::DescriptorType::StorageBuffer,
                     count: 1,
                     stage_flags: pso::ShaderStageFlags::COMPUTE,
+                    immutable_samplers: false,
                 }
             ],
+            &[],
         );
 
         let pipeline_layout = device.create_pipeline_layout(Some(&set_layout), &[]);
         let entry_point = pso::EntryPoint { entry: "main", module: &shader, specialization: &[] };
         let pipeline = device
-            .create_compute_pipeline(&pso::ComputePipelineDesc::new(entry_point, &pipeline_layout))
+            .create_compute_pipeline(&pso::ComputePipelineDesc::new(entry_point, &pipeline_layout), None)
             .expect("Error creating compute pipeline!");
 
         let desc_pool = device.create_descriptor_pool(
@@ -73,7 +75,8 @@ fn main() {
                     count: 1,
                 },
             ],
-        );
+            pso::DescriptorPoolCreateFlags::empty(),
+        ).expect("Can't create descriptor pool");
         (pipeline_layout, pipeline, set_layout, desc_pool)
     };
 
@@ -101,7 +104,7 @@ fn main() {
         numbers.len() as u64,
     );
 
-    let desc_set = desc_pool.allocate_set(&set_layout);
+    let desc_set = desc_pool.allocate_set(&set_layout).unwrap();
     device.write_descriptor_sets(Some(
         pso::DescriptorSetWrite {
             set: &desc_set,
@@ -113,8 +116,9 @@ fn main() {
         }
     ));
 
-    let mut command_pool = device.create_command_pool_typed(&queue_group, pool::CommandPoolCreateFlags::empty(), 16);
-    let fence = device.create_fence(false);
+    let mut command_pool = device.create_command_pool_typed(&queue_group, pool::CommandPoolCreateFlags::empty(), 16)
+        .expect("Can't create command pool");
+    let fence = device.create_fence(false).unwrap();
     let submission = queue::Submission::new().submit(Some({
         let mut command_buffer = command_pool.acquire_command_buffer(false);
         command_buffer.copy_buffer(&staging_buffer, &device_buffer, &[command::BufferCopy { src: 0, dst: 0, size: stride * numbers.len() as u64}]);
@@ -140,8 +144,8 @@ fn main() {
         command_buffer.copy_buffer(&device_buffer, &staging_buffer, &[command::BufferCopy { src: 0, dst: 0, size: stride * numbers.len() as u64}]);
         command_buffer.finish()
     }));
-    queue_group.queues[0].submit(submission, Some(&fence));
-    device.wait_for_fence(&fence, !0);
+    queue_group.queues[0].submit(submission, Some(&fence)).unwrap();
+    device.wait_for_fence(&fence, !0).unwrap();
 
     {
         let reader = device.acquire_mapping_reader::<u32>(&staging_memory, 0..stride * numbers.len() as u64).unwrap();
@@ -134,7 +134,8 @@ fn main() {
             surface.supports_queue_family(family)
         }).unwrap();
 
-    let mut command_pool = device.create_command_pool_typed(&queue_group, pool::CommandPoolCreateFlags::empty(), 16);
+    let mut command_pool = device.create_command_pool_typed(&queue_group, pool::CommandPoolCreateFlags::empty(), 16)
+        .expect("Can't create command pool");
     let mut queue = &mut queue_group.queues[0];
 
     println!("Surface format: {:?}", surface_format);
@@ -149,14 +150,17 @@ fn main() {
                 ty: pso::DescriptorType::SampledImage,
                 count: 1,
                 stage_flags: ShaderStageFlags::FRAGMENT,
+                immutable_samplers: false,
             },
             pso::DescriptorSetLayoutBinding {
                 binding: 1,
                 ty: pso::DescriptorType::Sampler,
                 count: 1,
                 stage_flags: ShaderStageFlags::FRAGMENT,
+                immutable_samplers: false,
             },
         ],
+        &[],
     );
 
     let pipeline_layout = device.create_pipeline_layout(
@@ -291,7 +295,7 @@ fn main() {
             });
 
 
-            device.create_graphics_pipeline(&pipeline_desc)
+            device.create_graphics_pipeline(&pipeline_desc, None)
         };
 
         device.destroy_shader_module(vs_module);
@@ -319,8 +323,9 @@ fn main() {
                 count: 1,
             },
         ],
-    );
-    let desc_set = desc_pool.allocate_set(&set_layout);
+        pso::DescriptorPoolCreateFlags::empty(),
+    ).expect("Can't create descriptor pool");
+    let desc_set = desc_pool.allocate_set(&set_layout).unwrap();
 
     // Framebuffer and render target creation
     let (frame_images, framebuffers) = match backbuffer {
@@ -406,7 +411,7 @@ fn main() {
         device.release_mapping_writer(data);
     }
 
-    let image_unbound = device.create_image(kind, 1, ColorFormat::SELF, i::Usage::TRANSFER_DST | i::Usage::SAMPLED).unwrap(); // TODO: usage
+    let image_unbound = device.create_image(kind, 1, ColorFormat::SELF, i::Tiling::Optimal, i::Usage::TRANSFER_DST | i::Usage::SAMPLED, i::StorageFlags::empty()).unwrap(); // TODO: usage
     let image_req = device.get_image_requirements(&image_unbound);
 
     let device_type = memory_types
@@ -428,7 +433,7 @@ fn main() {
             i::FilterMethod::Bilinear,
             i::WrapMode::Clamp,
         )
-    );
+    ).expect("Can't create sampler");
 
     device.write_descriptor_sets(vec![
         pso::DescriptorSetWrite {
@@ -458,8 +463,8 @@ fn main() {
         depth: 0.0 .. 1.0,
     };
 
-    let mut frame_semaphore = device.create_semaphore();
-    let mut frame_fence = device.create_fence(false); // TODO: remove
+    let mut frame_semaphore = device.create_semaphore().expect("Can't create semaphore");
+    let mut frame_fence = device.create_fence(false).expect("Can't create fence"); // TODO: remove
 
     // copy buffer to texture
     {
@@ -512,9 +517,9 @@ fn main() {
 
         let submission = Submission::new()
             .submit(Some(submit));
-        queue.submit(submission, Some(&mut frame_fence));
+        queue.submit(submission, Some(&mut frame_fence)).unwrap();
 
-        device.wait_for_fence(&frame_fence, !0);
+        device.wait_for_fence(&frame_fence, !0).unwrap();
     }
 
     //
@@ -536,7 +541,9 @@ fn main() {
 
         device.reset_fence(&frame_fence);
         command_pool.reset();
-        let frame = swap_chain.acquire_frame(FrameSync::Semaphore(&mut frame_semaphore));
+        let frame = swap_chain
+            .acquire_frame(FrameSync::Semaphore(&mut frame_semaphore), !0)
+            .expect("Failed to acquire frame");
 
         // Rendering
         let submit = {
@@ -564,13 +571,14 @@ fn main() {
         let submission = Submission::new()
             .wait_on(&[(&frame_semaphore, PipelineStage::BOTTOM_OF_PIPE)])
             .submit(Some(submit));
-        queue.submit(submission, Some(&mut frame_fence));
+        queue.submit(submission, Some(&mut frame_fence)).unwrap();
 
         // TODO: replace with semaphore
-        device.wait_for_fence(&frame_fence, !0);
+        device.wait_for_fence(&frame_fence, !0).unwrap();
 
         // present frame
-        swap_chain.present(&mut queue, &[]);
+        swap_chain.present(&mut queue, &[])
+            .expect("Failed to present frame");
 
         #[cfg(feature = "metal")]
         unsafe {
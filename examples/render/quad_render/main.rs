@@ -4,6 +4,8 @@ extern crate gfx_hal as hal;
 extern crate gfx_backend_vulkan as back;
 #[cfg(feature = "metal")]
 extern crate gfx_backend_metal as back;
+#[cfg(feature = "gl")]
+extern crate gfx_backend_gl as back;
 #[macro_use]
 extern crate gfx_render as gfx;
 
@@ -14,6 +16,8 @@ use std::io::Cursor;
 
 use hal::{command, device as d, format as f, image as i, pso};
 use hal::{Device, Instance, PhysicalDevice, Primitive};
+#[cfg(feature = "gl")]
+use hal::format::AsFormat;
 use gfx::format::{Rgba8Srgb as ColorFormat};
 use gfx::allocators::StackAllocator as Allocator;
 
@@ -56,19 +60,40 @@ fn main() {
     let mut autorelease_pool = unsafe { back::AutoreleasePool::new() };
 
     let mut events_loop = winit::EventsLoop::new();
-    let window = winit::WindowBuilder::new()
+    let wb = winit::WindowBuilder::new()
         .with_dimensions(1024, 768)
-        .with_title("quad".to_string())
+        .with_title("quad".to_string());
+    #[cfg(any(feature = "vulkan", feature = "metal"))]
+    let window = wb
         .build(&events_loop)
         .unwrap();
+    #[cfg(feature = "gl")]
+    let window = {
+        let builder = back::config_context(
+            back::glutin::ContextBuilder::new(),
+            ColorFormat::SELF,
+            None,
+        ).with_vsync(true);
+        back::glutin::GlWindow::new(wb, builder, &events_loop).unwrap()
+    };
     let window_size = window.get_inner_size().unwrap();
     let pixel_width = window_size.0 as u16;
     let pixel_height = window_size.1 as u16;
 
     // instantiate backend
-    let instance = back::Instance::create("gfx-rs quad", 1);
-    let surface = instance.create_surface(&window);
-    let mut adapters = instance.enumerate_adapters();
+    #[cfg(any(feature = "vulkan", feature = "metal"))]
+    let (_instance, mut adapters, surface) = {
+        let instance = back::Instance::create("gfx-rs quad", 1);
+        let surface = instance.create_surface(&window);
+        let adapters = instance.enumerate_adapters();
+        (instance, adapters, surface)
+    };
+    #[cfg(feature = "gl")]
+    let (mut adapters, surface) = {
+        let surface = back::Surface::from_window(window);
+        let adapters = surface.enumerate_adapters();
+        (adapters, surface)
+    };
     for adapter in &adapters {
         println!("{:?}", adapter.info);
     }
@@ -84,7 +109,7 @@ fn main() {
     let vs_module = device.raw.create_shader_module(include_bytes!("../../hal/quad/data/vert.spv")).unwrap();
     let fs_module = device.raw.create_shader_module(include_bytes!("../../hal/quad/data/frag.spv")).unwrap();
 
-    let (desc, mut desc_data) = device.create_descriptors(1).pop().unwrap();
+    let (desc, mut desc_data) = device.create_descriptors(1).unwrap().pop().unwrap();
     let pipe_init = pipe::Init {
         desc: &desc,
         color: pso::ColorBlendDesc(pso::ColorMask::ALL, pso::BlendState::ALPHA),
@@ -110,74 +135,31 @@ fn main() {
     };
 
     // Framebuffer creation
-    let frame_rtvs = backbuffers.iter().map(|backbuffer| {
-        device.create_image_view(&backbuffer.color, image_range.clone())
-            .unwrap()
-    }).collect::<Vec<_>>();
-    let framebuffers = frame_rtvs.iter().map(|rtv| {
-        let extent = d::Extent { width: pixel_width as _, height: pixel_height as _, depth: 1 };
-        device.create_framebuffer(&pipeline, &[rtv.as_ref()], extent)
+    let frame_targets = backbuffers.iter().map(|backbuffer| {
+        backbuffer.create_target(&mut device, &pipeline)
             .unwrap()
     }).collect::<Vec<_>>();
 
-    let mut upload = Allocator::new(
-        gfx::memory::Usage::Upload,
-        &context.ref_device(),
-        limits,
-    );
     let mut data = Allocator::new(
         gfx::memory::Usage::Data,
         &context.ref_device(),
         limits,
     );
+    let mut uploader = gfx::Uploader::new(&context.ref_device(), limits);
     println!("Memory types: {:?}", context.ref_device().memory_types());
     println!("Memory heaps: {:?}", context.ref_device().memory_heaps());
 
     let vertex_count = QUAD.len() as u64;
     let (vertex_buffer, vertex_token) = device.create_buffer::<Vertex, _>(
-        &mut upload,
-        gfx::buffer::Usage::VERTEX,
+        &mut data,
+        gfx::buffer::Usage::VERTEX | gfx::buffer::Usage::TRANSFER_DST,
         vertex_count
     ).unwrap();
 
-    println!("vertex data uploading not implemented!");
-    // TODO
-    /*
-    device.write_mapping(&vertex_buffer, 0..vertex_count)
-        .unwrap()
-        .copy_from_slice(&QUAD);
-    */
-
     let img_data = include_bytes!("../../hal/quad/data/logo.png");
     let img = image::load(Cursor::new(&img_data[..]), image::PNG).unwrap().to_rgba();
     let (width, height) = img.dimensions();
     let kind = i::Kind::D2(width as i::Size, height as i::Size, i::AaMode::Single);
-    let row_alignment_mask = limits.min_buffer_copy_pitch_alignment as u32 - 1;
-    let image_stride = 4usize;
-    let row_pitch = (width * image_stride as u32 + row_alignment_mask) & !row_alignment_mask;
-    let upload_size = (height * row_pitch) as u64;
-    println!("upload row pitch {}, total size {}", row_pitch, upload_size);
-
-    let (image_upload_buffer, image_upload_token) = device.create_buffer_raw(
-        &mut upload,
-        gfx::buffer::Usage::TRANSFER_SRC,
-        upload_size,
-        image_stride as u64,
-    ).unwrap();
-
-    println!("copy image data into staging buffer");
-
-    // TODO:
-    println!("image uploading not implemented!");
-    /*
-    if let Ok(mut image_data) = device.write_mapping(&image_upload_buffer, 0..upload_size) {
-        for y in 0 .. height as usize {
-            let row = &(*img)[y*(width as usize)*image_stride .. (y+1)*(width as usize)*image_stride];
-            let dest_base = y * row_pitch as usize;
-            image_data[dest_base .. dest_base + row.len()].copy_from_slice(row);
-        }
-    }
-    */
 
     let (image, image_token) = device.create_image::<ColorFormat, _>(
         &mut data,
@@ -186,6 +168,23 @@ fn main() {
         1,
     ).unwrap();
 
+    uploader.init_resources(vec![vertex_token, image_token]);
+    uploader.upload_buffer(&vertex_buffer, 0, &QUAD).unwrap();
+    let image_upload = uploader.upload_image(
+        &image,
+        gfx::image::SubresourceLayers {
+            aspects: f::Aspects::COLOR,
+            level: 0,
+            layers: 0 .. 1,
+        },
+        i::Offset { x: 0, y: 0, z: 0 },
+        d::Extent { width, height, depth: 1 },
+        &*img,
+        0,
+        0,
+    ).unwrap();
+    uploader.flush(&mut context).unwrap();
+
     let image_srv = device.create_image_view(&image, image_range)
         .unwrap();
 
@@ -194,7 +193,7 @@ fn main() {
             i::FilterMethod::Bilinear,
             i::WrapMode::Clamp,
         )
-    );
+    ).expect("Can't create sampler");
 
     device.update_descriptor_sets()
         .write(desc_data.sampled_image(&desc), 0, &[image_srv.as_ref()])
@@ -211,31 +210,7 @@ fn main() {
         depth: 0.0 .. 1.0,
     };
 
-    let mut encoder_pool = context.acquire_encoder_pool();
-    let mut init_encoder = encoder_pool.acquire_encoder();
-    init_encoder.init_resources(vec![
-        vertex_token,
-        image_upload_token,
-        image_token,
-    ]);
-    init_encoder.copy_buffer_to_image(
-        &image_upload_buffer,
-        &image,
-        &[command::BufferImageCopy {
-            buffer_offset: 0,
-            buffer_width: row_pitch / image_stride as u32,
-            buffer_height: height as u32,
-            image_layers: gfx::image::SubresourceLayers {
-                aspects: f::Aspects::COLOR,
-                level: 0,
-                layers: 0 .. 1,
-            },
-            image_offset: i::Offset { x: 0, y: 0, z: 0 },
-            image_extent: d::Extent { width, height, depth: 1 },
-        }]);
-
-    let init_submit = init_encoder.finish();
-    let mut submits = vec![init_submit];
+    uploader.wait(image_upload).unwrap();
 
     let mut running = true;
     while running {
@@ -253,24 +228,28 @@ fn main() {
             }
         });
 
-        let frame = context.acquire_frame();
-        let mut encoder_pool = context.acquire_encoder_pool();
+        let frame = context.acquire_frame()
+            .expect("Failed to acquire frame");
+        let mut encoder_pool = context.acquire_encoder_pool()
+            .expect("Can't create encoder pool");
         let mut encoder = encoder_pool.acquire_encoder();
 
         {
             let data = pipe::Data {
                 desc: (&desc, &desc_data),
-                color: &frame_rtvs[frame.id()],
+                color: frame_targets[frame.id()].color.as_ref(),
                 vertices: &vertex_buffer,
                 viewports: &[viewport.clone()],
                 scissors: &[scissor],
-                framebuffer: &framebuffers[frame.id()],
+                framebuffer: &frame_targets[frame.id()].framebuffer,
+                index_buffer: gfx::slice::IndexBuffer::Auto,
+                instances: 0 .. 1,
             };
             encoder.draw(0..6, &pipeline, data);
         }
 
-        submits.push(encoder.finish());
-        context.present(submits.drain(..).collect::<Vec<_>>());
+        context.present(vec![encoder.finish()])
+            .expect("Failed to present frame");
 
         #[cfg(feature = "metal")]
         unsafe {
@@ -605,9 +605,19 @@ impl CommandBuffer {
 }
 
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
-    fn begin(&mut self, _flags: com::CommandBufferFlags) {
+    fn begin(
+        &mut self,
+        _flags: com::CommandBufferFlags,
+        inheritance_info: com::CommandBufferInheritanceInfo<Backend>,
+    ) {
         // TODO: Implement flags somehow.
         self.reset();
+        // Secondary command buffers are bundles, which inherit the render targets
+        // and active queries of the executing command list. Only the subpass index
+        // needs to be tracked.
+        if let Some(subpass) = inheritance_info.subpass {
+            self.cur_subpass = subpass.index;
+        }
     }
 
     fn finish(&mut self) {
@@ -1614,8 +1624,35 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         I: IntoIterator,
         I::Item: Borrow<CommandBuffer>,
     {
-        for _cmd_buf in buffers {
-            error!("TODO: execute_commands");
+        for cmd_buf in buffers {
+            unsafe { self.raw.ExecuteBundle(cmd_buf.borrow().raw.as_raw()); }
+        }
+    }
+
+    // Events use the unicode PIX format, which doesn't carry a color.
+    fn begin_debug_marker(&mut self, name: &str, _color: u32) {
+        let name = conv::map_debug_name(name);
+        unsafe {
+            self.raw.BeginEvent(
+                0, // WINPIX_EVENT_UNICODE_VERSION
+                name.as_ptr() as *const _,
+                (name.len() * mem::size_of::<u16>()) as UINT,
+            );
+        }
+    }
+
+    fn end_debug_marker(&mut self) {
+        unsafe { self.raw.EndEvent(); }
+    }
+
+    fn insert_debug_marker(&mut self, name: &str, _color: u32) {
+        let name = conv::map_debug_name(name);
+        unsafe {
+            self.raw.SetMarker(
+                0, // WINPIX_EVENT_UNICODE_VERSION
+                name.as_ptr() as *const _,
+                (name.len() * mem::size_of::<u16>()) as UINT,
+            );
         }
     }
 }
@@ -316,6 +316,38 @@ pub fn map_wrap(wrap: image::WrapMode) -> D3D12_TEXTURE_ADDRESS_MODE {
     }
 }
 
+/// Static samplers only support a fixed set of border colors,
+/// pick the closest one.
+fn map_static_border_color(color: [f32; 4]) -> D3D12_STATIC_BORDER_COLOR {
+    if color[3] == 0.0 {
+        D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK
+    } else if color[0] + color[1] + color[2] < 1.5 {
+        D3D12_STATIC_BORDER_COLOR_OPAQUE_BLACK
+    } else {
+        D3D12_STATIC_BORDER_COLOR_OPAQUE_WHITE
+    }
+}
+
+pub fn map_static_sampler(
+    desc: &D3D12_SAMPLER_DESC, register: u32, register_space: u32
+) -> D3D12_STATIC_SAMPLER_DESC {
+    D3D12_STATIC_SAMPLER_DESC {
+        Filter: desc.Filter,
+        AddressU: desc.AddressU,
+        AddressV: desc.AddressV,
+        AddressW: desc.AddressW,
+        MipLODBias: desc.MipLODBias,
+        MaxAnisotropy: desc.MaxAnisotropy,
+        ComparisonFunc: desc.ComparisonFunc,
+        BorderColor: map_static_border_color(desc.BorderColor),
+        MinLOD: desc.MinLOD,
+        MaxLOD: desc.MaxLOD,
+        ShaderRegister: register,
+        RegisterSpace: register_space,
+        ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL, //TODO
+    }
+}
+
 pub enum FilterOp {
     Product,
     Comparison,
@@ -501,3 +533,8 @@ pub fn map_stage(stage: pso::Stage) -> spirv::ExecutionModel {
         pso::Stage::Domain => spirv::ExecutionModel::TessellationEvaluation,
     }
 }
+
+/// Encode a debug name as null-terminated UTF-16, as expected by `SetName` and PIX events.
+pub fn map_debug_name(name: &str) -> Vec<u16> {
+    name.encode_utf16().chain(Some(0)).collect()
+}
@@ -1,5 +1,7 @@
 use std::borrow::Borrow;
 use std::collections::{BTreeMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
 use std::ops::Range;
 use std::{ffi, mem, ptr, slice};
 
@@ -15,12 +17,12 @@ use hal::{self, buffer, device as d, error, format, image, mapping, memory, pass
 use hal::format::Aspects;
 use hal::memory::Requirements;
 use hal::pool::CommandPoolCreateFlags;
-use hal::queue::{RawCommandQueue, QueueFamilyId};
+use hal::queue::{RawCommandQueue, QueueFamilyId, SubmissionId};
 use hal::range::RangeArg;
 
 use {
     conv, free_list, native as n, root_constants, shade, window as w,
-    Backend as B, Device, MemoryGroup, QUEUE_FAMILIES, MAX_VERTEX_BUFFERS, NUM_HEAP_PROPERTIES,
+    Backend as B, CommandQueue, Device, MemoryGroup, QUEUE_FAMILIES, MAX_VERTEX_BUFFERS, NUM_HEAP_PROPERTIES,
 };
 use pool::RawCommandPool;
 use root_constants::RootConstant;
@@ -36,6 +38,117 @@ const MEM_TYPE_BUFFER_SHIFT: u64 = MEM_TYPE_SHIFT * MemoryGroup::BufferOnly as u
 const MEM_TYPE_IMAGE_SHIFT: u64 = MEM_TYPE_SHIFT * MemoryGroup::ImageOnly as u64;
 const MEM_TYPE_TARGET_SHIFT: u64 = MEM_TYPE_SHIFT * MemoryGroup::TargetOnly as u64;
 
+/// Feed the raw bytes of a plain D3D12 structure into `hasher`.
+fn hash_pod<T>(hasher: &mut DefaultHasher, value: &T) {
+    let bytes = unsafe {
+        slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>())
+    };
+    hasher.write(bytes);
+}
+
+fn hash_bytecode(hasher: &mut DefaultHasher, bytecode: &d3d12::D3D12_SHADER_BYTECODE) {
+    let bytes = if bytecode.pShaderBytecode.is_null() {
+        &[][..]
+    } else {
+        unsafe {
+            slice::from_raw_parts(bytecode.pShaderBytecode as *const u8, bytecode.BytecodeLength)
+        }
+    };
+    hasher.write_usize(bytes.len());
+    hasher.write(bytes);
+}
+
+/// Name of a graphics pipeline inside a pipeline library.
+///
+/// The root signature isn't part of the name, a mismatching signature makes
+/// loading fail and the pipeline will be recreated instead.
+fn graphics_pipeline_key(desc: &d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for bytecode in &[desc.VS, desc.PS, desc.GS, desc.DS, desc.HS] {
+        hash_bytecode(&mut hasher, bytecode);
+    }
+    hash_pod(&mut hasher, &desc.BlendState);
+    hash_pod(&mut hasher, &desc.SampleMask);
+    hash_pod(&mut hasher, &desc.RasterizerState);
+    hash_pod(&mut hasher, &desc.DepthStencilState);
+    let elements = unsafe {
+        slice::from_raw_parts(desc.InputLayout.pInputElementDescs, desc.InputLayout.NumElements as _)
+    };
+    for element in elements {
+        let name = unsafe { ffi::CStr::from_ptr(element.SemanticName) };
+        hasher.write(name.to_bytes_with_nul());
+        hash_pod(&mut hasher, &element.SemanticIndex);
+        hash_pod(&mut hasher, &element.Format);
+        hash_pod(&mut hasher, &element.InputSlot);
+        hash_pod(&mut hasher, &element.AlignedByteOffset);
+        hash_pod(&mut hasher, &element.InputSlotClass);
+        hash_pod(&mut hasher, &element.InstanceDataStepRate);
+    }
+    hash_pod(&mut hasher, &desc.IBStripCutValue);
+    hash_pod(&mut hasher, &desc.PrimitiveTopologyType);
+    hash_pod(&mut hasher, &desc.NumRenderTargets);
+    hash_pod(&mut hasher, &desc.RTVFormats);
+    hash_pod(&mut hasher, &desc.DSVFormat);
+    hash_pod(&mut hasher, &desc.SampleDesc);
+    hasher.finish()
+}
+
+/// Name of a compute pipeline inside a pipeline library.
+fn compute_pipeline_key(desc: &d3d12::D3D12_COMPUTE_PIPELINE_STATE_DESC) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_bytecode(&mut hasher, &desc.CS);
+    hasher.finish()
+}
+
+/// Load a pipeline state from the pipeline library of `cache`, if available.
+/// Otherwise, the pipeline state is created and stored in the library.
+fn load_or_create_pipeline_state<L, C>(
+    cache: Option<&n::PipelineCache>,
+    key: u64,
+    load: L,
+    create: C,
+) -> Result<*mut d3d12::ID3D12PipelineState, pso::CreationError>
+where
+    L: FnOnce(&d3d12::ID3D12PipelineLibrary, *const u16, &mut *mut d3d12::ID3D12PipelineState) -> winerror::HRESULT,
+    C: FnOnce(&mut *mut d3d12::ID3D12PipelineState) -> winerror::HRESULT,
+{
+    let mut pipeline = ptr::null_mut();
+    let library = match cache.and_then(|cache| cache.library.as_ref()) {
+        Some(library) => library,
+        None => {
+            let hr = create(&mut pipeline);
+            return if winerror::SUCCEEDED(hr) {
+                Ok(pipeline)
+            } else {
+                Err(pso::CreationError::Other)
+            };
+        }
+    };
+
+    let name = format!("{:016x}", key)
+        .encode_utf16()
+        .chain(Some(0))
+        .collect::<Vec<u16>>();
+
+    if winerror::SUCCEEDED(load(library, name.as_ptr(), &mut pipeline)) {
+        return Ok(pipeline);
+    }
+
+    let hr = create(&mut pipeline);
+    if !winerror::SUCCEEDED(hr) {
+        return Err(pso::CreationError::Other);
+    }
+
+    // Storing only fails if the name is already taken (e.g. by a pipeline
+    // with a different root signature), which is fine to ignore.
+    let hr = unsafe { library.StorePipeline(name.as_ptr(), pipeline) };
+    if !winerror::SUCCEEDED(hr) {
+        warn!("Failed to store pipeline in the pipeline library: {:x}", hr);
+    }
+
+    Ok(pipeline)
+}
+
 /// Emit error during shader module creation. Used if we don't expect an error
 /// but might panic due to an exception in SPIRV-Cross.
 fn gen_unexpected_error(err: SpirvErrorCode) -> d::ShaderError {
@@ -444,6 +557,111 @@ impl Device {
         }
     }
 
+    fn buffer_desc(buffer: &UnboundBuffer) -> d3d12::D3D12_RESOURCE_DESC {
+        d3d12::D3D12_RESOURCE_DESC {
+            Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+            Alignment: 0,
+            Width: buffer.requirements.size,
+            Height: 1,
+            DepthOrArraySize: 1,
+            MipLevels: 1,
+            Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+            Flags: conv::map_buffer_flags(buffer.usage),
+        }
+    }
+
+    fn wrap_buffer(&self, resource: *mut d3d12::ID3D12Resource, buffer: &UnboundBuffer) -> n::Buffer {
+        let clear_uav = if buffer.usage.contains(buffer::Usage::TRANSFER_DST) {
+            let handles = self.uav_pool.lock().unwrap().alloc_handles(1);
+            let mut desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
+                Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
+                ViewDimension: d3d12::D3D12_UAV_DIMENSION_BUFFER,
+                u: unsafe { mem::zeroed() },
+            };
+
+           *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_UAV {
+                FirstElement: 0,
+                NumElements: (buffer.requirements.size / 4) as _,
+                StructureByteStride: 0,
+                CounterOffsetInBytes: 0,
+                Flags: d3d12::D3D12_BUFFER_UAV_FLAG_RAW,
+            };
+
+            unsafe {
+                self.raw.clone().CreateUnorderedAccessView(
+                    resource as *mut _,
+                    ptr::null_mut(),
+                    &desc,
+                    handles.cpu,
+                );
+            }
+            Some(handles)
+        } else {
+            None
+        };
+
+        n::Buffer {
+            resource,
+            size_in_bytes: buffer.requirements.size as _,
+            clear_uav,
+        }
+    }
+
+    fn wrap_image(&self, resource: *mut d3d12::ID3D12Resource, image: &UnboundImage) -> n::Image {
+        use self::image::Usage;
+
+        //TODO: the clear_Xv is incomplete. We should support clearing images created without XXX_ATTACHMENT usage.
+        // for this, we need to check the format and force the `RENDER_TARGET` flag behind the user's back
+        // if the format supports being rendered into, allowing us to create clear_Xv
+
+        n::Image {
+            resource,
+            kind: image.kind,
+            usage: image.usage,
+            dxgi_format: image.desc.Format,
+            bytes_per_block: image.bytes_per_block,
+            block_dim: image.block_dim,
+            num_levels: image.num_levels,
+            num_layers: image.num_layers,
+            aspects: image.aspects,
+            clear_cv: if image.aspects.contains(Aspects::COLOR) && image.usage.contains(Usage::COLOR_ATTACHMENT) {
+                let range = image::SubresourceRange {
+                    aspects: Aspects::COLOR,
+                    levels: 0 .. 1, //TODO?
+                    layers: 0 .. image.num_layers,
+                };
+                Some(self.view_image_as_render_target(resource, image.kind, image.desc.Format, &range).unwrap())
+            } else {
+                None
+            },
+            clear_dv: if image.aspects.contains(Aspects::DEPTH) && image.usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) {
+                let range = image::SubresourceRange {
+                    aspects: Aspects::DEPTH,
+                    levels: 0 .. 1, //TODO?
+                    layers: 0 .. image.num_layers,
+                };
+                Some(self.view_image_as_depth_stencil(resource, image.kind, image.dsv_format, &range).unwrap())
+            } else {
+                None
+            },
+            clear_sv: if image.aspects.contains(Aspects::STENCIL) && image.usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) {
+                let range = image::SubresourceRange {
+                    aspects: Aspects::STENCIL,
+                    levels: 0 .. 1, //TODO?
+                    layers: 0 .. image.num_layers,
+                };
+                Some(self.view_image_as_depth_stencil(resource, image.kind, image.dsv_format, &range).unwrap())
+            } else {
+                None
+            },
+        }
+    }
+
     fn view_image_as_render_target(
         &self,
         resource: *mut d3d12::ID3D12Resource,
@@ -607,17 +825,28 @@ impl Device {
         Ok(handle)
     }
 
-    pub(crate) fn create_raw_fence(&self, signalled: bool) -> *mut d3d12::ID3D12Fence {
+    pub(crate) fn create_raw_fence(
+        &self, signalled: bool,
+    ) -> Result<*mut d3d12::ID3D12Fence, d::OutOfMemory> {
         let mut handle = ptr::null_mut();
-        assert_eq!(winerror::S_OK, unsafe {
+        let hr = unsafe {
             self.raw.clone().CreateFence(
                 if signalled { 1 } else { 0 },
                 d3d12::D3D12_FENCE_FLAG_NONE,
                 &d3d12::IID_ID3D12Fence,
                 &mut handle,
             )
-        });
-        handle as *mut _
+        };
+        if hr == winerror::E_OUTOFMEMORY {
+            return Err(d::OutOfMemory::OutOfHostMemory);
+        }
+        assert_eq!(winerror::S_OK, hr);
+        Ok(handle as *mut _)
+    }
+
+    /// Check if the device has been removed, e.g by a driver reset or a hang.
+    pub(crate) fn is_device_lost(&self) -> bool {
+        unsafe { self.raw.GetDeviceRemovedReason() != winerror::S_OK }
     }
 }
 
@@ -661,7 +890,7 @@ impl d::Device<B> for Device {
             self.raw.clone().CreateHeap(&desc, &d3d12::IID_ID3D12Heap, &mut heap)
         };
         if hr == winerror::E_OUTOFMEMORY {
-            return Err(d::OutOfMemory);
+            return Err(d::OutOfMemory::OutOfDeviceMemory);
         }
         assert_eq!(winerror::S_OK, hr);
 
@@ -718,7 +947,7 @@ impl d::Device<B> for Device {
 
     fn create_command_pool(
         &self, family: QueueFamilyId, _create_flags: CommandPoolCreateFlags
-    ) -> RawCommandPool {
+    ) -> Result<RawCommandPool, d::OutOfMemory> {
         let list_type = QUEUE_FAMILIES[family.0].native_type();
         // create command allocator
         let mut command_allocator: *mut d3d12::ID3D12CommandAllocator = ptr::null_mut();
@@ -729,17 +958,21 @@ impl d::Device<B> for Device {
                 &mut command_allocator as *mut *mut _ as *mut *mut _,
             )
         };
+        if hr == winerror::E_OUTOFMEMORY {
+            return Err(d::OutOfMemory::OutOfHostMemory);
+        }
         // TODO: error handling
         if !winerror::SUCCEEDED(hr) {
             error!("error on command allocator creation: {:x}", hr);
         }
 
-        RawCommandPool {
+        Ok(RawCommandPool {
             inner: unsafe { ComPtr::from_raw(command_allocator) },
             device: self.raw.clone(),
             list_type,
+            bundle_allocator: None,
             signatures: self.signatures.clone(),
-        }
+        })
     }
 
     fn destroy_command_pool(&self, _pool: RawCommandPool) {
@@ -922,6 +1155,9 @@ impl d::Device<B> for Device {
         //     DescriptorTable0: Space: 3 (+1) (Sampler)
         //     DescriptorTable1: Space: 4 (+1) (SrvCbvUav)
         //     ...
+        //
+        // Immutable samplers are not part of the sampler tables, they are
+        // baked into the root signature as static samplers in the sampler space.
 
         let sets = sets.into_iter().collect::<Vec<_>>();
         let root_constants = root_constants::split(push_constant_ranges)
@@ -970,10 +1206,10 @@ impl d::Device<B> for Device {
                     .bindings;
 
                 for binding in bindings {
-                    sum += if binding.ty == pso::DescriptorType::CombinedImageSampler {
-                        2
-                    } else {
-                        1
+                    sum += match binding.ty {
+                        pso::DescriptorType::Sampler if binding.immutable_samplers => 0,
+                        pso::DescriptorType::CombinedImageSampler if !binding.immutable_samplers => 2,
+                        _ => 1,
                     };
                 }
 
@@ -982,6 +1218,7 @@ impl d::Device<B> for Device {
             .sum();
         let mut ranges = Vec::with_capacity(total);
         let mut set_tables = Vec::with_capacity(sets.len());
+        let mut static_samplers = Vec::new();
 
         for (i, set) in sets.iter().enumerate() {
             let set = set.borrow();
@@ -1010,15 +1247,17 @@ impl d::Device<B> for Device {
                 table_type |= n::SRV_CBV_UAV;
             }
 
+            let sampler_space = (table_space_offset + 2*i+1) as u32;
             let range_base = ranges.len();
             ranges.extend(set
                 .bindings
                 .iter()
                 .filter(|bind| bind.ty == pso::DescriptorType::Sampler || bind.ty == pso::DescriptorType::CombinedImageSampler)
+                .filter(|bind| !bind.immutable_samplers)
                 .map(|bind| {
                     conv::map_descriptor_range(
                         bind,
-                        (table_space_offset + 2*i+1) as u32,
+                        sampler_space,
                         true,
                     )
                 }));
@@ -1033,6 +1272,20 @@ impl d::Device<B> for Device {
                 table_type |= n::SAMPLERS;
             }
 
+            let mut immutable_samplers = set.immutable_samplers.iter();
+            for bind in set.bindings.iter().filter(|bind| bind.immutable_samplers) {
+                for array_index in 0 .. bind.count {
+                    let desc = immutable_samplers
+                        .next()
+                        .expect("Not enough immutable samplers for the set layout");
+                    static_samplers.push(conv::map_static_sampler(
+                        desc,
+                        bind.binding + array_index as u32,
+                        sampler_space,
+                    ));
+                }
+            }
+
             set_tables.push(table_type);
         }
 
@@ -1046,8 +1299,8 @@ impl d::Device<B> for Device {
         let desc = d3d12::D3D12_ROOT_SIGNATURE_DESC {
             NumParameters: parameters.len() as u32,
             pParameters: parameters.as_ptr(),
-            NumStaticSamplers: 0,
-            pStaticSamplers: ptr::null(),
+            NumStaticSamplers: static_samplers.len() as u32,
+            pStaticSamplers: static_samplers.as_ptr(),
             Flags: d3d12::D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
         };
 
@@ -1090,9 +1343,85 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Result<n::PipelineCache, d::OutOfMemory> {
+        // Pipeline libraries require `ID3D12Device1`.
+        let device = match self.raw.cast::<d3d12::ID3D12Device1>() {
+            Ok(device) => device,
+            Err(_) => return Ok(n::PipelineCache { library: None, data: Vec::new() }),
+        };
+
+        let create_library = |data: &[u8]| {
+            let mut library: *mut d3d12::ID3D12PipelineLibrary = ptr::null_mut();
+            let hr = unsafe {
+                device.CreatePipelineLibrary(
+                    if data.is_empty() { ptr::null() } else { data.as_ptr() as *const _ },
+                    data.len(),
+                    &d3d12::IID_ID3D12PipelineLibrary,
+                    &mut library as *mut *mut _ as *mut *mut _,
+                )
+            };
+            (hr, library)
+        };
+
+        let mut data = data.map_or(Vec::new(), |data| data.to_vec());
+        let (mut hr, mut library) = create_library(&data);
+        if !winerror::SUCCEEDED(hr) && hr != winerror::E_OUTOFMEMORY && !data.is_empty() {
+            // Stale or corrupted data, e.g. from a different driver version.
+            warn!("Discarding incompatible pipeline cache data: {:x}", hr);
+            data.clear();
+            let (retry_hr, retry_library) = create_library(&data);
+            hr = retry_hr;
+            library = retry_library;
+        }
+
+        if hr == winerror::E_OUTOFMEMORY {
+            return Err(d::OutOfMemory::OutOfHostMemory);
+        }
+
+        let library = if winerror::SUCCEEDED(hr) {
+            Some(unsafe { ComPtr::from_raw(library) })
+        } else {
+            warn!("Pipeline libraries are not supported: {:x}", hr);
+            None
+        };
+
+        Ok(n::PipelineCache { library, data })
+    }
+
+    fn get_pipeline_cache_data(&self, cache: &n::PipelineCache) -> Result<Vec<u8>, d::OutOfMemory> {
+        let library = match cache.library {
+            Some(ref library) => library,
+            None => return Ok(Vec::new()),
+        };
+
+        let size = unsafe { library.GetSerializedSize() };
+        let mut data = vec![0u8; size];
+        let hr = unsafe { library.Serialize(data.as_mut_ptr() as *mut _, size) };
+
+        if winerror::SUCCEEDED(hr) {
+            Ok(data)
+        } else if hr == winerror::E_OUTOFMEMORY {
+            Err(d::OutOfMemory::OutOfHostMemory)
+        } else {
+            error!("Failed to serialize pipeline library: {:x}", hr);
+            Ok(Vec::new())
+        }
+    }
+
+    fn merge_pipeline_caches<I>(&self, _target: &n::PipelineCache, _sources: I) -> Result<(), d::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::PipelineCache>,
+    {
+        // Pipeline libraries can't be merged, the target keeps its own pipelines.
+        warn!("Merging pipeline caches is not supported");
+        Ok(())
+    }
+
     fn create_graphics_pipeline<'a>(
         &self,
         desc: &pso::GraphicsPipelineDesc<'a, B>,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
         let build_shader =
             |stage: pso::Stage, source: Option<&pso::EntryPoint<'a, B>>| {
@@ -1243,13 +1572,23 @@ impl d::Device<B> for Device {
         let topology = conv::map_topology(desc.input_assembler.primitive);
 
         // Create PSO
-        let mut pipeline = ptr::null_mut();
-        let hr = unsafe {
-            self.raw.clone().CreateGraphicsPipelineState(
-                &pso_desc,
-                &d3d12::IID_ID3D12PipelineState,
-                &mut pipeline as *mut *mut _ as *mut *mut _)
-        };
+        let pipeline = load_or_create_pipeline_state(
+            cache,
+            graphics_pipeline_key(&pso_desc),
+            |library, name, pipeline| unsafe {
+                library.LoadGraphicsPipeline(
+                    name,
+                    &pso_desc,
+                    &d3d12::IID_ID3D12PipelineState,
+                    pipeline as *mut *mut _ as *mut *mut _)
+            },
+            |pipeline| unsafe {
+                self.raw.clone().CreateGraphicsPipelineState(
+                    &pso_desc,
+                    &d3d12::IID_ID3D12PipelineState,
+                    pipeline as *mut *mut _ as *mut *mut _)
+            },
+        );
 
         let destroy_shader = |shader: *mut d3dcommon::ID3DBlob| unsafe { (*shader).Release() };
 
@@ -1259,23 +1598,20 @@ impl d::Device<B> for Device {
         if hs_destroy { destroy_shader(hs); }
         if ds_destroy { destroy_shader(ds); }
 
-        if winerror::SUCCEEDED(hr) {
-            Ok(n::GraphicsPipeline {
-                raw: pipeline,
-                signature: desc.layout.raw,
-                num_parameter_slots: desc.layout.num_parameter_slots,
-                topology,
-                constants: desc.layout.root_constants.clone(),
-                vertex_strides,
-            })
-        } else {
-            Err(pso::CreationError::Other)
-        }
+        pipeline.map(|pipeline| n::GraphicsPipeline {
+            raw: pipeline,
+            signature: desc.layout.raw,
+            num_parameter_slots: desc.layout.num_parameter_slots,
+            topology,
+            constants: desc.layout.root_constants.clone(),
+            vertex_strides,
+        })
     }
 
     fn create_compute_pipeline<'a>(
         &self,
         desc: &pso::ComputePipelineDesc<'a, B>,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<n::ComputePipeline, pso::CreationError> {
         let (cs, cs_destroy) =
             Self::extract_entry_point(
@@ -1297,28 +1633,34 @@ impl d::Device<B> for Device {
         };
 
         // Create PSO
-        let mut pipeline = ptr::null_mut();
-        let hr = unsafe {
-            self.raw.clone().CreateComputePipelineState(
-                &pso_desc,
-                &d3d12::IID_ID3D12PipelineState,
-                &mut pipeline as *mut *mut _ as *mut *mut _)
-        };
+        let pipeline = load_or_create_pipeline_state(
+            cache,
+            compute_pipeline_key(&pso_desc),
+            |library, name, pipeline| unsafe {
+                library.LoadComputePipeline(
+                    name,
+                    &pso_desc,
+                    &d3d12::IID_ID3D12PipelineState,
+                    pipeline as *mut *mut _ as *mut *mut _)
+            },
+            |pipeline| unsafe {
+                self.raw.clone().CreateComputePipelineState(
+                    &pso_desc,
+                    &d3d12::IID_ID3D12PipelineState,
+                    pipeline as *mut *mut _ as *mut *mut _)
+            },
+        );
 
         if cs_destroy {
             unsafe { (*cs).Release(); }
         }
 
-        if winerror::SUCCEEDED(hr) {
-            Ok(n::ComputePipeline {
-                raw: pipeline,
-                signature: desc.layout.raw,
-                num_parameter_slots: desc.layout.num_parameter_slots,
-                constants: desc.layout.root_constants.clone(),
-            })
-        } else {
-            Err(pso::CreationError::Other)
-        }
+        pipeline.map(|pipeline| n::ComputePipeline {
+            raw: pipeline,
+            signature: desc.layout.raw,
+            num_parameter_slots: desc.layout.num_parameter_slots,
+            constants: desc.layout.root_constants.clone(),
+        })
     }
 
     fn create_framebuffer<I>(
@@ -1390,21 +1732,7 @@ impl d::Device<B> for Device {
         }
 
         let mut resource = ptr::null_mut();
-        let desc = d3d12::D3D12_RESOURCE_DESC {
-            Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
-            Alignment: 0,
-            Width: buffer.requirements.size,
-            Height: 1,
-            DepthOrArraySize: 1,
-            MipLevels: 1,
-            Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
-            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
-            Flags: conv::map_buffer_flags(buffer.usage),
-        };
+        let desc = Self::buffer_desc(&buffer);
 
         assert_eq!(winerror::S_OK, unsafe {
             self.raw.clone().CreatePlacedResource(
@@ -1418,40 +1746,46 @@ impl d::Device<B> for Device {
             )
         });
 
-        let clear_uav = if buffer.usage.contains(buffer::Usage::TRANSFER_DST) {
-            let handles = self.uav_pool.lock().unwrap().alloc_handles(1);
-            let mut desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
-                Format: dxgiformat::DXGI_FORMAT_R32_TYPELESS,
-                ViewDimension: d3d12::D3D12_UAV_DIMENSION_BUFFER,
-                u: unsafe { mem::zeroed() },
-            };
+        Ok(self.wrap_buffer(resource as *mut _, &buffer))
+    }
 
-           *unsafe { desc.u.Buffer_mut() } = d3d12::D3D12_BUFFER_UAV {
-                FirstElement: 0,
-                NumElements: (buffer.requirements.size / 4) as _,
-                StructureByteStride: 0,
-                CounterOffsetInBytes: 0,
-                Flags: d3d12::D3D12_BUFFER_UAV_FLAG_RAW,
-            };
+    fn create_sparse_buffer(
+        &self,
+        size: u64,
+        usage: buffer::Usage,
+        _flags: memory::SparseFlags,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // Reserved resources support residency and aliasing without additional flags.
+        let buffer = d::Device::create_buffer(self, size, usage)?;
+        let desc = Self::buffer_desc(&buffer);
 
-            unsafe {
-                self.raw.clone().CreateUnorderedAccessView(
-                    resource as *mut _,
-                    ptr::null_mut(),
-                    &desc,
-                    handles.cpu,
-                );
-            }
-            Some(handles)
+        let mut resource = ptr::null_mut();
+        assert_eq!(winerror::S_OK, unsafe {
+            self.raw.clone().CreateReservedResource(
+                &desc,
+                d3d12::D3D12_RESOURCE_STATE_COMMON,
+                ptr::null(),
+                &d3d12::IID_ID3D12Resource,
+                &mut resource,
+            )
+        });
+
+        Ok(self.wrap_buffer(resource as *mut _, &buffer))
+    }
+
+    fn get_sparse_buffer_requirements(&self, buffer: &n::Buffer) -> Requirements {
+        let tile_size = d3d12::D3D12_TILED_RESOURCE_TILE_SIZE_IN_BYTES as u64;
+        let type_mask_shift = if self.private_caps.heterogeneous_resource_heaps {
+            MEM_TYPE_UNIVERSAL_SHIFT
         } else {
-            None
+            MEM_TYPE_BUFFER_SHIFT
         };
 
-        Ok(n::Buffer {
-            resource: resource as *mut _,
-            size_in_bytes: buffer.requirements.size as _,
-            clear_uav,
-        })
+        Requirements {
+            size: (buffer.size_in_bytes as u64 + tile_size - 1) / tile_size * tile_size,
+            alignment: tile_size,
+            type_mask: MEM_TYPE_MASK << type_mask_shift,
+        }
     }
 
     fn create_buffer_view<R: RangeArg<u64>>(
@@ -1468,8 +1802,22 @@ impl d::Device<B> for Device {
         kind: image::Kind,
         mip_levels: image::Level,
         format: format::Format,
+        tiling: image::Tiling,
         usage: image::Usage,
+        storage_flags: image::StorageFlags,
     ) -> Result<UnboundImage, image::CreationError> {
+        // D3D12 only supports row-major textures in cross-adapter heaps.
+        if tiling == image::Tiling::Linear {
+            return Err(image::CreationError::Tiling(tiling));
+        }
+        // Any 2D array with a multiple of 6 layers can be viewed as cube.
+        // TODO: `MUTABLE_FORMAT` requires creating the resource with a typeless format.
+        // 3D textures can't be viewed as 2D arrays at all.
+        let unsupported = image::StorageFlags::MUTABLE_FORMAT | image::StorageFlags::ARRAY_2D_VIEW;
+        if storage_flags.intersects(unsupported) {
+            return Err(image::CreationError::StorageFlags(storage_flags));
+        }
+
         let base_format = format.base_format();
         let format_desc = base_format.0.desc();
 
@@ -1540,14 +1888,135 @@ impl d::Device<B> for Device {
         image.requirements
     }
 
+    fn create_sparse_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+        _flags: memory::SparseFlags,
+    ) -> Result<n::Image, image::CreationError> {
+        // Reserved resources support residency and aliasing without additional flags.
+        let mut image = d::Device::create_image(
+            self, kind, mip_levels, format, image::Tiling::Optimal, usage, storage_flags,
+        )?;
+        image.desc.Layout = d3d12::D3D12_TEXTURE_LAYOUT_64KB_UNDEFINED_SWIZZLE;
+
+        let mut resource = ptr::null_mut();
+        assert_eq!(winerror::S_OK, unsafe {
+            self.raw.clone().CreateReservedResource(
+                &image.desc,
+                d3d12::D3D12_RESOURCE_STATE_COMMON,
+                ptr::null(),
+                &d3d12::IID_ID3D12Resource,
+                &mut resource,
+            )
+        });
+
+        Ok(self.wrap_image(resource as *mut _, &image))
+    }
+
+    fn get_sparse_image_requirements(&self, image: &n::Image) -> Requirements {
+        let tile_size = d3d12::D3D12_TILED_RESOURCE_TILE_SIZE_IN_BYTES as u64;
+        let tiling = image.tiling(&self.raw);
+        let type_mask_shift = if self.private_caps.heterogeneous_resource_heaps {
+            MEM_TYPE_UNIVERSAL_SHIFT
+        } else if image.usage.can_target() {
+            MEM_TYPE_TARGET_SHIFT
+        } else {
+            MEM_TYPE_IMAGE_SHIFT
+        };
+
+        Requirements {
+            size: tiling.num_tiles as u64 * tile_size,
+            alignment: tile_size,
+            type_mask: MEM_TYPE_MASK << type_mask_shift,
+        }
+    }
+
+    fn get_sparse_image_page_layout(&self, image: &n::Image) -> Vec<image::SparsePageLayout> {
+        let tile_size = d3d12::D3D12_TILED_RESOURCE_TILE_SIZE_IN_BYTES as u64;
+        let tiling = image.tiling(&self.raw);
+        let packed_mips = tiling.packed_mips;
+        let tiles_per_layer = tiling.num_tiles / image.num_layers as u32;
+
+        vec![image::SparsePageLayout {
+            aspects: image.aspects,
+            granularity: d::Extent {
+                width: tiling.tile_shape.WidthInTexels,
+                height: tiling.tile_shape.HeightInTexels,
+                depth: tiling.tile_shape.DepthInTexels,
+            },
+            mip_tail_first_level: packed_mips.NumStandardMips,
+            mip_tail_size: packed_mips.NumTilesForPackedMips as u64 * tile_size,
+            mip_tail_offset: if packed_mips.NumPackedMips != 0 {
+                packed_mips.StartTileIndexInOverallResource as u64 * tile_size
+            } else {
+                0
+            },
+            // Each array layer has its own packed mips.
+            mip_tail_stride: Some(tiles_per_layer as u64 * tile_size),
+        }]
+    }
+
+    fn get_image_subresource_footprint(
+        &self, image: &n::Image, aspect: Aspects, (level, layer): image::Subresource
+    ) -> image::SubresourceFootprint {
+        // Linear images are rejected by `create_image`, return the layout
+        // used by copies between the image and a buffer instead.
+        let desc = unsafe { (*image.resource).GetDesc() };
+        let num_levels = image.num_levels as usize;
+        let num_layers = image.num_layers as usize;
+        // Depth-stencil formats store the stencil aspect in a second plane.
+        let (num_planes, plane) = if image.aspects.contains(Aspects::DEPTH | Aspects::STENCIL) {
+            (2, if aspect == Aspects::STENCIL { 1 } else { 0 })
+        } else {
+            (1, 0)
+        };
+
+        let num_subresources = num_planes * num_layers * num_levels;
+        let mut layouts = Vec::with_capacity(num_subresources);
+        let mut num_rows = Vec::with_capacity(num_subresources);
+        let mut total_size = 0;
+        unsafe {
+            self.raw.GetCopyableFootprints(
+                &desc,
+                0,
+                num_subresources as _,
+                0,
+                layouts.as_mut_ptr(),
+                num_rows.as_mut_ptr(),
+                ptr::null_mut(),
+                &mut total_size,
+            );
+            layouts.set_len(num_subresources);
+            num_rows.set_len(num_subresources);
+        }
+
+        let layer_start = (plane * num_layers + layer as usize) * num_levels;
+        let layer_end = layouts
+            .get(layer_start + num_levels)
+            .map_or(total_size, |next| next.Offset);
+        let subresource = layer_start + level as usize;
+        let layout = layouts[subresource];
+
+        let row_pitch = layout.Footprint.RowPitch as u64;
+        let depth_pitch = row_pitch * num_rows[subresource] as u64;
+        image::SubresourceFootprint {
+            slice: layout.Offset .. layout.Offset + depth_pitch * layout.Footprint.Depth as u64,
+            row_pitch,
+            array_pitch: layer_end - layouts[layer_start].Offset,
+            depth_pitch,
+        }
+    }
+
     fn bind_image_memory(
         &self,
         memory: &n::Memory,
         offset: u64,
         image: UnboundImage,
     ) -> Result<n::Image, d::BindError> {
-        use self::image::Usage;
-
         if image.requirements.type_mask & (1 << memory.type_id) == 0 {
             error!("Bind memory failure: supported mask 0x{:x}, given id {}",
                 image.requirements.type_mask, memory.type_id);
@@ -1571,50 +2040,7 @@ impl d::Device<B> for Device {
             )
         });
 
-        //TODO: the clear_Xv is incomplete. We should support clearing images created without XXX_ATTACHMENT usage.
-        // for this, we need to check the format and force the `RENDER_TARGET` flag behind the user's back
-        // if the format supports being rendered into, allowing us to create clear_Xv
-
-        Ok(n::Image {
-            resource: resource as *mut _,
-            kind: image.kind,
-            usage: image.usage,
-            dxgi_format: image.desc.Format,
-            bytes_per_block: image.bytes_per_block,
-            block_dim: image.block_dim,
-            num_levels: image.num_levels,
-            num_layers: image.num_layers,
-            clear_cv: if image.aspects.contains(Aspects::COLOR) && image.usage.contains(Usage::COLOR_ATTACHMENT) {
-                let range = image::SubresourceRange {
-                    aspects: Aspects::COLOR,
-                    levels: 0 .. 1, //TODO?
-                    layers: 0 .. image.num_layers,
-                };
-                Some(self.view_image_as_render_target(resource as *mut _, image.kind, image.desc.Format, &range).unwrap())
-            } else {
-                None
-            },
-            clear_dv: if image.aspects.contains(Aspects::DEPTH) && image.usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) {
-                let range = image::SubresourceRange {
-                    aspects: Aspects::DEPTH,
-                    levels: 0 .. 1, //TODO?
-                    layers: 0 .. image.num_layers,
-                };
-                Some(self.view_image_as_depth_stencil(resource as *mut _, image.kind, image.dsv_format, &range).unwrap())
-            } else {
-                None
-            },
-            clear_sv: if image.aspects.contains(Aspects::STENCIL) && image.usage.contains(Usage::DEPTH_STENCIL_ATTACHMENT) {
-                let range = image::SubresourceRange {
-                    aspects: Aspects::STENCIL,
-                    levels: 0 .. 1, //TODO?
-                    layers: 0 .. image.num_layers,
-                };
-                Some(self.view_image_as_depth_stencil(resource as *mut _, image.kind, image.dsv_format, &range).unwrap())
-            } else {
-                None
-            },
-        })
+        Ok(self.wrap_image(resource as *mut _, &image))
     }
 
     fn create_image_view(
@@ -1654,7 +2080,7 @@ impl d::Device<B> for Device {
         })
     }
 
-    fn create_sampler(&self, info: image::SamplerInfo) -> n::Sampler {
+    fn create_sampler(&self, info: image::SamplerInfo) -> Result<n::Sampler, d::OutOfMemory> {
         let handle = self.sampler_pool.lock().unwrap().alloc_handles(1).cpu;
 
         let op = match info.comparison {
@@ -1681,14 +2107,15 @@ impl d::Device<B> for Device {
             self.raw.clone().CreateSampler(&desc, handle);
         }
 
-        n::Sampler { handle }
+        Ok(n::Sampler { handle, desc })
     }
 
     fn create_descriptor_pool<I>(
         &self,
         max_sets: usize,
         descriptor_pools: I,
-    ) -> n::DescriptorPool
+        _flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<n::DescriptorPool, d::OutOfMemory>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>
@@ -1723,14 +2150,13 @@ impl d::Device<B> for Device {
             let range = heap_srv_cbv_uav
                 .allocator
                 .allocate(num_srv_cbv_uav as _)
-                .unwrap(); // TODO: error/resize
-            n::DescriptorHeapSlice {
-                heap: heap_srv_cbv_uav.raw.clone(),
-                handle_size: heap_srv_cbv_uav.handle_size as _,
-                next: range.start,
+                .ok_or(d::OutOfMemory::OutOfDeviceMemory)?; // TODO: resize
+            n::DescriptorHeapSlice::new(
+                heap_srv_cbv_uav.raw.clone(),
                 range,
-                start: heap_srv_cbv_uav.start,
-            }
+                heap_srv_cbv_uav.start,
+                heap_srv_cbv_uav.handle_size as _,
+            )
         };
 
         let heap_sampler = {
@@ -1739,37 +2165,45 @@ impl d::Device<B> for Device {
                 .lock()
                 .unwrap();
 
-            let range = heap_sampler
-                .allocator
-                .allocate(num_samplers as _)
-                .unwrap(); // TODO: error/resize
-            n::DescriptorHeapSlice {
-                heap: heap_sampler.raw.clone(),
-                handle_size: heap_sampler.handle_size as _,
-                next: range.start as _,
+            let range = match heap_sampler.allocator.allocate(num_samplers as _) {
+                Some(range) => range,
+                None => {
+                    // TODO: resize
+                    self.heap_srv_cbv_uav.lock().unwrap()
+                        .allocator.deallocate(heap_srv_cbv_uav.range);
+                    return Err(d::OutOfMemory::OutOfDeviceMemory);
+                }
+            };
+            n::DescriptorHeapSlice::new(
+                heap_sampler.raw.clone(),
                 range,
-                start: heap_sampler.start,
-            }
+                heap_sampler.start,
+                heap_sampler.handle_size as _,
+            )
         };
 
-        n::DescriptorPool {
+        Ok(n::DescriptorPool {
             heap_srv_cbv_uav,
             heap_sampler,
             pools: descriptor_pools,
             max_size: max_sets as _,
-        }
+        })
     }
 
-    fn create_descriptor_set_layout<I>(
+    fn create_descriptor_set_layout<I, J>(
         &self,
         bindings: I,
+        immutable_samplers: J,
     )-> n::DescriptorSetLayout
     where
         I: IntoIterator,
-        I::Item: Borrow<pso::DescriptorSetLayoutBinding>
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<n::Sampler>,
     {
         n::DescriptorSetLayout {
-            bindings: bindings.into_iter().map(|bind| bind.borrow().clone()).collect()
+            bindings: bindings.into_iter().map(|bind| bind.borrow().clone()).collect(),
+            immutable_samplers: immutable_samplers.into_iter().map(|sampler| sampler.borrow().desc).collect(),
         }
     }
 
@@ -1876,9 +2310,12 @@ impl d::Device<B> for Device {
                         src_views.push(image.handle_srv.unwrap());
                         dst_views.push(bind_info.view_range.as_ref().unwrap().at(offset));
                         num_views.push(1);
-                        src_samplers.push(sampler.handle);
-                        dst_samplers.push(bind_info.sampler_range.as_ref().unwrap().at(offset));
-                        num_samplers.push(1);
+                        // Immutable samplers are static, nothing to write
+                        if let Some(ref sampler_range) = bind_info.sampler_range {
+                            src_samplers.push(sampler.handle);
+                            dst_samplers.push(sampler_range.at(offset));
+                            num_samplers.push(1);
+                        }
                     }
                     pso::Descriptor::Sampler(sampler) => {
                         src_samplers.push(sampler.handle);
@@ -1984,6 +2421,40 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_descriptor_update_template<I>(
+        &self, layout: &n::DescriptorSetLayout, entries: I
+    ) -> n::DescriptorUpdateTemplate
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|entry| *entry.borrow())
+            .collect::<Vec<_>>();
+        for entry in &entries {
+            let binding = layout.bindings
+                .iter()
+                .find(|bind| bind.binding == entry.binding)
+                .expect("Descriptor update template doesn't match the set layout!");
+            assert!(entry.array_offset + entry.count <= binding.count);
+        }
+
+        n::DescriptorUpdateTemplate { entries }
+    }
+
+    fn destroy_descriptor_update_template(&self, _template: n::DescriptorUpdateTemplate) {
+    }
+
+    unsafe fn update_descriptor_set_with_template(
+        &self, set: &n::DescriptorSet, template: &n::DescriptorUpdateTemplate, data: &[u8]
+    ) {
+        self.write_descriptor_sets(template.entries
+            .iter()
+            .map(|entry| entry.write::<B>(set, data))
+        );
+    }
+
     fn map_memory<R>(&self, memory: &n::Memory, range: R) -> Result<*mut u8, mapping::Error>
     where
         R: RangeArg<u64>,
@@ -2101,17 +2572,17 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_semaphore(&self) -> n::Semaphore {
-        let fence = self.create_fence(false);
-        n::Semaphore {
+    fn create_semaphore(&self) -> Result<n::Semaphore, d::OutOfMemory> {
+        let fence = self.create_fence(false)?;
+        Ok(n::Semaphore {
             raw: fence.raw,
-        }
+        })
     }
 
-    fn create_fence(&self, signalled: bool) -> n::Fence {
-        n::Fence {
-            raw: unsafe { ComPtr::from_raw(self.create_raw_fence(signalled)) },
-        }
+    fn create_fence(&self, signalled: bool) -> Result<n::Fence, d::OutOfMemory> {
+        Ok(n::Fence {
+            raw: unsafe { ComPtr::from_raw(self.create_raw_fence(signalled)?) },
+        })
     }
 
     fn reset_fence(&self, fence: &n::Fence) {
@@ -2120,7 +2591,9 @@ impl d::Device<B> for Device {
         });
     }
 
-    fn wait_for_fences<I>(&self, fences: I, wait: d::WaitFor, timeout_ms: u32) -> bool
+    fn wait_for_fences<I>(
+        &self, fences: I, wait: d::WaitFor, timeout_ms: u32,
+    ) -> Result<bool, d::DeviceLost>
     where
         I: IntoIterator,
         I::Item: Borrow<n::Fence>,
@@ -2156,15 +2629,72 @@ impl d::Device<B> for Device {
         const WAIT_OBJECT_LAST: u32 = winbase::WAIT_OBJECT_0 + winnt::MAXIMUM_WAIT_OBJECTS;
         const WAIT_ABANDONED_LAST: u32 = winbase::WAIT_ABANDONED_0 + winnt::MAXIMUM_WAIT_OBJECTS;
         match hr {
-            winbase::WAIT_OBJECT_0 ... WAIT_OBJECT_LAST => true,
-            winbase::WAIT_ABANDONED_0 ... WAIT_ABANDONED_LAST => true, //TODO?
-            winerror::WAIT_TIMEOUT => false,
+            winbase::WAIT_OBJECT_0 ... WAIT_OBJECT_LAST => {}
+            winbase::WAIT_ABANDONED_0 ... WAIT_ABANDONED_LAST => {} //TODO?
+            winerror::WAIT_TIMEOUT => return Ok(false),
             _ => panic!("Unexpected wait status 0x{:X}", hr),
         }
+
+        // Fences of a removed device are signalled with `u64::MAX`,
+        // which also wakes up the waiting events.
+        if self.is_device_lost() {
+            Err(d::DeviceLost)
+        } else {
+            Ok(true)
+        }
     }
 
-    fn get_fence_status(&self, _fence: &n::Fence) -> bool {
-        unimplemented!()
+    fn get_fence_status(&self, fence: &n::Fence) -> Result<bool, d::DeviceLost> {
+        match unsafe { fence.raw.GetCompletedValue() } {
+            !0 => Err(d::DeviceLost),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn get_submission_status(
+        &self, queue: &CommandQueue, id: SubmissionId,
+    ) -> Result<bool, d::DeviceLost> {
+        match unsafe { (*queue.progress_fence).GetCompletedValue() } {
+            !0 => Err(d::DeviceLost),
+            value => Ok(value >= id.0),
+        }
+    }
+
+    fn wait_for_submission(
+        &self, queue: &CommandQueue, id: SubmissionId, timeout_ms: u32,
+    ) -> Result<bool, d::DeviceLost> {
+        let mut events = self.events.lock().unwrap();
+        if events.is_empty() {
+            events.push(unsafe {
+                synchapi::CreateEventA(
+                    ptr::null_mut(),
+                    FALSE,
+                    FALSE,
+                    ptr::null(),
+                )
+            });
+        }
+
+        let event = events[0];
+        assert_eq!(winerror::S_OK, unsafe {
+            synchapi::ResetEvent(event);
+            (*queue.progress_fence).SetEventOnCompletion(id.0, event)
+        });
+
+        match unsafe { synchapi::WaitForSingleObject(event, timeout_ms) } {
+            winbase::WAIT_OBJECT_0 => {}
+            winerror::WAIT_TIMEOUT => return Ok(false),
+            hr => panic!("Unexpected wait status 0x{:X}", hr),
+        }
+
+        // Fences of a removed device are signalled with `u64::MAX`,
+        // which also wakes up the waiting event.
+        if self.is_device_lost() {
+            Err(d::DeviceLost)
+        } else {
+            Ok(true)
+        }
     }
 
     fn free_memory(&self, memory: n::Memory) {
@@ -2224,6 +2754,10 @@ impl d::Device<B> for Device {
         unsafe { (*layout.raw).Release(); }
     }
 
+    fn destroy_pipeline_cache(&self, _cache: n::PipelineCache) {
+        // Just drop
+    }
+
     fn destroy_graphics_pipeline(&self, pipeline: n::GraphicsPipeline) {
         unsafe { (*pipeline.raw).Release(); }
     }
@@ -2378,6 +2912,7 @@ impl d::Device<B> for Device {
                 block_dim,
                 num_levels: 1,
                 num_layers: 1,
+                aspects: Aspects::COLOR,
                 clear_cv: Some(rtv_handle),
                 clear_dv: None,
                 clear_sv: None,
@@ -2400,4 +2935,19 @@ impl d::Device<B> for Device {
         }
         Ok(())
     }
+
+    fn set_object_name(&self, object: d::NamedObject<B>, name: &str) {
+        let object = match object {
+            d::NamedObject::Buffer(buffer) => buffer.resource as *mut d3d12::ID3D12Object,
+            d::NamedObject::Image(image) => image.resource as *mut _,
+            d::NamedObject::GraphicsPipeline(pipeline) => pipeline.raw as *mut _,
+            d::NamedObject::ComputePipeline(pipeline) => pipeline.raw as *mut _,
+            d::NamedObject::CommandBuffer(cmd_buffer) => unsafe { cmd_buffer.as_raw_list() } as *mut _,
+            // Views and samplers are descriptors, which can't be named.
+            d::NamedObject::ImageView(_) |
+            d::NamedObject::Sampler(_) => return,
+        };
+        let name = conv::map_debug_name(name);
+        unsafe { (*object).SetName(name.as_ptr()); }
+    }
 }
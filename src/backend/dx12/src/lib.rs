@@ -23,7 +23,7 @@ mod root_constants;
 mod shade;
 mod window;
 
-use hal::{error, format as f, memory, Features, Limits, QueueType};
+use hal::{error, format as f, image, memory, Features, Limits, QueueType};
 use hal::queue::{QueueFamily as HalQueueFamily, QueueFamilyId, Queues};
 
 use winapi::shared::{dxgi, dxgi1_2, dxgi1_3, dxgi1_4, winerror};
@@ -151,6 +151,10 @@ impl hal::QueueFamily for QueueFamily {
             _ => unreachable!(),
         })
     }
+    fn supports_sparse_binding(&self) -> bool {
+        // Tile mappings can be updated on any queue type.
+        true
+    }
 }
 
 impl QueueFamily {
@@ -174,6 +178,8 @@ static QUEUE_FAMILIES: [QueueFamily; 4] = [
 
 pub struct PhysicalDevice {
     adapter: ComPtr<dxgi1_2::IDXGIAdapter2>,
+    // Device used for querying format support.
+    device: ComPtr<d3d12::ID3D12Device>,
     features: hal::Features,
     limits: hal::Limits,
     private_caps: Capabilities,
@@ -267,8 +273,12 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                         // violates the specification.
                         let queue = CommandQueue {
                             raw: device.present_queue.clone(),
-                            idle_fence: device.create_raw_fence(false),
+                            idle_fence: device.create_raw_fence(false)
+                                .expect("Failed to create idle fence"),
                             idle_event: create_idle_event(),
+                            progress_fence: device.create_raw_fence(false)
+                                .expect("Failed to create progress fence"),
+                            submission_count: 0,
                         };
                         device.append_queue(queue.clone());
                         group.add_queue(queue);
@@ -294,8 +304,12 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                             if winerror::SUCCEEDED(hr) {
                                 let queue = CommandQueue {
                                     raw: unsafe { ComPtr::from_raw(queue) },
-                                    idle_fence: device.create_raw_fence(false),
+                                    idle_fence: device.create_raw_fence(false)
+                                        .expect("Failed to create idle fence"),
                                     idle_event: create_idle_event(),
+                                    progress_fence: device.create_raw_fence(false)
+                                        .expect("Failed to create progress fence"),
+                                    submission_count: 0,
                                 };
                                 device.append_queue(queue.clone());
                                 group.add_queue(queue);
@@ -323,6 +337,123 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         format::query_properties()[idx]
     }
 
+    fn image_format_properties(
+        &self,
+        format: f::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        let dxgi_format = conv::map_format(format)?;
+        // Linear images are rejected by `Device::create_image`.
+        if tiling == image::Tiling::Linear {
+            return None;
+        }
+
+        let mut data = d3d12::D3D12_FEATURE_DATA_FORMAT_SUPPORT {
+            Format: dxgi_format,
+            Support1: 0,
+            Support2: 0,
+        };
+        let hr = unsafe {
+            self.device.CheckFeatureSupport(d3d12::D3D12_FEATURE_FORMAT_SUPPORT,
+                &mut data as *mut _ as *mut _,
+                mem::size_of::<d3d12::D3D12_FEATURE_DATA_FORMAT_SUPPORT>() as _)
+        };
+        if !winerror::SUCCEEDED(hr) {
+            return None;
+        }
+
+        let (dimension_support, max_extent, max_layers) = match dimensions {
+            1 => (
+                d3d12::D3D12_FORMAT_SUPPORT1_TEXTURE1D,
+                hal::device::Extent {
+                    width: d3d12::D3D12_REQ_TEXTURE1D_U_DIMENSION,
+                    height: 1,
+                    depth: 1,
+                },
+                d3d12::D3D12_REQ_TEXTURE1D_ARRAY_AXIS_DIMENSION,
+            ),
+            2 => (
+                d3d12::D3D12_FORMAT_SUPPORT1_TEXTURE2D,
+                hal::device::Extent {
+                    width: d3d12::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    height: d3d12::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    depth: 1,
+                },
+                d3d12::D3D12_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION,
+            ),
+            3 => (
+                d3d12::D3D12_FORMAT_SUPPORT1_TEXTURE3D,
+                hal::device::Extent {
+                    width: d3d12::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    height: d3d12::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    depth: d3d12::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                },
+                1,
+            ),
+            _ => panic!("Unexpected image dimensionality: {}", dimensions),
+        };
+
+        let mut required_support = dimension_support;
+        if usage.contains(image::Usage::SAMPLED) {
+            required_support |= d3d12::D3D12_FORMAT_SUPPORT1_SHADER_SAMPLE;
+        }
+        if usage.contains(image::Usage::STORAGE) {
+            required_support |= d3d12::D3D12_FORMAT_SUPPORT1_TYPED_UNORDERED_ACCESS_VIEW;
+        }
+        if usage.contains(image::Usage::COLOR_ATTACHMENT) {
+            required_support |= d3d12::D3D12_FORMAT_SUPPORT1_RENDER_TARGET;
+        }
+        if usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT) {
+            required_support |= d3d12::D3D12_FORMAT_SUPPORT1_DEPTH_STENCIL;
+        }
+        if storage_flags.contains(image::StorageFlags::CUBE_VIEW) {
+            required_support |= d3d12::D3D12_FORMAT_SUPPORT1_TEXTURECUBE;
+        }
+        // Mirrors the storage flag restrictions of `Device::create_image`.
+        if storage_flags.intersects(image::StorageFlags::MUTABLE_FORMAT | image::StorageFlags::ARRAY_2D_VIEW) ||
+            data.Support1 & required_support != required_support
+        {
+            return None;
+        }
+
+        let mut sample_count_mask = 1;
+        if dimensions == 2 && data.Support1 & d3d12::D3D12_FORMAT_SUPPORT1_MULTISAMPLE_RENDERTARGET != 0 {
+            for count in (1 .. 6).map(|i| 1 << i) {
+                let mut levels = d3d12::D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS {
+                    Format: dxgi_format,
+                    SampleCount: count,
+                    Flags: d3d12::D3D12_MULTISAMPLE_QUALITY_LEVELS_FLAG_NONE,
+                    NumQualityLevels: 0,
+                };
+                let hr = unsafe {
+                    self.device.CheckFeatureSupport(d3d12::D3D12_FEATURE_MULTISAMPLE_QUALITY_LEVELS,
+                        &mut levels as *mut _ as *mut _,
+                        mem::size_of::<d3d12::D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS>() as _)
+                };
+                if winerror::SUCCEEDED(hr) && levels.NumQualityLevels != 0 {
+                    sample_count_mask |= count as image::NumSamples;
+                }
+            }
+        }
+
+        let max_levels = if data.Support1 & d3d12::D3D12_FORMAT_SUPPORT1_MIP != 0 {
+            d3d12::D3D12_REQ_MIP_LEVELS as _
+        } else {
+            1
+        };
+
+        Some(image::FormatProperties {
+            max_extent,
+            max_levels,
+            max_layers: max_layers as _,
+            sample_count_mask,
+            max_resource_size: (d3d12::D3D12_REQ_RESOURCE_SIZE_IN_MEGABYTES_EXPRESSION_A_TERM as u64) << 20,
+        })
+    }
+
     fn memory_properties(&self) -> hal::MemoryProperties {
         self.memory_properties.clone()
     }
@@ -336,17 +467,84 @@ pub struct CommandQueue {
     pub(crate) raw: ComPtr<d3d12::ID3D12CommandQueue>,
     idle_fence: *mut d3d12::ID3D12Fence,
     idle_event: winnt::HANDLE,
+    // Signalled with the submission id after each submission.
+    pub(crate) progress_fence: *mut d3d12::ID3D12Fence,
+    // Only advanced by the queue itself, the copies owned by the device don't submit.
+    submission_count: u64,
 }
 
 unsafe impl Send for CommandQueue {}
 unsafe impl Sync for CommandQueue {}
 
+impl CommandQueue {
+    unsafe fn signal_fence(&mut self, fence: *mut d3d12::ID3D12Fence, value: u64) -> hal::SubmissionResult<()> {
+        match self.raw.Signal(fence, value) {
+            winerror::S_OK => Ok(()),
+            winerror::DXGI_ERROR_DEVICE_REMOVED |
+            winerror::DXGI_ERROR_DEVICE_RESET => Err(hal::SubmissionError::DeviceLost),
+            winerror::E_OUTOFMEMORY => Err(hal::SubmissionError::OutOfHostMemory),
+            hr => panic!("Fence signal failed: {:x}", hr),
+        }
+    }
+
+    unsafe fn wait_fence(&mut self, fence: *mut d3d12::ID3D12Fence, value: u64) -> hal::SubmissionResult<()> {
+        match self.raw.Wait(fence, value) {
+            winerror::S_OK => Ok(()),
+            winerror::DXGI_ERROR_DEVICE_REMOVED |
+            winerror::DXGI_ERROR_DEVICE_RESET => Err(hal::SubmissionError::DeviceLost),
+            winerror::E_OUTOFMEMORY => Err(hal::SubmissionError::OutOfHostMemory),
+            hr => panic!("Fence wait failed: {:x}", hr),
+        }
+    }
+
+    unsafe fn resource_device(resource: *mut d3d12::ID3D12Resource) -> ComPtr<d3d12::ID3D12Device> {
+        let mut device: *mut d3d12::ID3D12Device = ptr::null_mut();
+        assert_eq!(winerror::S_OK, (*resource).GetDevice(
+            &d3d12::IID_ID3D12Device,
+            &mut device as *mut *mut _ as *mut *mut _,
+        ));
+        ComPtr::from_raw(device)
+    }
+
+    unsafe fn update_tile_mapping(
+        &mut self,
+        resource: *mut d3d12::ID3D12Resource,
+        coordinate: d3d12::D3D12_TILED_RESOURCE_COORDINATE,
+        size: d3d12::D3D12_TILE_REGION_SIZE,
+        memory: Option<(&native::Memory, u64)>,
+    ) {
+        let tile_size = d3d12::D3D12_TILED_RESOURCE_TILE_SIZE_IN_BYTES as u64;
+        let (heap, flags, offset) = match memory {
+            Some((memory, offset)) => (
+                memory.heap.as_raw(),
+                d3d12::D3D12_TILE_RANGE_FLAG_NONE,
+                (offset / tile_size) as u32,
+            ),
+            None => (ptr::null_mut(), d3d12::D3D12_TILE_RANGE_FLAG_NULL, 0),
+        };
+
+        self.raw.UpdateTileMappings(
+            resource,
+            1,
+            &coordinate,
+            &size,
+            heap,
+            1,
+            &flags,
+            &offset,
+            &size.NumTiles,
+            d3d12::D3D12_TILE_MAPPING_FLAG_NONE,
+        );
+    }
+}
+
 impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
     unsafe fn submit_raw<IC>(
         &mut self,
         submission: hal::queue::RawSubmission<Backend, IC>,
         fence: Option<&native::Fence>,
-    ) where
+    ) -> hal::SubmissionResult<hal::queue::SubmissionId>
+    where
         IC: IntoIterator,
         IC::Item: Borrow<command::CommandBuffer>,
     {
@@ -364,13 +562,101 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
         self.raw.ExecuteCommandLists(lists.len() as _, lists.as_mut_ptr());
 
         if let Some(fence) = fence {
-            assert_eq!(winerror::S_OK,
-                self.raw.Signal(fence.raw.as_raw(), 1)
-            );
+            self.signal_fence(fence.raw.as_raw(), 1)?;
+        }
+
+        self.submission_count += 1;
+        let (progress_fence, id) = (self.progress_fence, self.submission_count);
+        self.signal_fence(progress_fence, id)?;
+
+        Ok(hal::queue::SubmissionId(id))
+    }
+
+    unsafe fn bind_sparse(
+        &mut self,
+        binding: hal::queue::RawSparseBinding<Backend>,
+        fence: Option<&native::Fence>,
+    ) -> hal::SubmissionResult<()> {
+        let tile_size = d3d12::D3D12_TILED_RESOURCE_TILE_SIZE_IN_BYTES as u64;
+        let linear_region = |size: u64| d3d12::D3D12_TILE_REGION_SIZE {
+            NumTiles: ((size + tile_size - 1) / tile_size) as _,
+            UseBox: FALSE,
+            Width: 0,
+            Height: 0,
+            Depth: 0,
+        };
+
+        // Semaphores are fences signalled with 1, waiting resets them to 0 for reuse.
+        for semaphore in binding.wait_semaphores {
+            self.wait_fence(semaphore.raw.as_raw(), 1)?;
+            self.signal_fence(semaphore.raw.as_raw(), 0)?;
+        }
+
+        for &(buffer, binds) in binding.buffers {
+            for bind in binds {
+                let coordinate = d3d12::D3D12_TILED_RESOURCE_COORDINATE {
+                    X: (bind.resource_offset / tile_size) as _,
+                    Y: 0,
+                    Z: 0,
+                    Subresource: 0,
+                };
+                self.update_tile_mapping(buffer.resource, coordinate, linear_region(bind.size), bind.memory);
+            }
+        }
+
+        for &(image, binds) in binding.image_opaques {
+            let device = Self::resource_device(image.resource);
+            let tiling = image.tiling(&device);
+            for bind in binds {
+                let tile = (bind.resource_offset / tile_size) as _;
+                let coordinate = tiling.coordinate(image.num_levels, image.num_layers, tile);
+                self.update_tile_mapping(image.resource, coordinate, linear_region(bind.size), bind.memory);
+            }
+        }
+
+        for &(image, binds) in binding.images {
+            let device = Self::resource_device(image.resource);
+            let shape = image.tiling(&device).tile_shape;
+            for bind in binds {
+                let tiles = |texels: u32, tile: u32| (texels + tile - 1) / tile;
+                let width = tiles(bind.extent.width, shape.WidthInTexels);
+                let height = tiles(bind.extent.height, shape.HeightInTexels);
+                let depth = tiles(bind.extent.depth, shape.DepthInTexels);
+                let coordinate = d3d12::D3D12_TILED_RESOURCE_COORDINATE {
+                    X: bind.offset.x as u32 / shape.WidthInTexels,
+                    Y: bind.offset.y as u32 / shape.HeightInTexels,
+                    Z: bind.offset.z as u32 / shape.DepthInTexels,
+                    Subresource: image.calc_subresource(
+                        bind.subresource.0 as _,
+                        bind.subresource.1 as _,
+                        0,
+                    ),
+                };
+                let size = d3d12::D3D12_TILE_REGION_SIZE {
+                    NumTiles: width * height * depth,
+                    UseBox: TRUE,
+                    Width: width,
+                    Height: height as _,
+                    Depth: depth as _,
+                };
+                self.update_tile_mapping(image.resource, coordinate, size, bind.memory);
+            }
+        }
+
+        for semaphore in binding.signal_semaphores {
+            self.signal_fence(semaphore.raw.as_raw(), 1)?;
+        }
+
+        if let Some(fence) = fence {
+            self.signal_fence(fence.raw.as_raw(), 1)?;
         }
+
+        Ok(())
     }
 
-    fn present<IS, IW>(&mut self, swapchains: IS, _wait_semaphores: IW)
+    fn present<IS, IW>(
+        &mut self, swapchains: IS, _wait_semaphores: IW,
+    ) -> Result<hal::PresentStatus, hal::PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<window::Swapchain>,
@@ -379,8 +665,22 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
     {
         // TODO: semaphores
         for swapchain in swapchains {
-            unsafe { swapchain.borrow().inner.Present(1, 0); }
+            let hr = unsafe { swapchain.borrow().inner.Present(1, 0) };
+            match hr {
+                // An occluded window doesn't require any action from the user.
+                winerror::S_OK | winerror::DXGI_STATUS_OCCLUDED => {}
+                winerror::DXGI_ERROR_DEVICE_REMOVED |
+                winerror::DXGI_ERROR_DEVICE_RESET => return Err(hal::PresentError::DeviceLost),
+                winerror::E_OUTOFMEMORY => return Err(hal::PresentError::OutOfMemory(
+                    hal::device::OutOfMemory::OutOfHostMemory,
+                )),
+                _ => panic!("Present failed: {:x}", hr),
+            }
         }
+
+        // DXGI doesn't report swapchains mismatching the window size,
+        // the buffers are stretched on presentation instead.
+        Ok(hal::PresentStatus::Optimal)
     }
 
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
@@ -579,6 +879,7 @@ impl Drop for Device {
             unsafe {
                 (*queue.idle_fence).Release();
                 handleapi::CloseHandle(queue.idle_event);
+                (*queue.progress_fence).Release();
             }
         }
     }
@@ -709,6 +1010,20 @@ impl hal::Instance for Instance {
 
             let heterogeneous_resource_heaps = features.ResourceHeapTier != d3d12::D3D12_RESOURCE_HEAP_TIER_1;
 
+            let sparse_features = match features.TiledResourcesTier {
+                d3d12::D3D12_TILED_RESOURCES_TIER_NOT_SUPPORTED => Features::empty(),
+                tier => {
+                    let mut sparse = Features::SPARSE_BINDING |
+                        Features::SPARSE_RESIDENCY_BUFFER |
+                        Features::SHADER_RESIDENCY_IMAGE_2D |
+                        Features::SPARSE_RESIDENCY_ALIASED;
+                    if tier >= d3d12::D3D12_TILED_RESOURCES_TIER_3 {
+                        sparse |= Features::SHADER_RESIDENSY_IMAGE_3D;
+                    }
+                    sparse
+                }
+            };
+
             let uma = features_architecture.UMA == TRUE;
             let cc_uma = features_architecture.CacheCoherentUMA == TRUE;
 
@@ -844,16 +1159,19 @@ impl hal::Instance for Instance {
 
             let physical_device = PhysicalDevice {
                 adapter,
+                device,
                 features:
                     // TODO: add more features, based on
                     // https://msdn.microsoft.com/de-de/library/windows/desktop/mt186615(v=vs.85).aspx
                     Features::IMAGE_CUBE_ARRAY |
+                    Features::INDEPENDENT_BLENDING |
                     Features::GEOMETRY_SHADER |
                     Features::TESSELLATION_SHADER |
                     //logic_op: false, // Optional on feature level 11_0
                     Features::MULTI_DRAW_INDIRECT |
                     Features::FORMAT_BC |
-                    Features::INSTANCE_RATE,
+                    Features::INSTANCE_RATE |
+                    sparse_features,
                 limits: Limits { // TODO
                     max_texture_size: 0,
                     max_patch_size: 0,
@@ -871,6 +1189,8 @@ impl hal::Instance for Instance {
                     min_buffer_copy_offset_alignment: d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as _,
                     min_buffer_copy_pitch_alignment: d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as _,
                     min_uniform_buffer_offset_alignment: 256, // Required alignment for CBVs
+                    buffer_image_granularity: 1,
+                    non_coherent_atom_size: 1,
                 },
                 private_caps: Capabilities {
                     heterogeneous_resource_heaps,
@@ -926,8 +1246,10 @@ impl hal::Backend for Backend {
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
+    type PipelineCache = native::PipelineCache;
     type PipelineLayout = native::PipelineLayout;
     type DescriptorSetLayout = native::DescriptorSetLayout;
+    type DescriptorUpdateTemplate = native::DescriptorUpdateTemplate;
     type DescriptorPool = native::DescriptorPool;
     type DescriptorSet = native::DescriptorSet;
 
@@ -8,6 +8,7 @@ use {free_list, Backend, MAX_VERTEX_BUFFERS};
 use root_constants::RootConstant;
 
 use std::collections::BTreeMap;
+use std::mem;
 use std::ops::Range;
 
 // ShaderModule is either a precompiled if the source comes from HLSL or
@@ -112,6 +113,19 @@ bitflags! {
 pub const SRV_CBV_UAV: SetTableTypes = SetTableTypes::SRV_CBV_UAV;
 pub const SAMPLERS: SetTableTypes = SetTableTypes::SAMPLERS;
 
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PipelineCache {
+    // `None` if pipeline libraries aren't supported by the device.
+    #[derivative(Debug="ignore")]
+    pub(crate) library: Option<ComPtr<d3d12::ID3D12PipelineLibrary>>,
+    // Serialized library the pipeline library was created from,
+    // which needs to outlive the library.
+    pub(crate) data: Vec<u8>,
+}
+unsafe impl Send for PipelineCache { }
+unsafe impl Sync for PipelineCache { }
+
 #[derive(Debug, Hash)]
 pub struct PipelineLayout {
     pub(crate) raw: *mut d3d12::ID3D12RootSignature,
@@ -157,6 +171,7 @@ pub struct Image {
     pub(crate) block_dim: (u8, u8),
     pub(crate) num_levels: image::Level,
     pub(crate) num_layers: image::Layer,
+    pub(crate) aspects: format::Aspects,
     #[derivative(Debug="ignore")]
     pub(crate) clear_cv: Option<d3d12::D3D12_CPU_DESCRIPTOR_HANDLE>,
     #[derivative(Debug="ignore")]
@@ -180,6 +195,76 @@ impl Image {
     pub fn calc_subresource(&self, mip_level: UINT, layer: UINT, plane: UINT) -> UINT {
         mip_level + (layer * self.num_levels as UINT) + (plane * self.num_levels as UINT * self.num_layers as UINT)
     }
+
+    /// Query the tile layout of a reserved (sparse) image.
+    pub(crate) fn tiling(&self, device: &d3d12::ID3D12Device) -> ResourceTiling {
+        let mut num_tiles = 0;
+        let mut packed_mips = unsafe { mem::zeroed() };
+        let mut tile_shape = unsafe { mem::zeroed() };
+        let mut num_subresources = self.num_levels as UINT * self.num_layers as UINT;
+        let mut subresources = Vec::with_capacity(num_subresources as usize);
+        unsafe {
+            device.GetResourceTiling(
+                self.resource,
+                &mut num_tiles,
+                &mut packed_mips,
+                &mut tile_shape,
+                &mut num_subresources,
+                0,
+                subresources.as_mut_ptr(),
+            );
+            subresources.set_len(num_subresources as usize);
+        }
+
+        ResourceTiling {
+            num_tiles,
+            packed_mips,
+            tile_shape,
+            subresources,
+        }
+    }
+}
+
+/// Tile layout of a reserved resource.
+pub(crate) struct ResourceTiling {
+    pub num_tiles: UINT,
+    pub packed_mips: d3d12::D3D12_PACKED_MIP_INFO,
+    pub tile_shape: d3d12::D3D12_TILE_SHAPE,
+    pub subresources: Vec<d3d12::D3D12_SUBRESOURCE_TILING>,
+}
+
+impl ResourceTiling {
+    /// Get the coordinate of a tile, given its index in the whole resource.
+    pub fn coordinate(&self, num_levels: image::Level, num_layers: image::Layer, tile: UINT) -> d3d12::D3D12_TILED_RESOURCE_COORDINATE {
+        let num_standard_mips = self.packed_mips.NumStandardMips as UINT;
+        for (index, sub) in self.subresources.iter().enumerate() {
+            if index as UINT % num_levels as UINT >= num_standard_mips {
+                continue;
+            }
+            let row = sub.WidthInTiles;
+            let slice = row * sub.HeightInTiles as UINT;
+            let start = sub.StartTileIndexInOverallResource;
+            if tile >= start && tile < start + slice * sub.DepthInTiles as UINT {
+                let offset = tile - start;
+                return d3d12::D3D12_TILED_RESOURCE_COORDINATE {
+                    X: offset % row,
+                    Y: (offset % slice) / row,
+                    Z: offset / slice,
+                    Subresource: index as UINT,
+                };
+            }
+        }
+
+        // The packed mips of each array layer follow the standard mips of the layer.
+        let tiles_per_layer = self.num_tiles / num_layers as UINT;
+        let layer = tile / tiles_per_layer;
+        d3d12::D3D12_TILED_RESOURCE_COORDINATE {
+            X: tile - layer * tiles_per_layer - self.packed_mips.StartTileIndexInOverallResource,
+            Y: 0,
+            Z: 0,
+            Subresource: layer * num_levels as UINT + num_standard_mips,
+        }
+    }
 }
 
 #[derive(Copy, Derivative, Clone)]
@@ -204,11 +289,17 @@ unsafe impl Sync for ImageView { }
 pub struct Sampler {
     #[derivative(Debug="ignore")]
     pub(crate) handle: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
+    #[derivative(Debug="ignore")]
+    pub(crate) desc: d3d12::D3D12_SAMPLER_DESC,
 }
 
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct DescriptorSetLayout {
     pub(crate) bindings: Vec<pso::DescriptorSetLayoutBinding>,
+    // Immutable samplers are turned into static samplers of the root signature.
+    #[derivative(Debug="ignore")]
+    pub(crate) immutable_samplers: Vec<d3d12::D3D12_SAMPLER_DESC>,
 }
 
 #[derive(Derivative)]
@@ -268,6 +359,11 @@ pub struct DescriptorBindingInfo {
     pub(crate) is_uav: bool,
 }
 
+#[derive(Debug)]
+pub struct DescriptorUpdateTemplate {
+    pub(crate) entries: Vec<pso::DescriptorUpdateTemplateEntry>,
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct DescriptorSet {
@@ -364,19 +460,44 @@ pub struct DescriptorHeapSlice {
     pub(crate) range: Range<u64>,
     pub(crate) start: DualHandle,
     pub(crate) handle_size: u64,
-    pub(crate) next: u64,
+    /// Allocator for the handles of the slice, relative to the start of `range`.
+    pub(crate) allocator: free_list::Allocator,
 }
 
 impl DescriptorHeapSlice {
-    pub(crate) fn alloc_handles(&mut self, count: u64) -> DualHandle {
-        assert!(self.next + count <= self.range.end);
-        let index = self.next;
-        self.next += count;
-        DualHandle {
-            cpu: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE { ptr: self.start.cpu.ptr + (self.handle_size * index) as usize },
-            gpu: d3d12::D3D12_GPU_DESCRIPTOR_HANDLE { ptr: self.start.gpu.ptr + (self.handle_size * index) as u64 },
+    pub(crate) fn new(
+        heap: ComPtr<d3d12::ID3D12DescriptorHeap>,
+        range: Range<u64>,
+        start: DualHandle,
+        handle_size: u64,
+    ) -> Self {
+        DescriptorHeapSlice {
+            heap,
+            allocator: free_list::Allocator::new(range.end - range.start),
+            range,
+            start,
+            handle_size,
         }
     }
+
+    pub(crate) fn alloc_handles(&mut self, count: u64) -> Option<DualHandle> {
+        self.allocator.allocate(count).map(|allocated| {
+            let index = self.range.start + allocated.start;
+            DualHandle {
+                cpu: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE { ptr: self.start.cpu.ptr + (self.handle_size * index) as usize },
+                gpu: d3d12::D3D12_GPU_DESCRIPTOR_HANDLE { ptr: self.start.gpu.ptr + (self.handle_size * index) as u64 },
+            }
+        })
+    }
+
+    pub(crate) fn free_handles(&mut self, handle: DualHandle, count: u64) {
+        let start = (handle.gpu.ptr - self.start.gpu.ptr) / self.handle_size - self.range.start;
+        self.allocator.deallocate(start .. start + count);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.allocator = free_list::Allocator::new(self.range.end - self.range.start);
+    }
 }
 
 #[derive(Debug)]
@@ -389,8 +510,21 @@ pub struct DescriptorPool {
 unsafe impl Send for DescriptorPool {}
 unsafe impl Sync for DescriptorPool {}
 
+impl DescriptorPool {
+    fn free_binding_infos(&mut self, binding_infos: &[DescriptorBindingInfo]) {
+        for info in binding_infos {
+            if let Some(ref range) = info.view_range {
+                self.heap_srv_cbv_uav.free_handles(range.handle, range.count);
+            }
+            if let Some(ref range) = info.sampler_range {
+                self.heap_sampler.free_handles(range.handle, range.count);
+            }
+        }
+    }
+}
+
 impl HalDescriptorPool<Backend> for DescriptorPool {
-    fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> DescriptorSet {
+    fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet, pso::AllocationError> {
         let mut binding_infos = Vec::new();
         let mut first_gpu_sampler = None;
         let mut first_gpu_view = None;
@@ -414,49 +548,71 @@ impl HalDescriptorPool<Backend> for DescriptorPool {
             }
             binding_infos[binding.binding as usize] = DescriptorBindingInfo {
                 count: binding.count as _,
-                view_range: if has_view {
-                    let handle = self.heap_srv_cbv_uav.alloc_handles(binding.count as u64);
-                    if first_gpu_view.is_none() {
-                        first_gpu_view = Some(handle.gpu);
-                    }
-                    Some(DescriptorRange {
-                        handle,
-                        ty: binding.ty,
-                        count: binding.count as _,
-                        handle_size: self.heap_srv_cbv_uav.handle_size,
-                    })
-                } else {
-                    None
-                },
-                sampler_range: if has_sampler {
-                    let handle = self.heap_sampler.alloc_handles(binding.count as u64);
-                    if first_gpu_sampler.is_none() {
-                        first_gpu_sampler = Some(handle.gpu);
-                    }
-                    Some(DescriptorRange {
-                        handle,
-                        ty: binding.ty,
-                        count: binding.count as _,
-                        handle_size: self.heap_sampler.handle_size,
-                    })
-                } else {
-                    None
-                },
+                view_range: None,
+                sampler_range: None,
                 is_uav,
             };
+
+            if has_view {
+                let handle = match self.heap_srv_cbv_uav.alloc_handles(binding.count as u64) {
+                    Some(handle) => handle,
+                    None => {
+                        self.free_binding_infos(&binding_infos);
+                        return Err(pso::AllocationError::OutOfPoolMemory);
+                    }
+                };
+                if first_gpu_view.is_none() {
+                    first_gpu_view = Some(handle.gpu);
+                }
+                binding_infos[binding.binding as usize].view_range = Some(DescriptorRange {
+                    handle,
+                    ty: binding.ty,
+                    count: binding.count as _,
+                    handle_size: self.heap_srv_cbv_uav.handle_size,
+                });
+            }
+
+            if has_sampler && !binding.immutable_samplers {
+                let handle = match self.heap_sampler.alloc_handles(binding.count as u64) {
+                    Some(handle) => handle,
+                    None => {
+                        self.free_binding_infos(&binding_infos);
+                        return Err(pso::AllocationError::OutOfPoolMemory);
+                    }
+                };
+                if first_gpu_sampler.is_none() {
+                    first_gpu_sampler = Some(handle.gpu);
+                }
+                binding_infos[binding.binding as usize].sampler_range = Some(DescriptorRange {
+                    handle,
+                    ty: binding.ty,
+                    count: binding.count as _,
+                    handle_size: self.heap_sampler.handle_size,
+                });
+            }
         }
 
-        DescriptorSet {
+        Ok(DescriptorSet {
             heap_srv_cbv_uav: self.heap_srv_cbv_uav.heap.clone(),
             heap_samplers: self.heap_sampler.heap.clone(),
             binding_infos,
             first_gpu_sampler,
             first_gpu_view,
+        })
+    }
+
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        for set in descriptor_sets {
+            self.free_binding_infos(&set.binding_infos);
         }
     }
 
     fn reset(&mut self) {
-        unimplemented!()
+        self.heap_srv_cbv_uav.clear();
+        self.heap_sampler.clear();
     }
 }
 
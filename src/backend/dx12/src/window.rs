@@ -96,8 +96,13 @@ pub struct Swapchain {
 }
 
 impl hal::Swapchain<Backend> for Swapchain {
-    fn acquire_frame(&mut self, _sync: hal::FrameSync<Backend>) -> hal::Frame {
+    fn acquire_frame(
+        &mut self, _sync: hal::FrameSync<Backend>, _timeout_ms: u32,
+    ) -> Result<hal::Frame, hal::AcquireError> {
         // TODO: sync
+        // The current backbuffer is always available immediately, DXGI blocks
+        // in `Present` instead when running out of backbuffers. Acquisition
+        // therefore never waits and `_timeout_ms` has no effect.
 
         if false {
             // TODO: we need to block this at some point? (running out of backbuffers)
@@ -110,7 +115,7 @@ impl hal::Swapchain<Backend> for Swapchain {
 
         // TODO:
         let index = unsafe { self.inner.GetCurrentBackBufferIndex() };
-        hal::Frame::new(index as usize)
+        Ok(hal::Frame::new(index as usize))
     }
 }
 
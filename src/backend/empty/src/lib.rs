@@ -1,9 +1,11 @@
 //! Dummy backend implementation to test the code for compile errors
 //! outside of the graphics development environment.
+//! Resource creation, command recording and submission succeed without doing anything.
 
 extern crate gfx_hal as hal;
 
 use std::borrow::{Borrow, BorrowMut};
+use std::collections::HashMap;
 use std::ops::Range;
 use hal::{
     buffer, command, device, error, format, image, mapping,
@@ -42,10 +44,12 @@ impl hal::Backend for Backend {
 
     type ComputePipeline = ();
     type GraphicsPipeline = ();
+    type PipelineCache = ();
     type PipelineLayout = ();
     type DescriptorSetLayout = ();
     type DescriptorPool = DescriptorPool;
     type DescriptorSet = ();
+    type DescriptorUpdateTemplate = ();
 
     type Fence = ();
     type Semaphore = ();
@@ -58,38 +62,74 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
     fn open(
         &self, _: Vec<(&QueueFamily, Vec<hal::QueuePriority>)>
     ) -> Result<hal::Gpu<Backend>, error::DeviceCreationError> {
-        unimplemented!()
+        let mut group = hal::backend::RawQueueGroup::new(QueueFamily);
+        group.add_queue(RawCommandQueue);
+        let mut queues = HashMap::new();
+        queues.insert(queue::QueueFamilyId(0), group);
+        Ok(hal::Gpu {
+            device: Device,
+            queues: queue::Queues::new(queues),
+        })
     }
 
     fn format_properties(&self, _: Option<format::Format>) -> format::Properties {
+        format::Properties {
+            linear_tiling: format::ImageFeature::empty(),
+            optimal_tiling: format::ImageFeature::empty(),
+            buffer_features: format::BufferFeature::empty(),
+        }
+    }
+
+    fn image_format_properties(
+        &self, _: format::Format, _: u8, _: image::Tiling, _: image::Usage, _: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
         unimplemented!()
     }
 
     fn memory_properties(&self) -> hal::MemoryProperties {
-        unimplemented!()
+        hal::MemoryProperties {
+            memory_types: vec![hal::MemoryType {
+                properties: memory::Properties::all(),
+                heap_index: 0,
+            }],
+            memory_heaps: vec![!0],
+        }
     }
 
     fn features(&self) -> hal::Features {
-        unimplemented!()
+        hal::Features::empty()
     }
 
     fn limits(&self) -> hal::Limits {
-        unimplemented!()
+        hal::Limits {
+            max_compute_group_count: [!0; 3],
+            max_compute_group_size: [!0; 3],
+            min_buffer_copy_offset_alignment: 1,
+            min_buffer_copy_pitch_alignment: 1,
+            min_uniform_buffer_offset_alignment: 1,
+            buffer_image_granularity: 1,
+            non_coherent_atom_size: 1,
+            .. hal::Limits::default()
+        }
     }
 }
 
 /// Dummy command queue doing nothing.
 pub struct RawCommandQueue;
 impl queue::RawCommandQueue<Backend> for RawCommandQueue {
-    unsafe fn submit_raw<IC>(&mut self, _: queue::RawSubmission<Backend, IC>, _: Option<&()>)
+    unsafe fn submit_raw<IC>(&mut self, _: queue::RawSubmission<Backend, IC>, _: Option<&()>) -> hal::SubmissionResult<queue::SubmissionId>
     where
         IC: IntoIterator,
         IC::Item: Borrow<RawCommandBuffer>,
     {
+        Ok(queue::SubmissionId(0))
+    }
+
+    unsafe fn bind_sparse(&mut self, _: queue::RawSparseBinding<Backend>, _: Option<&()>) -> hal::SubmissionResult<()> {
         unimplemented!()
     }
 
-    fn present<IS, IW>(&mut self, _: IS, _: IW)
+    fn present<IS, IW>(&mut self, _: IS, _: IW) -> Result<hal::PresentStatus, hal::PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<Swapchain>,
@@ -100,23 +140,21 @@ impl queue::RawCommandQueue<Backend> for RawCommandQueue {
     }
 
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
-        unimplemented!()
+        Ok(())
     }
 }
 
 /// Dummy device doing nothing.
 pub struct Device;
 impl hal::Device<Backend> for Device {
-    fn create_command_pool(&self, _: queue::QueueFamilyId, _: pool::CommandPoolCreateFlags) -> RawCommandPool {
-        unimplemented!()
+    fn create_command_pool(&self, _: queue::QueueFamilyId, _: pool::CommandPoolCreateFlags) -> Result<RawCommandPool, device::OutOfMemory> {
+        Ok(RawCommandPool)
     }
 
-    fn destroy_command_pool(&self, _: RawCommandPool) {
-        unimplemented!()
-    }
+    fn destroy_command_pool(&self, _: RawCommandPool) {}
 
     fn allocate_memory(&self, _: hal::MemoryTypeId, _: u64) -> Result<(), device::OutOfMemory> {
-        unimplemented!()
+        Ok(())
     }
 
     fn create_render_pass<'a ,IA, IS, ID>(&self, _: IA, _: IS, _: ID) -> ()
@@ -138,7 +176,7 @@ impl hal::Device<Backend> for Device {
         IR: IntoIterator,
         IR::Item: Borrow<(pso::ShaderStageFlags, Range<u32>)>,
     {
-        unimplemented!()
+        ()
     }
 
     fn create_framebuffer<I>(
@@ -152,42 +190,68 @@ impl hal::Device<Backend> for Device {
     }
 
     fn create_shader_module(&self, _: &[u8]) -> Result<(), device::ShaderError> {
-        unimplemented!()
+        Ok(())
     }
 
-    fn create_sampler(&self, _: image::SamplerInfo) -> () {
+    fn create_sampler(&self, _: image::SamplerInfo) -> Result<(), device::OutOfMemory> {
         unimplemented!()
     }
     fn create_buffer(&self, _: u64, _: buffer::Usage) -> Result<(), buffer::CreationError> {
-        unimplemented!()
+        Ok(())
     }
 
     fn get_buffer_requirements(&self, _: &()) -> memory::Requirements {
-        unimplemented!()
+        memory::Requirements { size: 0, alignment: 1, type_mask: !0 }
     }
 
     fn bind_buffer_memory(&self, _: &(), _: u64, _: ()) -> Result<(), device::BindError> {
+        Ok(())
+    }
+
+    fn create_sparse_buffer(&self, _: u64, _: buffer::Usage, _: memory::SparseFlags) -> Result<(), buffer::CreationError> {
         unimplemented!()
     }
 
-    fn create_buffer_view<R: RangeArg<u64>>(&self, _: &(), _: Option<format::Format>, _: R) -> Result<(), buffer::ViewError> {
+    fn get_sparse_buffer_requirements(&self, _: &()) -> memory::Requirements {
         unimplemented!()
     }
 
-    fn create_image(&self, _: image::Kind, _: image::Level, _: format::Format, _: image::Usage)
-         -> Result<(), image::CreationError>
-    {
+    fn create_buffer_view<R: RangeArg<u64>>(&self, _: &(), _: Option<format::Format>, _: R) -> Result<(), buffer::ViewError> {
         unimplemented!()
     }
 
+    fn create_image(
+        &self, _: image::Kind, _: image::Level, _: format::Format, _: image::Tiling, _: image::Usage, _: image::StorageFlags,
+    ) -> Result<(), image::CreationError> {
+        Ok(())
+    }
+
     fn get_image_requirements(&self, _: &()) -> memory::Requirements {
+        memory::Requirements { size: 0, alignment: 1, type_mask: !0 }
+    }
+
+    fn create_sparse_image(
+        &self, _: image::Kind, _: image::Level, _: format::Format, _: image::Usage, _: image::StorageFlags, _: memory::SparseFlags,
+    ) -> Result<(), image::CreationError> {
         unimplemented!()
     }
 
-    fn bind_image_memory(&self, _: &(), _: u64, _: ()) -> Result<(), device::BindError> {
+    fn get_sparse_image_requirements(&self, _: &()) -> memory::Requirements {
+        unimplemented!()
+    }
+
+    fn get_sparse_image_page_layout(&self, _: &()) -> Vec<image::SparsePageLayout> {
+        unimplemented!()
+    }
+
+    fn get_image_subresource_footprint(&self, _: &(), _: format::Aspects, _: image::Subresource) -> image::SubresourceFootprint {
         unimplemented!()
     }
 
+    fn bind_image_memory(&self, _: &(), _: u64, _: ()) -> Result<(), device::BindError> {
+        Ok(())
+    }
+
     fn create_image_view(
         &self,
         _: &(),
@@ -195,23 +259,27 @@ impl hal::Device<Backend> for Device {
         _: format::Swizzle,
         _: image::SubresourceRange,
     ) -> Result<(), image::ViewError> {
-        unimplemented!()
+        Ok(())
     }
 
-    fn create_descriptor_pool<I>(&self, _: usize, _: I) -> DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self, _: usize, _: I, _: pso::DescriptorPoolCreateFlags,
+    ) -> Result<DescriptorPool, device::OutOfMemory>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
     {
-        unimplemented!()
+        Ok(DescriptorPool)
     }
 
-    fn create_descriptor_set_layout<I>(&self, _: I) -> ()
+    fn create_descriptor_set_layout<I, J>(&self, _: I, _: J) -> ()
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<()>,
     {
-        unimplemented!()
+        ()
     }
 
     fn write_descriptor_sets<'a, I, J>(&self, _: I)
@@ -219,27 +287,51 @@ impl hal::Device<Backend> for Device {
         I: IntoIterator<Item = pso::DescriptorSetWrite<'a, Backend, J>>,
         J: IntoIterator,
         J::Item: Borrow<pso::Descriptor<'a, Backend>>,
+    {}
+
+    fn copy_descriptor_sets<'a, I>(&self, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetCopy<'a, Backend>>
     {
         unimplemented!()
     }
 
-    fn copy_descriptor_sets<'a, I>(&self, _: I)
+    fn create_descriptor_update_template<I>(&self, _: &(), _: I) -> ()
     where
         I: IntoIterator,
-        I::Item: Borrow<pso::DescriptorSetCopy<'a, Backend>>
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>,
     {
         unimplemented!()
     }
 
-    fn create_semaphore(&self) -> () {
+    fn destroy_descriptor_update_template(&self, _: ()) {
         unimplemented!()
     }
 
-    fn create_fence(&self, _: bool) -> () {
+    unsafe fn update_descriptor_set_with_template(&self, _: &(), _: &(), _: &[u8]) {
         unimplemented!()
     }
 
-    fn get_fence_status(&self, _: &()) -> bool {
+    fn create_semaphore(&self) -> Result<(), device::OutOfMemory> {
+        Ok(())
+    }
+
+    fn create_fence(&self, _: bool) -> Result<(), device::OutOfMemory> {
+        Ok(())
+    }
+
+    fn reset_fence(&self, _: &()) {}
+
+    fn wait_for_fence(&self, _: &(), _: u32) -> Result<bool, device::DeviceLost> {
+        Ok(true)
+    }
+
+    fn get_fence_status(&self, _: &()) -> Result<bool, device::DeviceLost> {
+        Ok(true)
+    }
+
+    fn get_submission_status(&self, _: &RawCommandQueue, _: queue::SubmissionId) -> Result<bool, device::DeviceLost> {
         unimplemented!()
     }
 
@@ -277,62 +369,61 @@ impl hal::Device<Backend> for Device {
         unimplemented!()
     }
 
-    fn free_memory(&self, _: ()) {
-        unimplemented!()
-    }
+    fn free_memory(&self, _: ()) {}
 
-    fn destroy_shader_module(&self, _: ()) {
-        unimplemented!()
-    }
+    fn destroy_shader_module(&self, _: ()) {}
 
     fn destroy_render_pass(&self, _: ()) {
         unimplemented!()
     }
 
-    fn destroy_pipeline_layout(&self, _: ()) {
+    fn destroy_pipeline_layout(&self, _: ()) {}
+    fn create_pipeline_cache(&self, _: Option<&[u8]>) -> Result<(), device::OutOfMemory> {
         unimplemented!()
     }
-    fn destroy_graphics_pipeline(&self, _: ()) {
+    fn get_pipeline_cache_data(&self, _: &()) -> Result<Vec<u8>, device::OutOfMemory> {
         unimplemented!()
     }
-    fn destroy_compute_pipeline(&self, _: ()) {
+    fn merge_pipeline_caches<I>(&self, _: &(), _: I) -> Result<(), device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<()>,
+    {
         unimplemented!()
     }
-    fn destroy_framebuffer(&self, _: ()) {
+    fn destroy_pipeline_cache(&self, _: ()) {
         unimplemented!()
     }
-
-    fn destroy_buffer(&self, _: ()) {
+    fn destroy_graphics_pipeline(&self, _: ()) {
         unimplemented!()
     }
-    fn destroy_buffer_view(&self, _: ()) {
-        unimplemented!()
+    fn create_compute_pipeline<'a>(
+        &self, _: &pso::ComputePipelineDesc<'a, Backend>, _: Option<&()>,
+    ) -> Result<(), pso::CreationError> {
+        Ok(())
     }
-    fn destroy_image(&self, _: ()) {
+    fn destroy_compute_pipeline(&self, _: ()) {}
+    fn destroy_framebuffer(&self, _: ()) {
         unimplemented!()
     }
-    fn destroy_image_view(&self, _: ()) {
+
+    fn destroy_buffer(&self, _: ()) {}
+    fn destroy_buffer_view(&self, _: ()) {
         unimplemented!()
     }
+    fn destroy_image(&self, _: ()) {}
+    fn destroy_image_view(&self, _: ()) {}
     fn destroy_sampler(&self, _: ()) {
         unimplemented!()
     }
 
-    fn destroy_descriptor_pool(&self, _: DescriptorPool) {
-        unimplemented!()
-    }
+    fn destroy_descriptor_pool(&self, _: DescriptorPool) {}
 
-    fn destroy_descriptor_set_layout(&self, _: ()) {
-        unimplemented!()
-    }
+    fn destroy_descriptor_set_layout(&self, _: ()) {}
 
-    fn destroy_fence(&self, _: ()) {
-        unimplemented!()
-    }
+    fn destroy_fence(&self, _: ()) {}
 
-    fn destroy_semaphore(&self, _: ()) {
-        unimplemented!()
-    }
+    fn destroy_semaphore(&self, _: ()) {}
 
     fn create_swapchain(
         &self,
@@ -343,6 +434,10 @@ impl hal::Device<Backend> for Device {
     }
 
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
+        Ok(())
+    }
+
+    fn set_object_name(&self, _: device::NamedObject<Backend>, _: &str) {
         unimplemented!()
     }
 }
@@ -351,47 +446,37 @@ impl hal::Device<Backend> for Device {
 pub struct QueueFamily;
 impl queue::QueueFamily for QueueFamily {
     fn queue_type(&self) -> hal::QueueType {
-        unimplemented!()
+        hal::QueueType::General
     }
     fn max_queues(&self) -> usize {
-        unimplemented!()
+        1
     }
     fn id(&self) -> queue::QueueFamilyId {
-        unimplemented!()
+        queue::QueueFamilyId(0)
     }
 }
 
 /// Dummy raw command pool.
 pub struct RawCommandPool;
 impl pool::RawCommandPool<Backend> for RawCommandPool {
-    fn reset(&mut self) {
-        unimplemented!()
-    }
+    fn reset(&mut self) {}
 
-    fn allocate(&mut self, _: usize, _: command::RawLevel) -> Vec<RawCommandBuffer> {
-        unimplemented!()
+    fn allocate(&mut self, num: usize, _: command::RawLevel) -> Vec<RawCommandBuffer> {
+        vec![RawCommandBuffer; num]
     }
 
-    unsafe fn free(&mut self, _: Vec<RawCommandBuffer>) {
-        unimplemented!()
-    }
+    unsafe fn free(&mut self, _: Vec<RawCommandBuffer>) {}
 }
 
 /// Dummy command buffer, which ignores all the calls.
 #[derive(Clone)]
 pub struct RawCommandBuffer;
 impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
-    fn begin(&mut self, _: command::CommandBufferFlags) {
-        unimplemented!()
-    }
+    fn begin(&mut self, _: command::CommandBufferFlags, _: command::CommandBufferInheritanceInfo<Backend>) {}
 
-    fn finish(&mut self) {
-        unimplemented!()
-    }
+    fn finish(&mut self) {}
 
-    fn reset(&mut self, _: bool) {
-        unimplemented!()
-    }
+    fn reset(&mut self, _: bool) {}
 
     fn pipeline_barrier<'a, T>(
         &mut self,
@@ -401,17 +486,11 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<memory::Barrier<'a, Backend>>,
-    {
-        unimplemented!()
-    }
+    {}
 
-    fn fill_buffer(&mut self, _: &(), _: Range<buffer::Offset>, _: u32) {
-        unimplemented!()
-    }
+    fn fill_buffer(&mut self, _: &(), _: Range<buffer::Offset>, _: u32) {}
 
-    fn update_buffer(&mut self, _: &(), _: buffer::Offset, _: &[u8]) {
-        unimplemented!()
-    }
+    fn update_buffer(&mut self, _: &(), _: buffer::Offset, _: &[u8]) {}
 
     fn clear_color_image_raw(
         &mut self,
@@ -419,9 +498,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         _: image::ImageLayout,
         _: image::SubresourceRange,
         _: command::ClearColorRaw,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn clear_depth_stencil_image_raw(
         &mut self,
@@ -429,9 +506,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         _: image::ImageLayout,
         _: image::SubresourceRange,
         _: command::ClearDepthStencilRaw,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn clear_attachments<T, U>(&mut self, _: T, _: U)
     where
@@ -439,9 +514,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         T::Item: Borrow<command::AttachmentClear>,
         U: IntoIterator,
         U::Item: Borrow<command::Rect>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn resolve_image<T>(
         &mut self,
@@ -453,9 +526,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::ImageResolve>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn blit_image<T>(
         &mut self,
@@ -468,43 +539,29 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::ImageBlit>,
-    {
-        unimplemented!()
-    }
+    {}
 
-    fn bind_index_buffer(&mut self, _: buffer::IndexBufferView<Backend>) {
-        unimplemented!()
-    }
+    fn bind_index_buffer(&mut self, _: buffer::IndexBufferView<Backend>) {}
 
-    fn bind_vertex_buffers(&mut self, _: pso::VertexBufferSet<Backend>) {
-        unimplemented!()
-    }
+    fn bind_vertex_buffers(&mut self, _: pso::VertexBufferSet<Backend>) {}
 
     fn set_viewports<T>(&mut self, _: T)
     where
         T: IntoIterator,
         T::Item: Borrow<command::Viewport>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn set_scissors<T>(&mut self, _: T)
     where
         T: IntoIterator,
         T::Item: Borrow<command::Rect>,
-    {
-        unimplemented!()
-    }
+    {}
 
 
-    fn set_stencil_reference(&mut self, _: command::StencilValue, _: command::StencilValue) {
-        unimplemented!()
-    }
+    fn set_stencil_reference(&mut self, _: command::StencilValue, _: command::StencilValue) {}
 
 
-    fn set_blend_constants(&mut self, _: command::ColorValue) {
-        unimplemented!()
-    }
+    fn set_blend_constants(&mut self, _: command::ColorValue) {}
 
 
     fn begin_render_pass_raw<T>(
@@ -517,57 +574,37 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::ClearValueRaw>,
-    {
-        unimplemented!()
-    }
+    {}
 
-    fn next_subpass(&mut self, _: command::SubpassContents) {
-        unimplemented!()
-    }
+    fn next_subpass(&mut self, _: command::SubpassContents) {}
 
-    fn end_render_pass(&mut self) {
-        unimplemented!()
-    }
+    fn end_render_pass(&mut self) {}
 
-    fn bind_graphics_pipeline(&mut self, _: &()) {
-        unimplemented!()
-    }
+    fn bind_graphics_pipeline(&mut self, _: &()) {}
 
     fn bind_graphics_descriptor_sets<I>(&mut self, _: &(), _: usize, _: I)
     where
         I: IntoIterator,
         I::Item: Borrow<()>,
-    {
-        unimplemented!()
-    }
+    {}
 
-    fn bind_compute_pipeline(&mut self, _: &()) {
-        unimplemented!()
-    }
+    fn bind_compute_pipeline(&mut self, _: &()) {}
 
     fn bind_compute_descriptor_sets<I>(&mut self, _: &(), _: usize, _: I)
     where
         I: IntoIterator,
         I::Item: Borrow<()>,
-    {
-        unimplemented!()
-    }
+    {}
 
-    fn dispatch(&mut self, _: hal::WorkGroupCount) {
-        unimplemented!()
-    }
+    fn dispatch(&mut self, _: hal::WorkGroupCount) {}
 
-    fn dispatch_indirect(&mut self, _: &(), _: buffer::Offset) {
-        unimplemented!()
-    }
+    fn dispatch_indirect(&mut self, _: &(), _: buffer::Offset) {}
 
     fn copy_buffer<T>(&mut self, _: &(), _: &(), _: T)
     where
         T: IntoIterator,
         T::Item: Borrow<command::BufferCopy>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn copy_image<T>(
         &mut self,
@@ -579,9 +616,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::ImageCopy>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn copy_buffer_to_image<T>(
         &mut self,
@@ -592,9 +627,7 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::BufferImageCopy>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn copy_image_to_buffer<T>(
         &mut self,
@@ -605,29 +638,21 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         T: IntoIterator,
         T::Item: Borrow<command::BufferImageCopy>,
-    {
-        unimplemented!()
-    }
+    {}
 
     fn draw(&mut self,
         _: Range<hal::VertexCount>,
         _: Range<hal::InstanceCount>,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn draw_indexed(
         &mut self,
         _: Range<hal::IndexCount>,
         _: hal::VertexOffset,
         _: Range<hal::InstanceCount>,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
-    fn draw_indirect(&mut self, _: &(), _: buffer::Offset, _: u32, _: u32) {
-        unimplemented!()
-    }
+    fn draw_indirect(&mut self, _: &(), _: buffer::Offset, _: u32, _: u32) {}
 
     fn draw_indexed_indirect(
         &mut self,
@@ -635,40 +660,30 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         _: buffer::Offset,
         _: u32,
         _: u32,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn begin_query(
         &mut self,
         _: query::Query<Backend>,
         _: query::QueryControl,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn end_query(
         &mut self,
         _: query::Query<Backend>,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn reset_query_pool(
         &mut self,
         _: &(),
         _: Range<query::QueryId>,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn write_timestamp(
         &mut self,
         _: pso::PipelineStage,
         _: query::Query<Backend>,
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn push_graphics_constants(
         &mut self,
@@ -676,18 +691,14 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         _: pso::ShaderStageFlags,
         _: u32,
         _: &[u32],
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn push_compute_constants(
         &mut self,
         _: &(),
         _: u32,
         _: &[u32],
-    ) {
-        unimplemented!()
-    }
+    ) {}
 
     fn execute_commands<I>(
         &mut self,
@@ -695,19 +706,29 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) where
         I: IntoIterator,
         I::Item: Borrow<RawCommandBuffer>
-    {
-        unimplemented!()
-    }
+    {}
 
+    fn begin_debug_marker(&mut self, _: &str, _: u32) {}
+
+    fn end_debug_marker(&mut self) {}
+
+    fn insert_debug_marker(&mut self, _: &str, _: u32) {}
 }
 
 // Dummy descriptor pool.
 #[derive(Debug)]
 pub struct DescriptorPool;
 impl pso::DescriptorPool<Backend> for DescriptorPool {
-    fn reset(&mut self) {
-        unimplemented!()
+    fn allocate_set(&mut self, _: &()) -> Result<(), pso::AllocationError> {
+        Ok(())
     }
+
+    fn free_sets<I>(&mut self, _: I)
+    where
+        I: IntoIterator<Item = ()>,
+    {}
+
+    fn reset(&mut self) {}
 }
 
 /// Dummy surface.
@@ -731,7 +752,7 @@ impl hal::Surface<Backend> for Surface {
 /// Dummy swapchain.
 pub struct Swapchain;
 impl hal::Swapchain<Backend> for Swapchain {
-    fn acquire_frame(&mut self, _: hal::FrameSync<Backend>) -> hal::Frame {
+    fn acquire_frame(&mut self, _: hal::FrameSync<Backend>, _: u32) -> Result<hal::Frame, hal::AcquireError> {
         unimplemented!()
     }
 }
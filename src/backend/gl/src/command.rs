@@ -104,6 +104,13 @@ pub enum Command {
     CopyBufferToSurface(n::RawBuffer, n::Surface, command::BufferImageCopy),
     CopyTextureToBuffer(n::Texture, n::RawBuffer, command::BufferImageCopy),
     CopySurfaceToBuffer(n::Surface, n::RawBuffer, command::BufferImageCopy),
+    BindSampler(gl::types::GLuint, n::Sampler),
+
+    /// Push a debug group, the buffer slice contains the UTF-8 name.
+    PushDebugGroup(BufferSlice),
+    PopDebugGroup,
+    /// Insert a debug marker, the buffer slice contains the UTF-8 name.
+    InsertDebugMarker(BufferSlice),
 }
 
 pub type FrameBufferTarget = gl::types::GLenum;
@@ -303,6 +310,28 @@ impl RawCommandBuffer {
         slice
     }
 
+    fn bind_descriptor_sets<T>(&mut self, sets: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<n::DescriptorSet>,
+    {
+        for set in sets {
+            for &(binding, ref samplers) in &set.borrow().immutable_samplers {
+                for (i, sampler) in samplers.iter().enumerate() {
+                    let unit = binding + i as gl::types::GLuint;
+                    match *sampler {
+                        n::FatSampler::Sampler(sampler) => {
+                            self.push_cmd(Command::BindSampler(unit, sampler));
+                        }
+                        n::FatSampler::Info(_) => {
+                            warn!("Immutable sampler at unit {} requires sampler objects", unit);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn update_blend_targets(&mut self, blend_targets: &Vec<pso::ColorBlendDesc>) {
         let max_blend_slots = blend_targets.len();
 
@@ -472,7 +501,11 @@ impl RawCommandBuffer {
 }
 
 impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
-    fn begin(&mut self, _flags: hal::command::CommandBufferFlags) { // TODO: Implement flags!
+    fn begin(
+        &mut self,
+        _flags: hal::command::CommandBufferFlags,
+        _inheritance_info: hal::command::CommandBufferInheritanceInfo<Backend>,
+    ) { // TODO: Implement flags!
         if self.individual_reset {
             // Implicit buffer reset when individual reset is set.
             self.reset(false);
@@ -823,12 +856,12 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         &mut self,
         _layout: &n::PipelineLayout,
         _first_set: usize,
-        _sets: T,
+        sets: T,
     ) where
         T: IntoIterator,
         T::Item: Borrow<n::DescriptorSet>,
     {
-        // TODO
+        self.bind_descriptor_sets(sets);
     }
 
     fn bind_compute_pipeline(&mut self, pipeline: &n::ComputePipeline) {
@@ -846,12 +879,12 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         &mut self,
         _layout: &n::PipelineLayout,
         _first_set: usize,
-        _sets: T,
+        sets: T,
     ) where
         T: IntoIterator,
         T::Item: Borrow<n::DescriptorSet>,
     {
-        // TODO
+        self.bind_descriptor_sets(sets);
     }
 
     fn dispatch(&mut self, count: hal::WorkGroupCount) {
@@ -1086,6 +1119,20 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     {
         unimplemented!()
     }
+
+    fn begin_debug_marker(&mut self, name: &str, _color: u32) {
+        let name_ptr = self.add_raw(name.as_bytes());
+        self.push_cmd(Command::PushDebugGroup(name_ptr));
+    }
+
+    fn end_debug_marker(&mut self) {
+        self.push_cmd(Command::PopDebugGroup);
+    }
+
+    fn insert_debug_marker(&mut self, name: &str, _color: u32) {
+        let name_ptr = self.add_raw(name.as_bytes());
+        self.push_cmd(Command::InsertDebugMarker(name_ptr));
+    }
 }
 
 /// Avoids creating second mutable borrows of `self` by requiring mutable
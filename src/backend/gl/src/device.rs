@@ -1,6 +1,8 @@
 use std::borrow::Borrow;
 use std::cell::Cell;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
 use std::iter::repeat;
 use std::ops::Range;
 use std::{ptr, mem, slice};
@@ -10,9 +12,9 @@ use gl;
 use gl::types::{GLint, GLenum, GLfloat, GLuint};
 
 use hal::{self as c, device as d, error, image as i, memory, pass, pso, buffer, mapping, query};
-use hal::format::{ChannelType, Format, Swizzle};
+use hal::format::{Aspects, ChannelType, Format, FormatDesc, Swizzle};
 use hal::pool::CommandPoolCreateFlags;
-use hal::queue::QueueFamilyId;
+use hal::queue::{QueueFamilyId, SubmissionId};
 use hal::range::RangeArg;
 
 use spirv_cross::{glsl, spirv, ErrorCode as SpirvErrorCode};
@@ -21,6 +23,7 @@ use {Backend as B, Share, Surface, Swapchain, Starc};
 use {conv, native as n, state};
 use info::LegacyFeatures;
 use pool::{BufferMemory, OwnedBuffer, RawCommandPool};
+use queue::CommandQueue;
 
 /// Emit error during shader module creation. Used if we don't expect an error
 /// but might panic due to an exception in SPIRV-Cross.
@@ -32,6 +35,103 @@ fn gen_unexpected_error(err: SpirvErrorCode) -> d::ShaderError {
     d::ShaderError::CompilationFailed(msg)
 }
 
+const SPIRV_HEADER_WORDS: usize = 5;
+const SPIRV_OP_DECORATE: u32 = 71;
+const SPIRV_OP_TYPE_BOOL: u32 = 20;
+const SPIRV_OP_TYPE_INT: u32 = 21;
+const SPIRV_OP_TYPE_FLOAT: u32 = 22;
+const SPIRV_OP_SPEC_CONSTANT_TRUE: u32 = 48;
+const SPIRV_OP_SPEC_CONSTANT_FALSE: u32 = 49;
+const SPIRV_OP_SPEC_CONSTANT: u32 = 50;
+const SPIRV_DECORATION_SPEC_ID: u32 = 1;
+
+/// Scalar type of a specialization constant, integer signedness is ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SpecConstantType {
+    Bool,
+    Int(u32),
+    Float(u32),
+}
+
+fn spirv_words(raw_data: &[u8]) -> &[u32] {
+    // spec requires "codeSize must be a multiple of 4"
+    assert_eq!(raw_data.len() & 3, 0);
+
+    unsafe {
+        slice::from_raw_parts(
+            raw_data.as_ptr() as *const u32,
+            raw_data.len() / mem::size_of::<u32>(),
+        )
+    }
+}
+
+/// Collect the types of all specialization constants of a SPIR-V module,
+/// indexed by their `SpecId`.
+///
+/// SPIRV-Cross doesn't expose the type of a constant, so we walk the
+/// instructions ourselves.
+fn spec_constant_types(words: &[u32]) -> HashMap<u32, SpecConstantType> {
+    let mut spec_ids = HashMap::new();
+    let mut types = HashMap::new();
+    let mut constants = Vec::new();
+
+    let mut offset = SPIRV_HEADER_WORDS;
+    while offset < words.len() {
+        let word_count = (words[offset] >> 16) as usize;
+        let opcode = words[offset] & 0xFFFF;
+        if word_count == 0 || offset + word_count > words.len() {
+            warn!("Malformed SPIR-V instruction at word {}", offset);
+            break;
+        }
+
+        let operands = &words[offset + 1 .. offset + word_count];
+        match opcode {
+            SPIRV_OP_DECORATE if operands.len() >= 3 && operands[1] == SPIRV_DECORATION_SPEC_ID => {
+                spec_ids.insert(operands[0], operands[2]);
+            }
+            SPIRV_OP_TYPE_BOOL if operands.len() >= 1 => {
+                types.insert(operands[0], SpecConstantType::Bool);
+            }
+            SPIRV_OP_TYPE_INT if operands.len() >= 2 => {
+                types.insert(operands[0], SpecConstantType::Int(operands[1]));
+            }
+            SPIRV_OP_TYPE_FLOAT if operands.len() >= 2 => {
+                types.insert(operands[0], SpecConstantType::Float(operands[1]));
+            }
+            SPIRV_OP_SPEC_CONSTANT_TRUE |
+            SPIRV_OP_SPEC_CONSTANT_FALSE |
+            SPIRV_OP_SPEC_CONSTANT if operands.len() >= 2 => {
+                // (result type, result id)
+                constants.push((operands[0], operands[1]));
+            }
+            _ => {}
+        }
+
+        offset += word_count;
+    }
+
+    constants
+        .into_iter()
+        .filter_map(|(ty, id)| match (spec_ids.get(&id), types.get(&ty)) {
+            (Some(&spec_id), Some(&ty)) => Some((spec_id, ty)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Type and raw bits of a specialization constant value, as expected by SPIRV-Cross.
+fn map_spec_constant(constant: &pso::Constant) -> (SpecConstantType, u64) {
+    match *constant {
+        pso::Constant::Bool(v) => (SpecConstantType::Bool, v as u64),
+        pso::Constant::U32(v) => (SpecConstantType::Int(32), v as u64),
+        pso::Constant::I32(v) => (SpecConstantType::Int(32), v as u32 as u64),
+        pso::Constant::U64(v) => (SpecConstantType::Int(64), v),
+        pso::Constant::I64(v) => (SpecConstantType::Int(64), v as u64),
+        pso::Constant::F32(v) => (SpecConstantType::Float(32), v.to_bits() as u64),
+        pso::Constant::F64(v) => (SpecConstantType::Float(64), v.to_bits()),
+    }
+}
+
 fn get_shader_iv(gl: &gl::Gl, name: n::Shader, query: GLenum) -> gl::types::GLint {
     let mut iv = 0;
     unsafe { gl.GetShaderiv(name, query, &mut iv) };
@@ -94,8 +194,11 @@ pub struct UnboundBuffer {
 
 #[derive(Debug)]
 pub struct UnboundImage {
-    raw: GLuint,
+    raw: n::ImageKind,
     channel: ChannelType,
+    kind: i::Kind,
+    num_levels: i::Level,
+    format_desc: FormatDesc,
     requirements: memory::Requirements,
 }
 
@@ -193,15 +296,7 @@ impl Device {
     }
 
     fn parse_spirv(&self, raw_data: &[u8]) -> Result<spirv::Ast<glsl::Target>, d::ShaderError> {
-        // spec requires "codeSize must be a multiple of 4"
-        assert_eq!(raw_data.len() & 3, 0);
-
-        let module = spirv::Module::from_words(unsafe {
-            slice::from_raw_parts(
-                raw_data.as_ptr() as *const u32,
-                raw_data.len() / mem::size_of::<u32>(),
-            )
-        });
+        let module = spirv::Module::from_words(spirv_words(raw_data));
 
         spirv::Ast::parse(&module)
             .map_err(|err| {
@@ -251,19 +346,100 @@ impl Device {
             })
     }
 
+    /// Override the values of specialization constants, including the ones
+    /// defining the workgroup size of compute shaders.
+    fn specialize_spirv(
+        &self,
+        ast: &mut spirv::Ast<glsl::Target>,
+        raw_data: &[u8],
+        specialization: &[pso::Specialization],
+    ) -> Result<(), pso::CreationError> {
+        if specialization.is_empty() {
+            return Ok(());
+        }
+
+        let types = spec_constant_types(spirv_words(raw_data));
+        let spec_constants = ast
+            .get_specialization_constants()
+            .map_err(|err| pso::CreationError::Shader(gen_unexpected_error(err)))?;
+
+        for constant in specialization {
+            let (ty, value) = map_spec_constant(&constant.value);
+            if types.get(&constant.id) != Some(&ty) {
+                error!("Specialization constant {} with value {:?} doesn't match the shader ({:?})",
+                    constant.id, constant.value, types.get(&constant.id));
+                return Err(pso::CreationError::InvalidSpecialization(constant.id));
+            }
+
+            let spec_constant = spec_constants
+                .iter()
+                .find(|spec_constant| spec_constant.constant_id == constant.id)
+                .ok_or(pso::CreationError::InvalidSpecialization(constant.id))?;
+            ast.set_scalar_constant(spec_constant.id, value)
+                .map_err(|err| pso::CreationError::Shader(gen_unexpected_error(err)))?;
+        }
+
+        Ok(())
+    }
+
+    fn translate_specialized_spirv(
+        &self, raw_data: &[u8], specialization: &[pso::Specialization]
+    ) -> Result<String, pso::CreationError> {
+        let mut ast = self.parse_spirv(raw_data)
+            .map_err(pso::CreationError::Shader)?;
+        self.specialize_spirv(&mut ast, raw_data, specialization)?;
+        self.translate_spirv(&mut ast)
+            .map_err(pso::CreationError::Shader)
+    }
+
+    /// Translate a SPIR-V module to GLSL, reusing the translation stored in
+    /// `cache` if available.
+    fn translate_spirv_cached(
+        &self,
+        raw_data: &[u8],
+        specialization: &[pso::Specialization],
+        cache: Option<&n::PipelineCache>,
+    ) -> Result<String, pso::CreationError> {
+        let cache = match cache {
+            Some(cache) => cache,
+            None => return self.translate_specialized_spirv(raw_data, specialization),
+        };
+
+        let key = {
+            let (major, minor) = self.share.info.shading_language.tuple();
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u32(major);
+            hasher.write_u32(minor);
+            hasher.write(raw_data);
+            for constant in specialization {
+                let (_, value) = map_spec_constant(&constant.value);
+                hasher.write_u32(constant.id);
+                hasher.write_u64(value);
+            }
+            hasher.finish()
+        };
+        if let Some(glsl) = cache.shaders.lock().unwrap().get(&key) {
+            return Ok(glsl.clone());
+        }
+
+        let glsl = self.translate_specialized_spirv(raw_data, specialization)?;
+        cache.shaders.lock().unwrap().insert(key, glsl.clone());
+        Ok(glsl)
+    }
+
     fn compile_shader(
-        &self, point: &pso::EntryPoint<B>, stage: pso::Stage
-    ) -> n::Shader {
+        &self, point: &pso::EntryPoint<B>, stage: pso::Stage, cache: Option<&n::PipelineCache>
+    ) -> Result<n::Shader, pso::CreationError> {
         assert_eq!(point.entry, "main");
         match *point.module {
-            n::ShaderModule::Raw(raw) => raw,
+            n::ShaderModule::Raw(raw) => Ok(raw),
             n::ShaderModule::Spirv(ref spirv) => {
-                let mut ast = self.parse_spirv(spirv).unwrap();
-                let glsl = self.translate_spirv(&mut ast).unwrap();
+                let glsl = self.translate_spirv_cached(spirv, point.specialization, cache)?;
                 info!("Generated:\n{:?}", glsl);
-                match self.create_shader_module_from_source(glsl.as_bytes(), stage).unwrap() {
-                    n::ShaderModule::Raw(raw) => raw,
-                    _ => panic!("Unhandled")
+                match self.create_shader_module_from_source(glsl.as_bytes(), stage) {
+                    Ok(n::ShaderModule::Raw(raw)) => Ok(raw),
+                    Ok(_) => panic!("Unhandled"),
+                    Err(err) => Err(pso::CreationError::Shader(err)),
                 }
             }
         }
@@ -286,7 +462,7 @@ impl d::Device<B> for Device {
         &self,
         _family: QueueFamilyId,
         flags: CommandPoolCreateFlags,
-    ) -> RawCommandPool {
+    ) -> Result<RawCommandPool, d::OutOfMemory> {
         let fbo = create_fbo_internal(&self.share.context);
         let limits = self.share.limits.into();
         let memory = if flags.contains(CommandPoolCreateFlags::RESET_INDIVIDUAL) {
@@ -300,11 +476,11 @@ impl d::Device<B> for Device {
 
         // Ignoring `TRANSIENT` hint, unsure how to make use of this.
 
-        RawCommandPool {
+        Ok(RawCommandPool {
             fbo,
             limits,
             memory: Arc::new(Mutex::new(memory)),
-        }
+        })
     }
 
     fn destroy_command_pool(&self, pool: RawCommandPool) {
@@ -359,8 +535,40 @@ impl d::Device<B> for Device {
         n::PipelineLayout
     }
 
+    fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Result<n::PipelineCache, d::OutOfMemory> {
+        Ok(data
+            .and_then(|data| {
+                let cache = n::PipelineCache::from_data(data);
+                if cache.is_none() {
+                    warn!("Discarding invalid pipeline cache data");
+                }
+                cache
+            })
+            .unwrap_or_default())
+    }
+
+    fn get_pipeline_cache_data(&self, cache: &n::PipelineCache) -> Result<Vec<u8>, d::OutOfMemory> {
+        Ok(cache.data())
+    }
+
+    fn merge_pipeline_caches<I>(&self, target: &n::PipelineCache, sources: I) -> Result<(), d::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::PipelineCache>,
+    {
+        for source in sources {
+            let source = source.borrow();
+            if source as *const _ == target as *const _ {
+                continue;
+            }
+            let shaders = source.shaders.lock().unwrap().clone();
+            target.shaders.lock().unwrap().extend(shaders);
+        }
+        Ok(())
+    }
+
     fn create_graphics_pipeline<'a>(
-        &self, desc: &pso::GraphicsPipelineDesc<'a, B>
+        &self, desc: &pso::GraphicsPipelineDesc<'a, B>, cache: Option<&n::PipelineCache>
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
         let gl = &self.share.context;
         let share = &self.share;
@@ -373,6 +581,17 @@ impl d::Device<B> for Device {
             }
         };
 
+        if !share.features.contains(c::Features::INDEPENDENT_BLENDING) {
+            // All color targets share one blend state, only the write masks
+            // can differ if they can be set per draw buffer.
+            let blender = &desc.blender;
+            if !blender.has_uniform_blend_state() ||
+                !(share.private_caps.color_mask_indexed || blender.is_uniform())
+            {
+                return Err(pso::CreationError::UnsupportedBlending);
+            }
+        }
+
         let program = {
             let name = unsafe { gl.CreateProgram() };
 
@@ -385,16 +604,29 @@ impl d::Device<B> for Device {
                 (pso::Stage::Fragment, desc.shaders.fragment.as_ref()),
             ];
 
-            let shader_names = &shaders
-                .iter()
-                .filter_map(|&(stage, point_maybe)| {
-                    point_maybe.map(|point| {
-                        let shader_name = self.compile_shader(point, stage);
+            let mut shader_names = Vec::with_capacity(shaders.len());
+            for &(stage, point_maybe) in &shaders {
+                let point = match point_maybe {
+                    Some(point) => point,
+                    None => continue,
+                };
+                match self.compile_shader(point, stage, cache) {
+                    Ok(shader_name) => {
                         unsafe { gl.AttachShader(name, shader_name); }
-                        shader_name
-                    })
-                })
-                .collect::<Vec<_>>();
+                        shader_names.push(shader_name);
+                    }
+                    Err(err) => {
+                        for &shader_name in &shader_names {
+                            unsafe {
+                                gl.DetachShader(name, shader_name);
+                                gl.DeleteShader(shader_name);
+                            }
+                        }
+                        unsafe { gl.DeleteProgram(name) };
+                        return Err(err);
+                    }
+                }
+            }
 
             if !share.private_caps.program_interface && share.private_caps.frag_data_location {
                 for i in 0..subpass.color_attachments.len() {
@@ -413,8 +645,8 @@ impl d::Device<B> for Device {
 
             for shader_name in shader_names {
                 unsafe {
-                    gl.DetachShader(name, *shader_name);
-                    gl.DeleteShader(*shader_name);
+                    gl.DetachShader(name, shader_name);
+                    gl.DeleteShader(shader_name);
                 }
             }
 
@@ -462,13 +694,20 @@ impl d::Device<B> for Device {
     fn create_compute_pipeline<'a>(
         &self,
         desc: &pso::ComputePipelineDesc<'a, B>,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<n::ComputePipeline, pso::CreationError> {
         let gl = &self.share.context;
         let share = &self.share;
         let program = {
             let name = unsafe { gl.CreateProgram() };
 
-            let shader = self.compile_shader(&desc.shader, pso::Stage::Compute);
+            let shader = match self.compile_shader(&desc.shader, pso::Stage::Compute, cache) {
+                Ok(shader) => shader,
+                Err(err) => {
+                    unsafe { gl.DeleteProgram(name) };
+                    return Err(err);
+                }
+            };
             unsafe { gl.AttachShader(name, shader) };
 
             unsafe { gl.LinkProgram(name) };
@@ -565,9 +804,9 @@ impl d::Device<B> for Device {
         Ok(n::ShaderModule::Spirv(raw_data.into()))
     }
 
-    fn create_sampler(&self, info: i::SamplerInfo) -> n::FatSampler {
+    fn create_sampler(&self, info: i::SamplerInfo) -> Result<n::FatSampler, d::OutOfMemory> {
         if !self.share.legacy_features.contains(LegacyFeatures::SAMPLER_OBJECTS) {
-            return n::FatSampler::Info(info);
+            return Ok(n::FatSampler::Info(info));
         }
 
         let gl = &self.share.context;
@@ -618,11 +857,13 @@ impl d::Device<B> for Device {
             }
         }
 
-        if let Err(err) = self.share.check() {
-            panic!("Error {:?} creating sampler: {:?}", err, info)
+        match self.share.check() {
+            Ok(()) => {}
+            Err(::Error::OutOfMemory) => return Err(d::OutOfMemory::OutOfDeviceMemory),
+            Err(err) => panic!("Error {:?} creating sampler: {:?}", err, info),
         }
 
-        n::FatSampler::Sampler(name)
+        Ok(n::FatSampler::Sampler(name))
     }
 
     fn create_buffer(
@@ -794,6 +1035,17 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
+    fn create_sparse_buffer(
+        &self, _size: u64, _usage: buffer::Usage, _flags: memory::SparseFlags,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // Sparse binding is not exposed in `features`.
+        Err(buffer::CreationError::Sparse)
+    }
+
+    fn get_sparse_buffer_requirements(&self, _buffer: &n::Buffer) -> memory::Requirements {
+        unreachable!("sparse buffers can't be created")
+    }
+
     fn create_buffer_view<R: RangeArg<u64>>(
         &self, _: &n::Buffer, _: Option<Format>, _: R
     ) -> Result<n::BufferView, buffer::ViewError> {
@@ -801,15 +1053,24 @@ impl d::Device<B> for Device {
     }
 
     fn create_image(
-        &self, kind: i::Kind, num_levels: i::Level, format: Format, _: i::Usage
+        &self,
+        kind: i::Kind,
+        num_levels: i::Level,
+        format: Format,
+        tiling: i::Tiling,
+        _usage: i::Usage,
+        storage_flags: i::StorageFlags,
     ) -> Result<UnboundImage, i::CreationError> {
-        let gl = &self.share.context;
+        // Texture memory is never exposed to the host in GL.
+        if tiling == i::Tiling::Linear {
+            return Err(i::CreationError::Tiling(tiling));
+        }
+        // Reinterpreting textures requires texture views, which are not used yet.
+        if !storage_flags.is_empty() {
+            return Err(i::CreationError::StorageFlags(storage_flags));
+        }
 
-        let name = unsafe {
-            let mut raw = 0;
-            gl.GenTextures(1, &mut raw);
-            raw
-        };
+        let gl = &self.share.context;
 
         let int_format = match format {
             Format::Rgba8Unorm => gl::RGBA8,
@@ -819,12 +1080,30 @@ impl d::Device<B> for Device {
 
         let channel = format.base_format().1;
 
-        let (width, height) = match kind {
-            i::Kind::D2(w, h, aa) => unsafe {
-                assert_eq!(aa, i::AaMode::Single);
+        let (raw, width, height, samples) = match kind {
+            i::Kind::D2(w, h, i::AaMode::Single) => unsafe {
+                let mut name = 0;
+                gl.GenTextures(1, &mut name);
                 gl.BindTexture(gl::TEXTURE_2D, name);
                 gl.TexStorage2D(gl::TEXTURE_2D, num_levels as _, int_format, w as _, h as _);
-                (w, h)
+                (n::ImageKind::Texture(name), w, h, 1)
+            }
+            // Multisampled images are backed by renderbuffers.
+            i::Kind::D2(w, h, i::AaMode::Multi(samples)) => unsafe {
+                if num_levels != 1 {
+                    return Err(i::CreationError::Kind);
+                }
+                if samples as usize > self.share.private_caps.max_samples {
+                    return Err(i::CreationError::Samples(i::AaMode::Multi(samples)));
+                }
+                let mut name = 0;
+                gl.GenRenderbuffers(1, &mut name);
+                gl.BindRenderbuffer(gl::RENDERBUFFER, name);
+                gl.RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as _, int_format, w as _, h as _);
+                (n::ImageKind::Surface(name), w, h, samples)
+            }
+            i::Kind::D2(_, _, aa) => {
+                return Err(i::CreationError::Samples(aa));
             }
             _ => {
                 unimplemented!();
@@ -840,10 +1119,13 @@ impl d::Device<B> for Device {
         }
 
         Ok(UnboundImage {
-            raw: name,
+            raw,
             channel,
+            kind,
+            num_levels,
+            format_desc: surface_desc,
             requirements: memory::Requirements {
-                size: width as u64 * height as u64 * bytes_per_texel as u64,
+                size: width as u64 * height as u64 * bytes_per_texel as u64 * samples as u64,
                 alignment: 1,
                 type_mask: 0x7,
             }
@@ -854,10 +1136,42 @@ impl d::Device<B> for Device {
         unbound.requirements
     }
 
+    fn create_sparse_image(
+        &self,
+        _kind: i::Kind,
+        _mip_levels: i::Level,
+        _format: Format,
+        _usage: i::Usage,
+        _storage_flags: i::StorageFlags,
+        _flags: memory::SparseFlags,
+    ) -> Result<n::Image, i::CreationError> {
+        // Sparse binding is not exposed in `features`.
+        Err(i::CreationError::Sparse)
+    }
+
+    fn get_sparse_image_requirements(&self, _image: &n::Image) -> memory::Requirements {
+        unreachable!("sparse images can't be created")
+    }
+
+    fn get_sparse_image_page_layout(&self, _image: &n::Image) -> Vec<i::SparsePageLayout> {
+        unreachable!("sparse images can't be created")
+    }
+
+    fn get_image_subresource_footprint(
+        &self, image: &n::Image, _aspect: Aspects, sub: i::Subresource
+    ) -> i::SubresourceFootprint {
+        // Linear images are rejected by `create_image`, return the packed
+        // layout used by pixel transfers instead.
+        i::SubresourceFootprint::packed(image.shape, image.num_levels, image.format_desc, sub)
+    }
+
     fn bind_image_memory(&self, _memory: &n::Memory, _offset: u64, image: UnboundImage) -> Result<n::Image, d::BindError> {
         Ok(n::Image {
-            kind: n::ImageKind::Texture(image.raw),
+            kind: image.raw,
             channel: image.channel,
+            shape: image.kind,
+            num_levels: image.num_levels,
+            format_desc: image.format_desc,
         })
     }
 
@@ -893,20 +1207,49 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_descriptor_pool<I>(&self, _: usize, _: I) -> n::DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self, _: usize, _: I, _: pso::DescriptorPoolCreateFlags,
+    ) -> Result<n::DescriptorPool, d::OutOfMemory>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
     {
-        n::DescriptorPool { }
+        Ok(n::DescriptorPool { })
     }
 
-    fn create_descriptor_set_layout<I>(&self, _: I) -> n::DescriptorSetLayout
+    fn create_descriptor_set_layout<I, J>(
+        &self, bindings: I, immutable_samplers: J,
+    ) -> n::DescriptorSetLayout
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<n::FatSampler>,
     {
-        n::DescriptorSetLayout
+        // Immutable samplers are bound together with the descriptor sets.
+        let mut sampler_iter = immutable_samplers.into_iter();
+        let immutable_samplers = bindings
+            .into_iter()
+            .filter_map(|desc| {
+                let desc: &pso::DescriptorSetLayoutBinding = desc.borrow();
+                if !desc.immutable_samplers {
+                    return None;
+                }
+                let samplers = sampler_iter
+                    .by_ref()
+                    .take(desc.count)
+                    .map(|sampler| sampler.borrow().clone())
+                    .collect::<Vec<_>>();
+                if samplers.len() < desc.count {
+                    error!("Not enough immutable samplers for binding {}", desc.binding);
+                }
+                Some((desc.binding, samplers))
+            })
+            .collect();
+
+        n::DescriptorSetLayout {
+            immutable_samplers,
+        }
     }
 
     fn write_descriptor_sets<'a, I, J>(&self, writes: I)
@@ -931,18 +1274,44 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_semaphore(&self) -> n::Semaphore {
-        n::Semaphore
+    fn create_descriptor_update_template<I>(
+        &self, _layout: &n::DescriptorSetLayout, entries: I
+    ) -> n::DescriptorUpdateTemplate
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|entry| *entry.borrow())
+            .collect::<Vec<_>>();
+        n::DescriptorUpdateTemplate { entries }
+    }
+
+    fn destroy_descriptor_update_template(&self, _template: n::DescriptorUpdateTemplate) {
+    }
+
+    unsafe fn update_descriptor_set_with_template(
+        &self, set: &n::DescriptorSet, template: &n::DescriptorUpdateTemplate, data: &[u8]
+    ) {
+        self.write_descriptor_sets(template.entries
+            .iter()
+            .map(|entry| entry.write::<B>(set, data))
+        );
+    }
+
+    fn create_semaphore(&self) -> Result<n::Semaphore, d::OutOfMemory> {
+        Ok(n::Semaphore)
     }
 
-    fn create_fence(&self, signalled: bool) -> n::Fence {
+    fn create_fence(&self, signalled: bool) -> Result<n::Fence, d::OutOfMemory> {
         let sync = if signalled && self.share.private_caps.sync {
             let gl = &self.share.context;
             unsafe { gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) }
         } else {
             ptr::null()
         };
-        n::Fence::new(sync)
+        Ok(n::Fence::new(sync))
     }
 
     fn reset_fences<I>(&self, fences: I)
@@ -967,24 +1336,30 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn wait_for_fence(&self, fence: &n::Fence, timeout_ms: u32) -> bool {
+    fn wait_for_fence(&self, fence: &n::Fence, timeout_ms: u32) -> Result<bool, d::DeviceLost> {
         if !self.share.private_caps.sync {
-            return true;
+            return Ok(true);
         }
         match wait_fence(fence, &self.share.context, timeout_ms) {
-            gl::TIMEOUT_EXPIRED => false,
+            gl::TIMEOUT_EXPIRED => Ok(false),
             gl::WAIT_FAILED => {
                 if let Err(err) = self.share.check() {
                     error!("Error when waiting on fence: {:?}", err);
                 }
-                false
+                Ok(false)
             }
-            _ => true,
+            _ => Ok(true),
         }
     }
 
-    fn get_fence_status(&self, _: &n::Fence) -> bool {
-        unimplemented!()
+    fn get_fence_status(&self, fence: &n::Fence) -> Result<bool, d::DeviceLost> {
+        self.wait_for_fence(fence, 0)
+    }
+
+    fn get_submission_status(
+        &self, queue: &CommandQueue, id: SubmissionId,
+    ) -> Result<bool, d::DeviceLost> {
+        Ok(queue.poll_submissions().map_or(true, |pending| pending > id))
     }
 
     fn free_memory(&self, _memory: n::Memory) {
@@ -1010,6 +1385,10 @@ impl d::Device<B> for Device {
     fn destroy_pipeline_layout(&self, _: n::PipelineLayout) {
         unimplemented!()
     }
+
+    fn destroy_pipeline_cache(&self, _: n::PipelineCache) {
+        // Just drop
+    }
     fn destroy_graphics_pipeline(&self, _: n::GraphicsPipeline) {
         unimplemented!()
     }
@@ -1068,6 +1447,31 @@ impl d::Device<B> for Device {
         unsafe { self.share.context.Finish(); }
         Ok(())
     }
+
+    fn set_object_name(&self, object: d::NamedObject<B>, name: &str) {
+        if !self.share.private_caps.debug {
+            return;
+        }
+        let (identifier, raw) = match object {
+            d::NamedObject::Buffer(buffer) => (gl::BUFFER, buffer.raw),
+            d::NamedObject::Image(image) => match image.kind {
+                n::ImageKind::Surface(surface) => (gl::RENDERBUFFER, surface),
+                n::ImageKind::Texture(texture) => (gl::TEXTURE, texture),
+            },
+            d::NamedObject::Sampler(&n::FatSampler::Sampler(sampler)) => (gl::SAMPLER, sampler),
+            d::NamedObject::GraphicsPipeline(pipeline) => (gl::PROGRAM, pipeline.program),
+            d::NamedObject::ComputePipeline(pipeline) => (gl::PROGRAM, pipeline.program),
+            // Views share the objects of their images, emulated samplers and
+            // command buffers have no GL object.
+            d::NamedObject::ImageView(_) |
+            d::NamedObject::Sampler(_) |
+            d::NamedObject::CommandBuffer(_) => return,
+        };
+        let gl = &self.share.context;
+        unsafe {
+            gl.ObjectLabel(identifier, raw, name.len() as _, name.as_ptr() as *const _);
+        }
+    }
 }
 
 pub fn wait_fence(fence: &n::Fence, gl: &gl::Gl, timeout_ms: u32) -> GLenum {
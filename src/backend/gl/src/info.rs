@@ -177,6 +177,12 @@ pub struct PrivateCaps {
     pub map: bool,
     /// Indicates if we only have support via the EXT.
     pub sampler_anisotropy_ext: bool,
+    /// Object labels and debug groups
+    pub debug: bool,
+    /// Color write masks per draw buffer (`glColorMaski`)
+    pub color_mask_indexed: bool,
+    /// Maximum number of samples of multisampled renderbuffers (`GL_MAX_SAMPLES`)
+    pub max_samples: usize,
 }
 
 /// OpenGL implementation information
@@ -303,6 +309,8 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
         min_buffer_copy_offset_alignment: 1,
         min_buffer_copy_pitch_alignment: 1,
         min_uniform_buffer_offset_alignment: 1, // TODO
+        buffer_image_granularity: 1,
+        non_coherent_atom_size: 1,
         .. Limits::default()
     };
 
@@ -352,6 +360,13 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
     ]) {
         features |= Features::INSTANCE_RATE;
     }
+    if info.is_supported(&[
+        Core(4, 0),
+        Es(3, 2),
+        Ext("GL_ARB_draw_buffers_blend"),
+    ]) {
+        features |= Features::INDEPENDENT_BLENDING;
+    }
 
     if info.is_supported(&[Core(4, 3), Es(3, 1)]) { // TODO: extension
         legacy |= LegacyFeatures::INDIRECT_EXECUTION;
@@ -451,6 +466,19 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
         sampler_anisotropy_ext:             !info.is_supported(&[Core(4,6),
                                                                 Ext ("GL_ARB_texture_filter_anisotropic")]) &&
                                             info.is_supported(&[Ext ("GL_EXT_texture_filter_anisotropic")]),
+        debug:                              info.is_supported(&[Core(4,3),
+                                                                Es  (3,2),
+                                                                Ext ("GL_KHR_debug")]),
+        color_mask_indexed:                 info.is_supported(&[Core(3,0),
+                                                                Es  (3,2),
+                                                                Ext ("GL_EXT_draw_buffers_indexed")]),
+        max_samples:                        if info.is_supported(&[Core(3,0),
+                                                                Es  (3,0),
+                                                                Ext ("GL_ARB_framebuffer_object")]) {
+                                                get_usize(gl, gl::MAX_SAMPLES)
+                                            } else {
+                                                1
+                                            },
     };
 
     (info, features, legacy, limits, private)
@@ -15,6 +15,7 @@ extern crate spirv_cross;
 pub extern crate glutin;
 
 use std::cell::Cell;
+use std::cmp;
 use std::fmt;
 use std::sync::Arc;
 use std::ops::Deref;
@@ -69,8 +70,10 @@ impl hal::Backend for Backend {
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
+    type PipelineCache = native::PipelineCache;
     type PipelineLayout = native::PipelineLayout;
     type DescriptorSetLayout = native::DescriptorSetLayout;
+    type DescriptorUpdateTemplate = native::DescriptorUpdateTemplate;
     type DescriptorPool = native::DescriptorPool;
     type DescriptorSet = native::DescriptorSet;
 
@@ -285,6 +288,47 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         unimplemented!()
     }
 
+    fn image_format_properties(
+        &self,
+        format: hal::format::Format,
+        dimensions: u8,
+        tiling: hal::image::Tiling,
+        _usage: hal::image::Usage,
+        storage_flags: hal::image::StorageFlags,
+    ) -> Option<hal::image::FormatProperties> {
+        use hal::format::Format;
+
+        // Mirrors the restrictions of `Device::create_image`.
+        match format {
+            Format::Rgba8Unorm | Format::Rgba8Srgb => {}
+            _ => return None,
+        }
+        if tiling == hal::image::Tiling::Linear || dimensions != 2 || !storage_flags.is_empty() {
+            return None;
+        }
+
+        let max_size = self.0.limits.max_texture_size as u32;
+        // Multisampled images are renderbuffers, which support any power of two
+        // sample count up to `GL_MAX_SAMPLES`.
+        let max_samples = cmp::max(self.0.private_caps.max_samples, 1) as u64;
+        let sample_count_mask = (0 .. 8)
+            .map(|i| 1u64 << i)
+            .filter(|&count| count <= max_samples)
+            .fold(0, |mask, count| mask | count);
+        let bytes_per_texel = (format.base_format().0.desc().bits / 8) as u64;
+        Some(hal::image::FormatProperties {
+            max_extent: hal::device::Extent {
+                width: max_size,
+                height: max_size,
+                depth: 1,
+            },
+            max_levels: (32 - max_size.leading_zeros()) as _,
+            max_layers: 1,
+            sample_count_mask: sample_count_mask as _,
+            max_resource_size: max_size as u64 * max_size as u64 * bytes_per_texel * max_samples,
+        })
+    }
+
     fn memory_properties(&self) -> hal::MemoryProperties {
         use hal::memory::Properties;
 
@@ -1,4 +1,6 @@
 use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 use hal::{format, image as i, pass, pso};
 use hal::memory::Properties;
@@ -58,6 +60,10 @@ pub struct Image {
     pub(crate) kind: ImageKind,
     // Required for clearing operations
     pub(crate) channel: format::ChannelType,
+    // Required for subresource footprints
+    pub(crate) shape: i::Kind,
+    pub(crate) num_levels: i::Level,
+    pub(crate) format_desc: format::FormatDesc,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -81,26 +87,47 @@ pub enum ImageView {
     TextureLayer(Texture, i::Level, i::Layer),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct DescriptorSetLayout;
+#[derive(Clone, Debug)]
+pub struct DescriptorSetLayout {
+    /// Immutable samplers per binding, assigned to every set allocated with this layout.
+    pub(crate) immutable_samplers: Vec<(pso::DescriptorBinding, Vec<FatSampler>)>,
+}
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct DescriptorSet;
+#[derive(Debug)]
+pub struct DescriptorUpdateTemplate {
+    pub(crate) entries: Vec<pso::DescriptorUpdateTemplateEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DescriptorSet {
+    pub(crate) immutable_samplers: Vec<(pso::DescriptorBinding, Vec<FatSampler>)>,
+}
 
 #[derive(Debug)]
 pub struct DescriptorPool {}
 
 impl pso::DescriptorPool<Backend> for DescriptorPool {
-    fn allocate_sets<I>(&mut self, layouts: I) -> Vec<DescriptorSet>
+    fn allocate_sets<I>(&mut self, layouts: I) -> Result<Vec<DescriptorSet>, pso::AllocationError>
     where
         I: IntoIterator,
         I::Item: Borrow<DescriptorSetLayout>,
     {
-        layouts.into_iter().map(|_| DescriptorSet).collect()
+        Ok(layouts
+            .into_iter()
+            .map(|layout| DescriptorSet {
+                immutable_samplers: layout.borrow().immutable_samplers.clone(),
+            })
+            .collect())
+    }
+
+    fn free_sets<I>(&mut self, _descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        // Descriptor sets don't hold any resources yet
     }
 
     fn reset(&mut self) {
-        unimplemented!()
     }
 }
 
@@ -164,6 +191,92 @@ impl SubpassDesc {
 #[derive(Debug)]
 pub struct PipelineLayout;
 
+const PIPELINE_CACHE_MAGIC: &[u8] = b"GFXGLPC\0";
+const PIPELINE_CACHE_VERSION: u32 = 1;
+
+/// Caches the GLSL translation of SPIR-V shaders, keyed by a hash of the
+/// SPIR-V module and the targeted GLSL version.
+#[derive(Debug, Default)]
+pub struct PipelineCache {
+    pub(crate) shaders: Mutex<HashMap<u64, String>>,
+}
+
+impl PipelineCache {
+    /// Load a cache from serialized data, returns `None` for invalid data.
+    pub(crate) fn from_data(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader(data);
+        if reader.bytes(PIPELINE_CACHE_MAGIC.len())? != PIPELINE_CACHE_MAGIC ||
+            reader.u32()? != PIPELINE_CACHE_VERSION
+        {
+            return None;
+        }
+
+        let mut shaders = HashMap::new();
+        for _ in 0 .. reader.u32()? {
+            let key = reader.u64()?;
+            let source = reader.string()?;
+            shaders.insert(key, source);
+        }
+
+        Some(PipelineCache {
+            shaders: Mutex::new(shaders),
+        })
+    }
+
+    /// Serialize the cached shaders.
+    pub(crate) fn data(&self) -> Vec<u8> {
+        let shaders = self.shaders.lock().unwrap();
+        let mut data = PIPELINE_CACHE_MAGIC.to_vec();
+        write_u32(&mut data, PIPELINE_CACHE_VERSION);
+        write_u32(&mut data, shaders.len() as _);
+        for (&key, source) in shaders.iter() {
+            write_u64(&mut data, key);
+            write_u32(&mut data, source.len() as _);
+            data.extend_from_slice(source.as_bytes());
+        }
+        data
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|bytes| {
+            bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as u32)
+        })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let low = self.u32()? as u64;
+        let high = self.u32()? as u64;
+        Some(high << 32 | low)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).ok()
+    }
+}
+
+fn write_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend((0 .. 4).map(|i| (value >> (8 * i)) as u8));
+}
+
+fn write_u64(data: &mut Vec<u8>, value: u64) {
+    write_u32(data, value as u32);
+    write_u32(data, (value >> 32) as u32);
+}
+
 #[derive(Debug)]
 // No inter-queue synchronization required for GL.
 pub struct Semaphore;
@@ -1,5 +1,7 @@
 use std::{mem, ptr, slice};
 use std::borrow::{Borrow, BorrowMut};
+use std::collections::VecDeque;
+use std::sync::Mutex;
 use Starc;
 
 use hal;
@@ -9,6 +11,7 @@ use gl;
 use smallvec::SmallVec;
 
 use {command as com, native, state, window};
+use device::wait_fence;
 use info::LegacyFeatures;
 use {Backend, Share};
 
@@ -61,6 +64,9 @@ pub struct CommandQueue {
     pub(crate) share: Starc<Share>,
     vao: ArrayBuffer,
     state: State,
+    submission_count: u64,
+    // Sync objects of the submissions in flight, ordered by submission id.
+    pub(crate) pending_submissions: Mutex<VecDeque<(hal::queue::SubmissionId, native::Fence)>>,
 }
 
 impl CommandQueue {
@@ -70,6 +76,8 @@ impl CommandQueue {
             share: share.clone(),
             vao,
             state: State::new(),
+            submission_count: 0,
+            pending_submissions: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -472,8 +480,20 @@ impl CommandQueue {
             com::Command::BindProgram(program) => unsafe {
                 self.share.context.UseProgram(program);
             }
+            com::Command::BindSampler(unit, sampler) => unsafe {
+                self.share.context.BindSampler(unit, sampler);
+            }
             com::Command::BindBlendSlot(slot, ref blend) => {
-                state::bind_blend_slot(&self.share.context, slot, blend);
+                if self.share.features.contains(hal::Features::INDEPENDENT_BLENDING) {
+                    state::bind_blend_slot(&self.share.context, slot, blend);
+                } else {
+                    state::bind_shared_blend_slot(
+                        &self.share.context,
+                        slot,
+                        blend,
+                        self.share.private_caps.color_mask_indexed,
+                    );
+                }
             }
             com::Command::BindAttribute(ref attribute, handle, stride, function_type) => unsafe {
                 use native::VertexAttribFunction::*;
@@ -545,6 +565,33 @@ impl CommandQueue {
             com::Command::CopySurfaceToBuffer(..) => {
                 unimplemented!() //TODO: use FBO
             }
+            com::Command::PushDebugGroup(name_ptr) => if self.share.private_caps.debug {
+                let name = Self::get_raw(data_buf, name_ptr);
+                unsafe {
+                    self.share.context.PushDebugGroup(
+                        gl::DEBUG_SOURCE_APPLICATION,
+                        0,
+                        name.len() as _,
+                        name.as_ptr() as *const _,
+                    );
+                }
+            }
+            com::Command::PopDebugGroup => if self.share.private_caps.debug {
+                unsafe { self.share.context.PopDebugGroup() };
+            }
+            com::Command::InsertDebugMarker(name_ptr) => if self.share.private_caps.debug {
+                let name = Self::get_raw(data_buf, name_ptr);
+                unsafe {
+                    self.share.context.DebugMessageInsert(
+                        gl::DEBUG_SOURCE_APPLICATION,
+                        gl::DEBUG_TYPE_MARKER,
+                        0,
+                        gl::DEBUG_SEVERITY_NOTIFICATION,
+                        name.len() as _,
+                        name.as_ptr() as *const _,
+                    );
+                }
+            }
             /*
             com::Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot)) => unsafe {
                 self.share.context.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer);
@@ -652,6 +699,28 @@ impl CommandQueue {
             panic!("Error {:?} executing command: {:?}", err, cmd)
         }
     }
+    /// Release the sync objects of all finished submissions.
+    /// Returns the id of the oldest submission still in flight.
+    pub(crate) fn poll_submissions(&self) -> Option<hal::queue::SubmissionId> {
+        let gl = &self.share.context;
+        let mut pending = self.pending_submissions.lock().unwrap();
+        loop {
+            let id = match pending.front() {
+                Some(&(id, ref fence)) => match wait_fence(fence, gl, 0) {
+                    gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => None,
+                    _ => Some(id),
+                },
+                None => return None,
+            };
+            if id.is_some() {
+                return id;
+            }
+            if let Some((_, fence)) = pending.pop_front() {
+                unsafe { gl.DeleteSync(fence.0.get()); }
+            }
+        }
+    }
+
     fn signal_fence(&mut self, fence: &native::Fence) {
         if self.share.private_caps.sync {
             let gl = &self.share.context;
@@ -669,7 +738,8 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
         &mut self,
         submit_info: hal::queue::RawSubmission<Backend, IC>,
         fence: Option<&native::Fence>,
-    ) where
+    ) -> hal::SubmissionResult<hal::queue::SubmissionId>
+    where
         IC: IntoIterator,
         IC::Item: Borrow<com::RawCommandBuffer>,
     {
@@ -698,25 +768,48 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
             }
         }
         fence.map(|fence| self.signal_fence(fence));
+
+        self.submission_count += 1;
+        let id = hal::queue::SubmissionId(self.submission_count);
+        if self.share.private_caps.sync {
+            self.poll_submissions();
+            let progress = native::Fence::new(ptr::null());
+            self.signal_fence(&progress);
+            self.pending_submissions.lock().unwrap().push_back((id, progress));
+        }
+
+        Ok(id)
+    }
+
+    unsafe fn bind_sparse(
+        &mut self, _binding: hal::queue::RawSparseBinding<Backend>, _fence: Option<&native::Fence>,
+    ) -> hal::SubmissionResult<()> {
+        // No queue family supports sparse binding.
+        error!("Sparse binding is not supported");
+        Ok(())
     }
 
     #[cfg(feature = "glutin")]
-    fn present<IS, IW>(&mut self, swapchains: IS, _wait_semaphores: IW)
+    fn present<IS, IW>(
+        &mut self, swapchains: IS, _wait_semaphores: IW,
+    ) -> Result<hal::PresentStatus, hal::PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<window::glutin::Swapchain>,
         IW: IntoIterator,
         IW::Item: Borrow<native::Semaphore>,
     {
-        use glutin::GlContext;
+        use glutin::{ContextError, GlContext};
 
         for swapchain in swapchains {
-            swapchain
-                .borrow()
-                .window
-                .swap_buffers()
-                .unwrap();
+            match swapchain.borrow().window.swap_buffers() {
+                Ok(()) => {}
+                Err(ContextError::ContextLost) => return Err(hal::PresentError::SurfaceLost),
+                Err(err) => panic!("Unable to swap buffers: {:?}", err),
+            }
         }
+
+        Ok(hal::PresentStatus::Optimal)
     }
 
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
@@ -217,6 +217,38 @@ pub fn bind_blend_slot(gl: &gl::Gl, slot: ColorSlot, desc: &pso::ColorBlendDesc)
     )};
 }
 
+/// Bind the blend state of a color slot without independent blending support.
+/// All slots share the same blend state, only the color write mask is set per slot
+/// if `glColorMaski` is available.
+pub fn bind_shared_blend_slot(gl: &gl::Gl, slot: ColorSlot, desc: &pso::ColorBlendDesc, color_mask_indexed: bool) {
+    use hal::pso::ColorMask as Cm;
+
+    if !color_mask_indexed {
+        // Pipeline creation ensures all slots are equal in this case.
+        return bind_blend(gl, desc);
+    }
+
+    match desc.1 {
+        pso::BlendState::On { color, alpha } => unsafe {
+            let (color_eq, color_src, color_dst) = map_blend_op(color);
+            let (alpha_eq, alpha_src, alpha_dst) = map_blend_op(alpha);
+            gl.Enable(gl::BLEND);
+            gl.BlendEquationSeparate(color_eq, alpha_eq);
+            gl.BlendFuncSeparate(color_src, color_dst, alpha_src, alpha_dst);
+        },
+        pso::BlendState::Off => unsafe {
+            gl.Disable(gl::BLEND);
+        },
+    };
+
+    unsafe { gl.ColorMaski(slot as _,
+        desc.0.contains(Cm::RED) as _,
+        desc.0.contains(Cm::GREEN) as _,
+        desc.0.contains(Cm::BLUE) as _,
+        desc.0.contains(Cm::ALPHA) as _,
+    )};
+}
+
 pub fn unlock_color_mask(gl: &gl::Gl) {
     unsafe { gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE) };
 }
@@ -63,9 +63,13 @@ pub struct Swapchain {
 }
 
 impl hal::Swapchain<B> for Swapchain {
-    fn acquire_frame(&mut self, _sync: hal::FrameSync<B>) -> hal::Frame {
+    fn acquire_frame(
+        &mut self, _sync: hal::FrameSync<B>, _timeout_ms: u32,
+    ) -> Result<hal::Frame, hal::AcquireError> {
         // TODO: sync
-        hal::Frame::new(0)
+        // The default framebuffer is always available, buffer swaps block in
+        // `present` instead. Acquisition never waits, so `_timeout_ms` has no effect.
+        Ok(hal::Frame::new(0))
     }
 }
 
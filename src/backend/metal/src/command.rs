@@ -4,18 +4,20 @@ use {native, window};
 use std::borrow::{Borrow, BorrowMut};
 use std::cell::UnsafeCell;
 use std::ops::{Deref, Range};
-use std::sync::{Arc};
-use std::{iter, mem};
+use std::sync::{Arc, Mutex};
+use std::{cmp, iter, mem};
 
 use hal::{buffer, command as com, error, memory, pool, pso};
+use hal::{PresentError, PresentStatus, SubmissionResult};
 use hal::{VertexCount, VertexOffset, InstanceCount, IndexCount, WorkGroupCount};
 use hal::format::FormatDesc;
 use hal::image::{ImageLayout, SubresourceRange};
 use hal::query::{Query, QueryControl, QueryId};
-use hal::queue::{RawCommandQueue, RawSubmission};
+use hal::queue::{RawCommandQueue, RawSparseBinding, RawSubmission, SubmissionId};
 
 use metal::{self, MTLViewport, MTLScissorRect, MTLPrimitiveType, MTLClearColor, MTLIndexType, MTLSize, MTLOrigin};
-use cocoa::foundation::NSUInteger;
+use cocoa::base::nil;
+use cocoa::foundation::{NSString, NSUInteger};
 use block::{ConcreteBlock};
 use conversions::map_index_type;
 use soft;
@@ -25,6 +27,10 @@ pub struct CommandQueue(pub(crate) Arc<QueueInner>);
 
 pub(crate) struct QueueInner {
     queue: metal::CommandQueue,
+    // Identifier of the last submission.
+    submission_count: Mutex<u64>,
+    // Identifier of the last completed submission, updated by completion handlers.
+    pub(crate) completed_submission: Arc<Mutex<u64>>,
 }
 
 unsafe impl Send for QueueInner {}
@@ -321,6 +327,34 @@ impl CommandBufferInner {
         }
     }
 
+    /// Record a debug command on the render encoder inside of a render pass.
+    /// Blit and compute encoders are opened on demand, so outside of render passes
+    /// debug groups are recorded on the command buffer to keep them balanced.
+    fn debug_command(&mut self, command: soft::DebugCommand) {
+        let in_render_pass = match self.sink {
+            CommandSink::Immediate { encoder_state: EncoderState::Render(_), .. } => true,
+            CommandSink::Deferred { ref passes, is_encoding: true } => match passes.last() {
+                Some(&soft::Pass::Render(..)) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+        if in_render_pass {
+            self.sink.render_commands(iter::once(soft::RenderCommand::Debug(command)));
+            return;
+        }
+
+        self.stop_encoding();
+        match self.sink {
+            CommandSink::Immediate { ref cmd_buffer, .. } => {
+                exec_debug(&**cmd_buffer, false, &command);
+            }
+            CommandSink::Deferred { ref mut passes, .. } => {
+                passes.push(soft::Pass::Debug(command));
+            }
+        }
+    }
+
     fn begin_render_pass(&mut self, descriptor: metal::RenderPassDescriptor) {
         self.stop_encoding();
 
@@ -535,6 +569,37 @@ fn exec_render(encoder: &metal::RenderCommandEncoderRef, command: &soft::RenderC
                 ];
             }
         }
+        Cmd::Debug(ref command) => {
+            exec_debug(encoder, true, command);
+        }
+    }
+}
+
+/// Record a debug command on an encoder or a command buffer.
+/// Command buffers have no signposts, so these become empty debug groups there.
+fn exec_debug<T: ::objc::Message>(object: &T, has_signposts: bool, command: &soft::DebugCommand) {
+    use soft::DebugCommand as Cmd;
+    unsafe {
+        match *command {
+            Cmd::PushGroup(ref name) => {
+                let label = NSString::alloc(nil).init_str(name);
+                msg_send![object, pushDebugGroup: label];
+                msg_send![label, release];
+            }
+            Cmd::PopGroup => {
+                msg_send![object, popDebugGroup];
+            }
+            Cmd::InsertSignpost(ref name) => {
+                let label = NSString::alloc(nil).init_str(name);
+                if has_signposts {
+                    msg_send![object, insertDebugSignpost: label];
+                } else {
+                    msg_send![object, pushDebugGroup: label];
+                    msg_send![object, popDebugGroup];
+                }
+                msg_send![label, release];
+            }
+        }
     }
 }
 
@@ -651,6 +716,9 @@ fn record_commands(command_buf: &metal::CommandBufferRef, passes: &[soft::Pass])
                 }
                 encoder.end_encoding();
             }
+            soft::Pass::Debug(ref command) => {
+                exec_debug(command_buf, false, command);
+            }
         }
     }
 }
@@ -662,6 +730,8 @@ impl CommandQueue {
     pub fn new(device: &metal::DeviceRef) -> CommandQueue {
         CommandQueue(Arc::new(QueueInner {
             queue: device.new_command_queue(),
+            submission_count: Mutex::new(0),
+            completed_submission: Arc::new(Mutex::new(0)),
         }))
     }
 
@@ -671,7 +741,9 @@ impl CommandQueue {
 }
 
 impl RawCommandQueue<Backend> for CommandQueue {
-    unsafe fn submit_raw<IC>(&mut self, submit: RawSubmission<Backend, IC>, fence: Option<&native::Fence>)
+    unsafe fn submit_raw<IC>(
+        &mut self, submit: RawSubmission<Backend, IC>, fence: Option<&native::Fence>,
+    ) -> SubmissionResult<SubmissionId>
     where
         IC: IntoIterator,
         IC::Item: Borrow<CommandBuffer>,
@@ -692,6 +764,17 @@ impl RawCommandQueue<Backend> for CommandQueue {
             None
         };
 
+        let id = {
+            let mut count = self.0.submission_count.lock().unwrap();
+            *count += 1;
+            *count
+        };
+        let completed = self.0.completed_submission.clone();
+        let progress_block = ConcreteBlock::new(move |_cb: *mut ()| -> () {
+            let mut completed = completed.lock().unwrap();
+            *completed = cmp::max(*completed, id);
+        }).copy();
+
         let buffers = submit.cmd_buffers.into_iter().collect::<Vec<_>>();
         let num_buffers = buffers.len();
         for (i, buffer) in buffers.into_iter().enumerate() {
@@ -716,12 +799,32 @@ impl RawCommandQueue<Backend> for CommandQueue {
                     }).copy();
                     msg_send![command_buffer, addCompletedHandler: fence_block.deref() as *const _];
                 }
+                msg_send![command_buffer, addCompletedHandler: progress_block.deref() as *const _];
             }
             command_buffer.commit();
         }
+
+        // Track empty submissions with an empty command buffer.
+        if num_buffers == 0 {
+            let command_buffer = self.0.queue.new_command_buffer();
+            msg_send![command_buffer, addCompletedHandler: progress_block.deref() as *const _];
+            command_buffer.commit();
+        }
+
+        Ok(SubmissionId(id))
     }
 
-    fn present<IS, IW>(&mut self, swapchains: IS, _wait_semaphores: IW)
+    unsafe fn bind_sparse(
+        &mut self, _binding: RawSparseBinding<Backend>, _fence: Option<&native::Fence>,
+    ) -> SubmissionResult<()> {
+        // No queue family supports sparse binding.
+        error!("Sparse binding is not supported");
+        Ok(())
+    }
+
+    fn present<IS, IW>(
+        &mut self, swapchains: IS, _wait_semaphores: IW,
+    ) -> Result<PresentStatus, PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<window::Swapchain>,
@@ -738,6 +841,8 @@ impl RawCommandQueue<Backend> for CommandQueue {
                 msg_send![render_layer, setContents: io_surface.obj];
             }
         }
+
+        Ok(PresentStatus::Optimal)
     }
 
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
@@ -754,13 +859,21 @@ impl pool::RawCommandPool<Backend> for CommandPool {
         }
     }
 
-    fn allocate(&mut self, num: usize, _level: com::RawLevel) -> Vec<CommandBuffer> { //TODO: Implement secondary buffers
+    fn allocate(&mut self, num: usize, level: com::RawLevel) -> Vec<CommandBuffer> {
         let buffers: Vec<_> = (0..num).map(|_| CommandBuffer {
             inner: Arc::new({
                 UnsafeCell::new(CommandBufferInner {
-                    sink: CommandSink::Immediate {
-                        cmd_buffer: self.queue.queue.new_command_buffer().to_owned(),
-                        encoder_state: EncoderState::None,
+                    // Secondary command buffers are always recorded deferred,
+                    // and get replayed into the encoders of the primary one.
+                    sink: match level {
+                        com::RawLevel::Primary => CommandSink::Immediate {
+                            cmd_buffer: self.queue.queue.new_command_buffer().to_owned(),
+                            encoder_state: EncoderState::None,
+                        },
+                        com::RawLevel::Secondary => CommandSink::Deferred {
+                            passes: Vec::new(),
+                            is_encoding: false,
+                        },
                     },
                     device: unsafe {
                         CommandQueue(self.queue.clone()).device().to_owned()
@@ -779,7 +892,7 @@ impl pool::RawCommandPool<Backend> for CommandPool {
                     depth_stencil_state: None,
                 })
             }),
-            queue: if self.managed.is_some() {
+            queue: if self.managed.is_some() || level == com::RawLevel::Secondary {
                 None
             } else {
                 Some(self.queue.clone())
@@ -835,7 +948,11 @@ impl CommandBuffer {
 }
 
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
-    fn begin(&mut self, flags: com::CommandBufferFlags) {
+    fn begin(
+        &mut self,
+        flags: com::CommandBufferFlags,
+        inheritance_info: com::CommandBufferInheritanceInfo<Backend>,
+    ) {
         let inner = unsafe { &mut *self.inner.get() };
         inner.reset_resources();
 
@@ -862,6 +979,13 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 inner.sink = CommandSink::Deferred { passes, is_encoding: false };
             }
         }
+
+        // Commands of a secondary command buffer continuing a render pass are
+        // recorded into a placeholder pass, which is executed by the encoder of the
+        // primary command buffer. Only the list of commands is used.
+        if let Some(subpass) = inheritance_info.subpass {
+            inner.begin_render_pass(subpass.main_pass.desc.clone());
+        }
     }
 
     fn finish(&mut self) {
@@ -1589,12 +1713,48 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
 
     fn execute_commands<I>(
         &mut self,
-        _buffers: I,
+        buffers: I,
     ) where
         I: IntoIterator,
         I::Item: Borrow<CommandBuffer>
     {
-        unimplemented!()
+        for cmd_buffer in buffers {
+            let cmd_buffer = cmd_buffer.borrow();
+            let passes = match cmd_buffer.inner_ref().sink {
+                CommandSink::Deferred { ref passes, .. } => passes,
+                CommandSink::Immediate { .. } => panic!("Only secondary command buffers can be executed"),
+            };
+            for pass in passes {
+                let inner = self.inner();
+                match *pass {
+                    soft::Pass::Render(_, ref commands) => {
+                        inner.sink.render_commands(commands.iter().cloned());
+                    }
+                    soft::Pass::Blit(ref commands) => {
+                        inner.sink.blit_commands(commands.iter().cloned());
+                    }
+                    soft::Pass::Compute(ref commands) => {
+                        inner.begin_compute();
+                        inner.sink.compute_commands(commands.iter().cloned());
+                        inner.stop_encoding();
+                    }
+                    soft::Pass::Debug(ref command) => {
+                        inner.debug_command(command.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    fn begin_debug_marker(&mut self, name: &str, _color: u32) {
+        self.inner().debug_command(soft::DebugCommand::PushGroup(name.to_owned()));
     }
 
+    fn end_debug_marker(&mut self) {
+        self.inner().debug_command(soft::DebugCommand::PopGroup);
+    }
+
+    fn insert_debug_marker(&mut self, name: &str, _color: u32) {
+        self.inner().debug_command(soft::DebugCommand::InsertSignpost(name.to_owned()));
+    }
 }
@@ -4,20 +4,23 @@ use conversions::*;
 
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
 use std::ops::Range;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::{cmp, mem, ptr, slice};
 
 use hal::{self, error, image, pass, format, mapping, memory, buffer, pso, query};
-use hal::device::{BindError, OutOfMemory, FramebufferError, ShaderError, Extent};
+use hal::device::{BindError, DeviceLost, OutOfMemory, FramebufferError, ShaderError, Extent};
 use hal::memory::Properties;
 use hal::pool::CommandPoolCreateFlags;
 use hal::pso::{DescriptorType, DescriptorSetLayoutBinding, AttributeDesc, DepthTest, StencilTest};
 use hal::queue::{QueueFamily as HalQueueFamily, QueueFamilyId, Queues};
 use hal::range::RangeArg;
 
-use cocoa::foundation::{NSRange, NSUInteger};
+use cocoa::base::{nil, BOOL, YES};
+use cocoa::foundation::{NSRange, NSString, NSUInteger};
 use metal::{self, MTLFeatureSet, MTLLanguageVersion, MTLArgumentAccess, MTLDataType, MTLPrimitiveType, MTLPrimitiveTopologyClass};
 use metal::{MTLVertexStepFunction, MTLSamplerMinMagFilter, MTLSamplerMipFilter, MTLStorageMode, MTLResourceOptions, MTLTextureType};
 use foreign_types::ForeignType;
@@ -46,7 +49,34 @@ fn gen_parse_error(err: SpirvErrorCode) -> ShaderError {
     ShaderError::CompilationFailed(msg)
 }
 
-fn create_function_constants(specialization: &[pso::Specialization]) -> metal::FunctionConstantValues {
+/// Key of a translated shader in the pipeline cache.
+fn translated_shader_key(
+    raw_data: &[u8],
+    overrides: &HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
+) -> u64 {
+    let mut bindings = overrides
+        .iter()
+        .map(|(location, binding)| {
+            (location.stage as u32, location.desc_set, location.binding, binding.resource_id, binding.force_used)
+        })
+        .collect::<Vec<_>>();
+    bindings.sort();
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(raw_data);
+    for &(stage, desc_set, binding, resource_id, force_used) in &bindings {
+        hasher.write_u32(stage);
+        hasher.write_u32(desc_set);
+        hasher.write_u32(binding);
+        hasher.write_u32(resource_id);
+        hasher.write_u8(force_used as u8);
+    }
+    hasher.finish()
+}
+
+fn create_function_constants(
+    specialization: &[pso::Specialization],
+) -> Result<metal::FunctionConstantValues, pso::CreationError> {
     let constants_raw = metal::FunctionConstantValues::new();
     for constant in specialization {
         unsafe {
@@ -55,26 +85,32 @@ fn create_function_constants(specialization: &[pso::Specialization]) -> metal::F
                 pso::Constant::U32(ref v) => (MTLDataType::UInt, v as *const _ as *const _),
                 pso::Constant::I32(ref v) => (MTLDataType::Int, v as *const _ as *const _),
                 pso::Constant::F32(ref v) => (MTLDataType::Float, v as *const _ as *const _),
-                _ => panic!("Unsupported specialization constant type"),
+                // 64-bit function constants are not supported by MSL
+                _ => {
+                    error!("Unsupported specialization constant type {:?}", constant.value);
+                    return Err(pso::CreationError::InvalidSpecialization(constant.id));
+                }
             };
             constants_raw.set_constant_value_at_index(constant.id as u64, ty, value);
         }
     }
-    constants_raw
+    Ok(constants_raw)
 }
 
-fn get_final_function(library: &metal::LibraryRef, entry: &str, specialization: &[pso::Specialization]) -> Result<metal::Function, ()> {
+fn get_final_function(
+    library: &metal::LibraryRef, entry: &str, specialization: &[pso::Specialization],
+) -> Result<metal::Function, pso::CreationError> {
     let initial_constants = if specialization.is_empty() {
         None
     } else {
-        Some(create_function_constants(specialization))
+        Some(create_function_constants(specialization)?)
     };
 
     let mut mtl_function = library
         .get_function(entry, initial_constants)
         .map_err(|_| {
             error!("Invalid vertex shader entry point");
-            ()
+            pso::CreationError::Other
         })?;
     let has_more_function_constants = unsafe {
         let dictionary: *mut ::objc::runtime::Object = msg_send![mtl_function, functionConstantsDictionary];
@@ -87,10 +123,10 @@ fn get_final_function(library: &metal::LibraryRef, entry: &str, specialization:
             // These may be optional function constants, in which case we need to specialize the function with an empty set of constants
             // or we'll get an error when we make the PSO
             mtl_function = library
-                .get_function(entry, Some(create_function_constants(&[])))
+                .get_function(entry, Some(create_function_constants(&[])?))
                 .map_err(|_| {
                     error!("Invalid vertex shader entry point");
-                    ()
+                    pso::CreationError::Other
                 })?;
         }
     }
@@ -98,6 +134,13 @@ fn get_final_function(library: &metal::LibraryRef, entry: &str, specialization:
     Ok(mtl_function)
 }
 
+fn supports_texture_sample_count(device: &metal::DeviceRef, count: image::NumSamples) -> bool {
+    let supported: BOOL = unsafe {
+        msg_send![device, supportsTextureSampleCount: count as NSUInteger]
+    };
+    supported == YES
+}
+
 #[derive(Clone, Copy)]
 struct PrivateCapabilities {
     resource_heaps: bool,
@@ -197,6 +240,50 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         unimplemented!()
     }
 
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        // Mirrors the restrictions of `Device::create_image`.
+        if tiling == image::Tiling::Linear || dimensions != 2 ||
+            storage_flags.intersects(image::StorageFlags::CUBE_VIEW | image::StorageFlags::ARRAY_2D_VIEW)
+        {
+            return None;
+        }
+        map_format(format)?;
+
+        let aspects = format.aspects();
+        if usage.contains(image::Usage::COLOR_ATTACHMENT) && !aspects.contains(format::Aspects::COLOR) {
+            return None;
+        }
+        if usage.contains(image::Usage::DEPTH_STENCIL_ATTACHMENT) && aspects.contains(format::Aspects::COLOR) {
+            return None;
+        }
+
+        let max_size = hal::PhysicalDevice::<Backend>::limits(self).max_texture_size as u32;
+        let sample_count_mask = (0 .. 8)
+            .map(|i| 1 << i)
+            .filter(|&count| supports_texture_sample_count(&self.raw, count))
+            .fold(1, |mask, count| mask | count);
+        let max_samples = 1u64 << (7 - sample_count_mask.leading_zeros());
+        let bytes_per_texel = (format.base_format().0.desc().bits / 8) as u64;
+        Some(image::FormatProperties {
+            max_extent: Extent {
+                width: max_size,
+                height: max_size,
+                depth: 1,
+            },
+            max_levels: (32 - max_size.leading_zeros()) as _,
+            max_layers: 1,
+            sample_count_mask,
+            max_resource_size: max_size as u64 * max_size as u64 * bytes_per_texel * max_samples,
+        })
+    }
+
     fn memory_properties(&self) -> hal::MemoryProperties {
         hal::MemoryProperties {
             memory_heaps: vec![!0, !0], //TODO
@@ -205,7 +292,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
     }
 
     fn features(&self) -> hal::Features {
-        hal::Features::empty() //TODO
+        hal::Features::INDEPENDENT_BLENDING //TODO
     }
 
     fn limits(&self) -> hal::Limits {
@@ -217,6 +304,8 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             min_buffer_copy_offset_alignment: if self.is_mac() {256} else {64},
             min_buffer_copy_pitch_alignment: 4, // TODO: made this up
             min_uniform_buffer_offset_alignment: 1, // TODO
+            buffer_image_granularity: 1,
+            non_coherent_atom_size: 1,
 
             max_compute_group_count: [16; 3], // TODO
             max_compute_group_size: [64; 3], // TODO
@@ -269,11 +358,10 @@ impl Device {
         }
     }
 
-    fn compile_shader_library(
-        &self,
+    fn translate_shader(
         raw_data: &[u8],
         overrides: &HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
-    ) -> Result<(metal::Library, HashMap<String, spirv::EntryPoint>), ShaderError> {
+    ) -> Result<n::TranslatedShader, ShaderError> {
         // spec requires "codeSize must be a multiple of 4"
         assert_eq!(raw_data.len() & 3, 0);
 
@@ -332,27 +420,67 @@ impl Device {
                     };
                     ShaderError::CompilationFailed(msg)
                 })?;
-            entry_point_map.insert(entry_point.name, spirv::EntryPoint {
+            let work_group_size = entry_point.work_group_size;
+            entry_point_map.insert(entry_point.name, n::EntryPoint {
                 name: cleansed,
-                .. entry_point
+                work_group_size: [work_group_size.x, work_group_size.y, work_group_size.z],
             });
         }
 
         // done
         debug!("SPIRV-Cross generated shader:\n{}", shader_code);
 
+        Ok(n::TranslatedShader {
+            source: shader_code,
+            entry_points: entry_point_map,
+        })
+    }
+
+    fn compile_library(&self, source: &str) -> Result<metal::Library, ShaderError> {
         let options = metal::CompileOptions::new();
         options.set_language_version(MTLLanguageVersion::V1_2);
 
-        let library = self.device
-            .new_library_with_source(shader_code.as_ref(), &options)
-            .map_err(|err| ShaderError::CompilationFailed(err.into()))?;
+        self.device
+            .new_library_with_source(source, &options)
+            .map_err(|err| ShaderError::CompilationFailed(err.into()))
+    }
+
+    fn compile_shader_library(
+        &self,
+        raw_data: &[u8],
+        overrides: &HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
+    ) -> Result<(metal::Library, HashMap<String, n::EntryPoint>), ShaderError> {
+        let shader = Self::translate_shader(raw_data, overrides)?;
+        let library = self.compile_library(&shader.source)?;
+        Ok((library, shader.entry_points))
+    }
+
+    /// Translate a SPIR-V shader, reusing the translation stored in `cache` if available.
+    fn translate_shader_cached(
+        raw_data: &[u8],
+        overrides: &HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
+        cache: Option<&n::PipelineCache>,
+    ) -> Result<n::TranslatedShader, ShaderError> {
+        let cache = match cache {
+            Some(cache) => cache,
+            None => return Self::translate_shader(raw_data, overrides),
+        };
+
+        let key = translated_shader_key(raw_data, overrides);
+        if let Some(shader) = cache.shaders.lock().unwrap().get(&key) {
+            return Ok(shader.clone());
+        }
 
-        Ok((library, entry_point_map))
+        let shader = Self::translate_shader(raw_data, overrides)?;
+        cache.shaders.lock().unwrap().insert(key, shader.clone());
+        Ok(shader)
     }
 
     fn load_shader(
-        &self, ep: &pso::EntryPoint<Backend>, layout: &n::PipelineLayout
+        &self,
+        ep: &pso::EntryPoint<Backend>,
+        layout: &n::PipelineLayout,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<(metal::Library, metal::Function, metal::MTLSize), pso::CreationError> {
         let entries_owned;
         let (lib, entry_point_map) = match *ep.module {
@@ -360,26 +488,25 @@ impl Device {
                 (library.to_owned(), entry_point_map)
             }
             n::ShaderModule::Raw(ref data) => {
-                let raw = self.compile_shader_library(data, &layout.res_overrides).unwrap();
-                entries_owned = raw.1;
-                (raw.0, &entries_owned)
+                let shader = Self::translate_shader_cached(data, &layout.res_overrides, cache)
+                    .map_err(pso::CreationError::Shader)?;
+                let library = self.compile_library(&shader.source)
+                    .map_err(pso::CreationError::Shader)?;
+                entries_owned = shader.entry_points;
+                (library, &entries_owned)
             }
         };
 
         let (name, wg_size) = match entry_point_map.get(ep.entry) {
             Some(p) => (p.name.as_str(), metal::MTLSize {
-                width : p.work_group_size.x as _,
-                height: p.work_group_size.y as _,
-                depth : p.work_group_size.z as _,
+                width : p.work_group_size[0] as _,
+                height: p.work_group_size[1] as _,
+                depth : p.work_group_size[2] as _,
             }),
             // this can only happen if the shader came directly from the user
             None => (ep.entry, metal::MTLSize { width: 0, height: 0, depth: 0 }),
         };
-        let mtl_function = get_final_function(&lib, name, ep.specialization)
-            .map_err(|_| {
-                error!("Invalid shader entry point");
-                pso::CreationError::Other
-            })?;
+        let mtl_function = get_final_function(&lib, name, ep.specialization)?;
 
         Ok((lib, mtl_function, wg_size))
     }
@@ -421,15 +548,15 @@ impl Device {
 impl hal::Device<Backend> for Device {
     fn create_command_pool(
         &self, _family: QueueFamilyId, flags: CommandPoolCreateFlags
-    ) -> command::CommandPool {
-        command::CommandPool {
+    ) -> Result<command::CommandPool, OutOfMemory> {
+        Ok(command::CommandPool {
             queue: self.queue.clone(),
             managed: if flags.contains(CommandPoolCreateFlags::RESET_INDIVIDUAL) {
                 None
             } else {
                 Some(Vec::new())
             },
-        }
+        })
     }
 
     fn destroy_command_pool(&self, _pool: command::CommandPool) {
@@ -510,7 +637,7 @@ impl hal::Device<Backend> for Device {
 
         for (set_index, set_layout) in set_layouts.into_iter().enumerate() {
             match set_layout.borrow() {
-                &n::DescriptorSetLayout::Emulated(ref set_bindings) => {
+                &n::DescriptorSetLayout::Emulated(ref set_bindings, _) => {
                     for set_binding in set_bindings {
                         for &mut(stage_bit, stage, ref mut counters) in stage_infos.iter_mut() {
                             if !set_binding.stage_flags.contains(stage_bit) {
@@ -539,7 +666,7 @@ impl hal::Device<Backend> for Device {
                         }
                     }
                 }
-                &n::DescriptorSetLayout::ArgumentBuffer(_, stage_flags) => {
+                &n::DescriptorSetLayout::ArgumentBuffer(_, stage_flags, _) => {
                     for &mut(stage_bit, stage, ref mut counters) in stage_infos.iter_mut() {
                         if !stage_flags.contains(stage_bit) {
                             continue
@@ -573,9 +700,42 @@ impl hal::Device<Backend> for Device {
         }
     }
 
+    fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Result<n::PipelineCache, OutOfMemory> {
+        Ok(data
+            .and_then(|data| {
+                let cache = n::PipelineCache::from_data(data);
+                if cache.is_none() {
+                    warn!("Discarding invalid pipeline cache data");
+                }
+                cache
+            })
+            .unwrap_or_default())
+    }
+
+    fn get_pipeline_cache_data(&self, cache: &n::PipelineCache) -> Result<Vec<u8>, OutOfMemory> {
+        Ok(cache.data())
+    }
+
+    fn merge_pipeline_caches<I>(&self, target: &n::PipelineCache, sources: I) -> Result<(), OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::PipelineCache>,
+    {
+        for source in sources {
+            let source = source.borrow();
+            if source as *const _ == target as *const _ {
+                continue;
+            }
+            let shaders = source.shaders.lock().unwrap().clone();
+            target.shaders.lock().unwrap().extend(shaders);
+        }
+        Ok(())
+    }
+
     fn create_graphics_pipeline<'a>(
         &self,
         pipeline_desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
         let pipeline = metal::RenderPipelineDescriptor::new();
         let pipeline_layout = &pipeline_desc.layout;
@@ -599,13 +759,13 @@ impl hal::Device<Backend> for Device {
         pipeline.set_input_primitive_topology(primitive_class);
 
         // Vertex shader
-        let (vs_lib, vs_function, _) = self.load_shader(&pipeline_desc.shaders.vertex, pipeline_layout)?;
+        let (vs_lib, vs_function, _) = self.load_shader(&pipeline_desc.shaders.vertex, pipeline_layout, cache)?;
         pipeline.set_vertex_function(Some(&vs_function));
 
         // Fragment shader
         let fs_lib = match pipeline_desc.shaders.fragment {
             Some(ref ep) => {
-                let (lib, fun, _) = self.load_shader(ep, pipeline_layout)?;
+                let (lib, fun, _) = self.load_shader(ep, pipeline_layout, cache)?;
                 pipeline.set_fragment_function(Some(&fun));
                 Some(lib)
             }
@@ -742,10 +902,11 @@ impl hal::Device<Backend> for Device {
     fn create_compute_pipeline<'a>(
         &self,
         pipeline_desc: &pso::ComputePipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
     ) -> Result<n::ComputePipeline, pso::CreationError> {
         let pipeline = metal::ComputePipelineDescriptor::new();
 
-        let (cs_lib, cs_function, work_group_size) = self.load_shader(&pipeline_desc.shader, &pipeline_desc.layout)?;
+        let (cs_lib, cs_function, work_group_size) = self.load_shader(&pipeline_desc.shader, &pipeline_desc.layout, cache)?;
         pipeline.set_compute_function(Some(&cs_function));
 
         let mut err_ptr: *mut ObjcObject = ptr::null_mut();
@@ -816,7 +977,7 @@ impl hal::Device<Backend> for Device {
         })
     }
 
-    fn create_sampler(&self, info: image::SamplerInfo) -> n::Sampler {
+    fn create_sampler(&self, info: image::SamplerInfo) -> Result<n::Sampler, OutOfMemory> {
         let descriptor = metal::SamplerDescriptor::new();
 
         use self::image::FilterMethod::*;
@@ -839,7 +1000,7 @@ impl hal::Device<Backend> for Device {
 
         // FIXME: more state
 
-        n::Sampler(self.device.new_sampler(&descriptor))
+        Ok(n::Sampler(self.device.new_sampler(&descriptor)))
     }
 
     fn destroy_sampler(&self, _sampler: n::Sampler) {
@@ -916,17 +1077,19 @@ impl hal::Device<Backend> for Device {
         // Do nothing.
     }
 
-    fn create_semaphore(&self) -> n::Semaphore {
-        unsafe { n::Semaphore(n::dispatch_semaphore_create(1)) } // Returns retained
+    fn create_semaphore(&self) -> Result<n::Semaphore, OutOfMemory> {
+        Ok(unsafe { n::Semaphore(n::dispatch_semaphore_create(1)) }) // Returns retained
     }
 
-    fn create_descriptor_pool<I>(&self, _max_sets: usize, descriptor_ranges: I) -> n::DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self, _max_sets: usize, descriptor_ranges: I, _flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<n::DescriptorPool, OutOfMemory>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>,
     {
         if !self.private_caps.argument_buffers {
-            return n::DescriptorPool::Emulated;
+            return Ok(n::DescriptorPool::Emulated);
         }
 
         let mut num_samplers = 0;
@@ -952,34 +1115,56 @@ impl hal::Device<Backend> for Device {
         let total_size = encoder.encoded_length();
         let buffer = self.device.new_buffer(total_size, MTLResourceOptions::empty());
 
-        n::DescriptorPool::ArgumentBuffer {
-            buffer,
-            total_size,
-            offset: 0,
-        }
+        Ok(n::DescriptorPool::new_argument(buffer, total_size))
     }
 
-    fn create_descriptor_set_layout<I>(&self, bindings: I) -> n::DescriptorSetLayout
+    fn create_descriptor_set_layout<I, J>(
+        &self, bindings: I, immutable_samplers: J
+    ) -> n::DescriptorSetLayout
     where
         I: IntoIterator,
         I::Item: Borrow<DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<n::Sampler>,
     {
+        let bindings = bindings
+            .into_iter()
+            .map(|desc| desc.borrow().clone())
+            .collect::<Vec<_>>();
+
+        // Metal has no notion of immutable samplers, they are assigned
+        // to every descriptor set allocated with this layout instead.
+        let mut sampler_iter = immutable_samplers.into_iter();
+        let immutable_samplers = bindings
+            .iter()
+            .filter(|desc| desc.immutable_samplers)
+            .map(|desc| {
+                let samplers = sampler_iter
+                    .by_ref()
+                    .take(desc.count)
+                    .map(|sampler| sampler.borrow().0.clone())
+                    .collect::<Vec<_>>();
+                debug_assert_eq!(samplers.len(), desc.count, "Not enough immutable samplers");
+                if samplers.len() < desc.count {
+                    error!("Not enough immutable samplers for binding {}", desc.binding);
+                }
+                (desc.binding, samplers)
+            })
+            .collect();
+
         if !self.private_caps.argument_buffers {
-            return n::DescriptorSetLayout::Emulated(
-                bindings.into_iter().map(|desc| desc.borrow().clone()).collect()
-            )
+            return n::DescriptorSetLayout::Emulated(bindings, immutable_samplers)
         }
 
         let mut stage_flags = pso::ShaderStageFlags::empty();
-        let arguments = bindings.into_iter().map(|desc| {
-            let desc = desc.borrow();
+        let arguments = bindings.iter().map(|desc| {
             stage_flags |= desc.stage_flags;
             Self::describe_argument(desc.ty, desc.binding, desc.count)
         }).collect::<Vec<_>>();
         let arg_array = metal::Array::from_owned_slice(&arguments);
         let encoder = self.device.new_argument_encoder(&arg_array);
 
-        n::DescriptorSetLayout::ArgumentBuffer(encoder, stage_flags)
+        n::DescriptorSetLayout::ArgumentBuffer(encoder, stage_flags, immutable_samplers)
     }
 
     fn write_descriptor_sets<'a, I, J>(&self, write_iter: I)
@@ -1004,7 +1189,13 @@ impl hal::Device<Backend> for Device {
                             array_offset = 0;
                             binding += 1;
                         }
+                        let immutable_samplers = set.layout.iter()
+                            .find(|layout| layout.binding == binding)
+                            .unwrap()
+                            .immutable_samplers;
                         match (descriptor.borrow(), set.bindings.get_mut(&binding).unwrap()) {
+                            (&pso::Descriptor::Sampler(_), &mut n::DescriptorSetBinding::Sampler(_))
+                                if immutable_samplers => {}
                             (&pso::Descriptor::Sampler(sampler), &mut n::DescriptorSetBinding::Sampler(ref mut vec)) => {
                                 vec[array_offset] = Some(sampler.0.clone());
                             }
@@ -1064,6 +1255,42 @@ impl hal::Device<Backend> for Device {
         }
     }
 
+    fn create_descriptor_update_template<I>(
+        &self, layout: &n::DescriptorSetLayout, entries: I
+    ) -> n::DescriptorUpdateTemplate
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|entry| *entry.borrow())
+            .collect::<Vec<_>>();
+        if let n::DescriptorSetLayout::Emulated(ref bindings, _) = *layout {
+            for entry in &entries {
+                let binding = bindings
+                    .iter()
+                    .find(|layout| layout.binding == entry.binding)
+                    .expect("Descriptor update template doesn't match the set layout!");
+                assert!(entry.array_offset + entry.count <= binding.count);
+            }
+        }
+
+        n::DescriptorUpdateTemplate { entries }
+    }
+
+    fn destroy_descriptor_update_template(&self, _template: n::DescriptorUpdateTemplate) {
+    }
+
+    unsafe fn update_descriptor_set_with_template(
+        &self, set: &n::DescriptorSet, template: &n::DescriptorUpdateTemplate, data: &[u8]
+    ) {
+        self.write_descriptor_sets(template.entries
+            .iter()
+            .map(|entry| entry.write::<Backend>(set, data))
+        );
+    }
+
     fn destroy_descriptor_pool(&self, _pool: n::DescriptorPool) {
     }
 
@@ -1073,6 +1300,9 @@ impl hal::Device<Backend> for Device {
     fn destroy_pipeline_layout(&self, _pipeline_layout: n::PipelineLayout) {
     }
 
+    fn destroy_pipeline_cache(&self, _cache: n::PipelineCache) {
+    }
+
     fn destroy_shader_module(&self, _module: n::ShaderModule) {
     }
 
@@ -1192,6 +1422,17 @@ impl hal::Device<Backend> for Device {
         }
     }
 
+    fn create_sparse_buffer(
+        &self, _size: u64, _usage: buffer::Usage, _flags: memory::SparseFlags,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // Sparse binding is not exposed in `features`.
+        Err(buffer::CreationError::Sparse)
+    }
+
+    fn get_sparse_buffer_requirements(&self, _buffer: &n::Buffer) -> memory::Requirements {
+        unreachable!("sparse buffers can't be created")
+    }
+
     fn create_buffer_view<R: RangeArg<u64>>(
         &self, _buffer: &n::Buffer, _format: Option<format::Format>, _range: R
     ) -> Result<n::BufferView, buffer::ViewError> {
@@ -1203,9 +1444,23 @@ impl hal::Device<Backend> for Device {
     }
 
     fn create_image(
-        &self, kind: image::Kind, mip_levels: image::Level, format: format::Format, usage: image::Usage)
-         -> Result<n::UnboundImage, image::CreationError>
-    {
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Result<n::UnboundImage, image::CreationError> {
+        // TODO: linear images could be emulated with textures backed by buffers
+        if tiling == image::Tiling::Linear {
+            return Err(image::CreationError::Tiling(tiling));
+        }
+        // Textures always allow format reinterpretation, but only 2D textures are created.
+        if storage_flags.intersects(image::StorageFlags::CUBE_VIEW | image::StorageFlags::ARRAY_2D_VIEW) {
+            return Err(image::CreationError::StorageFlags(storage_flags));
+        }
+
         let base_format = format.base_format();
         let format_desc = base_format.0.desc();
         let (mtl_format, _) = map_format(format).ok_or(image::CreationError::Format(format))?;
@@ -1213,8 +1468,22 @@ impl hal::Device<Backend> for Device {
         let descriptor = metal::TextureDescriptor::new();
 
         match kind {
-            image::Kind::D2(width, height, _aa) => {
-                descriptor.set_texture_type(MTLTextureType::D2);
+            image::Kind::D2(width, height, aa) => {
+                match aa {
+                    image::AaMode::Single => {
+                        descriptor.set_texture_type(MTLTextureType::D2);
+                    }
+                    image::AaMode::Multi(_) if mip_levels != 1 => {
+                        return Err(image::CreationError::Kind);
+                    }
+                    image::AaMode::Multi(samples) if supports_texture_sample_count(&self.device, samples) => {
+                        descriptor.set_texture_type(MTLTextureType::D2Multisample);
+                        unsafe {
+                            msg_send![&*descriptor, setSampleCount: samples as NSUInteger];
+                        }
+                    }
+                    _ => return Err(image::CreationError::Samples(aa)),
+                }
                 descriptor.set_width(width as u64);
                 descriptor.set_height(height as u64);
             },
@@ -1261,6 +1530,41 @@ impl hal::Device<Backend> for Device {
         }
     }
 
+    fn create_sparse_image(
+        &self,
+        _kind: image::Kind,
+        _mip_levels: image::Level,
+        _format: format::Format,
+        _usage: image::Usage,
+        _storage_flags: image::StorageFlags,
+        _flags: memory::SparseFlags,
+    ) -> Result<n::Image, image::CreationError> {
+        // Sparse binding is not exposed in `features`.
+        Err(image::CreationError::Sparse)
+    }
+
+    fn get_sparse_image_requirements(&self, _image: &n::Image) -> memory::Requirements {
+        unreachable!("sparse images can't be created")
+    }
+
+    fn get_sparse_image_page_layout(&self, _image: &n::Image) -> Vec<image::SparsePageLayout> {
+        unreachable!("sparse images can't be created")
+    }
+
+    fn get_image_subresource_footprint(
+        &self, image: &n::Image, _aspect: format::Aspects, sub: image::Subresource
+    ) -> image::SubresourceFootprint {
+        // Linear images are rejected by `create_image`, return the packed
+        // layout used by blits between the image and a buffer instead.
+        let kind = image::Kind::D2(
+            image.raw.width() as _,
+            image.raw.height() as _,
+            image::AaMode::Single,
+        );
+        let num_levels = image.raw.mipmap_level_count() as _;
+        image::SubresourceFootprint::packed(kind, num_levels, image.format_desc, sub)
+    }
+
     fn bind_image_memory(
         &self, memory: &n::Memory, _offset: u64, image: n::UnboundImage
     ) -> Result<n::Image, BindError> {
@@ -1319,28 +1623,33 @@ impl hal::Device<Backend> for Device {
 
     // Emulated fence implementations
     #[cfg(not(feature = "native_fence"))]
-    fn create_fence(&self, signaled: bool) -> n::Fence {
-        n::Fence(Arc::new(Mutex::new(signaled)))
+    fn create_fence(&self, signaled: bool) -> Result<n::Fence, OutOfMemory> {
+        Ok(n::Fence(Arc::new(Mutex::new(signaled))))
     }
     fn reset_fence(&self, fence: &n::Fence) {
         *fence.0.lock().unwrap() = false;
     }
-    fn wait_for_fence(&self, fence: &n::Fence, mut timeout_ms: u32) -> bool {
+    fn wait_for_fence(&self, fence: &n::Fence, mut timeout_ms: u32) -> Result<bool, DeviceLost> {
         use std::{thread, time};
         let tick = 1;
         loop {
             if *fence.0.lock().unwrap() {
-                return true
+                return Ok(true)
             }
             if timeout_ms < tick {
-                return false
+                return Ok(false)
             }
             timeout_ms -= tick;
             thread::sleep(time::Duration::from_millis(tick as u64));
         }
     }
-    fn get_fence_status(&self, fence: &n::Fence) -> bool {
-        *fence.0.lock().unwrap()
+    fn get_fence_status(&self, fence: &n::Fence) -> Result<bool, DeviceLost> {
+        Ok(*fence.0.lock().unwrap())
+    }
+    fn get_submission_status(
+        &self, queue: &command::CommandQueue, id: hal::queue::SubmissionId,
+    ) -> Result<bool, DeviceLost> {
+        Ok(*queue.0.completed_submission.lock().unwrap() >= id.0)
     }
     #[cfg(not(feature = "native_fence"))]
     fn destroy_fence(&self, _fence: n::Fence) {
@@ -1365,6 +1674,26 @@ impl hal::Device<Backend> for Device {
     fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
         unimplemented!()
     }
+
+    fn set_object_name(&self, object: hal::device::NamedObject<Backend>, name: &str) {
+        use hal::device::NamedObject;
+        let object = match object {
+            NamedObject::Buffer(buffer) => buffer.raw.as_ptr() as *mut ObjcObject,
+            NamedObject::Image(image) => image.raw.as_ptr() as *mut ObjcObject,
+            NamedObject::ImageView(view) => view.0.as_ptr() as *mut ObjcObject,
+            // Labels of samplers and pipeline states are immutable after creation.
+            NamedObject::Sampler(_) |
+            NamedObject::GraphicsPipeline(_) |
+            NamedObject::ComputePipeline(_) => return,
+            // TODO: command buffers are created on submission for deferred recording.
+            NamedObject::CommandBuffer(_) => return,
+        };
+        unsafe {
+            let label = NSString::alloc(nil).init_str(name);
+            msg_send![object, setLabel: label];
+            msg_send![label, release];
+        }
+    }
 }
 
 #[test]
@@ -12,7 +12,7 @@ use cocoa::foundation::{NSRange, NSUInteger};
 use foreign_types::ForeignType;
 use metal::{self, MTLPrimitiveType};
 use objc;
-use spirv_cross::{msl, spirv};
+use spirv_cross::msl;
 
 
 /// Shader module can be compiled in advance if it's resource bindings do not
@@ -21,7 +21,7 @@ use spirv_cross::{msl, spirv};
 pub enum ShaderModule {
     Compiled {
         library: metal::Library,
-        entry_point_map: HashMap<String, spirv::EntryPoint>,
+        entry_point_map: HashMap<String, EntryPoint>,
     },
     Raw(Vec<u8>),
 }
@@ -29,6 +29,126 @@ pub enum ShaderModule {
 unsafe impl Send for ShaderModule {}
 unsafe impl Sync for ShaderModule {}
 
+/// Entry point of a shader translated to MSL.
+#[derive(Clone, Debug)]
+pub struct EntryPoint {
+    /// Cleansed MSL function name.
+    pub(crate) name: String,
+    pub(crate) work_group_size: [u32; 3],
+}
+
+/// SPIR-V shader translated to MSL, indexed by the SPIR-V entry point names.
+#[derive(Clone, Debug)]
+pub struct TranslatedShader {
+    pub(crate) source: String,
+    pub(crate) entry_points: HashMap<String, EntryPoint>,
+}
+
+const PIPELINE_CACHE_MAGIC: &[u8] = b"GFXMTLPC";
+const PIPELINE_CACHE_VERSION: u32 = 1;
+
+/// Caches the MSL translation of shaders, keyed by a hash of the SPIR-V module
+/// and the resource bindings of the pipeline layout.
+#[derive(Debug, Default)]
+pub struct PipelineCache {
+    pub(crate) shaders: Mutex<HashMap<u64, TranslatedShader>>,
+}
+
+impl PipelineCache {
+    /// Load a cache from serialized data, returns `None` for invalid data.
+    pub(crate) fn from_data(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader(data);
+        if reader.bytes(PIPELINE_CACHE_MAGIC.len())? != PIPELINE_CACHE_MAGIC ||
+            reader.u32()? != PIPELINE_CACHE_VERSION
+        {
+            return None;
+        }
+
+        let mut shaders = HashMap::new();
+        for _ in 0 .. reader.u32()? {
+            let key = reader.u64()?;
+            let source = reader.string()?;
+            let mut entry_points = HashMap::new();
+            for _ in 0 .. reader.u32()? {
+                let spirv_name = reader.string()?;
+                let name = reader.string()?;
+                let work_group_size = [reader.u32()?, reader.u32()?, reader.u32()?];
+                entry_points.insert(spirv_name, EntryPoint { name, work_group_size });
+            }
+            shaders.insert(key, TranslatedShader { source, entry_points });
+        }
+
+        Some(PipelineCache {
+            shaders: Mutex::new(shaders),
+        })
+    }
+
+    /// Serialize the cached shaders.
+    pub(crate) fn data(&self) -> Vec<u8> {
+        let shaders = self.shaders.lock().unwrap();
+        let mut data = PIPELINE_CACHE_MAGIC.to_vec();
+        write_u32(&mut data, PIPELINE_CACHE_VERSION);
+        write_u32(&mut data, shaders.len() as _);
+        for (&key, shader) in shaders.iter() {
+            write_u64(&mut data, key);
+            write_str(&mut data, &shader.source);
+            write_u32(&mut data, shader.entry_points.len() as _);
+            for (spirv_name, entry_point) in &shader.entry_points {
+                write_str(&mut data, spirv_name);
+                write_str(&mut data, &entry_point.name);
+                for &size in &entry_point.work_group_size {
+                    write_u32(&mut data, size);
+                }
+            }
+        }
+        data
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|bytes| {
+            bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as u32)
+        })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let low = self.u32()? as u64;
+        let high = self.u32()? as u64;
+        Some(high << 32 | low)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).ok()
+    }
+}
+
+fn write_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend((0 .. 4).map(|i| (value >> (8 * i)) as u8));
+}
+
+fn write_u64(data: &mut Vec<u8>, value: u64) {
+    write_u32(data, value as u32);
+    write_u32(data, (value >> 32) as u32);
+}
+
+fn write_str(data: &mut Vec<u8>, value: &str) {
+    write_u32(data, value.len() as _);
+    data.extend_from_slice(value.as_bytes());
+}
+
 #[derive(Debug)]
 pub struct RenderPass {
     pub(crate) desc: metal::RenderPassDescriptor,
@@ -125,24 +245,83 @@ pub enum DescriptorPool {
     ArgumentBuffer {
         buffer: metal::Buffer,
         total_size: NSUInteger,
-        offset: NSUInteger,
+        /// Sorted list of the unused ranges of the buffer.
+        free_ranges: Vec<Range<NSUInteger>>,
     }
 }
 //TODO: re-evaluate Send/Sync here
 unsafe impl Send for DescriptorPool {}
 unsafe impl Sync for DescriptorPool {}
 
+impl DescriptorPool {
+    pub(crate) fn new_argument(buffer: metal::Buffer, total_size: NSUInteger) -> Self {
+        DescriptorPool::ArgumentBuffer {
+            buffer,
+            total_size,
+            free_ranges: vec![0 .. total_size],
+        }
+    }
+}
+
+fn allocate_range(
+    free_ranges: &mut Vec<Range<NSUInteger>>, size: NSUInteger,
+) -> Result<NSUInteger, pso::AllocationError> {
+    match free_ranges.iter().position(|range| range.end - range.start >= size) {
+        Some(index) => {
+            let offset = free_ranges[index].start;
+            free_ranges[index].start += size;
+            if free_ranges[index].start == free_ranges[index].end {
+                free_ranges.remove(index);
+            }
+            Ok(offset)
+        }
+        None => {
+            let total_free = free_ranges.iter().map(|range| range.end - range.start).sum::<NSUInteger>();
+            if total_free >= size {
+                Err(pso::AllocationError::FragmentedPool)
+            } else {
+                Err(pso::AllocationError::OutOfPoolMemory)
+            }
+        }
+    }
+}
+
+fn free_range(free_ranges: &mut Vec<Range<NSUInteger>>, range: Range<NSUInteger>) {
+    let index = free_ranges
+        .iter()
+        .position(|r| r.start > range.start)
+        .unwrap_or(free_ranges.len());
+    free_ranges.insert(index, range);
+    // merge with the following and the preceding range
+    if index + 1 < free_ranges.len() && free_ranges[index].end == free_ranges[index + 1].start {
+        free_ranges[index].end = free_ranges.remove(index + 1).end;
+    }
+    if index > 0 && free_ranges[index - 1].end == free_ranges[index].start {
+        free_ranges[index - 1].end = free_ranges.remove(index).end;
+    }
+}
+
 impl hal::DescriptorPool<Backend> for DescriptorPool {
-    fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> DescriptorSet {
+    fn allocate_set(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet, pso::AllocationError> {
         match *self {
             DescriptorPool::Emulated => {
-                let layout_bindings = match layout {
-                    &DescriptorSetLayout::Emulated(ref bindings) => bindings,
+                let (layout_bindings, immutable_samplers) = match layout {
+                    &DescriptorSetLayout::Emulated(ref bindings, ref samplers) => (bindings, samplers),
                     _ => panic!("Incompatible descriptor set layout type"),
                 };
 
                 let bindings = layout_bindings.iter().map(|layout| {
                     let binding = match layout.ty {
+                        pso::DescriptorType::Sampler if layout.immutable_samplers => {
+                            let &(_, ref samplers) = immutable_samplers
+                                .iter()
+                                .find(|&&(binding, _)| binding == layout.binding)
+                                .unwrap();
+                            let mut samplers = samplers.iter().cloned().map(Some).collect::<Vec<_>>();
+                            // Missing immutable samplers are left unbound.
+                            samplers.resize(layout.count, None);
+                            DescriptorSetBinding::Sampler(samplers)
+                        }
                         pso::DescriptorType::Sampler => {
                             DescriptorSetBinding::Sampler(vec![None; layout.count])
                         }
@@ -163,41 +342,79 @@ impl hal::DescriptorPool<Backend> for DescriptorPool {
                     layout: layout_bindings.to_vec(),
                     bindings,
                 };
-                DescriptorSet::Emulated(Arc::new(Mutex::new(inner)))
+                Ok(DescriptorSet::Emulated(Arc::new(Mutex::new(inner))))
             }
-            DescriptorPool::ArgumentBuffer { ref buffer, total_size, ref mut offset } => {
-                let (encoder, stage_flags) = match layout {
-                    &DescriptorSetLayout::ArgumentBuffer(ref encoder, stages) => (encoder, stages),
+            DescriptorPool::ArgumentBuffer { ref buffer, ref mut free_ranges, .. } => {
+                let (encoder, stage_flags, immutable_samplers) = match layout {
+                    &DescriptorSetLayout::ArgumentBuffer(ref encoder, stages, ref samplers) => (encoder, stages, samplers),
                     _ => panic!("Incompatible descriptor set layout type"),
                 };
 
-                let cur_offset = *offset;
-                *offset += encoder.encoded_length();
-                assert!(*offset <= total_size);
+                let cur_offset = allocate_range(free_ranges, encoder.encoded_length())?;
 
-                DescriptorSet::ArgumentBuffer {
+                if !immutable_samplers.is_empty() {
+                    encoder.set_argument_buffer(buffer, cur_offset);
+                    for &(binding, ref samplers) in immutable_samplers {
+                        let samplers = samplers.iter().map(|s| &**s).collect::<Vec<_>>();
+                        encoder.set_sampler_states(&samplers, binding as _);
+                    }
+                }
+
+                Ok(DescriptorSet::ArgumentBuffer {
                     buffer: buffer.clone(),
                     offset: cur_offset,
                     encoder: encoder.clone(),
                     stage_flags,
+                })
+            }
+        }
+    }
+
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        match *self {
+            DescriptorPool::Emulated => {} // sets are just dropped
+            DescriptorPool::ArgumentBuffer { ref mut free_ranges, .. } => {
+                for set in descriptor_sets {
+                    match set {
+                        DescriptorSet::ArgumentBuffer { offset, ref encoder, .. } => {
+                            free_range(free_ranges, offset .. offset + encoder.encoded_length());
+                        }
+                        DescriptorSet::Emulated(_) => panic!("Incompatible descriptor set type"),
+                    }
                 }
             }
         }
     }
 
     fn reset(&mut self) {
-        unimplemented!()
+        match *self {
+            DescriptorPool::Emulated => {}
+            DescriptorPool::ArgumentBuffer { total_size, ref mut free_ranges, .. } => {
+                *free_ranges = vec![0 .. total_size];
+            }
+        }
     }
 }
 
+/// Immutable samplers of a set layout, grouped by binding.
+pub type ImmutableSamplers = Vec<(pso::DescriptorBinding, Vec<metal::SamplerState>)>;
+
 #[derive(Debug)]
 pub enum DescriptorSetLayout {
-    Emulated(Vec<pso::DescriptorSetLayoutBinding>),
-    ArgumentBuffer(metal::ArgumentEncoder, pso::ShaderStageFlags),
+    Emulated(Vec<pso::DescriptorSetLayoutBinding>, ImmutableSamplers),
+    ArgumentBuffer(metal::ArgumentEncoder, pso::ShaderStageFlags, ImmutableSamplers),
 }
 unsafe impl Send for DescriptorSetLayout {}
 unsafe impl Sync for DescriptorSetLayout {}
 
+#[derive(Debug)]
+pub struct DescriptorUpdateTemplate {
+    pub(crate) entries: Vec<pso::DescriptorUpdateTemplateEntry>,
+}
+
 #[derive(Clone, Debug)]
 pub enum DescriptorSet {
     Emulated(Arc<Mutex<DescriptorSetInner>>),
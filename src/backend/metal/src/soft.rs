@@ -6,6 +6,7 @@ use metal;
 use std::ops::Range;
 
 
+#[derive(Clone)]
 pub enum RenderCommand {
     SetViewport(metal::MTLViewport),
     SetScissor(metal::MTLScissorRect),
@@ -37,9 +38,11 @@ pub enum RenderCommand {
         indices: Range<hal::IndexCount>,
         base_vertex: hal::VertexOffset,
         instances: Range<hal::InstanceCount>,
-    }
+    },
+    Debug(DebugCommand),
 }
 
+#[derive(Clone)]
 pub enum BlitCommand {
     CopyBuffer {
         src: metal::Buffer,
@@ -60,6 +63,7 @@ pub enum BlitCommand {
     },
 }
 
+#[derive(Clone)]
 pub enum ComputeCommand {
     BindBuffer {
         index: usize,
@@ -86,8 +90,17 @@ pub enum ComputeCommand {
     },
 }
 
+#[derive(Clone)]
+pub enum DebugCommand {
+    PushGroup(String),
+    PopGroup,
+    InsertSignpost(String),
+}
+
 pub enum Pass {
     Render(metal::RenderPassDescriptor, Vec<RenderCommand>),
     Blit(Vec<BlitCommand>),
     Compute(Vec<ComputeCommand>),
+    /// Debug command recorded outside of a render pass, on the command buffer.
+    Debug(DebugCommand),
 }
@@ -167,7 +167,11 @@ impl Device {
 }
 
 impl hal::Swapchain<Backend> for Swapchain {
-    fn acquire_frame(&mut self, sync: hal::FrameSync<Backend>) -> hal::Frame {
+    fn acquire_frame(
+        &mut self, sync: hal::FrameSync<Backend>, _timeout_ms: u32,
+    ) -> Result<hal::Frame, hal::AcquireError> {
+        // Frames are handed out round-robin without waiting for the IO surfaces
+        // to be released, so acquisition never blocks and `_timeout_ms` has no effect.
         unsafe {
             match sync {
                 hal::FrameSync::Semaphore(semaphore) => {
@@ -179,7 +183,7 @@ impl hal::Swapchain<Backend> for Swapchain {
 
             let frame = hal::Frame::new(self.frame_index % self.io_surfaces.len());
             self.frame_index += 1;
-            frame
+            Ok(frame)
         }
     }
 }
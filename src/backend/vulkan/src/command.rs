@@ -1,4 +1,5 @@
 use std::borrow::Borrow;
+use std::ffi::CString;
 use std::{mem, ptr};
 use std::ops::Range;
 use std::sync::Arc;
@@ -79,12 +80,38 @@ impl CommandBuffer {
 }
 
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
-    fn begin(&mut self, flags: com::CommandBufferFlags) {
+    fn begin(
+        &mut self,
+        flags: com::CommandBufferFlags,
+        inheritance_info: com::CommandBufferInheritanceInfo<Backend>,
+    ) {
+        let (render_pass, subpass) = match inheritance_info.subpass {
+            Some(subpass) => (subpass.main_pass.raw, subpass.index as u32),
+            None => (vk::RenderPass::null(), 0),
+        };
+        let inheritance = vk::CommandBufferInheritanceInfo {
+            s_type: vk::StructureType::CommandBufferInheritanceInfo,
+            p_next: ptr::null(),
+            render_pass,
+            subpass,
+            framebuffer: inheritance_info.framebuffer
+                .map_or(vk::Framebuffer::null(), |framebuffer| framebuffer.raw),
+            occlusion_query_enable: if inheritance_info.occlusion_query.is_some() {
+                vk::VK_TRUE
+            } else {
+                vk::VK_FALSE
+            },
+            query_flags: inheritance_info.occlusion_query
+                .map_or(vk::QueryControlFlags::empty(), conv::map_query_control),
+            pipeline_statistics: conv::map_pipeline_statistics(inheritance_info.pipeline_statistics),
+        };
+
         let info = vk::CommandBufferBeginInfo {
             s_type: vk::StructureType::CommandBufferBeginInfo,
             p_next: ptr::null(),
             flags: conv::map_command_buffer_flags(flags),
-            p_inheritance_info: ptr::null(),
+            // Ignored for primary command buffers.
+            p_inheritance_info: &inheritance,
         };
 
         assert_eq!(Ok(()),
@@ -842,17 +869,12 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         query: query::Query<Backend>,
         control: query::QueryControl,
     ) {
-        let mut flags = vk::QueryControlFlags::empty();
-        if control.contains(query::QueryControl::PRECISE) {
-            flags |= vk::QUERY_CONTROL_PRECISE_BIT;
-        }
-
         unsafe {
             self.device.0.cmd_begin_query(
                 self.raw,
                 query.pool.0,
                 query.id,
-                flags
+                conv::map_query_control(control),
             )
         }
     }
@@ -945,4 +967,36 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         let command_buffers = buffers.into_iter().map(|b| b.borrow().raw).collect::<Vec<_>>();
         unsafe { self.device.0.cmd_execute_commands(self.raw, &command_buffers); }
     }
+
+    fn begin_debug_marker(&mut self, name: &str, color: u32) {
+        if let Some(ref debug_marker) = self.device.2 {
+            let name = CString::new(name).unwrap();
+            let info = vk::DebugMarkerMarkerInfoEXT {
+                s_type: vk::StructureType::DebugMarkerMarkerInfoExt,
+                p_next: ptr::null(),
+                p_marker_name: name.as_ptr(),
+                color: conv::map_debug_color(color),
+            };
+            unsafe { debug_marker.cmd_debug_marker_begin_ext(self.raw, &info); }
+        }
+    }
+
+    fn end_debug_marker(&mut self) {
+        if let Some(ref debug_marker) = self.device.2 {
+            unsafe { debug_marker.cmd_debug_marker_end_ext(self.raw); }
+        }
+    }
+
+    fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        if let Some(ref debug_marker) = self.device.2 {
+            let name = CString::new(name).unwrap();
+            let info = vk::DebugMarkerMarkerInfoEXT {
+                s_type: vk::StructureType::DebugMarkerMarkerInfoExt,
+                p_next: ptr::null(),
+                p_marker_name: name.as_ptr(),
+                color: conv::map_debug_color(color),
+            };
+            unsafe { debug_marker.cmd_debug_marker_insert_ext(self.raw, &info); }
+        }
+    }
 }
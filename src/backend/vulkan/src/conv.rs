@@ -2,7 +2,7 @@ use ash::vk;
 use byteorder::{NativeEndian, WriteBytesExt};
 use smallvec::SmallVec;
 
-use hal::{buffer, command, format, image, pass, pso, query};
+use hal::{buffer, command, format, image, memory, pass, pso, query};
 use hal::{IndexType, Primitive};
 use hal::device::Extent;
 use hal::range::RangeArg;
@@ -89,6 +89,21 @@ pub fn map_image_aspects(aspects: format::Aspects) -> vk::ImageAspectFlags {
     flags
 }
 
+pub fn map_vk_image_aspects(flags: vk::ImageAspectFlags) -> format::Aspects {
+    use self::format::Aspects;
+    let mut aspects = Aspects::empty();
+    if flags.intersects(vk::IMAGE_ASPECT_COLOR_BIT) {
+        aspects |= Aspects::COLOR;
+    }
+    if flags.intersects(vk::IMAGE_ASPECT_DEPTH_BIT) {
+        aspects |= Aspects::DEPTH;
+    }
+    if flags.intersects(vk::IMAGE_ASPECT_STENCIL_BIT) {
+        aspects |= Aspects::STENCIL;
+    }
+    aspects
+}
+
 pub fn map_clear_color(value: command::ClearColor) -> vk::ClearColorValue {
     match value {
         command::ClearColor::Float(v) => vk::ClearColorValue::new_float32(v),
@@ -365,6 +380,66 @@ pub fn map_buffer_usage(usage: buffer::Usage) -> vk::BufferUsageFlags {
     flags
 }
 
+pub fn map_tiling(tiling: image::Tiling) -> vk::ImageTiling {
+    match tiling {
+        image::Tiling::Optimal => vk::ImageTiling::Optimal,
+        image::Tiling::Linear => vk::ImageTiling::Linear,
+    }
+}
+
+/// Returns `None` if some of the flags are not supported.
+pub fn map_image_flags(flags: image::StorageFlags) -> Option<vk::ImageCreateFlags> {
+    use self::image::StorageFlags;
+    let mut vk_flags = vk::ImageCreateFlags::empty();
+
+    if flags.contains(StorageFlags::MUTABLE_FORMAT) {
+        vk_flags |= vk::IMAGE_CREATE_MUTABLE_FORMAT_BIT;
+    }
+    if flags.contains(StorageFlags::CUBE_VIEW) {
+        vk_flags |= vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT;
+    }
+    // TODO: `ARRAY_2D_VIEW` requires `VK_KHR_maintenance1`, which is not exposed by ash yet.
+    if flags.contains(StorageFlags::ARRAY_2D_VIEW) {
+        return None;
+    }
+
+    Some(vk_flags)
+}
+
+pub fn map_sparse_buffer_flags(flags: memory::SparseFlags) -> vk::BufferCreateFlags {
+    use self::memory::SparseFlags;
+    let mut vk_flags = vk::BufferCreateFlags::empty();
+
+    if flags.contains(SparseFlags::SPARSE_BINDING) {
+        vk_flags |= vk::BUFFER_CREATE_SPARSE_BINDING_BIT;
+    }
+    if flags.contains(SparseFlags::SPARSE_RESIDENCY) {
+        vk_flags |= vk::BUFFER_CREATE_SPARSE_RESIDENCY_BIT;
+    }
+    if flags.contains(SparseFlags::SPARSE_ALIASED) {
+        vk_flags |= vk::BUFFER_CREATE_SPARSE_ALIASED_BIT;
+    }
+
+    vk_flags
+}
+
+pub fn map_sparse_image_flags(flags: memory::SparseFlags) -> vk::ImageCreateFlags {
+    use self::memory::SparseFlags;
+    let mut vk_flags = vk::ImageCreateFlags::empty();
+
+    if flags.contains(SparseFlags::SPARSE_BINDING) {
+        vk_flags |= vk::IMAGE_CREATE_SPARSE_BINDING_BIT;
+    }
+    if flags.contains(SparseFlags::SPARSE_RESIDENCY) {
+        vk_flags |= vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT;
+    }
+    if flags.contains(SparseFlags::SPARSE_ALIASED) {
+        vk_flags |= vk::IMAGE_CREATE_SPARSE_ALIASED_BIT;
+    }
+
+    vk_flags
+}
+
 pub fn map_image_usage(usage: image::Usage) -> vk::ImageUsageFlags {
     use self::image::Usage;
     let mut flags = vk::ImageUsageFlags::empty();
@@ -402,6 +477,11 @@ pub fn map_descriptor_type(ty: pso::DescriptorType) -> vk::DescriptorType {
     unsafe { mem::transmute(ty) }
 }
 
+pub fn map_descriptor_pool_create_flags(flags: pso::DescriptorPoolCreateFlags) -> vk::DescriptorPoolCreateFlags {
+    // HAL flags share the values of the Vulkan flags
+    vk::DescriptorPoolCreateFlags::from_flags(flags.bits()).unwrap()
+}
+
 pub fn map_stage_flags(stages: pso::ShaderStageFlags) -> vk::ShaderStageFlags {
     use self::pso::ShaderStageFlags;
     let mut flags = vk::ShaderStageFlags::empty();
@@ -618,6 +698,14 @@ pub fn map_specialization_constants(
         .collect::<Result<_, _>>()
 }
 
+pub fn map_query_control(control: query::QueryControl) -> vk::QueryControlFlags {
+    let mut flags = vk::QueryControlFlags::empty();
+    if control.contains(query::QueryControl::PRECISE) {
+        flags |= vk::QUERY_CONTROL_PRECISE_BIT;
+    }
+    flags
+}
+
 pub fn map_pipeline_statistics(
     statistics: query::PipelineStatistic,
 ) -> vk::QueryPipelineStatisticFlags {
@@ -774,3 +862,12 @@ pub fn map_command_buffer_level(level: command::RawLevel) -> vk::CommandBufferLe
         command::RawLevel::Secondary => vk::CommandBufferLevel::Secondary,
     }
 }
+
+pub fn map_debug_color(color: u32) -> [f32; 4] {
+    [
+        (color >> 24) as u8 as f32 / 255.0,
+        (color >> 16) as u8 as f32 / 255.0,
+        (color >> 8) as u8 as f32 / 255.0,
+        color as u8 as f32 / 255.0,
+    ]
+}
@@ -3,7 +3,7 @@ use ash::extensions as ext;
 use ash::version::DeviceV1_0;
 use smallvec::SmallVec;
 
-use hal::{buffer, device as d, format, image, mapping, pass, pso, query, queue};
+use hal::{buffer, device as d, format, image, mapping, memory, pass, pso, query, queue};
 use hal::{Backbuffer, Features, MemoryTypeId, SwapchainConfig};
 use hal::error::HostExecutionError;
 use hal::memory::Requirements;
@@ -17,7 +17,7 @@ use std::ffi::CString;
 use std::ops::Range;
 use std::sync::Arc;
 
-use {Backend as B, Device};
+use {Backend as B, CommandQueue, Device};
 use {conv, native as n, result, window as w};
 use pool::RawCommandPool;
 
@@ -29,6 +29,16 @@ pub struct UnboundBuffer(n::Buffer);
 pub struct UnboundImage(n::Image);
 
 impl Device {
+    /// Resources are used concurrently by all opened queue families,
+    /// as there are no queue family ownership transfers.
+    fn sharing_mode(&self) -> (vk::SharingMode, &[u32]) {
+        if self.queue_families.len() > 1 {
+            (vk::SharingMode::Concurrent, &self.queue_families)
+        } else {
+            (vk::SharingMode::Exclusive, &[])
+        }
+    }
+
     #[cfg(feature = "glsl-to-spirv")]
     pub fn create_shader_module_from_glsl(
         &self,
@@ -57,6 +67,121 @@ impl Device {
             Err(string) => Err(d::ShaderError::CompilationFailed(string)),
         }
     }
+
+    fn create_buffer_raw(&self, size: u64, usage: buffer::Usage, flags: vk::BufferCreateFlags) -> n::Buffer {
+        let (sharing_mode, queue_family_indices) = self.sharing_mode();
+        let info = vk::BufferCreateInfo {
+            s_type: vk::StructureType::BufferCreateInfo,
+            p_next: ptr::null(),
+            flags,
+            size,
+            usage: conv::map_buffer_usage(usage),
+            sharing_mode,
+            queue_family_index_count: queue_family_indices.len() as u32,
+            p_queue_family_indices: queue_family_indices.as_ptr(),
+        };
+
+        let buffer = unsafe {
+            self.raw.0.create_buffer(&info, None)
+                .expect("Error on buffer creation") // TODO: error handling
+        };
+
+        n::Buffer { raw: buffer }
+    }
+
+    fn create_image_raw(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        flags: vk::ImageCreateFlags,
+    ) -> Result<n::Image, image::CreationError> {
+        use hal::image::Kind::*;
+
+        let flags = flags | match kind {
+            Cube(_) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
+            CubeArray(_, _) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
+            _ => vk::ImageCreateFlags::empty(),
+        };
+
+        let (image_type, extent, array_layers, aa_mode) = match kind {
+            D1(width) => (
+                vk::ImageType::Type1d,
+                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
+                1,
+                image::AaMode::Single,
+            ),
+            D1Array(width, layers) => (
+                vk::ImageType::Type1d,
+                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
+                layers,
+                image::AaMode::Single,
+            ),
+            D2(width, height, aa_mode) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
+                1,
+                aa_mode,
+            ),
+            D2Array(width, height, layers, aa_mode) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
+                layers,
+                aa_mode,
+            ),
+            D3(width, height, depth) => (
+                vk::ImageType::Type3d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: depth as u32 },
+                1,
+                image::AaMode::Single,
+            ),
+            Cube(size) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
+                6,
+                image::AaMode::Single,
+            ),
+            CubeArray(size, layers) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
+                6 * layers,
+                image::AaMode::Single,
+            ),
+        };
+
+        let samples = match aa_mode {
+            image::AaMode::Single => vk::SAMPLE_COUNT_1_BIT,
+            _ => unimplemented!(),
+        };
+
+        let (sharing_mode, queue_family_indices) = self.sharing_mode();
+        let info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::ImageCreateInfo,
+            p_next: ptr::null(),
+            flags,
+            image_type,
+            format: conv::map_format(format),
+            extent: extent.clone(),
+            mip_levels: mip_levels as u32,
+            array_layers: array_layers as u32,
+            samples,
+            tiling: conv::map_tiling(tiling),
+            usage: conv::map_image_usage(usage),
+            sharing_mode,
+            queue_family_index_count: queue_family_indices.len() as u32,
+            p_queue_family_indices: queue_family_indices.as_ptr(),
+            initial_layout: vk::ImageLayout::Undefined,
+        };
+
+        let raw = unsafe {
+            self.raw.0.create_image(&info, None)
+                .expect("Error on image creation") // TODO: error handling
+        };
+
+        Ok(n::Image { raw, extent })
+    }
 }
 
 impl d::Device<B> for Device {
@@ -70,14 +195,16 @@ impl d::Device<B> for Device {
 
         let memory = unsafe {
             self.raw.0.allocate_memory(&info, None)
-        }.expect("Error on memory allocation"); // TODO: error handling
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
         Ok(n::Memory { raw: memory })
     }
 
     fn create_command_pool(
         &self, family: queue::QueueFamilyId, create_flags: CommandPoolCreateFlags
-    ) -> RawCommandPool {
+    ) -> Result<RawCommandPool, d::OutOfMemory> {
         let mut flags = vk::CommandPoolCreateFlags::empty();
         if create_flags.contains(CommandPoolCreateFlags::TRANSIENT) {
             flags |= vk::COMMAND_POOL_CREATE_TRANSIENT_BIT;
@@ -96,12 +223,14 @@ impl d::Device<B> for Device {
         let command_pool_raw = unsafe {
             self.raw.0
                 .create_command_pool(&info, None)
-        }.expect("Error on command pool creation"); // TODO: better error handling
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
-        RawCommandPool {
+        Ok(RawCommandPool {
             raw: command_pool_raw,
             device: self.raw.clone(),
-        }
+        })
     }
 
     fn destroy_command_pool(&self, pool: RawCommandPool) {
@@ -268,8 +397,90 @@ impl d::Device<B> for Device {
         n::PipelineLayout { raw }
     }
 
+    fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Result<n::PipelineCache, d::OutOfMemory> {
+        let (initial_data_size, p_initial_data) = match data {
+            Some(data) => (data.len(), data.as_ptr() as *const _),
+            None => (0, ptr::null()),
+        };
+        let info = vk::PipelineCacheCreateInfo {
+            s_type: vk::StructureType::PipelineCacheCreateInfo,
+            p_next: ptr::null(),
+            flags: vk::PipelineCacheCreateFlags::empty(),
+            initial_data_size,
+            p_initial_data,
+        };
+
+        // Pipeline cache functions are called directly, as ash doesn't wrap all of them yet.
+        let mut raw = vk::PipelineCache::null();
+        let status = unsafe {
+            self.raw.0.fp_v1_0().create_pipeline_cache(self.raw.0.handle(), &info, ptr::null(), &mut raw)
+        };
+        match status {
+            vk::Result::Success => Ok(n::PipelineCache { raw }),
+            err => Err(d::OutOfMemory::from(result::Error::from(err))),
+        }
+    }
+
+    fn get_pipeline_cache_data(&self, cache: &n::PipelineCache) -> Result<Vec<u8>, d::OutOfMemory> {
+        let fp = self.raw.0.fp_v1_0();
+        let device = self.raw.0.handle();
+
+        let mut size = 0;
+        let status = unsafe {
+            fp.get_pipeline_cache_data(device, cache.raw, &mut size, ptr::null_mut())
+        };
+        if status != vk::Result::Success {
+            return Err(d::OutOfMemory::from(result::Error::from(status)));
+        }
+
+        let mut data = vec![0u8; size];
+        let status = unsafe {
+            fp.get_pipeline_cache_data(device, cache.raw, &mut size, data.as_mut_ptr() as *mut _)
+        };
+        match status {
+            // The cache may have grown in the meantime, a truncated header is still valid.
+            vk::Result::Success | vk::Result::Incomplete => {
+                data.truncate(size);
+                Ok(data)
+            }
+            err => Err(d::OutOfMemory::from(result::Error::from(err))),
+        }
+    }
+
+    fn merge_pipeline_caches<I>(&self, target: &n::PipelineCache, sources: I) -> Result<(), d::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<n::PipelineCache>,
+    {
+        let caches = sources
+            .into_iter()
+            .map(|cache| cache.borrow().raw)
+            .collect::<SmallVec<[_; 4]>>();
+
+        let status = unsafe {
+            self.raw.0.fp_v1_0().merge_pipeline_caches(
+                self.raw.0.handle(),
+                target.raw,
+                caches.len() as u32,
+                caches.as_ptr(),
+            )
+        };
+        match status {
+            vk::Result::Success => Ok(()),
+            err => Err(d::OutOfMemory::from(result::Error::from(err))),
+        }
+    }
+
+    fn destroy_pipeline_cache(&self, cache: n::PipelineCache) {
+        unsafe {
+            self.raw.0.fp_v1_0().destroy_pipeline_cache(self.raw.0.handle(), cache.raw, ptr::null());
+        }
+    }
+
     fn create_graphics_pipelines<'a, T>(
-        &self, descs: T
+        &self,
+        descs: T,
+        cache: Option<&n::PipelineCache>,
     ) -> Vec<Result<n::GraphicsPipeline, pso::CreationError>>
     where
         T: IntoIterator,
@@ -332,6 +543,10 @@ impl d::Device<B> for Device {
 
         let infos = descs.iter().map(|desc| {
             let desc = desc.borrow();
+            if !self.raw.1.contains(Features::INDEPENDENT_BLENDING) && !desc.blender.is_uniform() {
+                return Err(pso::CreationError::UnsupportedBlending);
+            }
+
             let mut stages = Vec::new();
             // Vertex stage
             if true { //vertex shader is required
@@ -584,7 +799,7 @@ impl d::Device<B> for Device {
         } else {
             unsafe {
                 self.raw.0.create_graphics_pipelines(
-                    vk::PipelineCache::null(),
+                    cache.map_or(vk::PipelineCache::null(), |cache| cache.raw),
                     &valid_infos,
                     None,
                 )
@@ -611,7 +826,9 @@ impl d::Device<B> for Device {
     }
 
     fn create_compute_pipelines<'a, T>(
-        &self, descs: T
+        &self,
+        descs: T,
+        cache: Option<&n::PipelineCache>,
     ) -> Vec<Result<n::ComputePipeline, pso::CreationError>>
     where
         T: IntoIterator,
@@ -690,7 +907,7 @@ impl d::Device<B> for Device {
         } else {
             unsafe {
                 self.raw.0.create_compute_pipelines(
-                    vk::PipelineCache::null(),
+                    cache.map_or(vk::PipelineCache::null(), |cache| cache.raw),
                     &valid_infos,
                     None,
                 )
@@ -775,7 +992,7 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_sampler(&self, sampler_info: image::SamplerInfo) -> n::Sampler {
+    fn create_sampler(&self, sampler_info: image::SamplerInfo) -> Result<n::Sampler, d::OutOfMemory> {
         use hal::pso::Comparison;
 
         let (min_filter, mag_filter, mipmap_mode) = conv::map_filter(sampler_info.filter);
@@ -819,31 +1036,16 @@ impl d::Device<B> for Device {
 
         let sampler = unsafe {
             self.raw.0.create_sampler(&info, None)
-                        .expect("error on sampler creation")
-        };
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
-        n::Sampler(sampler)
+        Ok(n::Sampler(sampler))
     }
 
     ///
     fn create_buffer(&self, size: u64, usage: buffer::Usage) -> Result<UnboundBuffer, buffer::CreationError> {
-        let info = vk::BufferCreateInfo {
-            s_type: vk::StructureType::BufferCreateInfo,
-            p_next: ptr::null(),
-            flags: vk::BufferCreateFlags::empty(), // TODO:
-            size,
-            usage: conv::map_buffer_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
-        };
-
-        let buffer = unsafe {
-            self.raw.0.create_buffer(&info, None)
-                .expect("Error on buffer creation") // TODO: error handling
-        };
-
-        Ok(UnboundBuffer(n::Buffer { raw: buffer }))
+        Ok(UnboundBuffer(self.create_buffer_raw(size, usage, vk::BufferCreateFlags::empty())))
     }
 
     fn get_buffer_requirements(&self, buffer: &UnboundBuffer) -> Requirements {
@@ -868,6 +1070,22 @@ impl d::Device<B> for Device {
         Ok(buffer)
     }
 
+    fn create_sparse_buffer(
+        &self, size: u64, usage: buffer::Usage, flags: memory::SparseFlags,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        Ok(self.create_buffer_raw(size, usage, conv::map_sparse_buffer_flags(flags)))
+    }
+
+    fn get_sparse_buffer_requirements(&self, buffer: &n::Buffer) -> Requirements {
+        let req = self.raw.0.get_buffer_memory_requirements(buffer.raw);
+
+        Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: req.memory_type_bits as _,
+        }
+    }
+
     fn create_buffer_view<R: RangeArg<u64>>(
         &self, buffer: &n::Buffer, format: Option<format::Format>, range: R
     ) -> Result<n::BufferView, buffer::ViewError> {
@@ -889,95 +1107,48 @@ impl d::Device<B> for Device {
         Ok(n::BufferView { raw: view })
     }
 
-    fn create_image(&self, kind: image::Kind, mip_levels: image::Level, format: format::Format, usage: image::Usage)
-         -> Result<UnboundImage, image::CreationError>
-    {
-        use hal::image::Kind::*;
-
-        let flags = match kind {
-            Cube(_) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
-            CubeArray(_, _) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
-            _ => vk::ImageCreateFlags::empty(),
-        };
-
-        let (image_type, extent, array_layers, aa_mode) = match kind {
-            D1(width) => (
-                vk::ImageType::Type1d,
-                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
-                1,
-                image::AaMode::Single,
-            ),
-            D1Array(width, layers) => (
-                vk::ImageType::Type1d,
-                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
-                layers,
-                image::AaMode::Single,
-            ),
-            D2(width, height, aa_mode) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
-                1,
-                aa_mode,
-            ),
-            D2Array(width, height, layers, aa_mode) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
-                layers,
-                aa_mode,
-            ),
-            D3(width, height, depth) => (
-                vk::ImageType::Type3d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: depth as u32 },
-                1,
-                image::AaMode::Single,
-            ),
-            Cube(size) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
-                6,
-                image::AaMode::Single,
-            ),
-            CubeArray(size, layers) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
-                6 * layers,
-                image::AaMode::Single,
-            ),
-        };
-
-        let samples = match aa_mode {
-            image::AaMode::Single => vk::SAMPLE_COUNT_1_BIT,
-            _ => unimplemented!(),
-        };
+    fn create_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Result<UnboundImage, image::CreationError> {
+        let flags = conv::map_image_flags(storage_flags)
+            .ok_or(image::CreationError::StorageFlags(storage_flags))?;
+        self.create_image_raw(kind, mip_levels, format, tiling, usage, flags)
+            .map(UnboundImage)
+    }
 
-        let info = vk::ImageCreateInfo {
-            s_type: vk::StructureType::ImageCreateInfo,
-            p_next: ptr::null(),
-            flags,
-            image_type,
-            format: conv::map_format(format),
-            extent: extent.clone(),
-            mip_levels: mip_levels as u32,
-            array_layers: array_layers as u32,
-            samples,
-            tiling: vk::ImageTiling::Optimal, // TODO: read back?
-            usage: conv::map_image_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
-            initial_layout: vk::ImageLayout::Undefined,
-        };
+    fn get_image_requirements(&self, image: &UnboundImage) -> Requirements {
+        let req = self.raw.0.get_image_memory_requirements(image.0.raw);
 
-        let raw = unsafe {
-            self.raw.0.create_image(&info, None)
-                .expect("Error on image creation") // TODO: error handling
-        };
+        Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: req.memory_type_bits as _,
+        }
+    }
 
-        Ok(UnboundImage(n::Image{ raw, extent }))
+    fn create_sparse_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+        flags: memory::SparseFlags,
+    ) -> Result<n::Image, image::CreationError> {
+        let flags = conv::map_image_flags(storage_flags)
+            .ok_or(image::CreationError::StorageFlags(storage_flags))?
+            | conv::map_sparse_image_flags(flags);
+        self.create_image_raw(kind, mip_levels, format, image::Tiling::Optimal, usage, flags)
     }
 
-    fn get_image_requirements(&self, image: &UnboundImage) -> Requirements {
-        let req = self.raw.0.get_image_memory_requirements(image.0.raw);
+    fn get_sparse_image_requirements(&self, image: &n::Image) -> Requirements {
+        let req = self.raw.0.get_image_memory_requirements(image.raw);
 
         Requirements {
             size: req.size,
@@ -986,6 +1157,60 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn get_sparse_image_page_layout(&self, image: &n::Image) -> Vec<image::SparsePageLayout> {
+        let fp = self.raw.0.fp_v1_0();
+        let device = self.raw.0.handle();
+        let mut count = 0;
+        let requirements = unsafe {
+            fp.get_image_sparse_memory_requirements(device, image.raw, &mut count, ptr::null_mut());
+            let mut requirements = Vec::with_capacity(count as usize);
+            fp.get_image_sparse_memory_requirements(device, image.raw, &mut count, requirements.as_mut_ptr());
+            requirements.set_len(count as usize);
+            requirements
+        };
+
+        requirements
+            .into_iter()
+            .map(|req| {
+                let props = req.format_properties;
+                image::SparsePageLayout {
+                    aspects: conv::map_vk_image_aspects(props.aspect_mask),
+                    granularity: d::Extent {
+                        width: props.image_granularity.width,
+                        height: props.image_granularity.height,
+                        depth: props.image_granularity.depth,
+                    },
+                    mip_tail_first_level: req.image_mip_tail_first_lod as _,
+                    mip_tail_size: req.image_mip_tail_size,
+                    mip_tail_offset: req.image_mip_tail_offset,
+                    mip_tail_stride: if props.flags.intersects(vk::SPARSE_IMAGE_FORMAT_SINGLE_MIPTAIL_BIT) {
+                        None
+                    } else {
+                        Some(req.image_mip_tail_stride)
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn get_image_subresource_footprint(
+        &self, image: &n::Image, aspect: format::Aspects, (level, layer): image::Subresource
+    ) -> image::SubresourceFootprint {
+        let sub = vk::ImageSubresource {
+            aspect_mask: conv::map_image_aspects(aspect),
+            mip_level: level as _,
+            array_layer: layer as _,
+        };
+        let layout = self.raw.0.get_image_subresource_layout(image.raw, sub);
+
+        image::SubresourceFootprint {
+            slice: layout.offset .. layout.offset + layout.size,
+            row_pitch: layout.row_pitch,
+            array_pitch: layout.array_pitch,
+            depth_pitch: layout.depth_pitch,
+        }
+    }
+
     fn bind_image_memory(&self, memory: &n::Memory, offset: u64, image: UnboundImage) -> Result<n::Image, d::BindError> {
         // TODO: error handling
         // TODO: check required type
@@ -1025,7 +1250,9 @@ impl d::Device<B> for Device {
         })
     }
 
-    fn create_descriptor_pool<T>(&self, max_sets: usize, descriptor_pools: T) -> n::DescriptorPool
+    fn create_descriptor_pool<T>(
+        &self, max_sets: usize, descriptor_pools: T, flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<n::DescriptorPool, d::OutOfMemory>
     where
         T: IntoIterator,
         T::Item: Borrow<pso::DescriptorRangeDesc>,
@@ -1041,7 +1268,7 @@ impl d::Device<B> for Device {
         let info = vk::DescriptorPoolCreateInfo {
             s_type: vk::StructureType::DescriptorPoolCreateInfo,
             p_next: ptr::null(),
-            flags: vk::DescriptorPoolCreateFlags::empty(), // disallow individual freeing
+            flags: conv::map_descriptor_pool_create_flags(flags),
             max_sets: max_sets as u32,
             pool_size_count: pools.len() as u32,
             p_pool_sizes: pools.as_ptr(),
@@ -1049,35 +1276,50 @@ impl d::Device<B> for Device {
 
         let pool = unsafe {
             self.raw.0.create_descriptor_pool(&info, None)
-                        .expect("Error on descriptor set pool creation") // TODO
-        };
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
-        n::DescriptorPool {
+        Ok(n::DescriptorPool {
             raw: pool,
             device: self.raw.clone(),
-        }
+        })
     }
 
-    fn create_descriptor_set_layout<T>(
-        &self, binding_iter: T
+    fn create_descriptor_set_layout<I, J>(
+        &self, binding_iter: I, immutable_sampler_iter: J
     )-> n::DescriptorSetLayout
     where
-        T: IntoIterator,
-        T::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<n::Sampler>,
     {
         let bindings = Arc::new(binding_iter
             .into_iter()
             .map(|b| b.borrow().clone())
             .collect::<Vec<_>>()
         );
+        let immutable_samplers = immutable_sampler_iter
+            .into_iter()
+            .map(|is| is.borrow().0)
+            .collect::<Vec<_>>();
 
+        let mut sampler_offset = 0;
         let raw_bindings = bindings.iter().map(|b| {
+            let p_immutable_samplers = if b.immutable_samplers {
+                let samplers = &immutable_samplers[sampler_offset .. sampler_offset + b.count];
+                sampler_offset += b.count;
+                samplers.as_ptr()
+            } else {
+                ptr::null()
+            };
             vk::DescriptorSetLayoutBinding {
                 binding: b.binding,
                 descriptor_type: conv::map_descriptor_type(b.ty),
                 descriptor_count: b.count as _,
                 stage_flags: conv::map_stage_flags(b.stage_flags),
-                p_immutable_samplers: ptr::null(), // TODO
+                p_immutable_samplers,
             }
         }).collect::<Vec<_>>();
 
@@ -1240,6 +1482,40 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_descriptor_update_template<I>(
+        &self, layout: &n::DescriptorSetLayout, entries: I
+    ) -> n::DescriptorUpdateTemplate
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|entry| *entry.borrow())
+            .collect::<Vec<_>>();
+        for entry in &entries {
+            let binding = layout.bindings
+                .iter()
+                .find(|lb| lb.binding == entry.binding)
+                .expect("Descriptor update template doesn't match the set layout!");
+            assert!(entry.array_offset + entry.count <= binding.count);
+        }
+
+        n::DescriptorUpdateTemplate { entries }
+    }
+
+    fn destroy_descriptor_update_template(&self, _template: n::DescriptorUpdateTemplate) {
+    }
+
+    unsafe fn update_descriptor_set_with_template(
+        &self, set: &n::DescriptorSet, template: &n::DescriptorUpdateTemplate, data: &[u8]
+    ) {
+        self.write_descriptor_sets(template.entries
+            .iter()
+            .map(|entry| entry.write::<B>(set, data))
+        );
+    }
+
     fn map_memory<R>(&self, memory: &n::Memory, range: R) -> Result<*mut u8, mapping::Error>
     where
         R: RangeArg<u64>,
@@ -1289,7 +1565,7 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_semaphore(&self) -> n::Semaphore {
+    fn create_semaphore(&self) -> Result<n::Semaphore, d::OutOfMemory> {
         let info = vk::SemaphoreCreateInfo {
             s_type: vk::StructureType::SemaphoreCreateInfo,
             p_next: ptr::null(),
@@ -1298,13 +1574,14 @@ impl d::Device<B> for Device {
 
         let semaphore = unsafe {
             self.raw.0.create_semaphore(&info, None)
-                        .expect("Error on semaphore creation") // TODO: error handling
-        };
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
-        n::Semaphore(semaphore)
+        Ok(n::Semaphore(semaphore))
     }
 
-    fn create_fence(&self, signaled: bool) -> n::Fence {
+    fn create_fence(&self, signaled: bool) -> Result<n::Fence, d::OutOfMemory> {
         let info = vk::FenceCreateInfo {
             s_type: vk::StructureType::FenceCreateInfo,
             p_next: ptr::null(),
@@ -1317,10 +1594,11 @@ impl d::Device<B> for Device {
 
         let fence = unsafe {
             self.raw.0.create_fence(&info, None)
-                        .expect("Error on fence creation") // TODO: error handling
-        };
+        }
+            .map_err(result::Error::from)
+            .map_err(d::OutOfMemory::from)?;
 
-        n::Fence(fence)
+        Ok(n::Fence(fence))
     }
 
     fn reset_fences<I>(&self, fences: I)
@@ -1334,7 +1612,9 @@ impl d::Device<B> for Device {
         });
     }
 
-    fn wait_for_fences<I>(&self, fences: I, wait: d::WaitFor, timeout_ms: u32) -> bool
+    fn wait_for_fences<I>(
+        &self, fences: I, wait: d::WaitFor, timeout_ms: u32,
+    ) -> Result<bool, d::DeviceLost>
     where
         I: IntoIterator,
         I::Item: Borrow<n::Fence>,
@@ -1344,27 +1624,46 @@ impl d::Device<B> for Device {
             d::WaitFor::Any => false,
             d::WaitFor::All => true,
         };
+        let timeout_ns = if timeout_ms == !0 {
+            !0
+        } else {
+            timeout_ms as u64 * 1_000_000
+        };
         let result = unsafe {
-            self.raw.0.wait_for_fences(&fences, all, timeout_ms as u64 * 1000)
+            self.raw.0.wait_for_fences(&fences, all, timeout_ns)
         };
         match result {
-            Ok(()) | Err(vk::Result::Success) => true,
-            Err(vk::Result::Timeout) => false,
+            Ok(()) | Err(vk::Result::Success) => Ok(true),
+            Err(vk::Result::Timeout) => Ok(false),
+            Err(vk::Result::ErrorDeviceLost) => Err(d::DeviceLost),
             _ => panic!("Unexpected wait result {:?}", result),
         }
     }
 
-    fn get_fence_status(&self, fence: &n::Fence) -> bool {
+    fn get_fence_status(&self, fence: &n::Fence) -> Result<bool, d::DeviceLost> {
         let result = unsafe {
             self.raw.0.get_fence_status(fence.0)
         };
         match result {
-            Ok(()) | Err(vk::Result::Success) => true,
-            Err(vk::Result::NotReady) => false,
+            Ok(()) | Err(vk::Result::Success) => Ok(true),
+            Err(vk::Result::NotReady) => Ok(false),
+            Err(vk::Result::ErrorDeviceLost) => Err(d::DeviceLost),
             _ => panic!("Unexpected get_fence_status result {:?}", result),
         }
     }
 
+    fn get_submission_status(
+        &self, queue: &CommandQueue, id: queue::SubmissionId,
+    ) -> Result<bool, d::DeviceLost> {
+        queue.submission_status(id)
+    }
+
+    fn wait_for_submission(
+        &self, queue: &CommandQueue, id: queue::SubmissionId, timeout_ms: u32,
+    ) -> Result<bool, d::DeviceLost> {
+        queue.wait_for_submission(id, timeout_ms)
+    }
+
     fn free_memory(&self, memory: n::Memory) {
         unsafe { self.raw.0.free_memory(memory.raw, None); }
     }
@@ -1440,9 +1739,23 @@ impl d::Device<B> for Device {
         let backbuffer_images = functor.get_swapchain_images_khr(swapchain_raw)
             .expect("Unable to get swapchain images");
 
+        // `ext::Swapchain::acquire_next_image_khr` drops the image index on
+        // `VK_SUBOPTIMAL_KHR`, so acquisition goes through the raw entry point.
+        let swapchain_fn = vk::SwapchainFn::load(|name| unsafe {
+            mem::transmute(
+                surface.raw.instance.0
+                    .get_device_proc_addr(
+                        self.raw.0.handle(),
+                        name.as_ptr(),
+                    )
+            )
+        }).expect("Unable to load swapchain functions");
+
         let swapchain = w::Swapchain {
             raw: swapchain_raw,
+            device: self.raw.0.handle(),
             functor,
+            swapchain_fn,
             frame_queue: VecDeque::new(),
         };
 
@@ -1534,6 +1847,46 @@ impl d::Device<B> for Device {
             .map_err(From::from)
             .map_err(From::<result::Error>::from)
     }
+
+    fn set_object_name(&self, object: d::NamedObject<B>, name: &str) {
+        let debug_marker = match self.raw.2 {
+            Some(ref debug_marker) => debug_marker,
+            None => return,
+        };
+
+        // Non-dispatchable handles are 64-bit, dispatchable ones are pointers.
+        let (object_type, object) = unsafe {
+            match object {
+                d::NamedObject::Buffer(buffer) =>
+                    (vk::DebugReportObjectTypeEXT::Buffer, mem::transmute::<_, u64>(buffer.raw)),
+                d::NamedObject::Image(image) =>
+                    (vk::DebugReportObjectTypeEXT::Image, mem::transmute::<_, u64>(image.raw)),
+                d::NamedObject::ImageView(view) =>
+                    (vk::DebugReportObjectTypeEXT::ImageView, mem::transmute::<_, u64>(view.view)),
+                d::NamedObject::Sampler(sampler) =>
+                    (vk::DebugReportObjectTypeEXT::Sampler, mem::transmute::<_, u64>(sampler.0)),
+                d::NamedObject::GraphicsPipeline(pipeline) =>
+                    (vk::DebugReportObjectTypeEXT::Pipeline, mem::transmute::<_, u64>(pipeline.0)),
+                d::NamedObject::ComputePipeline(pipeline) =>
+                    (vk::DebugReportObjectTypeEXT::Pipeline, mem::transmute::<_, u64>(pipeline.0)),
+                d::NamedObject::CommandBuffer(cmd_buffer) =>
+                    (vk::DebugReportObjectTypeEXT::CommandBuffer, mem::transmute::<_, usize>(cmd_buffer.raw) as u64),
+            }
+        };
+
+        let name = CString::new(name).unwrap();
+        let info = vk::DebugMarkerObjectNameInfoEXT {
+            s_type: vk::StructureType::DebugMarkerObjectNameInfoExt,
+            p_next: ptr::null(),
+            object_type,
+            object,
+            p_object_name: name.as_ptr(),
+        };
+        // Naming is only a hint for debugging tools, failures are not critical.
+        let _ = unsafe {
+            debug_marker.debug_marker_set_object_name_ext(self.raw.0.handle(), &info)
+        };
+    }
 }
 
 #[test]
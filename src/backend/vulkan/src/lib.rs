@@ -24,14 +24,16 @@ use ash::extensions as ext;
 use ash::version::{EntryV1_0, DeviceV1_0, InstanceV1_0, V1_0};
 use ash::vk;
 
-use hal::{format, memory, queue};
+use hal::{format, image, memory, queue};
 use hal::{Features, Limits, PatchSize, QueueType};
+use hal::device::OutOfMemory;
 use hal::error::{DeviceCreationError, HostExecutionError};
 
 use std::{fmt, mem, ptr};
 use std::borrow::{Borrow, BorrowMut};
+use std::collections::VecDeque;
 use std::ffi::{CStr, CString};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 mod command;
 mod conv;
@@ -306,6 +308,9 @@ impl hal::queue::QueueFamily for QueueFamily {
     fn max_queues(&self) -> usize {
         self.properties.queue_count as _
     }
+    fn supports_sparse_binding(&self) -> bool {
+        self.properties.queue_flags.subset(vk::QUEUE_SPARSE_BINDING_BIT)
+    }
     fn id(&self) -> queue::QueueFamilyId {
         queue::QueueFamilyId(self.index as _)
     }
@@ -334,15 +339,33 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             })
             .collect::<Vec<_>>();
 
+        // Some features are enabled if available, as they can't be requested yet.
+        let available_features = self.instance.0.get_physical_device_features(self.handle);
+
         // enabled features mask
-        let features = Features::empty();
+        let mut features = Features::empty();
+        if available_features.independent_blend != 0 {
+            features |= Features::INDEPENDENT_BLENDING;
+        }
+
+        // Debug markers are only available if a debugging tool is attached.
+        let debug_marker_name = ext::DebugMarker::name();
+        let supports_debug_marker = self.instance.0
+            .enumerate_device_extension_properties(self.handle)
+            .map(|extensions| extensions.iter().any(|extension| unsafe {
+                CStr::from_ptr(extension.extension_name.as_ptr()) == debug_marker_name
+            }))
+            .unwrap_or(false);
 
         // Create device
         let device_raw = {
-            let cstrings = DEVICE_EXTENSIONS
+            let mut cstrings = DEVICE_EXTENSIONS
                 .iter()
                 .map(|&s| CString::new(s).unwrap())
                 .collect::<Vec<_>>();
+            if supports_debug_marker {
+                cstrings.push(debug_marker_name.to_owned());
+            }
 
             let str_pointers = cstrings
                 .iter()
@@ -350,7 +373,19 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                 .collect::<Vec<_>>();
 
             // TODO: derive from `features`
-            let enabled_features = unsafe { mem::zeroed() };
+            let enabled_features = vk::PhysicalDeviceFeatures {
+                independent_blend: available_features.independent_blend,
+                sparse_binding: available_features.sparse_binding,
+                sparse_residency_buffer: available_features.sparse_residency_buffer,
+                sparse_residency_image2d: available_features.sparse_residency_image2d,
+                sparse_residency_image3d: available_features.sparse_residency_image3d,
+                sparse_residency2_samples: available_features.sparse_residency2_samples,
+                sparse_residency4_samples: available_features.sparse_residency4_samples,
+                sparse_residency8_samples: available_features.sparse_residency8_samples,
+                sparse_residency16_samples: available_features.sparse_residency16_samples,
+                sparse_residency_aliased: available_features.sparse_residency_aliased,
+                .. unsafe { mem::zeroed() }
+            };
             let info = vk::DeviceCreateInfo {
                 s_type: vk::StructureType::DeviceCreateInfo,
                 p_next: ptr::null(),
@@ -388,8 +423,15 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             )
         }).unwrap();
 
+        let debug_marker = if supports_debug_marker {
+            ext::DebugMarker::new(&self.instance.0, &device_raw).ok()
+        } else {
+            None
+        };
+
         let device = Device {
-            raw: Arc::new(RawDevice(device_raw, features)),
+            raw: Arc::new(RawDevice(device_raw, features, debug_marker)),
+            queue_families: families.iter().map(|&(family, _)| family.index).collect(),
         };
 
         let device_arc = device.raw.clone();
@@ -406,6 +448,13 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                         raw: Arc::new(queue_raw),
                         device: device_arc.clone(),
                         swapchain_fn: swapchain_fn.clone(),
+                        submission_count: 0,
+                        submission_fences: Mutex::new(SubmissionFences {
+                            pending: VecDeque::new(),
+                            free: Vec::new(),
+                            retired: Vec::new(),
+                            waiters: 0,
+                        }),
                     });
                 }
                 (queue::QueueFamilyId(family_index as _), family_raw)
@@ -434,6 +483,46 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         }
     }
 
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        let image_type = match dimensions {
+            1 => vk::ImageType::Type1d,
+            2 => vk::ImageType::Type2d,
+            3 => vk::ImageType::Type3d,
+            _ => panic!("Unexpected image dimensionality: {}", dimensions),
+        };
+        let properties = self
+            .instance
+            .0
+            .get_physical_device_image_format_properties(
+                self.handle,
+                conv::map_format(format),
+                image_type,
+                conv::map_tiling(tiling),
+                conv::map_image_usage(usage),
+                conv::map_image_flags(storage_flags)?,
+            )
+            .ok()?;
+
+        Some(image::FormatProperties {
+            max_extent: hal::device::Extent {
+                width: properties.max_extent.width,
+                height: properties.max_extent.height,
+                depth: properties.max_extent.depth,
+            },
+            max_levels: properties.max_mip_levels as _,
+            max_layers: properties.max_array_layers as _,
+            sample_count_mask: properties.sample_counts.flags() as _,
+            max_resource_size: properties.max_resource_size,
+        })
+    }
+
     fn memory_properties(&self) -> hal::MemoryProperties {
         let mem_properties = self.instance.0.get_physical_device_memory_properties(self.handle);
         let memory_heaps = mem_properties.memory_heaps[..mem_properties.memory_heap_count as usize]
@@ -557,6 +646,33 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         if features.fragment_stores_and_atomics != 0 {
             bits |= Features::FRAGMENT_STORES_AND_ATOMICS;
         }
+        if features.sparse_binding != 0 {
+            bits |= Features::SPARSE_BINDING;
+        }
+        if features.sparse_residency_buffer != 0 {
+            bits |= Features::SPARSE_RESIDENCY_BUFFER;
+        }
+        if features.sparse_residency_image2d != 0 {
+            bits |= Features::SHADER_RESIDENCY_IMAGE_2D;
+        }
+        if features.sparse_residency_image3d != 0 {
+            bits |= Features::SHADER_RESIDENSY_IMAGE_3D;
+        }
+        if features.sparse_residency2_samples != 0 {
+            bits |= Features::SPARSE_RESIDENCY_2_SAMPLES;
+        }
+        if features.sparse_residency4_samples != 0 {
+            bits |= Features::SPARSE_RESIDENCY_4_SAMPLES;
+        }
+        if features.sparse_residency8_samples != 0 {
+            bits |= Features::SPARSE_RESIDENCY_8_SAMPLES;
+        }
+        if features.sparse_residency16_samples != 0 {
+            bits |= Features::SPARSE_RESIDENCY_16_SAMPLES;
+        }
+        if features.sparse_residency_aliased != 0 {
+            bits |= Features::SPARSE_RESIDENCY_ALIASED;
+        }
         //TODO: cover more features
 
         bits
@@ -576,12 +692,14 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             min_buffer_copy_offset_alignment: limits.optimal_buffer_copy_offset_alignment as _,
             min_buffer_copy_pitch_alignment: limits.optimal_buffer_copy_row_pitch_alignment as _,
             min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment as _,
+            buffer_image_granularity: limits.buffer_image_granularity as _,
+            non_coherent_atom_size: limits.non_coherent_atom_size as _,
         }
     }
 }
 
 #[doc(hidden)]
-pub struct RawDevice(pub ash::Device<V1_0>, Features);
+pub struct RawDevice(pub ash::Device<V1_0>, Features, Option<ext::DebugMarker>);
 impl fmt::Debug for RawDevice {
     fn fmt(&self, _formatter: &mut fmt::Formatter) -> fmt::Result {
         unimplemented!()
@@ -596,17 +714,130 @@ impl Drop for RawDevice {
 // Need to explicitly synchronize on submission and present.
 pub type RawCommandQueue = Arc<vk::Queue>;
 
+// Fences tracking the progress of the submissions to a queue.
+struct SubmissionFences {
+    // Fences of the submissions in flight, ordered by submission id.
+    pending: VecDeque<(queue::SubmissionId, vk::Fence)>,
+    // Unsignalled fences ready for reuse.
+    free: Vec<vk::Fence>,
+    // Signalled fences which can't be reset yet, as a wait might still be using them.
+    retired: Vec<vk::Fence>,
+    // Number of waits currently blocking on a pending fence without holding the lock.
+    waiters: usize,
+}
+
 pub struct CommandQueue {
     raw: RawCommandQueue,
     device: Arc<RawDevice>,
     swapchain_fn: vk::SwapchainFn,
+    submission_count: u64,
+    submission_fences: Mutex<SubmissionFences>,
+}
+
+impl CommandQueue {
+    fn acquire_submission_fence(
+        &self, fences: &mut SubmissionFences,
+    ) -> Result<vk::Fence, result::Error> {
+        match fences.free.pop() {
+            Some(fence) => Ok(fence),
+            None => {
+                let info = vk::FenceCreateInfo {
+                    s_type: vk::StructureType::FenceCreateInfo,
+                    p_next: ptr::null(),
+                    flags: vk::FenceCreateFlags::empty(),
+                };
+                unsafe { self.device.0.create_fence(&info, None) }
+                    .map_err(result::Error::from)
+            }
+        }
+    }
+
+    // Recycle the fences of all finished submissions.
+    fn poll_submissions(&self, fences: &mut SubmissionFences) -> Result<(), hal::device::DeviceLost> {
+        while let Some(&(_, fence)) = fences.pending.front() {
+            match unsafe { self.device.0.get_fence_status(fence) } {
+                Ok(()) | Err(vk::Result::Success) => {}
+                Err(vk::Result::NotReady) => break,
+                Err(vk::Result::ErrorDeviceLost) => return Err(hal::device::DeviceLost),
+                Err(status) => panic!("Unexpected get_fence_status result {:?}", status),
+            }
+            fences.pending.pop_front();
+            fences.retired.push(fence);
+        }
+        if fences.waiters == 0 && !fences.retired.is_empty() {
+            unsafe {
+                self.device.0
+                    .reset_fences(&fences.retired)
+                    .expect("Failed to reset submission fences");
+            }
+            let SubmissionFences { ref mut retired, ref mut free, .. } = *fences;
+            free.extend(retired.drain(..));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn submission_status(&self, id: queue::SubmissionId) -> Result<bool, hal::device::DeviceLost> {
+        let mut fences = self.submission_fences.lock().unwrap();
+        self.poll_submissions(&mut fences)?;
+        Ok(fences.pending.front().map_or(true, |&(pending, _)| pending > id))
+    }
+
+    pub(crate) fn wait_for_submission(
+        &self, id: queue::SubmissionId, timeout_ms: u32,
+    ) -> Result<bool, hal::device::DeviceLost> {
+        let fence = {
+            let mut fences = self.submission_fences.lock().unwrap();
+            let fence = match fences.pending.iter().find(|&&(pending, _)| pending >= id) {
+                Some(&(_, fence)) => fence,
+                None => return Ok(true),
+            };
+            // Registering as a waiter keeps the fence from being reset until we are done.
+            fences.waiters += 1;
+            fence
+        };
+        let timeout_ns = if timeout_ms == !0 {
+            !0
+        } else {
+            timeout_ms as u64 * 1_000_000
+        };
+        let result = unsafe {
+            self.device.0.wait_for_fences(&[fence], true, timeout_ns)
+        };
+
+        let mut fences = self.submission_fences.lock().unwrap();
+        fences.waiters -= 1;
+        match result {
+            Ok(()) | Err(vk::Result::Success) => {}
+            Err(vk::Result::Timeout) => return Ok(false),
+            Err(vk::Result::ErrorDeviceLost) => return Err(hal::device::DeviceLost),
+            _ => panic!("Unexpected wait result {:?}", result),
+        }
+        self.poll_submissions(&mut fences)?;
+        Ok(true)
+    }
+}
+
+impl Drop for CommandQueue {
+    fn drop(&mut self) {
+        let fences = self.submission_fences.get_mut().unwrap();
+        let pending = fences.pending.iter().map(|&(_, fence)| fence).collect::<Vec<_>>();
+        unsafe {
+            if !pending.is_empty() {
+                let _ = self.device.0.wait_for_fences(&pending, true, !0);
+            }
+            let recycled = fences.free.drain(..).chain(fences.retired.drain(..));
+            for fence in pending.into_iter().chain(recycled) {
+                self.device.0.destroy_fence(fence, None);
+            }
+        }
+    }
 }
 
 impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
     unsafe fn submit_raw<IC>(&mut self,
         submission: hal::queue::RawSubmission<Backend, IC>,
         fence: Option<&native::Fence>,
-    )
+    ) -> hal::SubmissionResult<queue::SubmissionId>
     where
         IC: IntoIterator,
         IC::Item: Borrow<command::CommandBuffer>,
@@ -641,15 +872,169 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
             p_signal_semaphores: signals.as_ptr(),
         };
 
+        let id = queue::SubmissionId(self.submission_count + 1);
+        {
+            let mut fences = self.submission_fences.lock().unwrap();
+            self.poll_submissions(&mut fences)
+                .map_err(|_| hal::SubmissionError::DeviceLost)?;
+            let progress_fence = self.acquire_submission_fence(&mut fences)?;
+
+            let submit_fence = fence.map_or(progress_fence, |fence| fence.0);
+            if let Err(err) = self.device.0.queue_submit(*self.raw, &[info], submit_fence) {
+                fences.free.push(progress_fence);
+                return Err(result::Error::from(err).into());
+            }
+
+            if fence.is_some() {
+                // An empty submission signals its fence once all previous submissions finished.
+                if self.device.0.queue_submit(*self.raw, &[], progress_fence).is_err() {
+                    // The command buffers are already in flight, so the submission itself
+                    // succeeded. Without a progress fence, fall back to waiting for the queue
+                    // to drain, which completes every pending submission including this one.
+                    fences.free.push(progress_fence);
+                    self.device.0
+                        .queue_wait_idle(*self.raw)
+                        .map_err(|_| hal::SubmissionError::DeviceLost)?;
+                    self.poll_submissions(&mut fences)
+                        .map_err(|_| hal::SubmissionError::DeviceLost)?;
+                    self.submission_count = id.0;
+                    return Ok(id);
+                }
+            }
+            fences.pending.push_back((id, progress_fence));
+        }
+
+        self.submission_count = id.0;
+        Ok(id)
+    }
+
+    unsafe fn bind_sparse(
+        &mut self,
+        binding: hal::queue::RawSparseBinding<Backend>,
+        fence: Option<&native::Fence>,
+    ) -> hal::SubmissionResult<()> {
+        fn map_memory(memory: Option<(&native::Memory, u64)>) -> (vk::DeviceMemory, u64) {
+            memory.map_or((vk::DeviceMemory::null(), 0), |(memory, offset)| (memory.raw, offset))
+        }
+        fn map_bind(bind: &hal::queue::SparseBind<Backend>) -> vk::SparseMemoryBind {
+            let (memory, memory_offset) = map_memory(bind.memory);
+            vk::SparseMemoryBind {
+                resource_offset: bind.resource_offset,
+                size: bind.size,
+                memory,
+                memory_offset,
+                flags: vk::SparseMemoryBindFlags::empty(),
+            }
+        }
+
+        let waits = binding.wait_semaphores
+            .iter()
+            .map(|semaphore| semaphore.0)
+            .collect::<Vec<_>>();
+        let signals = binding.signal_semaphores
+            .iter()
+            .map(|semaphore| semaphore.0)
+            .collect::<Vec<_>>();
+
+        // The bind arrays need to outlive the bind infos pointing into them.
+        let buffer_binds = binding.buffers
+            .iter()
+            .map(|&(_, binds)| binds.iter().map(map_bind).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let buffer_infos = binding.buffers
+            .iter()
+            .zip(&buffer_binds)
+            .map(|(&(buffer, _), binds)| vk::SparseBufferMemoryBindInfo {
+                buffer: buffer.raw,
+                bind_count: binds.len() as _,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let image_opaque_binds = binding.image_opaques
+            .iter()
+            .map(|&(_, binds)| binds.iter().map(map_bind).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let image_opaque_infos = binding.image_opaques
+            .iter()
+            .zip(&image_opaque_binds)
+            .map(|(&(image, _), binds)| vk::SparseImageOpaqueMemoryBindInfo {
+                image: image.raw,
+                bind_count: binds.len() as _,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let image_binds = binding.images
+            .iter()
+            .map(|&(_, binds)| {
+                binds
+                    .iter()
+                    .map(|bind| {
+                        let (memory, memory_offset) = map_memory(bind.memory);
+                        vk::SparseImageMemoryBind {
+                            subresource: vk::ImageSubresource {
+                                aspect_mask: conv::map_image_aspects(bind.aspects),
+                                mip_level: bind.subresource.0 as _,
+                                array_layer: bind.subresource.1 as _,
+                            },
+                            offset: vk::Offset3D {
+                                x: bind.offset.x,
+                                y: bind.offset.y,
+                                z: bind.offset.z,
+                            },
+                            extent: vk::Extent3D {
+                                width: bind.extent.width,
+                                height: bind.extent.height,
+                                depth: bind.extent.depth,
+                            },
+                            memory,
+                            memory_offset,
+                            flags: vk::SparseMemoryBindFlags::empty(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let image_infos = binding.images
+            .iter()
+            .zip(&image_binds)
+            .map(|(&(image, _), binds)| vk::SparseImageMemoryBindInfo {
+                image: image.raw,
+                bind_count: binds.len() as _,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let info = vk::BindSparseInfo {
+            s_type: vk::StructureType::BindSparseInfo,
+            p_next: ptr::null(),
+            wait_semaphore_count: waits.len() as u32,
+            p_wait_semaphores: waits.as_ptr(),
+            buffer_bind_count: buffer_infos.len() as u32,
+            p_buffer_binds: buffer_infos.as_ptr(),
+            image_opaque_bind_count: image_opaque_infos.len() as u32,
+            p_image_opaque_binds: image_opaque_infos.as_ptr(),
+            image_bind_count: image_infos.len() as u32,
+            p_image_binds: image_infos.as_ptr(),
+            signal_semaphore_count: signals.len() as u32,
+            p_signal_semaphores: signals.as_ptr(),
+        };
+
         let fence_raw = fence
             .map(|fence| fence.0)
             .unwrap_or(vk::Fence::null());
 
-        let result = self.device.0.queue_submit(*self.raw, &[info], fence_raw);
-        assert_eq!(Ok(()), result);
+        // `queue_bind_sparse` has no wrapper in ash yet.
+        match self.device.0.fp_v1_0().queue_bind_sparse(*self.raw, 1, &info, fence_raw) {
+            vk::Result::Success => Ok(()),
+            err => Err(hal::SubmissionError::from(result::Error::from(err))),
+        }
     }
 
-    fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW)
+    fn present<IS, IW>(
+        &mut self, swapchains: IS, wait_semaphores: IW,
+    ) -> Result<hal::PresentStatus, hal::PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<window::Swapchain>,
@@ -685,10 +1070,22 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
             p_results: ptr::null_mut(),
         };
 
-        assert_eq!(vk::Result::Success, unsafe {
+        let result = unsafe {
             self.swapchain_fn
                 .queue_present_khr(*self.raw, &info)
-        });
+        };
+        match result {
+            vk::Result::Success => Ok(hal::PresentStatus::Optimal),
+            vk::Result::SuboptimalKhr => Ok(hal::PresentStatus::Suboptimal),
+            vk::Result::ErrorOutOfDateKhr => Err(hal::PresentError::OutOfDate),
+            vk::Result::ErrorSurfaceLostKhr => Err(hal::PresentError::SurfaceLost),
+            vk::Result::ErrorOutOfHostMemory =>
+                Err(hal::PresentError::OutOfMemory(OutOfMemory::OutOfHostMemory)),
+            vk::Result::ErrorOutOfDeviceMemory =>
+                Err(hal::PresentError::OutOfMemory(OutOfMemory::OutOfDeviceMemory)),
+            vk::Result::ErrorDeviceLost => Err(hal::PresentError::DeviceLost),
+            other => panic!("Unable to present: {:?}", other),
+        }
     }
 
     fn wait_idle(&self) -> Result<(), HostExecutionError> {
@@ -704,6 +1101,8 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
 
 pub struct Device {
     raw: Arc<RawDevice>,
+    // indices of the opened queue families, resources are shared between them
+    queue_families: Vec<u32>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
@@ -736,8 +1135,10 @@ impl hal::Backend for Backend {
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
+    type PipelineCache = native::PipelineCache;
     type PipelineLayout = native::PipelineLayout;
     type DescriptorSetLayout = native::DescriptorSetLayout;
+    type DescriptorUpdateTemplate = native::DescriptorUpdateTemplate;
     type DescriptorPool = native::DescriptorPool;
     type DescriptorSet = native::DescriptorSet;
 
@@ -4,7 +4,7 @@ use hal::pso;
 use hal::image::SubresourceRange;
 use std::borrow::Borrow;
 use std::sync::Arc;
-use {Backend, RawDevice};
+use {result, Backend, RawDevice};
 
 #[derive(Debug, Hash)]
 pub struct Semaphore(pub vk::Semaphore);
@@ -18,6 +18,11 @@ pub struct GraphicsPipeline(pub vk::Pipeline);
 #[derive(Debug, Hash)]
 pub struct ComputePipeline(pub vk::Pipeline);
 
+#[derive(Debug, Hash)]
+pub struct PipelineCache {
+    pub(crate) raw: vk::PipelineCache,
+}
+
 #[derive(Debug, Hash)]
 pub struct Memory {
     pub(crate) raw: vk::DeviceMemory,
@@ -68,6 +73,11 @@ pub struct DescriptorSetLayout {
     pub(crate) bindings: Arc<Vec<pso::DescriptorSetLayoutBinding>>,
 }
 
+#[derive(Debug)]
+pub struct DescriptorUpdateTemplate {
+    pub(crate) entries: Vec<pso::DescriptorUpdateTemplateEntry>,
+}
+
 #[derive(Debug)]
 pub struct DescriptorSet {
     pub(crate) raw: vk::DescriptorSet,
@@ -91,7 +101,7 @@ pub struct DescriptorPool {
 }
 
 impl pso::DescriptorPool<Backend> for DescriptorPool {
-    fn allocate_sets<I>(&mut self, layout_iter: I) -> Vec<DescriptorSet>
+    fn allocate_sets<I>(&mut self, layout_iter: I) -> Result<Vec<DescriptorSet>, pso::AllocationError>
     where
         I: IntoIterator,
         I::Item: Borrow<DescriptorSetLayout>,
@@ -115,15 +125,32 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
 
         let descriptor_sets = unsafe {
             self.device.0.allocate_descriptor_sets(&info)
-        }.expect("Error on descriptor sets creation"); // TODO
+        }
+            .map_err(result::Error::from)
+            .map_err(pso::AllocationError::from)?;
 
-        descriptor_sets
+        Ok(descriptor_sets
             .into_iter()
             .zip(layout_bindinds.into_iter())
             .map(|(raw, bindings)| {
                 DescriptorSet { raw, bindings }
             })
-            .collect()
+            .collect())
+    }
+
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = DescriptorSet>,
+    {
+        let sets = descriptor_sets
+            .into_iter()
+            .map(|set| set.raw)
+            .collect::<Vec<_>>();
+        if !sets.is_empty() {
+            unsafe {
+                self.device.0.free_descriptor_sets(self.raw, &sets);
+            }
+        }
     }
 
     fn reset(&mut self) {
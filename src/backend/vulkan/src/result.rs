@@ -1,6 +1,9 @@
 
 use ash::vk;
 
+use hal::SubmissionError;
+use hal::device::OutOfMemory;
+use hal::pso::AllocationError;
 use hal::error::{DeviceCreationError, HostExecutionError};
 
 // Generic error codes from Vulkan
@@ -96,3 +99,31 @@ from_error! {
         DeviceLost => DeviceLost,
     },
 }
+
+from_error! {
+    OutOfMemory {
+        OutOfHostMemory => OutOfHostMemory,
+        OutOfDeviceMemory => OutOfDeviceMemory,
+        // Implementations may limit the number of some objects (e.g samplers),
+        // which is exposed as exhausting device resources.
+        TooManyObjects => OutOfDeviceMemory,
+    },
+}
+
+from_error! {
+    AllocationError {
+        OutOfHostMemory => OutOfHostMemory,
+        OutOfDeviceMemory => OutOfDeviceMemory,
+        // `VK_ERROR_OUT_OF_POOL_MEMORY` requires `VK_KHR_maintenance1`,
+        // without it drivers report fragmentation for exhausted pools too.
+        FragmentedPool => FragmentedPool,
+    },
+}
+
+from_error! {
+    SubmissionError {
+        OutOfHostMemory => OutOfHostMemory,
+        OutOfDeviceMemory => OutOfDeviceMemory,
+        DeviceLost => DeviceLost,
+    },
+}
@@ -7,6 +7,7 @@ use ash::vk;
 use ash::extensions as ext;
 
 use hal;
+use hal::device::OutOfMemory;
 use hal::format::Format;
 
 #[cfg(feature = "winit")]
@@ -369,25 +370,56 @@ impl hal::Surface<Backend> for Surface {
 
 pub struct Swapchain {
     pub(crate) raw: vk::SwapchainKHR,
+    pub(crate) device: vk::Device,
     pub(crate) functor: ext::Swapchain,
+    pub(crate) swapchain_fn: vk::SwapchainFn,
     // Queued up frames for presentation
     pub(crate) frame_queue: VecDeque<usize>,
 }
 
 
 impl hal::Swapchain<Backend> for Swapchain {
-    fn acquire_frame(&mut self, sync: hal::FrameSync<Backend>) -> hal::Frame {
+    fn acquire_frame(
+        &mut self, sync: hal::FrameSync<Backend>, timeout_ms: u32,
+    ) -> Result<hal::Frame, hal::AcquireError> {
         let (semaphore, fence) = match sync {
             hal::FrameSync::Semaphore(semaphore) => (semaphore.0, vk::Fence::null()),
             hal::FrameSync::Fence(fence) => (vk::Semaphore::null(), fence.0),
         };
+        let timeout_ns = if timeout_ms == !0 {
+            !0
+        } else {
+            timeout_ms as u64 * 1_000_000
+        };
 
-        let index = unsafe {
+        let mut index = 0;
+        let result = unsafe {
             // will block if no image is available
-            self.functor.acquire_next_image_khr(self.raw, !0, semaphore, fence)
-        }.expect("Unable to acquire a swapchain image");
+            self.swapchain_fn.acquire_next_image_khr(
+                self.device, self.raw, timeout_ns, semaphore, fence, &mut index,
+            )
+        };
+        let suboptimal = match result {
+            vk::Result::Success => false,
+            // The image is still acquired and can be presented.
+            vk::Result::SuboptimalKhr => true,
+            vk::Result::NotReady => return Err(hal::AcquireError::NotReady),
+            vk::Result::Timeout => return Err(hal::AcquireError::Timeout),
+            vk::Result::ErrorOutOfDateKhr => return Err(hal::AcquireError::OutOfDate),
+            vk::Result::ErrorSurfaceLostKhr => return Err(hal::AcquireError::SurfaceLost),
+            vk::Result::ErrorOutOfHostMemory =>
+                return Err(hal::AcquireError::OutOfMemory(OutOfMemory::OutOfHostMemory)),
+            vk::Result::ErrorOutOfDeviceMemory =>
+                return Err(hal::AcquireError::OutOfMemory(OutOfMemory::OutOfDeviceMemory)),
+            vk::Result::ErrorDeviceLost => return Err(hal::AcquireError::DeviceLost),
+            other => panic!("Unable to acquire a swapchain image: {:?}", other),
+        };
 
         self.frame_queue.push_back(index as usize);
-        hal::Frame::new(index as usize)
+        if suboptimal {
+            Ok(hal::Frame::new_suboptimal(index as usize))
+        } else {
+            Ok(hal::Frame::new(index as usize))
+        }
     }
 }
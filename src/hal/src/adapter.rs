@@ -8,7 +8,7 @@
 
 use std::any::Any;
 
-use {format, memory, Backend, Gpu, Features, Limits};
+use {format, image, memory, Backend, Gpu, Features, Limits};
 use error::DeviceCreationError;
 use queue::{Capability, QueueGroup};
 
@@ -75,6 +75,19 @@ pub trait PhysicalDevice<B: Backend>: Any + Send + Sync {
     /// Fetch details for a particular image format.
     fn format_properties(&self, Option<format::Format>) -> format::Properties;
 
+    /// Fetch the limits of an image with the given creation parameters.
+    /// `dimensions` is the number of image dimensions (1, 2 or 3).
+    ///
+    /// Returns `None` if the combination of parameters is not supported.
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+    ) -> Option<image::FormatProperties>;
+
     /// Fetch details for the memory regions provided by the device.
     fn memory_properties(&self) -> MemoryProperties;
 
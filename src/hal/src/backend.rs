@@ -39,7 +39,12 @@ impl<B: Backend> RawQueueGroup<B> {
 impl Frame {
     /// Create a new frame.
     pub fn new(id: usize) -> Self {
-        Frame(id)
+        Frame { id, suboptimal: false }
+    }
+
+    /// Create a new frame acquired from a suboptimal swapchain.
+    pub fn new_suboptimal(id: usize) -> Self {
+        Frame { id, suboptimal: true }
     }
 }
 
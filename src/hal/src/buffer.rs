@@ -14,6 +14,8 @@ pub type Offset = u64;
 pub enum CreationError {
     /// Required `Usage` is not supported.
     Usage(Usage),
+    /// Sparse buffers are not supported.
+    Sparse,
     /// Some other problem.
     Other,
 }
@@ -33,6 +35,8 @@ impl Error for CreationError {
         match *self {
             CreationError::Usage(_) =>
                 "Required `Usage` is not supported",
+            CreationError::Sparse =>
+                "Sparse buffers are not supported",
             CreationError::Other =>
                 "Some other problem",
         }
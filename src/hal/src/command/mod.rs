@@ -24,7 +24,7 @@ mod render_pass;
 mod transfer;
 
 pub use self::graphics::*;
-pub use self::raw::{ClearValueRaw, ClearColorRaw, ClearDepthStencilRaw, RawCommandBuffer, CommandBufferFlags, Level as RawLevel};
+pub use self::raw::{ClearValueRaw, ClearColorRaw, ClearDepthStencilRaw, RawCommandBuffer, CommandBufferFlags, CommandBufferInheritanceInfo, Level as RawLevel};
 pub use self::render_pass::*;
 pub use self::transfer::*;
 
@@ -123,6 +123,21 @@ impl<'a, B: Backend, C, S: Shot, L: Level> CommandBuffer<'a, B, C, S, L> {
     {
         unsafe { ::std::mem::transmute(self) }
     }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn begin_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.begin_debug_marker(name, color)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn end_debug_marker(&mut self) {
+        self.raw.end_debug_marker()
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.insert_debug_marker(name, color)
+    }
 }
 
 impl<'a, B: Backend, C, S: Shot> CommandBuffer<'a, B, C, S, Primary> {
@@ -2,11 +2,11 @@ use std::any::Any;
 use std::borrow::Borrow;
 use std::ops::Range;
 
-use {buffer, pso};
+use {buffer, pass, pso};
 use {Backend, IndexCount, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
 use image::{ImageLayout, SubresourceRange};
 use memory::{Barrier, Dependencies};
-use query::{Query, QueryControl, QueryId};
+use query::{PipelineStatistic, Query, QueryControl, QueryId};
 use super::{
     BlitFilter, ColorValue, StencilValue, Rect, Viewport,
     AttachmentClear, BufferCopy, BufferImageCopy,
@@ -72,6 +72,36 @@ bitflags! {
     }
 }
 
+/// State that a secondary command buffer inherits from the primary
+/// command buffer it is executed in. Ignored for primary command buffers.
+#[derive(Debug)]
+pub struct CommandBufferInheritanceInfo<'a, B: Backend> {
+    /// Render pass and subpass the command buffer will be executed within.
+    /// Required if `RENDER_PASS_CONTINUE` is set.
+    pub subpass: Option<pass::Subpass<'a, B>>,
+    /// Framebuffer the command buffer will be executed with, if known.
+    /// Providing it may allow the backend to optimize the recording.
+    pub framebuffer: Option<&'a B::Framebuffer>,
+    /// Allow the command buffer to be executed while an occlusion query
+    /// is active in the primary command buffer, using the given control flags.
+    ///
+    /// Requires `Features::INHERITED_QUERIES`.
+    pub occlusion_query: Option<QueryControl>,
+    /// Pipeline statistics which may be active in the primary command buffer.
+    pub pipeline_statistics: PipelineStatistic,
+}
+
+impl<'a, B: Backend> Default for CommandBufferInheritanceInfo<'a, B> {
+    fn default() -> Self {
+        CommandBufferInheritanceInfo {
+            subpass: None,
+            framebuffer: None,
+            occlusion_query: None,
+            pipeline_statistics: PipelineStatistic::empty(),
+        }
+    }
+}
+
 /// An enum that indicates at runtime whether a command buffer
 /// is primary or secondary, similar to what `command::Primary`
 /// and `command::Secondary` do at compile-time.
@@ -86,7 +116,9 @@ pub enum Level {
 /// provided by a `Backend`'s command buffer.
 pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     /// Begins recording commands to a command buffer.
-    fn begin(&mut self, flags: CommandBufferFlags);
+    ///
+    /// The inheritance info is only used by secondary command buffers.
+    fn begin(&mut self, flags: CommandBufferFlags, inheritance_info: CommandBufferInheritanceInfo<B>);
 
     /// Finish recording commands to a command buffer.
     fn finish(&mut self);
@@ -542,4 +574,15 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
     ) where
         I: IntoIterator,
         I::Item: Borrow<B::CommandBuffer>;
+
+    /// Open a named debug group, which encloses all following commands until the
+    /// matching `end_debug_marker`. Groups can be nested.
+    /// `color` is an RGBA color packed as `0xRRGGBBAA`, which may be used by debugging tools.
+    fn begin_debug_marker(&mut self, name: &str, color: u32);
+
+    /// Close the innermost debug group opened by `begin_debug_marker`.
+    fn end_debug_marker(&mut self);
+
+    /// Insert a single named debug marker into the command stream.
+    fn insert_debug_marker(&mut self, name: &str, color: u32);
 }
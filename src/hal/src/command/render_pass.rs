@@ -120,6 +120,21 @@ impl<'a, B: Backend> RenderSubpassCommon<'a, B> {
         self.0.push_graphics_constants(layout, stages, offset, constants);
     }
 
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn begin_debug_marker(&mut self, name: &str, color: u32) {
+        self.0.begin_debug_marker(name, color)
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn end_debug_marker(&mut self) {
+        self.0.end_debug_marker()
+    }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        self.0.insert_debug_marker(name, color)
+    }
+
     // TODO: set_line_width
     // TODO: set_depth_bounds
     // TODO: set_depth_bias
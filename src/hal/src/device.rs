@@ -19,26 +19,54 @@ use {buffer, format, image, mapping, pass, pso, query};
 use {Backend, MemoryTypeId};
 
 use error::HostExecutionError;
-use memory::Requirements;
+use memory::{Requirements, SparseFlags};
 use pool::{CommandPool, CommandPoolCreateFlags};
-use queue::{QueueFamilyId, QueueGroup};
+use queue::{QueueFamilyId, QueueGroup, SubmissionId};
 use range::RangeArg;
 use window::{Backbuffer, SwapchainConfig};
 
 
 /// Error allocating memory.
-#[derive(Clone, PartialEq, Debug)]
-pub struct OutOfMemory;
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutOfMemory {
+    /// Memory allocation on the host side failed.
+    OutOfHostMemory,
+    /// Memory allocation on the device side failed.
+    OutOfDeviceMemory,
+}
 
 impl fmt::Display for OutOfMemory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Out of memory")
+        write!(f, "{}", self.description())
     }
 }
 
 impl Error for OutOfMemory {
     fn description(&self) -> &str {
-        "Out of memory"
+        match *self {
+            OutOfMemory::OutOfHostMemory => "Out of host memory",
+            OutOfMemory::OutOfDeviceMemory => "Out of device memory",
+        }
+    }
+}
+
+/// The logical or physical device has been lost.
+///
+/// This may be caused by hardware failure, physical device removal,
+/// driver timeouts, etc. All further operations on the device will fail,
+/// the device and all resources created from it need to be recreated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DeviceLost;
+
+impl fmt::Display for DeviceLost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Device lost")
+    }
+}
+
+impl Error for DeviceLost {
+    fn description(&self) -> &str {
+        "Device lost"
     }
 }
 
@@ -78,6 +106,25 @@ pub enum WaitFor {
     All,
 }
 
+/// Object which can be given a debug name with `Device::set_object_name`.
+#[derive(Debug)]
+pub enum NamedObject<'a, B: Backend> {
+    ///
+    Buffer(&'a B::Buffer),
+    ///
+    Image(&'a B::Image),
+    ///
+    ImageView(&'a B::ImageView),
+    ///
+    Sampler(&'a B::Sampler),
+    ///
+    GraphicsPipeline(&'a B::GraphicsPipeline),
+    ///
+    ComputePipeline(&'a B::ComputePipeline),
+    ///
+    CommandBuffer(&'a B::CommandBuffer),
+}
+
 /// Describes the size of an Image, which may be up to three dimensional.
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -144,7 +191,9 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// Creates a new command pool for a given queue family.
     ///
     /// *Note*: the family has to be associated by one as the `Gpu::queue_groups`.
-    fn create_command_pool(&self, QueueFamilyId, CommandPoolCreateFlags) -> B::CommandPool;
+    fn create_command_pool(
+        &self, QueueFamilyId, CommandPoolCreateFlags,
+    ) -> Result<B::CommandPool, OutOfMemory>;
 
     /// Creates a strongly typed command pool wrapper.
     fn create_command_pool_typed<C>(
@@ -152,9 +201,9 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         group: &QueueGroup<B, C>,
         flags: CommandPoolCreateFlags,
         max_buffers: usize,
-    ) -> CommandPool<B, C> {
-        let raw = self.create_command_pool(group.family(), flags);
-        CommandPool::new(raw, max_buffers)
+    ) -> Result<CommandPool<B, C>, OutOfMemory> {
+        let raw = self.create_command_pool(group.family(), flags)?;
+        Ok(CommandPool::new(raw, max_buffers))
     }
 
     /// Destroys a command pool.
@@ -200,23 +249,46 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     fn destroy_pipeline_layout(&self, B::PipelineLayout);
 
-    /// Create a graphics pipeline.
+    /// Create a pipeline cache, optionally initialized with data previously
+    /// retrieved with `get_pipeline_cache_data`.
+    ///
+    /// Data which is invalid or was produced by an incompatible device or driver
+    /// is ignored, resulting in an empty cache.
+    fn create_pipeline_cache(&self, data: Option<&[u8]>) -> Result<B::PipelineCache, OutOfMemory>;
+
+    /// Retrieve the data of a pipeline cache, which can be stored and used to
+    /// initialize a new cache on a later run of the application.
+    fn get_pipeline_cache_data(&self, cache: &B::PipelineCache) -> Result<Vec<u8>, OutOfMemory>;
+
+    /// Merge the contents of the source pipeline caches into the target cache.
+    fn merge_pipeline_caches<I>(&self, target: &B::PipelineCache, sources: I) -> Result<(), OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<B::PipelineCache>;
+
+    /// Destroys a pipeline cache.
+    fn destroy_pipeline_cache(&self, cache: B::PipelineCache);
+
+    /// Create a graphics pipeline, optionally using and filling a pipeline cache.
     fn create_graphics_pipeline<'a>(
         &self,
-        desc: &pso::GraphicsPipelineDesc<'a, B>
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+        cache: Option<&B::PipelineCache>,
     ) -> Result<B::GraphicsPipeline, pso::CreationError> {
-        self.create_graphics_pipelines(Some(desc)).remove(0)
+        self.create_graphics_pipelines(Some(desc), cache).remove(0)
     }
 
-    /// Create graphics pipelines.
+    /// Create graphics pipelines, optionally using and filling a pipeline cache.
     fn create_graphics_pipelines<'a, I>(
-        &self, descs: I
+        &self,
+        descs: I,
+        cache: Option<&B::PipelineCache>,
     ) -> Vec<Result<B::GraphicsPipeline, pso::CreationError>>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::GraphicsPipelineDesc<'a, B>>,
     {
-        descs.into_iter().map(|desc| self.create_graphics_pipeline(desc.borrow())).collect()
+        descs.into_iter().map(|desc| self.create_graphics_pipeline(desc.borrow(), cache)).collect()
     }
 
     /// Destroys a graphics pipeline.
@@ -225,23 +297,26 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// which references the graphics pipeline, has finished execution.
     fn destroy_graphics_pipeline(&self, B::GraphicsPipeline);
 
-    /// Create a compute pipeline.
+    /// Create a compute pipeline, optionally using and filling a pipeline cache.
     fn create_compute_pipeline<'a>(
         &self,
-        desc: &pso::ComputePipelineDesc<'a, B>
+        desc: &pso::ComputePipelineDesc<'a, B>,
+        cache: Option<&B::PipelineCache>,
     ) -> Result<B::ComputePipeline, pso::CreationError> {
-        self.create_compute_pipelines(Some(desc)).remove(0)
+        self.create_compute_pipelines(Some(desc), cache).remove(0)
     }
 
-    /// Create compute pipelines.
+    /// Create compute pipelines, optionally using and filling a pipeline cache.
     fn create_compute_pipelines<'a, I>(
-        &self, descs: I
+        &self,
+        descs: I,
+        cache: Option<&B::PipelineCache>,
     ) -> Vec<Result<B::ComputePipeline, pso::CreationError>>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::ComputePipelineDesc<'a, B>>,
     {
-        descs.into_iter().map(|desc| self.create_compute_pipeline(desc.borrow())).collect()
+        descs.into_iter().map(|desc| self.create_compute_pipeline(desc.borrow(), cache)).collect()
     }
 
     /// Destroys a compute pipeline.
@@ -300,6 +375,17 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// which references the images, has finished execution.
     fn destroy_buffer(&self, B::Buffer);
 
+    /// Create a sparse buffer, which is bound to memory with `RawCommandQueue::bind_sparse`.
+    ///
+    /// `flags` must contain `SparseFlags::SPARSE_BINDING`.
+    fn create_sparse_buffer(
+        &self, size: u64, usage: buffer::Usage, flags: SparseFlags,
+    ) -> Result<B::Buffer, buffer::CreationError>;
+
+    /// Returns the memory requirements of a sparse buffer.
+    /// The `alignment` is the size of a single page in bytes.
+    fn get_sparse_buffer_requirements(&self, &B::Buffer) -> Requirements;
+
     ///
     fn create_buffer_view<R: RangeArg<u64>>(
         &self, &B::Buffer, Option<format::Format>, R
@@ -308,14 +394,53 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     fn destroy_buffer_view(&self, B::BufferView);
 
+    /// Create a new image object, which has to be bound to memory before use.
     ///
+    /// Images with `Tiling::Linear` may be mapped and accessed by the host directly,
+    /// using `get_image_subresource_footprint` to locate their subresources.
     fn create_image(
-        &self, image::Kind, image::Level, format::Format, image::Usage,
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
     ) -> Result<B::UnboundImage, image::CreationError>;
 
     ///
     fn get_image_requirements(&self, &B::UnboundImage) -> Requirements;
 
+    /// Create a sparse image, which is bound to memory with `RawCommandQueue::bind_sparse`.
+    ///
+    /// `flags` must contain `SparseFlags::SPARSE_BINDING`.
+    fn create_sparse_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+        flags: SparseFlags,
+    ) -> Result<B::Image, image::CreationError>;
+
+    /// Returns the memory requirements of a sparse image.
+    /// The `alignment` is the size of a single page in bytes.
+    fn get_sparse_image_requirements(&self, &B::Image) -> Requirements;
+
+    /// Returns the page layout of a sparse image created with `SparseFlags::SPARSE_RESIDENCY`.
+    fn get_sparse_image_page_layout(&self, &B::Image) -> Vec<image::SparsePageLayout>;
+
+    /// Returns the memory layout of a single aspect of a subresource of an image,
+    /// created with `Tiling::Linear`.
+    ///
+    /// `aspect` must contain exactly one aspect of the image format.
+    /// Backends without linear image support return the linear layout used
+    /// for copies between the image and a buffer instead.
+    fn get_image_subresource_footprint(
+        &self, image: &B::Image, aspect: format::Aspects, subresource: image::Subresource
+    ) -> image::SubresourceFootprint;
+
     ///
     fn bind_image_memory(
         &self, &B::Memory, offset: u64, B::UnboundImage
@@ -340,7 +465,7 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     fn destroy_image_view(&self, B::ImageView);
 
     ///
-    fn create_sampler(&self, image::SamplerInfo) -> B::Sampler;
+    fn create_sampler(&self, image::SamplerInfo) -> Result<B::Sampler, OutOfMemory>;
 
     ///
     fn destroy_sampler(&self, B::Sampler);
@@ -349,7 +474,9 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     /// Descriptor pools allow allocation of descriptor sets.
     /// Ihe pool can't be modified directly, only through updating descriptor sets.
-    fn create_descriptor_pool<I>(&self, max_sets: usize, descriptor_ranges: I) -> B::DescriptorPool
+    fn create_descriptor_pool<I>(
+        &self, max_sets: usize, descriptor_ranges: I, flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<B::DescriptorPool, OutOfMemory>
     where
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorRangeDesc>;
@@ -358,10 +485,17 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     fn destroy_descriptor_pool(&self, B::DescriptorPool);
 
     /// Create a descriptor set layout.
-    fn create_descriptor_set_layout<I>(&self, bindings: I) -> B::DescriptorSetLayout
+    ///
+    /// Bindings marked with `immutable_samplers` consume their samplers from
+    /// `immutable_samplers`, in binding order. The samplers must outlive the layout.
+    fn create_descriptor_set_layout<I, J>(
+        &self, bindings: I, immutable_samplers: J
+    ) -> B::DescriptorSetLayout
     where
         I: IntoIterator,
-        I::Item: Borrow<pso::DescriptorSetLayoutBinding>;
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<B::Sampler>;
 
     ///
     fn destroy_descriptor_set_layout(&self, B::DescriptorSetLayout);
@@ -379,6 +513,32 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         I: IntoIterator,
         I::Item: Borrow<pso::DescriptorSetCopy<'a, B>>;
 
+    /// Create a descriptor update template for sets of the given layout.
+    ///
+    /// A template records which bindings to update and where to find their
+    /// descriptors, so that many descriptors of a set can be written at once
+    /// with `update_descriptor_set_with_template`.
+    fn create_descriptor_update_template<I>(
+        &self, layout: &B::DescriptorSetLayout, entries: I
+    ) -> B::DescriptorUpdateTemplate
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorUpdateTemplateEntry>;
+
+    ///
+    fn destroy_descriptor_update_template(&self, B::DescriptorUpdateTemplate);
+
+    /// Update a descriptor set from a raw blob of data, using a template
+    /// created for the layout of the set.
+    ///
+    /// # Safety
+    ///
+    /// For each entry of the template, `data` must contain a valid, properly aligned
+    /// `pso::Descriptor` at `offset + i * stride` for each `i` in `0 .. count`.
+    unsafe fn update_descriptor_set_with_template(
+        &self, set: &B::DescriptorSet, template: &B::DescriptorUpdateTemplate, data: &[u8]
+    );
+
     ///
     fn map_memory<R>(&self, &B::Memory, R) -> Result<*mut u8, mapping::Error>
     where
@@ -459,13 +619,13 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     }
 
     ///
-    fn create_semaphore(&self) -> B::Semaphore;
+    fn create_semaphore(&self) -> Result<B::Semaphore, OutOfMemory>;
 
     ///
     fn destroy_semaphore(&self, B::Semaphore);
 
     ///
-    fn create_fence(&self, signaled: bool) -> B::Fence;
+    fn create_fence(&self, signaled: bool) -> Result<B::Fence, OutOfMemory>;
 
     ///
     fn reset_fence(&self, fence: &B::Fence) {
@@ -484,14 +644,18 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     }
 
     /// Blocks until the given fence is signaled.
-    /// Returns true if the fence was signaled before the timeout.
-    fn wait_for_fence(&self, fence: &B::Fence, timeout_ms: u32) -> bool {
+    /// Returns true if the fence was signaled before the timeout,
+    /// false if the timeout expired.
+    fn wait_for_fence(&self, fence: &B::Fence, timeout_ms: u32) -> Result<bool, DeviceLost> {
         self.wait_for_fences(Some(fence), WaitFor::All, timeout_ms)
     }
 
     /// Blocks until all or one of the given fences are signaled.
-    /// Returns true if fences were signaled before the timeout.
-    fn wait_for_fences<I>(&self, fences: I, wait: WaitFor, timeout_ms: u32) -> bool
+    /// Returns true if fences were signaled before the timeout,
+    /// false if the timeout expired.
+    fn wait_for_fences<I>(
+        &self, fences: I, wait: WaitFor, timeout_ms: u32,
+    ) -> Result<bool, DeviceLost>
     where
         I: IntoIterator,
         I::Item: Borrow<B::Fence>,
@@ -504,28 +668,28 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         match wait {
             WaitFor::All => {
                 for fence in fences {
-                    if !self.wait_for_fence(fence.borrow(), 0) {
+                    if !self.wait_for_fence(fence.borrow(), 0)? {
                         let elapsed_ms = to_ms(start.elapsed());
                         if elapsed_ms > timeout_ms {
-                            return false;
+                            return Ok(false);
                         }
-                        if !self.wait_for_fence(fence.borrow(), timeout_ms - elapsed_ms) {
-                            return false;
+                        if !self.wait_for_fence(fence.borrow(), timeout_ms - elapsed_ms)? {
+                            return Ok(false);
                         }
                     }
                 }
-                true
+                Ok(true)
             },
             WaitFor::Any => {
                 let fences: Vec<_> = fences.into_iter().collect();
                 loop {
                     for fence in &fences {
-                        if self.wait_for_fence(fence.borrow(), 0) {
-                            return true;
+                        if self.wait_for_fence(fence.borrow(), 0)? {
+                            return Ok(true);
                         }
                     }
                     if to_ms(start.elapsed()) >= timeout_ms {
-                        return false;
+                        return Ok(false);
                     }
                     thread::sleep(time::Duration::from_millis(1));
                 }
@@ -534,7 +698,33 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     }
 
     /// true for signaled, false for not ready
-    fn get_fence_status(&self, &B::Fence) -> bool;
+    fn get_fence_status(&self, &B::Fence) -> Result<bool, DeviceLost>;
+
+    /// Check if the submission with the given identifier has finished execution on `queue`.
+    /// Any earlier submission to the same queue has finished as well if this returns true.
+    fn get_submission_status(
+        &self, queue: &B::CommandQueue, id: SubmissionId,
+    ) -> Result<bool, DeviceLost>;
+
+    /// Blocks until the submission with the given identifier has finished execution on `queue`.
+    /// Returns true if the submission finished before the timeout,
+    /// false if the timeout expired.
+    fn wait_for_submission(
+        &self, queue: &B::CommandQueue, id: SubmissionId, timeout_ms: u32,
+    ) -> Result<bool, DeviceLost> {
+        use std::{time, thread};
+        let start = time::Instant::now();
+        loop {
+            if self.get_submission_status(queue, id)? {
+                return Ok(true);
+            }
+            let elapsed = start.elapsed();
+            if elapsed.as_secs() as u32 * 1000 + elapsed.subsec_nanos() / 1_000_000 >= timeout_ms {
+                return Ok(false);
+            }
+            thread::sleep(time::Duration::from_millis(1));
+        }
+    }
 
     ///
     fn destroy_fence(&self, B::Fence);
@@ -582,4 +772,9 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     /// Host access to all queues needs to be **externally** sycnhronized!
     fn wait_idle(&self) -> Result<(), HostExecutionError>;
+
+    /// Attach a name to an object, which shows up in debugging and capture tools.
+    ///
+    /// Naming is a hint and may be ignored, e.g. if no debugging tool is attached.
+    fn set_object_name(&self, object: NamedObject<B>, name: &str);
 }
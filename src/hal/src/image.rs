@@ -11,6 +11,7 @@ use std::error::Error;
 use std::fmt;
 use std::ops::Range;
 
+use device::Extent;
 use format;
 use pso::Comparison;
 
@@ -49,6 +50,12 @@ pub enum CreationError {
     Data(usize),
     /// The mentioned usage mode is not supported
     Usage(Usage),
+    /// The requested tiling is not supported.
+    Tiling(Tiling),
+    /// Sparse images are not supported.
+    Sparse,
+    /// The requested storage flags are not supported.
+    StorageFlags(StorageFlags),
 }
 
 impl fmt::Display for CreationError {
@@ -59,6 +66,8 @@ impl fmt::Display for CreationError {
             CreationError::Size(size) => write!(f, "{}: {}", self.description(), size),
             CreationError::Data(data) => write!(f, "{}: {}", self.description(), data),
             CreationError::Usage(usage) => write!(f, "{}: {:?}", self.description(), usage),
+            CreationError::Tiling(tiling) => write!(f, "{}: {:?}", self.description(), tiling),
+            CreationError::StorageFlags(flags) => write!(f, "{}: {:?}", self.description(), flags),
             _ => write!(f, "{}", self.description()),
         }
     }
@@ -73,6 +82,9 @@ impl Error for CreationError {
             CreationError::Size(_) => "Unsupported size in one of the dimensions",
             CreationError::Data(_) => "The given data has a different size than the target texture slice",
             CreationError::Usage(_) => "The expected texture usage mode is not supported by a graphic API",
+            CreationError::Tiling(_) => "The requested image tiling is not supported",
+            CreationError::Sparse => "Sparse images are not supported",
+            CreationError::StorageFlags(_) => "The requested storage flags are not supported",
         }
     }
 }
@@ -501,6 +513,30 @@ pub struct RenderDesc {
     pub layer: Option<Layer>,
 }
 
+/// Memory layout of the texels of an image.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Tiling {
+    /// Implementation-defined layout, optimal for device access.
+    Optimal,
+    /// Texels are laid out in row-major order, possibly with padding between rows.
+    /// Allows the image memory to be accessed directly by the host.
+    Linear,
+}
+
+bitflags!(
+    /// Image creation flags, independent of the usage.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct StorageFlags: u32 {
+        /// Views may be created with a different (but compatible) format than the image.
+        const MUTABLE_FORMAT = 0x8;
+        /// Cube and cube array views may be created from a 2D array image.
+        const CUBE_VIEW = 0x10;
+        /// 2D and 2D array views may be created from a 3D image.
+        const ARRAY_2D_VIEW = 0x20;
+    }
+);
+
 bitflags!(
     /// Depth-stencil read-only flags
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -615,6 +651,89 @@ pub type State = (Access, ImageLayout);
 /// Selector of a concrete subresource in an image.
 pub type Subresource = (Level, Layer);
 
+/// Properties of an image with a particular combination of
+/// format, dimensionality, tiling, usage and storage flags.
+#[derive(Clone, Copy, Debug)]
+pub struct FormatProperties {
+    /// Maximum extent of the image.
+    pub max_extent: Extent,
+    /// Maximum number of mipmap levels.
+    pub max_levels: Level,
+    /// Maximum number of array layers.
+    pub max_layers: Layer,
+    /// Bit mask of the supported sample counts.
+    /// A sample count `n` is supported if the bit `n` is set.
+    pub sample_count_mask: NumSamples,
+    /// Maximum size of the image resource in bytes.
+    pub max_resource_size: u64,
+}
+
+/// Page layout of a sparse image, for a set of aspects.
+#[derive(Clone, Copy, Debug)]
+pub struct SparsePageLayout {
+    /// Aspects described by this layout.
+    pub aspects: format::Aspects,
+    /// Extent of a single page, in texels.
+    pub granularity: Extent,
+    /// First mipmap level stored in the mip tail.
+    /// Levels starting with this one can only be bound as opaque memory ranges.
+    pub mip_tail_first_level: Level,
+    /// Size of the mip tail of a single array layer, in bytes.
+    pub mip_tail_size: u64,
+    /// Offset of the mip tail of the first array layer, in bytes.
+    pub mip_tail_offset: u64,
+    /// Byte distance between the mip tails of consecutive array layers,
+    /// or `None` if all layers share a single mip tail.
+    pub mip_tail_stride: Option<u64>,
+}
+
+/// Memory footprint of a subresource of a linear image.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SubresourceFootprint {
+    /// Byte range occupied by the subresource, relative to the start of the image memory.
+    pub slice: Range<u64>,
+    /// Byte offset between consecutive rows.
+    pub row_pitch: u64,
+    /// Byte offset between consecutive array layers.
+    pub array_pitch: u64,
+    /// Byte offset between consecutive depth slices.
+    pub depth_pitch: u64,
+}
+
+impl SubresourceFootprint {
+    /// Footprint of a subresource in a tightly packed linear layout.
+    ///
+    /// Array layers follow each other, each containing all of its mip levels
+    /// in increasing order.
+    pub fn packed(
+        kind: Kind, num_levels: Level, desc: format::FormatDesc, (level, layer): Subresource,
+    ) -> Self {
+        let (block_width, block_height) = (desc.dim.0 as u64, desc.dim.1 as u64);
+        let level_pitches = |level| {
+            let (width, height, depth, _) = kind.level_dimensions(level);
+            let depth = match kind {
+                Kind::D3(..) => depth as u64,
+                _ => 1,
+            };
+            let row_pitch = (width as u64 + block_width - 1) / block_width * (desc.bits / 8) as u64;
+            let depth_pitch = row_pitch * ((height as u64 + block_height - 1) / block_height);
+            (row_pitch, depth_pitch, depth_pitch * depth)
+        };
+
+        let array_pitch = (0 .. num_levels).map(|l| level_pitches(l).2).sum::<u64>();
+        let offset = layer as u64 * array_pitch +
+            (0 .. level).map(|l| level_pitches(l).2).sum::<u64>();
+        let (row_pitch, depth_pitch, size) = level_pitches(level);
+
+        SubresourceFootprint {
+            slice: offset .. offset + size,
+            row_pitch,
+            array_pitch,
+            depth_pitch,
+        }
+    }
+}
+
 /// A subset of resource layers contained within an image's level.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -31,11 +31,12 @@ pub use self::device::Device;
 pub use self::pool::CommandPool;
 pub use self::pso::DescriptorPool;
 pub use self::queue::{
-    CommandQueue, QueueGroup, QueueFamily, QueueType, Submission,
+    CommandQueue, QueueGroup, QueueFamily, QueueType, Submission, SubmissionId,
     Capability, Supports, General, Graphics, Compute, Transfer,
 };
 pub use self::window::{
-    Backbuffer, Frame, FrameSync, Surface, SurfaceCapabilities, Swapchain, SwapchainConfig,
+    AcquireError, Backbuffer, Frame, FrameSync, PresentError, PresentStatus,
+    Surface, SurfaceCapabilities, Swapchain, SwapchainConfig,
 };
 
 pub mod adapter;
@@ -53,6 +54,7 @@ pub mod pso;
 pub mod query;
 pub mod queue;
 pub mod range;
+pub mod tracking;
 pub mod window;
 
 #[doc(hidden)]
@@ -243,6 +245,12 @@ pub struct Limits {
     pub min_buffer_copy_pitch_alignment: buffer::Offset,
     /// The alignment of the start of buffer used for uniform buffer updates, in bytes, non-zero.
     pub min_uniform_buffer_offset_alignment: buffer::Offset,
+    /// Granularity in bytes at which buffers and optimally tiled images bound to the
+    /// same memory object must be placed apart to avoid aliasing, non-zero.
+    pub buffer_image_granularity: buffer::Offset,
+    /// Alignment in bytes of the offset and size of mapped ranges of non-coherent memory
+    /// which are flushed or invalidated, non-zero.
+    pub non_coherent_atom_size: buffer::Offset,
 }
 
 /// Describes the type of geometric primitives,
@@ -342,20 +350,29 @@ pub trait Backend: 'static + Sized + Eq + Clone + Hash + fmt::Debug + Any + Send
 
     type ComputePipeline:     fmt::Debug + Any + Send + Sync;
     type GraphicsPipeline:    fmt::Debug + Any + Send + Sync;
+    type PipelineCache:       fmt::Debug + Any + Send + Sync;
     type PipelineLayout:      fmt::Debug + Any + Send + Sync;
     type DescriptorPool:      pso::DescriptorPool<Self>;
     type DescriptorSet:       fmt::Debug + Any + Send + Sync;
     type DescriptorSetLayout: fmt::Debug + Any + Send + Sync;
+    type DescriptorUpdateTemplate: fmt::Debug + Any + Send + Sync;
 
     type Fence:               fmt::Debug + Any + Send + Sync;
     type Semaphore:           fmt::Debug + Any + Send + Sync;
     type QueryPool:           fmt::Debug + Any + Send + Sync;
 }
 
-/// Marks that an error occured submitting a command to a command buffer.
-#[derive(Clone, Debug, PartialEq)]
+/// Marks that an error occured submitting command buffers to a queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub enum SubmissionError {}
+pub enum SubmissionError {
+    /// Memory allocation on the host side failed.
+    OutOfHostMemory,
+    /// Memory allocation on the device side failed.
+    OutOfDeviceMemory,
+    /// The logical or physical device has been lost.
+    DeviceLost,
+}
 
 impl fmt::Display for SubmissionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -365,11 +382,30 @@ impl fmt::Display for SubmissionError {
 
 impl Error for SubmissionError {
     fn description(&self) -> &str {
-        "Submission error"
+        match *self {
+            SubmissionError::OutOfHostMemory => "Submission failed: out of host memory",
+            SubmissionError::OutOfDeviceMemory => "Submission failed: out of device memory",
+            SubmissionError::DeviceLost => "Submission failed: device lost",
+        }
+    }
+}
+
+impl From<device::OutOfMemory> for SubmissionError {
+    fn from(error: device::OutOfMemory) -> Self {
+        match error {
+            device::OutOfMemory::OutOfHostMemory => SubmissionError::OutOfHostMemory,
+            device::OutOfMemory::OutOfDeviceMemory => SubmissionError::OutOfDeviceMemory,
+        }
+    }
+}
+
+impl From<device::DeviceLost> for SubmissionError {
+    fn from(_: device::DeviceLost) -> Self {
+        SubmissionError::DeviceLost
     }
 }
 
-/// Submission result for DX11 backend.  Currently mostly unused.
+/// Result of submitting command buffers to a queue.
 pub type SubmissionResult<T> = Result<T, SubmissionError>;
 
 
@@ -63,6 +63,27 @@ bitflags!(
     }
 );
 
+bitflags!(
+    /// Sparse resource creation flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct SparseFlags: u32 {
+        /// Memory is bound with `RawCommandQueue::bind_sparse` instead of
+        /// `Device::bind_*_memory`, and may be rebound during the lifetime of the resource.
+        ///
+        /// Requires `Features::SPARSE_BINDING`.
+        const SPARSE_BINDING = 0x1;
+        /// The resource may be partially resident, i.e. only some pages are bound to memory.
+        ///
+        /// Requires one of the `Features::SPARSE_RESIDENCY_*` features.
+        const SPARSE_RESIDENCY = 0x2;
+        /// The memory bound to the resource may be simultaneously bound to
+        /// other resources or other locations of the same resource.
+        ///
+        /// Requires `Features::SPARSE_RESIDENCY_ALIASED`.
+        const SPARSE_ALIASED = 0x4;
+    }
+);
+
 bitflags!(
     /// Barrier dependency flags.
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -2,8 +2,8 @@
 
 use {Backend};
 use command::{
-    CommandBuffer, RawCommandBuffer, SecondaryCommandBuffer, 
-    SubpassCommandBuffer, CommandBufferFlags, Shot, RawLevel
+    CommandBuffer, RawCommandBuffer, SecondaryCommandBuffer, SubpassCommandBuffer,
+    CommandBufferFlags, CommandBufferInheritanceInfo, Shot, RawLevel
 };
 use queue::capability::{Supports, Graphics};
 
@@ -105,7 +105,7 @@ impl<B: Backend, C> CommandPool<B, C> {
         if allow_pending_resubmit {
             flags |= CommandBufferFlags::SIMULTANEOUS_USE;
         }
-        buffer.begin(flags);
+        buffer.begin(flags, CommandBufferInheritanceInfo::default());
         self.next_buffer += 1;
         unsafe {
             CommandBuffer::new(buffer)
@@ -117,7 +117,14 @@ impl<B: Backend, C> CommandPool<B, C> {
     /// You can only record to one command buffer per pool at the same time.
     /// If more command buffers are requested than allocated, new buffers will be reserved.
     /// The command buffer will be returned in 'recording' state.
-    pub fn acquire_secondary_command_buffer<S: Shot>(&mut self, allow_pending_resubmit: bool) -> SecondaryCommandBuffer<B, C, S> {
+    ///
+    /// `inheritance_info` describes the query state inherited from the primary
+    /// command buffer executing it.
+    pub fn acquire_secondary_command_buffer<S: Shot>(
+        &mut self,
+        inheritance_info: CommandBufferInheritanceInfo<B>,
+        allow_pending_resubmit: bool,
+    ) -> SecondaryCommandBuffer<B, C, S> {
         self.reserve_secondary(1);
 
         let buffer = &mut self.secondary_buffers[self.next_secondary_buffer];
@@ -125,7 +132,7 @@ impl<B: Backend, C> CommandPool<B, C> {
         if allow_pending_resubmit {
             flags |= CommandBufferFlags::SIMULTANEOUS_USE;
         }
-        buffer.begin(flags);
+        buffer.begin(flags, inheritance_info);
         self.next_secondary_buffer += 1;
         unsafe {
             SecondaryCommandBuffer::new(buffer)
@@ -148,15 +155,23 @@ impl<B: Backend, C: Supports<Graphics>> CommandPool<B, C> {
     /// You can only record to one command buffer per pool at the same time.
     /// If more command buffers are requested than allocated, new buffers will be reserved.
     /// The command buffer will be returned in 'recording' state.
-    pub fn acquire_subpass_command_buffer<S: Shot>(&mut self, allow_pending_resubmit: bool) -> SubpassCommandBuffer<B, S> {
+    ///
+    /// `inheritance_info` must specify the subpass the command buffer will be
+    /// executed within.
+    pub fn acquire_subpass_command_buffer<S: Shot>(
+        &mut self,
+        inheritance_info: CommandBufferInheritanceInfo<B>,
+        allow_pending_resubmit: bool,
+    ) -> SubpassCommandBuffer<B, S> {
+        assert!(inheritance_info.subpass.is_some(), "Subpass command buffers require a subpass to inherit");
         self.reserve_secondary(1);
 
         let buffer = &mut self.secondary_buffers[self.next_secondary_buffer];
-        let mut flags = S::FLAGS;
+        let mut flags = S::FLAGS | CommandBufferFlags::RENDER_PASS_CONTINUE;
         if allow_pending_resubmit {
             flags |= CommandBufferFlags::SIMULTANEOUS_USE;
         }
-        buffer.begin(flags);
+        buffer.begin(flags, inheritance_info);
         self.next_secondary_buffer += 1;
         unsafe {
             SubpassCommandBuffer::new(buffer)
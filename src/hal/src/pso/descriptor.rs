@@ -6,7 +6,10 @@
 //! different sorts of resources, and a shader may use multiple descriptor sets at a time.
 
 use std::borrow::Borrow;
+use std::error::Error;
 use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
 use std::ops::Range;
 
 use {Backend};
@@ -74,7 +77,13 @@ pub struct DescriptorSetLayoutBinding {
     pub count: DescriptorArrayIndex,
     /// Valid shader stages.
     pub stage_flags: ShaderStageFlags,
-    // TODO: immutable samplers?
+    /// Use immutable samplers for this binding.
+    ///
+    /// Only valid for `Sampler` and `CombinedImageSampler` bindings.
+    /// The samplers are baked into the layout and taken, `count` at a time,
+    /// from the `immutable_samplers` passed to `create_descriptor_set_layout`
+    /// in the order of the bindings. Samplers written to such a binding are ignored.
+    pub immutable_samplers: bool,
 }
 
 /// Set of descriptors of a specific type.
@@ -87,36 +96,88 @@ pub struct DescriptorRangeDesc {
     pub count: usize,
 }
 
+bitflags!(
+    /// Descriptor pool creation flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct DescriptorPoolCreateFlags: u32 {
+        /// Allow descriptor sets to be freed individually with `free_sets`.
+        const FREE_DESCRIPTOR_SET = 0x1;
+    }
+);
+
+/// Error allocating descriptor sets from a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocationError {
+    /// Host memory allocation failed.
+    OutOfHostMemory,
+    /// Device memory allocation failed.
+    OutOfDeviceMemory,
+    /// The pool doesn't have enough space left for the requested sets.
+    OutOfPoolMemory,
+    /// The pool has enough space left in total, but it's too fragmented.
+    FragmentedPool,
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for AllocationError {
+    fn description(&self) -> &str {
+        match *self {
+            AllocationError::OutOfHostMemory => "Out of host memory.",
+            AllocationError::OutOfDeviceMemory => "Out of device memory.",
+            AllocationError::OutOfPoolMemory => "Out of descriptor pool memory.",
+            AllocationError::FragmentedPool => "Descriptor pool is fragmented.",
+        }
+    }
+}
 
 /// A descriptor pool is a collection of memory from which descriptor sets are allocated.
 pub trait DescriptorPool<B: Backend>: Send + Sync + fmt::Debug {
     /// Allocate a descriptor set from the pool.
     ///
     /// The descriptor set will be allocated from the pool according to the corresponding set layout.
-    /// The descriptor pool _must_ have enough space in to allocate the required descriptor.
     /// Descriptors will become invalid once the pool is reset. Usage of invalidated descriptor sets results
     /// in undefined behavior.
-    fn allocate_set(&mut self, layout: &B::DescriptorSetLayout) -> B::DescriptorSet {
-        self.allocate_sets(Some(layout)).remove(0)
+    fn allocate_set(&mut self, layout: &B::DescriptorSetLayout) -> Result<B::DescriptorSet, AllocationError> {
+        self.allocate_sets(Some(layout)).map(|mut sets| sets.remove(0))
     }
 
     /// Allocate one or multiple descriptor sets from the pool.
     ///
     /// Each descriptor set will be allocated from the pool according to the corresponding set layout.
-    /// The descriptor pool _must_ have enough space in to allocate the required descriptors.
+    /// Either all of the sets are allocated or none of them, in which case the error is returned.
     /// Descriptors will become invalid once the pool is reset. Usage of invalidated descriptor sets results
     /// in undefined behavior.
-    fn allocate_sets<I>(&mut self, layouts: I) -> Vec<B::DescriptorSet>
+    fn allocate_sets<I>(&mut self, layouts: I) -> Result<Vec<B::DescriptorSet>, AllocationError>
     where
         I: IntoIterator,
         I::Item: Borrow<B::DescriptorSetLayout>,
     {
-        layouts
-            .into_iter()
-            .map(|layout| self.allocate_set(layout.borrow()))
-            .collect()
+        let mut sets = Vec::new();
+        for layout in layouts {
+            match self.allocate_set(layout.borrow()) {
+                Ok(set) => sets.push(set),
+                Err(err) => {
+                    self.free_sets(sets);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(sets)
     }
 
+    /// Free the given descriptor sets, returning their descriptors to the pool.
+    ///
+    /// The pool _must_ have been created with `DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET`.
+    /// The sets _must_ not be in use by any pending command buffer.
+    fn free_sets<I>(&mut self, descriptor_sets: I)
+    where
+        I: IntoIterator<Item = B::DescriptorSet>;
+
     /// Resets a descriptor pool, releasing all resources from all the descriptor sets
     /// allocated from it and freeing the descriptor sets. Invalidates all descriptor
     /// sets allocated from the pool; trying to use one after the pool has been reset
@@ -161,3 +222,84 @@ pub struct DescriptorSetCopy<'a, B: Backend> {
     pub dst_array_offset: DescriptorArrayIndex,
     pub count: usize,
 }
+
+/// Location of the descriptors of a binding range inside the data
+/// passed to `Device::update_descriptor_set_with_template`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DescriptorUpdateTemplateEntry {
+    /// Binding to update.
+    pub binding: DescriptorBinding,
+    /// First array element of the binding to update.
+    pub array_offset: DescriptorArrayIndex,
+    /// Number of descriptors to update.
+    ///
+    /// *Note*: unlike `DescriptorSetWrite`, entries don't spill
+    /// over onto the next binding.
+    pub count: usize,
+    /// Offset in bytes of the first `Descriptor` in the data.
+    pub offset: usize,
+    /// Distance in bytes between two consecutive descriptors in the data.
+    pub stride: usize,
+}
+
+impl DescriptorUpdateTemplateEntry {
+    /// Build the descriptor set write of this entry from the template data.
+    ///
+    /// Backends without native template support use this to replay the template.
+    ///
+    /// # Safety
+    ///
+    /// `data` must contain a valid, properly aligned `Descriptor<'a, B>`
+    /// at `offset + i * stride` for each `i` in `0 .. count`.
+    pub unsafe fn write<'a, B: Backend>(
+        &self, set: &'a B::DescriptorSet, data: &'a [u8],
+    ) -> DescriptorSetWrite<'a, B, TemplateDescriptors<'a, B>> {
+        assert!(self.count == 0 ||
+            self.offset + (self.count - 1) * self.stride + mem::size_of::<Descriptor<B>>() <= data.len());
+        DescriptorSetWrite {
+            set,
+            binding: self.binding,
+            array_offset: self.array_offset,
+            descriptors: TemplateDescriptors {
+                data,
+                offset: self.offset,
+                stride: self.stride,
+                remaining: self.count,
+                phantom: PhantomData,
+            },
+        }
+    }
+}
+
+/// Iterator over the descriptors of a template entry, see `DescriptorUpdateTemplateEntry::write`.
+#[derive(Debug)]
+pub struct TemplateDescriptors<'a, B: Backend> {
+    data: &'a [u8],
+    offset: usize,
+    stride: usize,
+    remaining: usize,
+    phantom: PhantomData<&'a Descriptor<'a, B>>,
+}
+
+impl<'a, B: Backend> Iterator for TemplateDescriptors<'a, B> {
+    type Item = &'a Descriptor<'a, B>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let ptr = self.data[self.offset ..].as_ptr() as *const Descriptor<'a, B>;
+        debug_assert_eq!(ptr as usize % mem::align_of::<Descriptor<B>>(), 0);
+        self.offset += self.stride;
+        self.remaining -= 1;
+        // Validity of the data has been promised when creating the iterator.
+        Some(unsafe { &*ptr })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, B: Backend> ExactSizeIterator for TemplateDescriptors<'a, B> {}
@@ -194,6 +194,24 @@ pub struct BlendDesc {
     pub targets: Vec<ColorBlendDesc>,
 }
 
+impl BlendDesc {
+    /// Check if all color targets share the same blend state,
+    /// disregarding the color write masks.
+    pub fn has_uniform_blend_state(&self) -> bool {
+        self.targets
+            .windows(2)
+            .all(|pair| pair[0].1 == pair[1].1)
+    }
+
+    /// Check if all color targets share the same blend state and color write mask.
+    /// Blend descriptions which aren't uniform require `Features::INDEPENDENT_BLENDING`.
+    pub fn is_uniform(&self) -> bool {
+        self.targets
+            .windows(2)
+            .all(|pair| pair[0] == pair[1])
+    }
+}
+
 /// Logic operations used for specifying blend equations.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -31,6 +31,12 @@ pub enum CreationError {
     InvalidSubpass(pass::SubpassId),
     /// Shader compilation error.
     Shader(device::ShaderError),
+    /// The blend description can't be supported by the device, e.g. targets
+    /// with different blend states without `Features::INDEPENDENT_BLENDING`.
+    UnsupportedBlending,
+    /// A specialization constant doesn't exist in the shader, or its type
+    /// doesn't match the provided value.
+    InvalidSpecialization(u32),
 }
 
 impl fmt::Display for CreationError {
@@ -38,6 +44,7 @@ impl fmt::Display for CreationError {
         match *self {
             CreationError::InvalidSubpass(id) => write!(f, "{}: {:?}", self.description(), id),
             CreationError::Shader(ref err) => write!(f, "{}: {:?}", self.description(), err),
+            CreationError::InvalidSpecialization(id) => write!(f, "{}: {}", self.description(), id),
             _ => write!(f, "{}", self.description()),
         }
     }
@@ -49,6 +56,8 @@ impl Error for CreationError {
             CreationError::Other => "Unknown other error.",
             CreationError::InvalidSubpass(_) => "Invalid subpass index.",
             CreationError::Shader(_) => "Shader compilation error.",
+            CreationError::UnsupportedBlending => "Unsupported blend description.",
+            CreationError::InvalidSpecialization(_) => "Invalid specialization constant.",
         }
     }
 }
@@ -26,6 +26,10 @@ pub trait QueueFamily: Debug + Any + Send + Sync {
     fn supports_compute(&self) -> bool {
         Compute::supported_by(self.queue_type())
     }
+    /// Returns true if the queue supports `RawCommandQueue::bind_sparse`.
+    fn supports_sparse_binding(&self) -> bool {
+        false
+    }
     ///
     fn id(&self) -> QueueFamilyId;
 }
@@ -8,6 +8,7 @@
 
 pub mod capability;
 pub mod family;
+pub mod sparse;
 pub mod submission;
 
 use std::any::Any;
@@ -15,7 +16,8 @@ use std::borrow::{Borrow, BorrowMut};
 use std::marker::PhantomData;
 
 use error::HostExecutionError;
-use Backend;
+use window::{PresentError, PresentStatus};
+use {Backend, SubmissionResult};
 
 pub use self::capability::{
     Capability, Supports,
@@ -24,7 +26,8 @@ pub use self::capability::{
 pub use self::family::{
     QueueFamily, QueueFamilyId, QueueGroup, Queues,
 };
-pub use self::submission::{RawSubmission, Submission};
+pub use self::sparse::{RawSparseBinding, SparseBind, SparseImageBind};
+pub use self::submission::{RawSubmission, Submission, SubmissionId};
 
 
 /// The type of the queue, an enum encompassing `queue::Capability`
@@ -46,22 +49,36 @@ pub enum QueueType {
 pub trait RawCommandQueue<B: Backend>: Any + Send + Sync {
     /// Submit command buffers to queue for execution.
     /// `fence` will be signalled after submission and _must_ be unsignalled.
+    /// Returns the identifier of the submission, which can be used to track its progress
+    /// with `Device::get_submission_status` and `Device::wait_for_submission`.
     ///
     /// Unsafe because it's not checked that the queue can process the submitted command buffers.
     /// Trying to submit compute commands to a graphics queue will result in undefined behavior.
     /// Each queue implements safe wrappers according to their supported functionalities!
-    unsafe fn submit_raw<IC>(&mut self, RawSubmission<B, IC>, Option<&B::Fence>)
+    unsafe fn submit_raw<IC>(
+        &mut self, RawSubmission<B, IC>, Option<&B::Fence>,
+    ) -> SubmissionResult<SubmissionId>
     where
         Self: Sized,
         IC: IntoIterator,
         IC::Item: Borrow<B::CommandBuffer>;
 
+    /// Bind memory to sparse resources, after waiting on the `wait_semaphores` of the binding.
+    /// `fence` will be signalled after the binding and _must_ be unsignalled.
+    ///
+    /// Unsafe because it's not checked that the queue family supports sparse binding.
+    unsafe fn bind_sparse(
+        &mut self, RawSparseBinding<B>, Option<&B::Fence>,
+    ) -> SubmissionResult<()>;
+
     /// Presents the result of the queue to the given swapchains, after waiting on all the
     /// semaphores given in `wait_semaphores`. A given swapchain must not appear in this
     /// list more than once.
     ///
+    /// Returns `PresentStatus::Suboptimal` if any of the swapchains is suboptimal.
+    ///
     /// Unsafe for the same reasons as `submit_raw()`.
-    fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW)
+    fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW) -> Result<PresentStatus, PresentError>
     where
         Self: Sized,
         IS: IntoIterator,
@@ -89,10 +106,12 @@ impl<B: Backend, C> CommandQueue<B, C> {
 
     /// Submits the submission command buffers to the queue for execution.
     /// `fence` will be signalled after submission and _must_ be unsignalled.
+    /// Returns the identifier of the submission.
     pub fn submit<D>(&mut self,
         submission: Submission<B, D>,
         fence: Option<&B::Fence>,
-    ) where
+    ) -> SubmissionResult<SubmissionId>
+    where
         C: Supports<D>
     {
         unsafe {
@@ -103,7 +122,7 @@ impl<B: Backend, C> CommandQueue<B, C> {
     /// Presents the result of the queue to the given swapchains, after waiting on all the
     /// semaphores given in `wait_semaphores`. A given swapchain must not appear in this
     /// list more than once.
-    pub fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW)
+    pub fn present<IS, IW>(&mut self, swapchains: IS, wait_semaphores: IW) -> Result<PresentStatus, PresentError>
     where
         IS: IntoIterator,
         IS::Item: BorrowMut<B::Swapchain>,
@@ -0,0 +1,46 @@
+//! Sparse binding of memory to buffers and images.
+
+use {format, image, Backend};
+use device::Extent;
+
+/// Binding of a memory range to an opaque byte range of a sparse resource.
+#[derive(Debug)]
+pub struct SparseBind<'a, B: Backend + 'a> {
+    /// Offset into the resource in bytes, which must be a multiple of the page size.
+    pub resource_offset: u64,
+    /// Size of the bound range in bytes, which must be a multiple of the page size,
+    /// unless the range reaches the end of the resource.
+    pub size: u64,
+    /// Memory and offset to bind the range to, or `None` to unbind it.
+    pub memory: Option<(&'a B::Memory, u64)>,
+}
+
+/// Binding of memory to a region of a single subresource of a sparse image.
+#[derive(Debug)]
+pub struct SparseImageBind<'a, B: Backend + 'a> {
+    /// Aspect of the subresource.
+    pub aspects: format::Aspects,
+    /// Mipmap level and array layer of the subresource.
+    pub subresource: image::Subresource,
+    /// Offset of the region in texels, which must be a multiple of the page granularity.
+    pub offset: image::Offset,
+    /// Extent of the region in texels, which must be a multiple of the page granularity,
+    /// unless the region reaches the edge of the subresource.
+    pub extent: Extent,
+    /// Memory and offset to bind the region to, or `None` to unbind it.
+    pub memory: Option<(&'a B::Memory, u64)>,
+}
+
+/// Sparse binding operation for a command queue.
+pub struct RawSparseBinding<'a, B: Backend + 'a> {
+    /// Semaphores to wait being signalled before the binding.
+    pub wait_semaphores: &'a [&'a B::Semaphore],
+    /// Semaphores which get signalled after the binding.
+    pub signal_semaphores: &'a [&'a B::Semaphore],
+    /// Memory ranges to bind to sparse buffers.
+    pub buffers: &'a [(&'a B::Buffer, &'a [SparseBind<'a, B>])],
+    /// Opaque memory ranges to bind to sparse images, e.g. the mip tails.
+    pub image_opaques: &'a [(&'a B::Image, &'a [SparseBind<'a, B>])],
+    /// Image regions to bind to partially resident images.
+    pub images: &'a [(&'a B::Image, &'a [SparseImageBind<'a, B>])],
+}
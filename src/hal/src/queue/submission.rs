@@ -9,6 +9,14 @@ use std::ops::Deref;
 use std::marker::PhantomData;
 use smallvec::SmallVec;
 
+/// Identifier of a submission to a command queue.
+///
+/// Identifiers increase monotonically with every submission to the same queue,
+/// which allows to track the progress of a queue by comparing them.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SubmissionId(pub u64);
+
 /// Raw submission information for a command queue.
 pub struct RawSubmission<'a, B: Backend + 'a, IC>
 where
@@ -0,0 +1,500 @@
+//! Resource state tracking.
+//!
+//! Opt-in helpers which keep track of the current states of buffers and image
+//! subresources, and generate the barriers required to bring them into the
+//! states of an upcoming use.
+//!
+//! ```no_run
+//! # extern crate gfx_hal as hal;
+//! # use hal::{image as i, Backend, Graphics};
+//! # use hal::command::CommandBuffer;
+//! # use hal::format::Aspects;
+//! # use hal::memory::Dependencies;
+//! # use hal::pso::PipelineStage;
+//! # use hal::tracking::{ImageTracker, Transitions};
+//! # fn sample<B: Backend>(cmd_buffer: &mut CommandBuffer<B, Graphics>, image: &B::Image) {
+//! let mut tracker = ImageTracker::new(
+//!     (i::Access::empty(), i::ImageLayout::Undefined),
+//!     Aspects::COLOR,
+//!     4,
+//!     1,
+//! );
+//!
+//! let mut transitions = Transitions::new();
+//! tracker.transition(
+//!     image,
+//!     i::SubresourceRange { aspects: Aspects::COLOR, levels: 0 .. 4, layers: 0 .. 1 },
+//!     (i::Access::SHADER_READ, i::ImageLayout::ShaderReadOnlyOptimal),
+//!     PipelineStage::FRAGMENT_SHADER,
+//!     &mut transitions,
+//! );
+//! if !transitions.is_empty() {
+//!     cmd_buffer.pipeline_barrier(transitions.stages(), Dependencies::empty(), transitions.barriers());
+//! }
+//! # }
+//! # fn main() {}
+//! ```
+
+use std::ops::Range;
+
+use {buffer, image, Backend};
+use format::Aspects;
+use memory::Barrier;
+use pso::PipelineStage;
+
+/// Barriers and pipeline stages collected from one or more state transitions,
+/// to be recorded with a single `pipeline_barrier` command.
+#[derive(Debug)]
+pub struct Transitions<'a, B: Backend> {
+    src_stages: PipelineStage,
+    dst_stages: PipelineStage,
+    barriers: Vec<Barrier<'a, B>>,
+}
+
+impl<'a, B: Backend> Transitions<'a, B> {
+    /// Create an empty set of transitions.
+    pub fn new() -> Self {
+        Transitions {
+            src_stages: PipelineStage::empty(),
+            dst_stages: PipelineStage::empty(),
+            barriers: Vec::new(),
+        }
+    }
+
+    /// Check if no barriers are required.
+    pub fn is_empty(&self) -> bool {
+        self.barriers.is_empty()
+    }
+
+    /// Source and destination stages of the barriers.
+    pub fn stages(&self) -> Range<PipelineStage> {
+        let src = if self.src_stages.is_empty() {
+            PipelineStage::TOP_OF_PIPE
+        } else {
+            self.src_stages
+        };
+        let dst = if self.dst_stages.is_empty() {
+            PipelineStage::BOTTOM_OF_PIPE
+        } else {
+            self.dst_stages
+        };
+        src .. dst
+    }
+
+    /// The collected barriers.
+    pub fn barriers(&self) -> &[Barrier<'a, B>] {
+        &self.barriers
+    }
+
+    /// Clear all collected barriers and stages.
+    pub fn clear(&mut self) {
+        self.src_stages = PipelineStage::empty();
+        self.dst_stages = PipelineStage::empty();
+        self.barriers.clear();
+    }
+
+    fn push(&mut self, stages: Range<PipelineStage>, barrier: Barrier<'a, B>) {
+        self.src_stages |= stages.start;
+        self.dst_stages |= stages.end;
+        self.barriers.push(barrier);
+    }
+}
+
+impl<'a, B: Backend> Default for Transitions<'a, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_buffer_write(access: buffer::Access) -> bool {
+    access.intersects(
+        buffer::Access::TRANSFER_WRITE | buffer::Access::SHADER_WRITE |
+        buffer::Access::HOST_WRITE | buffer::Access::MEMORY_WRITE
+    )
+}
+
+fn is_image_write(access: image::Access) -> bool {
+    access.intersects(
+        image::Access::COLOR_ATTACHMENT_WRITE | image::Access::TRANSFER_WRITE |
+        image::Access::SHADER_WRITE | image::Access::DEPTH_STENCIL_ATTACHMENT_WRITE |
+        image::Access::HOST_WRITE | image::Access::MEMORY_WRITE
+    )
+}
+
+/// Tracks the state of a buffer.
+#[derive(Clone, Debug)]
+pub struct BufferTracker {
+    state: buffer::State,
+    stages: PipelineStage,
+}
+
+impl BufferTracker {
+    /// Start tracking a buffer in the given state.
+    pub fn new(state: buffer::State) -> Self {
+        BufferTracker {
+            state,
+            stages: PipelineStage::empty(),
+        }
+    }
+
+    /// Current state of the buffer.
+    pub fn state(&self) -> buffer::State {
+        self.state
+    }
+
+    /// Override the tracked state, e.g. after the buffer has been accessed
+    /// outside of the tracked command buffers.
+    pub fn set_state(&mut self, state: buffer::State, stages: PipelineStage) {
+        self.state = state;
+        self.stages = stages;
+    }
+
+    /// Bring the buffer into `state` for an access in `stages`, adding the
+    /// required barrier to `transitions`.
+    ///
+    /// No barrier is required if the buffer is only read in the same state.
+    pub fn transition<'a, B: Backend>(
+        &mut self,
+        buffer: &'a B::Buffer,
+        state: buffer::State,
+        stages: PipelineStage,
+        transitions: &mut Transitions<'a, B>,
+    ) {
+        if let Some((old, old_stages)) = self.update(state, stages) {
+            transitions.push(old_stages .. stages, Barrier::Buffer {
+                states: old .. state,
+                target: buffer,
+            });
+        }
+    }
+
+    // Returns the previous state and stages if a barrier is required.
+    fn update(
+        &mut self, state: buffer::State, stages: PipelineStage,
+    ) -> Option<(buffer::State, PipelineStage)> {
+        if self.state == state && !is_buffer_write(state) {
+            self.stages |= stages;
+            return None;
+        }
+
+        let old = (self.state, self.stages);
+        self.state = state;
+        self.stages = stages;
+        Some(old)
+    }
+}
+
+/// Tracks the states of all subresources of an image, separately for each
+/// of its aspects.
+#[derive(Clone, Debug)]
+pub struct ImageTracker {
+    aspects: Aspects,
+    levels: image::Level,
+    layers: image::Layer,
+    // Indexed by `(aspect * layers + layer) * levels + level`, where `aspect`
+    // counts the aspects of the image in the order color, depth, stencil.
+    states: Vec<(image::State, PipelineStage)>,
+}
+
+const ASPECTS: [Aspects; 3] = [Aspects::COLOR, Aspects::DEPTH, Aspects::STENCIL];
+
+impl ImageTracker {
+    /// Start tracking an image with the given aspects, number of mipmap
+    /// levels and array layers, with all subresources in the same state.
+    pub fn new(
+        state: image::State,
+        aspects: Aspects,
+        levels: image::Level,
+        layers: image::Layer,
+    ) -> Self {
+        let num_aspects = ASPECTS.iter().filter(|&&aspect| aspects.contains(aspect)).count();
+        let size = num_aspects * levels as usize * layers as usize;
+        ImageTracker {
+            aspects,
+            levels,
+            layers,
+            states: vec![(state, PipelineStage::empty()); size],
+        }
+    }
+
+    /// The full subresource range of the tracked image.
+    pub fn range(&self) -> image::SubresourceRange {
+        image::SubresourceRange {
+            aspects: self.aspects,
+            levels: 0 .. self.levels,
+            layers: 0 .. self.layers,
+        }
+    }
+
+    // Single aspects of the image contained in `aspects`, with their slot in `states`.
+    fn aspect_slots(&self, aspects: Aspects) -> Vec<(Aspects, usize)> {
+        ASPECTS
+            .iter()
+            .filter(|&&aspect| self.aspects.contains(aspect))
+            .enumerate()
+            .filter(|&(_, &aspect)| aspects.contains(aspect))
+            .map(|(slot, &aspect)| (aspect, slot))
+            .collect()
+    }
+
+    fn index(&self, slot: usize, level: image::Level, layer: image::Layer) -> usize {
+        assert!(level < self.levels && layer < self.layers);
+        (slot * self.layers as usize + layer as usize) * self.levels as usize + level as usize
+    }
+
+    /// Current state of a subresource of a single `aspect` of the image.
+    pub fn state(&self, aspect: Aspects, (level, layer): image::Subresource) -> image::State {
+        let slot = self.aspect_slots(aspect)
+            .first()
+            .expect("aspect is not part of the image")
+            .1;
+        self.states[self.index(slot, level, layer)].0
+    }
+
+    /// Override the tracked state of a subresource range, e.g. after a render
+    /// pass changed the layouts of its attachments.
+    pub fn set_state(
+        &mut self,
+        range: image::SubresourceRange,
+        state: image::State,
+        stages: PipelineStage,
+    ) {
+        for (_, slot) in self.aspect_slots(range.aspects) {
+            for layer in range.layers.clone() {
+                for level in range.levels.clone() {
+                    let index = self.index(slot, level, layer);
+                    self.states[index] = (state, stages);
+                }
+            }
+        }
+    }
+
+    /// Bring a subresource range into `state` for an access in `stages`,
+    /// adding the required barriers to `transitions`.
+    ///
+    /// Subresources which share their current state are merged into as few
+    /// barriers as possible. No barriers are required for subresources which
+    /// are only read in the same state.
+    pub fn transition<'a, B: Backend>(
+        &mut self,
+        image: &'a B::Image,
+        range: image::SubresourceRange,
+        state: image::State,
+        stages: PipelineStage,
+        transitions: &mut Transitions<'a, B>,
+    ) {
+        for barrier in self.update(range, state, stages) {
+            transitions.push(barrier.old_stages .. stages, Barrier::Image {
+                states: barrier.old .. state,
+                target: image,
+                range: image::SubresourceRange {
+                    aspects: barrier.aspects,
+                    levels: barrier.levels,
+                    layers: barrier.layers,
+                },
+            });
+        }
+    }
+
+    // Returns the barriers required to bring `range` into `state`.
+    fn update(
+        &mut self,
+        range: image::SubresourceRange,
+        state: image::State,
+        stages: PipelineStage,
+    ) -> Vec<PendingBarrier> {
+        let mut pending: Vec<PendingBarrier> = Vec::new();
+
+        for (aspect, slot) in self.aspect_slots(range.aspects) {
+            // Barriers of this aspect, merged with the other aspects afterwards.
+            let first = pending.len();
+            for layer in range.layers.clone() {
+                let mut level = range.levels.start;
+                while level < range.levels.end {
+                    let start = level;
+                    let old = self.states[self.index(slot, level, layer)].0;
+                    let mut old_stages = PipelineStage::empty();
+                    while level < range.levels.end && self.states[self.index(slot, level, layer)].0 == old {
+                        let index = self.index(slot, level, layer);
+                        old_stages |= self.states[index].1;
+                        self.states[index] = if old == state && !is_image_write(state.0) {
+                            (state, self.states[index].1 | stages)
+                        } else {
+                            (state, stages)
+                        };
+                        level += 1;
+                    }
+
+                    if old == state && !is_image_write(state.0) {
+                        continue;
+                    }
+
+                    // Extend a barrier of the previous layer covering the same levels.
+                    let levels = start .. level;
+                    let merged = pending[first ..].iter_mut().find(|barrier| {
+                        barrier.levels == levels && barrier.layers.end == layer && barrier.old == old
+                    });
+                    match merged {
+                        Some(barrier) => {
+                            barrier.layers.end = layer + 1;
+                            barrier.old_stages |= old_stages;
+                        }
+                        None => pending.push(PendingBarrier {
+                            aspects: aspect,
+                            levels,
+                            layers: layer .. layer + 1,
+                            old,
+                            old_stages,
+                        }),
+                    }
+                }
+            }
+
+            // Merge into a barrier of another aspect covering the same subresources.
+            let mut index = first;
+            while index < pending.len() {
+                let merged = pending[.. first].iter().position(|barrier| {
+                    barrier.levels == pending[index].levels &&
+                    barrier.layers == pending[index].layers &&
+                    barrier.old == pending[index].old
+                });
+                match merged {
+                    Some(position) => {
+                        let barrier = pending.remove(index);
+                        pending[position].aspects |= barrier.aspects;
+                        pending[position].old_stages |= barrier.old_stages;
+                    }
+                    None => index += 1,
+                }
+            }
+        }
+
+        pending
+    }
+}
+
+// Image barrier collected by `ImageTracker::update`.
+#[derive(Debug, PartialEq)]
+struct PendingBarrier {
+    aspects: Aspects,
+    levels: Range<image::Level>,
+    layers: Range<image::Layer>,
+    old: image::State,
+    old_stages: PipelineStage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNDEFINED: image::State = (image::Access::empty(), image::ImageLayout::Undefined);
+    const SAMPLED: image::State = (image::Access::SHADER_READ, image::ImageLayout::ShaderReadOnlyOptimal);
+    const STORAGE: image::State = (image::Access::SHADER_WRITE, image::ImageLayout::General);
+
+    fn range(aspects: Aspects, levels: Range<image::Level>, layers: Range<image::Layer>) -> image::SubresourceRange {
+        image::SubresourceRange { aspects, levels, layers }
+    }
+
+    #[test]
+    fn image_merges_levels_and_layers() {
+        let mut tracker = ImageTracker::new(UNDEFINED, Aspects::COLOR, 4, 3);
+        let barriers = tracker.update(tracker.range(), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+        assert_eq!(barriers, vec![PendingBarrier {
+            aspects: Aspects::COLOR,
+            levels: 0 .. 4,
+            layers: 0 .. 3,
+            old: UNDEFINED,
+            old_stages: PipelineStage::empty(),
+        }]);
+        assert_eq!(tracker.state(Aspects::COLOR, (3, 2)), SAMPLED);
+    }
+
+    #[test]
+    fn image_splits_differing_states() {
+        let mut tracker = ImageTracker::new(UNDEFINED, Aspects::COLOR, 4, 2);
+        tracker.update(range(Aspects::COLOR, 1 .. 2, 0 .. 2), STORAGE, PipelineStage::COMPUTE_SHADER);
+
+        let barriers = tracker.update(tracker.range(), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+        let ranges = barriers
+            .iter()
+            .map(|barrier| (barrier.levels.clone(), barrier.layers.clone(), barrier.old))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![
+            (0 .. 1, 0 .. 2, UNDEFINED),
+            (1 .. 2, 0 .. 2, STORAGE),
+            (2 .. 4, 0 .. 2, UNDEFINED),
+        ]);
+    }
+
+    #[test]
+    fn image_read_after_read() {
+        let mut tracker = ImageTracker::new(SAMPLED, Aspects::COLOR, 1, 1);
+        tracker.set_state(tracker.range(), SAMPLED, PipelineStage::VERTEX_SHADER);
+
+        let barriers = tracker.update(tracker.range(), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+        assert!(barriers.is_empty());
+
+        // Both reads have to complete before the next write.
+        let barriers = tracker.update(tracker.range(), STORAGE, PipelineStage::COMPUTE_SHADER);
+        assert_eq!(barriers[0].old_stages, PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER);
+    }
+
+    #[test]
+    fn image_write_after_write() {
+        let mut tracker = ImageTracker::new(STORAGE, Aspects::COLOR, 1, 1);
+        tracker.set_state(tracker.range(), STORAGE, PipelineStage::COMPUTE_SHADER);
+
+        let barriers = tracker.update(tracker.range(), STORAGE, PipelineStage::COMPUTE_SHADER);
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].old, STORAGE);
+        assert_eq!(barriers[0].old_stages, PipelineStage::COMPUTE_SHADER);
+    }
+
+    #[test]
+    fn image_write_after_read() {
+        let mut tracker = ImageTracker::new(SAMPLED, Aspects::COLOR, 1, 1);
+        tracker.set_state(tracker.range(), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+
+        let barriers = tracker.update(tracker.range(), STORAGE, PipelineStage::COMPUTE_SHADER);
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].old, SAMPLED);
+        assert_eq!(barriers[0].old_stages, PipelineStage::FRAGMENT_SHADER);
+    }
+
+    #[test]
+    fn image_tracks_aspects_separately() {
+        let depth_stencil = Aspects::DEPTH | Aspects::STENCIL;
+        let mut tracker = ImageTracker::new(UNDEFINED, depth_stencil, 1, 1);
+        tracker.update(range(Aspects::DEPTH, 0 .. 1, 0 .. 1), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+        assert_eq!(tracker.state(Aspects::DEPTH, (0, 0)), SAMPLED);
+        assert_eq!(tracker.state(Aspects::STENCIL, (0, 0)), UNDEFINED);
+
+        // Only the stencil aspect needs a barrier.
+        let barriers = tracker.update(tracker.range(), SAMPLED, PipelineStage::FRAGMENT_SHADER);
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].aspects, Aspects::STENCIL);
+
+        // Aspects in the same state share a barrier.
+        let barriers = tracker.update(tracker.range(), STORAGE, PipelineStage::COMPUTE_SHADER);
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].aspects, depth_stencil);
+    }
+
+    #[test]
+    fn buffer_transitions() {
+        let read = buffer::Access::SHADER_READ;
+        let write = buffer::Access::SHADER_WRITE;
+        let mut tracker = BufferTracker::new(read);
+        tracker.set_state(read, PipelineStage::VERTEX_SHADER);
+
+        assert_eq!(tracker.update(read, PipelineStage::FRAGMENT_SHADER), None);
+        assert_eq!(
+            tracker.update(write, PipelineStage::COMPUTE_SHADER),
+            Some((read, PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER))
+        );
+        assert_eq!(
+            tracker.update(write, PipelineStage::COMPUTE_SHADER),
+            Some((write, PipelineStage::COMPUTE_SHADER))
+        );
+    }
+}
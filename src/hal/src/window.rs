@@ -31,13 +31,15 @@
 //! # let mut swapchain: empty::Swapchain = return;
 //! # let device: empty::Device = return;
 //! # let mut present_queue: CommandQueue<empty::Backend, Graphics> = return;
-//! let acquisition_semaphore = device.create_semaphore();
-//! let render_semaphore = device.create_semaphore();
+//! let acquisition_semaphore = device.create_semaphore().unwrap();
+//! let render_semaphore = device.create_semaphore().unwrap();
 //!
-//! let frame = swapchain.acquire_frame(FrameSync::Semaphore(&acquisition_semaphore));
+//! let frame = swapchain
+//!     .acquire_frame(FrameSync::Semaphore(&acquisition_semaphore), !0)
+//!     .unwrap();
 //! // render the scene..
 //! // `render_semaphore` will be signalled once rendering has been finished
-//! swapchain.present(&mut present_queue, &[render_semaphore]);
+//! swapchain.present(&mut present_queue, &[render_semaphore]).unwrap();
 //! # }
 //! ```
 //!
@@ -47,10 +49,21 @@
 //!
 //! ### Recreation
 //!
-//! DOC TODO
+//! Swapchains may become incompatible with their surface, for example after
+//! a window resize. Acquisition and presentation report this via
+//! [`AcquireError::OutOfDate`] and [`PresentError::OutOfDate`], or via
+//! [`Frame::is_suboptimal`] and [`PresentStatus::Suboptimal`] if the swapchain
+//! can still be used but no longer matches the surface exactly. In these cases
+//! the swapchain should be recreated with the new surface capabilities.
+//!
+//! [`AcquireError::OutOfDate`]: enum.AcquireError.html#variant.OutOfDate
+//! [`PresentError::OutOfDate`]: enum.PresentError.html#variant.OutOfDate
+//! [`Frame::is_suboptimal`]: struct.Frame.html#method.is_suboptimal
+//! [`PresentStatus::Suboptimal`]: enum.PresentStatus.html#variant.Suboptimal
 
 use Backend;
 use image;
+use device::OutOfMemory;
 use format::{self, Format};
 use queue::CommandQueue;
 
@@ -131,7 +144,10 @@ pub trait Surface<B: Backend>: Any + Send + Sync {
 /// particular image in the swapchain.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Frame(pub(crate) usize);
+pub struct Frame {
+    pub(crate) id: usize,
+    pub(crate) suboptimal: bool,
+}
 
 impl Frame {
     /// Retrieve frame id.
@@ -145,7 +161,15 @@ impl Frame {
     ///
     /// ```
     pub fn id(&self) -> usize {
-        self.0
+        self.id
+    }
+
+    /// Check if the swapchain no longer matches the surface properties exactly.
+    ///
+    /// The frame can still be rendered to and presented, but the swapchain
+    /// should be recreated at a convenient point.
+    pub fn is_suboptimal(&self) -> bool {
+        self.suboptimal
     }
 }
 
@@ -164,6 +188,65 @@ pub enum FrameSync<'a, B: Backend> {
     Fence(&'a B::Fence),
 }
 
+/// Error on acquiring the next frame from a swapchain.
+#[derive(Fail, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireError {
+    /// No frame was available and `timeout_ms` was zero.
+    #[fail(display = "No swapchain frame is ready.")]
+    NotReady,
+    /// No frame became available before the timeout expired.
+    #[fail(display = "Timed out waiting for a swapchain frame.")]
+    Timeout,
+    /// The swapchain is no longer compatible with the surface and
+    /// needs to be recreated.
+    #[fail(display = "Swapchain is out of date.")]
+    OutOfDate,
+    /// The surface is no longer available.
+    ///
+    /// Both the surface and the swapchain need to be recreated.
+    #[fail(display = "Surface lost.")]
+    SurfaceLost,
+    /// Memory allocation on the host or device side failed.
+    #[fail(display = "{}", _0)]
+    OutOfMemory(OutOfMemory),
+    /// The logical or physical device has been lost.
+    #[fail(display = "Device lost.")]
+    DeviceLost,
+}
+
+/// Status of a successful presentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PresentStatus {
+    /// The frame has been presented and the swapchain matches the surface.
+    Optimal,
+    /// The frame has been presented, but the swapchain no longer matches
+    /// the surface properties exactly.
+    ///
+    /// The swapchain should be recreated at a convenient point.
+    Suboptimal,
+}
+
+/// Error on presenting frames of a swapchain.
+#[derive(Fail, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentError {
+    /// The swapchain is no longer compatible with the surface and
+    /// needs to be recreated.
+    #[fail(display = "Swapchain is out of date.")]
+    OutOfDate,
+    /// The surface is no longer available.
+    ///
+    /// Both the surface and the swapchain need to be recreated.
+    #[fail(display = "Surface lost.")]
+    SurfaceLost,
+    /// Memory allocation on the host or device side failed.
+    #[fail(display = "{}", _0)]
+    OutOfMemory(OutOfMemory),
+    /// The logical or physical device has been lost.
+    #[fail(display = "Device lost.")]
+    DeviceLost,
+}
+
 /// Contains all the data necessary to create a new `Swapchain`:
 /// color, depth, and number of images.
 ///
@@ -273,12 +356,20 @@ pub trait Swapchain<B: Backend>: Any + Send + Sync {
     /// This can either be a [`Semaphore`](../trait.Resources.html#associatedtype.Semaphore)
     /// or a [`Fence`](../trait.Resources.html#associatedtype.Fence).
     ///
+    /// # Timeout
+    ///
+    /// Blocks for at most `timeout_ms` milliseconds until a frame is available.
+    /// A timeout of `0` returns `AcquireError::NotReady` immediately if no frame
+    /// is available, a timeout of `!0` waits indefinitely.
+    /// Backends which always have a frame available return immediately and
+    /// ignore the timeout.
+    ///
     /// # Examples
     ///
     /// ```no_run
     ///
     /// ```
-    fn acquire_frame(&mut self, sync: FrameSync<B>) -> Frame;
+    fn acquire_frame(&mut self, sync: FrameSync<B>, timeout_ms: u32) -> Result<Frame, AcquireError>;
 
     /// Present one acquired frame in FIFO order.
     ///
@@ -296,7 +387,7 @@ pub trait Swapchain<B: Backend>: Any + Send + Sync {
         &'a mut self,
         present_queue: &mut CommandQueue<B, C>,
         wait_semaphores: IW,
-    )
+    ) -> Result<PresentStatus, PresentError>
     where
         &'a mut Self: BorrowMut<B::Swapchain>,
         Self: Sized + 'a,
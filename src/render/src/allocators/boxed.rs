@@ -1,27 +1,57 @@
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 
-use hal::{Device as CoreDevice};
-use memory::{self, Allocator, Memory};
+use hal::{Device as CoreDevice, MemoryTypeId};
+use hal::memory::Requirements;
+use memory::{self, AllocationError, Allocator, HeapBudgets, Memory, MemoryTypeStats, Stats};
 use {buffer, image};
 use {Backend, Device};
 
 pub struct BoxedAllocator<B: Backend> {
     usage: memory::Usage,
+    // allocated bytes by memory type
+    allocated: HashMap<MemoryTypeId, u64>,
+    receiver: mpsc::Receiver<(MemoryTypeId, u64)>,
+    sender: mpsc::Sender<(MemoryTypeId, u64)>,
     phantom: PhantomData<B>
 }
 
 impl<B: Backend> BoxedAllocator<B> {
     pub fn new(usage: memory::Usage, _: &Device<B>) -> Self {
+        let (sender, receiver) = mpsc::channel();
         BoxedAllocator {
             usage,
+            allocated: HashMap::new(),
+            receiver,
+            sender,
             phantom: PhantomData
         }
     }
 
-    fn make_memory(&self, device: Arc<B::Device>, memory: B::Memory) -> Memory {
+    fn find_memory(
+        &self,
+        device: &Device<B>,
+        requirements: &Requirements,
+    ) -> Result<MemoryTypeId, AllocationError> {
+        device.find_usage_memory(self.usage, requirements.type_mask)
+            .ok_or(AllocationError::NoSuitableMemory)
+    }
+
+    fn make_memory(&mut self,
+        device: Arc<B::Device>,
+        budgets: Arc<HeapBudgets>,
+        memory: B::Memory,
+        mem_type: MemoryTypeId,
+        size: u64,
+    ) -> Memory {
+        *self.allocated.entry(mem_type).or_insert(0) += size;
+        let sender = self.sender.clone();
         let mut memory = Some(memory);
-        let release = Box::new(move || device.free_memory(memory.take().unwrap()));
+        let release = Box::new(move || {
+            budgets.free_memory::<B>(&device, mem_type, memory.take().unwrap(), size);
+            let _ = sender.send((mem_type, size));
+        });
         Memory::new(release, self.usage)
     }
 }
@@ -31,31 +61,54 @@ impl<B: Backend> Allocator<B> for BoxedAllocator<B> {
         device: &Device<B>,
         _: buffer::Usage,
         buffer: B::UnboundBuffer
-    ) -> (B::Buffer, Memory) {
+    ) -> Result<(B::Buffer, Memory), AllocationError> {
         let requirements = device.raw.get_buffer_requirements(&buffer);
-        let mem_type = device.find_usage_memory(self.usage, requirements.type_mask).unwrap();
+        let mem_type = self.find_memory(device, &requirements)?;
+        let budgets = device.budgets().clone();
         let device = device.raw.clone();
-        let memory = device.allocate_memory(mem_type, requirements.size)
-            .unwrap();
-        let buffer = device.bind_buffer_memory(&memory, 0, buffer)
-            .unwrap();
+        let memory = budgets.allocate_memory::<B>(&device, mem_type, requirements.size)?;
+        let buffer = match device.bind_buffer_memory(&memory, 0, buffer) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                budgets.free_memory::<B>(&device, mem_type, memory, requirements.size);
+                return Err(err.into());
+            }
+        };
 
-        (buffer, self.make_memory(device, memory))
+        Ok((buffer, self.make_memory(device, budgets, memory, mem_type, requirements.size)))
     }
 
     fn allocate_image(&mut self,
         device: &mut Device<B>,
         _: image::Usage,
         image: B::UnboundImage
-    ) -> (B::Image, Memory) {
+    ) -> Result<(B::Image, Memory), AllocationError> {
         let requirements = device.raw.get_image_requirements(&image);
-        let mem_type = device.find_usage_memory(self.usage, requirements.type_mask).unwrap();
+        let mem_type = self.find_memory(device, &requirements)?;
+        let budgets = device.budgets().clone();
         let device = device.raw.clone();
-        let memory = device.allocate_memory(mem_type, requirements.size)
-            .unwrap();
-        let image = device.bind_image_memory(&memory, 0, image)
-            .unwrap();
+        let memory = budgets.allocate_memory::<B>(&device, mem_type, requirements.size)?;
+        let image = match device.bind_image_memory(&memory, 0, image) {
+            Ok(image) => image,
+            Err(err) => {
+                budgets.free_memory::<B>(&device, mem_type, memory, requirements.size);
+                return Err(err.into());
+            }
+        };
 
-        (image, self.make_memory(device, memory))
+        Ok((image, self.make_memory(device, budgets, memory, mem_type, requirements.size)))
+    }
+
+    fn stats(&mut self) -> Stats {
+        for (mem_type, size) in self.receiver.try_iter() {
+            *self.allocated.get_mut(&mem_type).unwrap() -= size;
+        }
+        // every allocation is fully used by its resource
+        Stats {
+            memory_types: self.allocated
+                .iter()
+                .map(|(&mem_type, &size)| (mem_type, MemoryTypeStats::new(size, size)))
+                .collect(),
+        }
     }
 }
@@ -0,0 +1,547 @@
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::{mpsc, Arc};
+
+use hal::{Device as Device_, Limits, MemoryTypeId};
+use hal::memory::Requirements;
+use memory::{self, AllocationError, Allocator, Memory, MemoryTypeStats, ReleaseFn, Provider, Dependency, Stats};
+use memory::HeapBudgets;
+use super::stack::complete_requirements;
+use {buffer, image};
+use {Backend, Device};
+
+/// Fragmentation state of a memory type, reported to the defragmentation hook.
+#[derive(Clone, Copy, Debug)]
+pub struct Fragmentation {
+    /// Memory type of the failed allocation.
+    pub memory_type: MemoryTypeId,
+    /// Size of the allocation which didn't fit into any free range.
+    pub requested: u64,
+    /// Total number of free bytes in the chunks of the memory type.
+    pub free: u64,
+    /// Size of the largest contiguous free range.
+    pub largest_free_range: u64,
+}
+
+/// Called when an allocation doesn't fit into the existing chunks of a memory
+/// type although enough memory is free in total, before a new chunk is allocated.
+///
+/// Applications can use it to recreate long-lived resources, which compacts
+/// the memory once the old resources have been released.
+pub type DefragmentHook = Box<FnMut(&Fragmentation)>;
+
+/// General purpose allocator, sub-allocating resources from large memory
+/// chunks. Allocations can be released in any order, free space is reused.
+///
+/// Larger resources than the chunk size get a dedicated memory object.
+/// Chunks count towards the heap budgets of the device, see
+/// `Device::set_memory_budget`.
+pub struct GeneralAllocator<B: Backend>(Provider<InnerGeneralAllocator<B>>);
+
+pub struct InnerGeneralAllocator<B: Backend> {
+    device: Arc<B::Device>,
+    usage: memory::Usage,
+    limits: Limits, // TODO: only store relevant data
+    chunk_size: u64,
+    budgets: Arc<HeapBudgets>,
+    chunks: HashMap<MemoryTypeId, ChunkList<B>>,
+    receiver: mpsc::Receiver<Release>,
+    sender: mpsc::Sender<Release>,
+    defragment_hook: Option<DefragmentHook>,
+}
+
+impl<B: Backend> Drop for InnerGeneralAllocator<B> {
+    fn drop(&mut self) {
+        self.shrink();
+    }
+}
+
+impl<B: Backend> GeneralAllocator<B> {
+    pub fn new(usage: memory::Usage, device: &Device<B>, limits: Limits) -> Self {
+        let mega = 1 << 20;
+        Self::with_chunk_size(usage, device, limits, 64 * mega)
+    }
+
+    pub fn with_chunk_size(
+        usage: memory::Usage,
+        device: &Device<B>,
+        limits: Limits,
+        chunk_size: u64
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        GeneralAllocator(Provider::new(InnerGeneralAllocator {
+            device: Arc::clone(&device.raw),
+            usage,
+            limits,
+            chunk_size,
+            budgets: Arc::clone(device.budgets()),
+            chunks: HashMap::new(),
+            receiver,
+            sender,
+            defragment_hook: None,
+        }))
+    }
+
+    /// Install a hook notified about fragmentation.
+    pub fn set_defragment_hook(&mut self, hook: DefragmentHook) {
+        self.0.defragment_hook = Some(hook);
+    }
+
+    /// Release all chunks which don't contain any allocations.
+    pub fn shrink(&mut self) {
+        self.0.shrink();
+    }
+}
+
+impl<B: Backend> InnerGeneralAllocator<B> {
+    fn shrink(&mut self) {
+        self.update_allocs();
+        for (_, list) in &mut self.chunks {
+            list.shrink(&self.device, &self.budgets);
+        }
+    }
+
+    fn update_allocs(&mut self) {
+        for release in self.receiver.try_iter() {
+            let list = self.chunks.get_mut(&release.memory_type).unwrap();
+            list.free(&self.device, &self.budgets, release.chunk_index, release.offset);
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        device: &Device<B>,
+        requirements: Requirements,
+        kind: ResourceKind,
+        dependency: Dependency<InnerGeneralAllocator<B>>,
+    ) -> Result<(&B::Memory, u64, ReleaseFn), AllocationError> {
+        self.update_allocs();
+
+        let memory_type = device.find_usage_memory(self.usage, requirements.type_mask)
+            .ok_or(AllocationError::NoSuitableMemory)?;
+        let granularity = max(self.limits.buffer_image_granularity, 1);
+        let requirements = Requirements {
+            size: max(requirements.size, 1),
+            alignment: max(requirements.alignment, 1),
+            .. requirements
+        };
+
+        let list = self.chunks.entry(memory_type)
+            .or_insert_with(|| ChunkList::new(memory_type));
+
+        let found = list.allocate(&requirements, kind, granularity);
+        let (chunk_index, offset) = match found {
+            Some(found) => found,
+            None => {
+                let free = list.free_bytes();
+                if requirements.size <= free {
+                    if let Some(ref mut hook) = self.defragment_hook {
+                        hook(&Fragmentation {
+                            memory_type,
+                            requested: requirements.size,
+                            free,
+                            largest_free_range: list.largest_free_range(),
+                        });
+                    }
+                }
+
+                let budgets = &self.budgets;
+                let (size, dedicated) = if requirements.size > self.chunk_size {
+                    (requirements.size, true)
+                } else if !budgets.fits(memory_type, self.chunk_size) {
+                    // Fall back to a tight allocation close to the budget.
+                    (requirements.size, true)
+                } else {
+                    (self.chunk_size, false)
+                };
+
+                let memory = match budgets.allocate_memory::<B>(&device.raw, memory_type, size) {
+                    Ok(memory) => memory,
+                    Err(_) => {
+                        // Retry after giving back unused chunks.
+                        list.shrink(&device.raw, budgets);
+                        budgets.allocate_memory::<B>(&device.raw, memory_type, size)?
+                    }
+                };
+                let chunk_index = list.add_chunk(Chunk::new(memory, size, dedicated));
+                let offset = list.chunks[chunk_index]
+                    .as_mut()
+                    .unwrap()
+                    .allocate(&requirements, kind, granularity)
+                    .unwrap();
+                (chunk_index, offset)
+            }
+        };
+
+        let sender = self.sender.clone();
+        let chunk = list.chunks[chunk_index].as_ref().unwrap();
+        Ok((&chunk.memory, offset, Box::new(move || {
+            let _ = dependency;
+            sender.send(Release { memory_type, chunk_index, offset }).unwrap_or_else(|_| {
+                error!("could not release GeneralAllocator's memory")
+            });
+        })))
+    }
+}
+
+impl<B: Backend> Allocator<B> for GeneralAllocator<B> {
+    fn allocate_buffer(&mut self,
+        device: &Device<B>,
+        usage: buffer::Usage,
+        buffer: B::UnboundBuffer
+    ) -> Result<(B::Buffer, Memory), AllocationError> {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerGeneralAllocator<B> = &mut self.0;
+        let requirements = complete_requirements::<B>(
+            &device.raw,
+            &buffer,
+            &inner.limits,
+            usage,
+        );
+        let usage = inner.usage;
+        let (memory, offset, release) = inner.allocate(
+            device,
+            requirements,
+            ResourceKind::Linear,
+            dependency,
+        )?;
+        // released on failure
+        let memory_release = Memory::new(release, usage);
+        let buffer = device.raw.bind_buffer_memory(memory, offset, buffer)?;
+        Ok((buffer, memory_release))
+    }
+
+    fn allocate_image(&mut self,
+        device: &mut Device<B>,
+        _: image::Usage,
+        image: B::UnboundImage
+    ) -> Result<(B::Image, Memory), AllocationError> {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerGeneralAllocator<B> = &mut self.0;
+        let requirements = device.raw.get_image_requirements(&image);
+        let usage = inner.usage;
+        // Images are created with optimal tiling.
+        let (memory, offset, release) = inner.allocate(
+            device,
+            requirements,
+            ResourceKind::NonLinear,
+            dependency,
+        )?;
+        // released on failure
+        let memory_release = Memory::new(release, usage);
+        let image = device.raw.bind_image_memory(memory, offset, image)?;
+        Ok((image, memory_release))
+    }
+
+    fn stats(&mut self) -> Stats {
+        let inner: &mut InnerGeneralAllocator<B> = &mut self.0;
+        inner.update_allocs();
+        Stats {
+            memory_types: inner.chunks
+                .iter()
+                .map(|(&memory_type, list)| (memory_type, list.stats()))
+                .collect(),
+        }
+    }
+}
+
+struct Release {
+    memory_type: MemoryTypeId,
+    chunk_index: usize,
+    offset: u64,
+}
+
+/// Linear resources (buffers) and non-linear resources (optimally tiled images)
+/// must not share a page of `buffer_image_granularity` bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ResourceKind {
+    Linear,
+    NonLinear,
+}
+
+struct Block {
+    end: u64,
+    kind: ResourceKind,
+}
+
+struct Chunk<B: Backend> {
+    memory: B::Memory,
+    size: u64,
+    dedicated: bool,
+    // sorted, disjoint and non-adjacent
+    free_ranges: Vec<Range<u64>>,
+    // allocated blocks by offset
+    blocks: BTreeMap<u64, Block>,
+}
+
+fn align(offset: u64, alignment: u64) -> u64 {
+    let rem = offset % alignment;
+    if rem == 0 {
+        offset
+    } else {
+        offset - rem + alignment
+    }
+}
+
+fn on_same_page(last: u64, first: u64, page_size: u64) -> bool {
+    last / page_size == first / page_size
+}
+
+impl<B: Backend> Chunk<B> {
+    fn new(memory: B::Memory, size: u64, dedicated: bool) -> Self {
+        Chunk {
+            memory,
+            size,
+            dedicated,
+            free_ranges: vec![0 .. size],
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    fn free_bytes(&self) -> u64 {
+        self.free_ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    fn largest_free_range(&self) -> u64 {
+        self.free_ranges.iter().map(|r| r.end - r.start).max().unwrap_or(0)
+    }
+
+    fn used_bytes(&self) -> u64 {
+        self.blocks.iter().map(|(&beg, block)| block.end - beg).sum()
+    }
+
+    /// First-fit allocation, returns the offset of the allocated block.
+    fn allocate(
+        &mut self,
+        req: &Requirements,
+        kind: ResourceKind,
+        granularity: u64,
+    ) -> Option<u64> {
+        for index in 0 .. self.free_ranges.len() {
+            let range = self.free_ranges[index].clone();
+            let mut beg = align(range.start, req.alignment);
+
+            // free ranges are maximal, neighbours are allocated blocks
+            if let Some((_, prev)) = self.blocks.range(.. range.start).next_back() {
+                if prev.kind != kind && on_same_page(prev.end - 1, beg, granularity) {
+                    beg = align(beg, granularity);
+                }
+            }
+            let end = beg + req.size;
+            if end > range.end {
+                continue;
+            }
+            if let Some((&next_beg, next)) = self.blocks.range(range.end ..).next() {
+                if next.kind != kind && on_same_page(end - 1, next_beg, granularity) {
+                    continue;
+                }
+            }
+
+            let mut remaining = Vec::with_capacity(2);
+            if range.start < beg {
+                remaining.push(range.start .. beg);
+            }
+            if end < range.end {
+                remaining.push(end .. range.end);
+            }
+            self.free_ranges.splice(index .. index + 1, remaining);
+            self.blocks.insert(beg, Block { end, kind });
+            return Some(beg);
+        }
+
+        None
+    }
+
+    fn free(&mut self, offset: u64) {
+        let block = self.blocks.remove(&offset)
+            .expect("released an unknown allocation");
+        let index = self.free_ranges
+            .binary_search_by_key(&offset, |r| r.start)
+            .unwrap_err();
+
+        let merge_prev = index > 0 && self.free_ranges[index - 1].end == offset;
+        let merge_next = index < self.free_ranges.len() &&
+            self.free_ranges[index].start == block.end;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                let next = self.free_ranges.remove(index);
+                self.free_ranges[index - 1].end = next.end;
+            }
+            (true, false) => self.free_ranges[index - 1].end = block.end,
+            (false, true) => self.free_ranges[index].start = offset,
+            (false, false) => self.free_ranges.insert(index, offset .. block.end),
+        }
+    }
+}
+
+struct ChunkList<B: Backend> {
+    memory_type: MemoryTypeId,
+    // `None` for released chunks, keeping the indices stable
+    chunks: Vec<Option<Chunk<B>>>,
+}
+
+impl<B: Backend> ChunkList<B> {
+    fn new(memory_type: MemoryTypeId) -> Self {
+        ChunkList {
+            memory_type,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        req: &Requirements,
+        kind: ResourceKind,
+        granularity: u64,
+    ) -> Option<(usize, u64)> {
+        self.chunks
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, chunk)| chunk.as_mut().map(|chunk| (index, chunk)))
+            .filter(|&(_, ref chunk)| !chunk.dedicated)
+            .filter_map(|(index, chunk)| {
+                chunk.allocate(req, kind, granularity).map(|offset| (index, offset))
+            })
+            .next()
+    }
+
+    fn add_chunk(&mut self, chunk: Chunk<B>) -> usize {
+        match self.chunks.iter().position(Option::is_none) {
+            Some(index) => {
+                self.chunks[index] = Some(chunk);
+                index
+            }
+            None => {
+                self.chunks.push(Some(chunk));
+                self.chunks.len() - 1
+            }
+        }
+    }
+
+    fn free_bytes(&self) -> u64 {
+        self.chunks
+            .iter()
+            .filter_map(Option::as_ref)
+            .filter(|chunk| !chunk.dedicated)
+            .map(Chunk::free_bytes)
+            .sum()
+    }
+
+    fn largest_free_range(&self) -> u64 {
+        self.chunks
+            .iter()
+            .filter_map(Option::as_ref)
+            .filter(|chunk| !chunk.dedicated)
+            .map(Chunk::largest_free_range)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn stats(&self) -> MemoryTypeStats {
+        let chunks = self.chunks.iter().filter_map(Option::as_ref);
+        let (allocated, used) = chunks.fold((0, 0), |(allocated, used), chunk| {
+            (allocated + chunk.size, used + chunk.used_bytes())
+        });
+        MemoryTypeStats::new(allocated, used)
+    }
+
+    /// Release an allocation.
+    /// Dedicated allocations are given back to the heap immediately.
+    fn free(&mut self, device: &B::Device, budgets: &HeapBudgets, chunk_index: usize, offset: u64) {
+        let dedicated = {
+            let chunk = self.chunks[chunk_index].as_mut().unwrap();
+            chunk.free(offset);
+            chunk.dedicated
+        };
+        if dedicated {
+            let chunk = self.chunks[chunk_index].take().unwrap();
+            budgets.free_memory::<B>(device, self.memory_type, chunk.memory, chunk.size);
+        }
+    }
+
+    /// Give all empty chunks back to the heap.
+    fn shrink(&mut self, device: &B::Device, budgets: &HeapBudgets) {
+        for slot in &mut self.chunks {
+            if slot.as_ref().map_or(false, Chunk::is_empty) {
+                let chunk = slot.take().unwrap();
+                debug!("releasing chunk of {} bytes from {:?}", chunk.size, self.memory_type);
+                budgets.free_memory::<B>(device, self.memory_type, chunk.memory, chunk.size);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_backend_empty::Backend as Empty;
+    use hal::memory::Requirements;
+    use super::{Chunk, ResourceKind};
+    use super::ResourceKind::*;
+
+    fn allocate(chunk: &mut Chunk<Empty>, size: u64, alignment: u64, kind: ResourceKind) -> Option<u64> {
+        let req = Requirements { size, alignment, type_mask: !0 };
+        chunk.allocate(&req, kind, 256)
+    }
+
+    #[test]
+    fn allocate_and_free() {
+        let mut chunk = Chunk::<Empty>::new((), 1024, false);
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(0));
+        assert_eq!(allocate(&mut chunk, 100, 64, Linear), Some(128));
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(228));
+        assert_eq!(chunk.free_ranges, vec![100 .. 128, 328 .. 1024]);
+        assert_eq!(chunk.used_bytes(), 300);
+
+        chunk.free(128);
+        assert_eq!(chunk.free_ranges, vec![100 .. 228, 328 .. 1024]);
+        // first fit reuses the freed block
+        assert_eq!(allocate(&mut chunk, 120, 1, Linear), Some(100));
+        assert_eq!(allocate(&mut chunk, 1000, 1, Linear), None);
+        assert_eq!(chunk.largest_free_range(), 696);
+    }
+
+    #[test]
+    fn coalescing() {
+        let mut chunk = Chunk::<Empty>::new((), 300, false);
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(0));
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(100));
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(200));
+        assert!(chunk.free_ranges.is_empty());
+
+        chunk.free(0);
+        chunk.free(200);
+        assert_eq!(chunk.free_ranges, vec![0 .. 100, 200 .. 300]);
+        // merges with both neighbours
+        chunk.free(100);
+        assert_eq!(chunk.free_ranges, vec![0 .. 300]);
+        assert!(chunk.is_empty());
+        assert_eq!(allocate(&mut chunk, 300, 1, Linear), Some(0));
+    }
+
+    #[test]
+    fn granularity_padding() {
+        let mut chunk = Chunk::<Empty>::new((), 1024, false);
+        assert_eq!(allocate(&mut chunk, 100, 1, Linear), Some(0));
+        // an image doesn't share the page of the previous buffer
+        assert_eq!(allocate(&mut chunk, 100, 16, NonLinear), Some(256));
+        // buffers fill the gap on their own page
+        assert_eq!(allocate(&mut chunk, 16, 1, Linear), Some(100));
+        assert_eq!(allocate(&mut chunk, 200, 1, Linear), Some(512));
+    }
+
+    #[test]
+    fn granularity_next_block() {
+        let mut chunk = Chunk::<Empty>::new((), 1024, false);
+        assert_eq!(allocate(&mut chunk, 400, 1, Linear), Some(0));
+        assert_eq!(allocate(&mut chunk, 400, 1, Linear), Some(400));
+        chunk.free(0);
+        // the image would end on the page of the next buffer
+        assert_eq!(allocate(&mut chunk, 300, 1, NonLinear), None);
+        assert_eq!(allocate(&mut chunk, 200, 1, NonLinear), Some(0));
+    }
+}
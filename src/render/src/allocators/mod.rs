@@ -1,5 +1,9 @@
 mod boxed;
+mod general;
+mod ring;
 mod stack;
 
 pub use self::boxed::BoxedAllocator;
+pub use self::general::{GeneralAllocator, DefragmentHook, Fragmentation};
+pub use self::ring::RingAllocator;
 pub use self::stack::StackAllocator;
@@ -0,0 +1,407 @@
+use std::cmp::max;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc};
+
+use hal::{Device as Device_, Limits, MemoryTypeId};
+use hal::memory::Requirements;
+use memory::{self, AllocationError, Allocator, Memory, MemoryTypeStats, ReleaseFn, Provider, Dependency, Stats};
+use memory::{HeapBudgets, QueueProgress};
+use super::stack::complete_requirements;
+use {buffer, image};
+use {Backend, Device};
+
+/// Ring buffer allocator for short-lived resources, e.g. per-frame uniform
+/// buffers with `memory::Usage::Dynamic`.
+///
+/// Memory is recycled in allocation order. Each allocation is tied to the
+/// submission following it, i.e. the frame it is allocated for, and is only
+/// reused once its resource is dropped and the fence of that submission is
+/// reached, so the GPU finished reading it. If the active ring runs full, a
+/// new ring of `ring_size` bytes is created, or as large as the allocation if
+/// it doesn't fit into `ring_size`; retired rings are freed once empty.
+pub struct RingAllocator<B: Backend>(Provider<InnerRingAllocator<B>>);
+
+pub struct InnerRingAllocator<B: Backend> {
+    device: Arc<B::Device>,
+    usage: memory::Usage,
+    limits: Limits, // TODO: only store relevant data
+    ring_size: u64,
+    budgets: Arc<HeapBudgets>,
+    progress: Arc<QueueProgress>,
+    rings: HashMap<MemoryTypeId, RingList<B>>,
+    receiver: mpsc::Receiver<Release>,
+    sender: mpsc::Sender<Release>,
+}
+
+impl<B: Backend> Drop for InnerRingAllocator<B> {
+    fn drop(&mut self) {
+        self.update_allocs();
+        let pending = self.rings.values()
+            .any(|list| list.rings.iter().any(|ring| !ring.allocs.is_empty()));
+        if pending {
+            // all resources are dropped, but pending submissions may still use them
+            self.device.wait_idle().unwrap_or_else(|_| {
+                error!("could not wait for RingAllocator's memory to be unused")
+            });
+        }
+        for (_, list) in &mut self.rings {
+            list.retire(!0);
+            list.shrink(&self.device, &self.budgets, true);
+        }
+    }
+}
+
+impl<B: Backend> RingAllocator<B> {
+    pub fn new(usage: memory::Usage, device: &Device<B>, limits: Limits) -> Self {
+        let mega = 1 << 20;
+        Self::with_ring_size(usage, device, limits, 16 * mega)
+    }
+
+    pub fn with_ring_size(
+        usage: memory::Usage,
+        device: &Device<B>,
+        limits: Limits,
+        ring_size: u64
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        RingAllocator(Provider::new(InnerRingAllocator {
+            device: Arc::clone(&device.raw),
+            usage,
+            limits,
+            ring_size,
+            budgets: Arc::clone(device.budgets()),
+            progress: Arc::clone(device.progress()),
+            rings: HashMap::new(),
+            receiver,
+            sender,
+        }))
+    }
+}
+
+impl<B: Backend> InnerRingAllocator<B> {
+    fn update_allocs(&mut self) {
+        for release in self.receiver.try_iter() {
+            self.rings.get_mut(&release.memory_type)
+                .unwrap()
+                .release(release.ring_id, release.alloc_id);
+        }
+        let completed = self.progress.completed();
+        for (_, list) in &mut self.rings {
+            list.retire(completed);
+            list.shrink(&self.device, &self.budgets, false);
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        device: &Device<B>,
+        requirements: Requirements,
+        dependency: Dependency<InnerRingAllocator<B>>,
+    ) -> Result<(&B::Memory, u64, ReleaseFn), AllocationError> {
+        self.update_allocs();
+
+        let memory_type = device.find_usage_memory(self.usage, requirements.type_mask)
+            .ok_or(AllocationError::NoSuitableMemory)?;
+        let requirements = Requirements {
+            size: max(requirements.size, 1),
+            alignment: max(requirements.alignment, 1),
+            .. requirements
+        };
+
+        let submission = self.progress.next();
+        let list = self.rings.entry(memory_type)
+            .or_insert_with(|| RingList::new(memory_type));
+        let found = list.rings.back_mut().and_then(|ring| {
+            ring.allocate(&requirements, submission).map(|ids| (ring.id, ids))
+        });
+        let (ring_id, (alloc_id, offset)) = match found {
+            Some(found) => found,
+            None => {
+                let size = max(self.ring_size, requirements.size);
+                let memory = self.budgets.allocate_memory::<B>(&device.raw, memory_type, size)?;
+                let ring_id = list.next_ring_id;
+                list.next_ring_id += 1;
+                list.rings.push_back(Ring::new(ring_id, memory, size));
+                let ring = list.rings.back_mut().unwrap();
+                (ring_id, ring.allocate(&requirements, submission).unwrap())
+            }
+        };
+
+        let sender = self.sender.clone();
+        let ring = list.rings.back().unwrap();
+        Ok((&ring.memory, offset, Box::new(move || {
+            let _ = dependency;
+            sender.send(Release { memory_type, ring_id, alloc_id }).unwrap_or_else(|_| {
+                error!("could not release RingAllocator's memory")
+            });
+        })))
+    }
+}
+
+impl<B: Backend> Allocator<B> for RingAllocator<B> {
+    fn allocate_buffer(&mut self,
+        device: &Device<B>,
+        usage: buffer::Usage,
+        buffer: B::UnboundBuffer
+    ) -> Result<(B::Buffer, Memory), AllocationError> {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerRingAllocator<B> = &mut self.0;
+        let requirements = complete_requirements::<B>(
+            &device.raw,
+            &buffer,
+            &inner.limits,
+            usage,
+        );
+        let usage = inner.usage;
+        let (memory, offset, release) = inner.allocate(device, requirements, dependency)?;
+        // released on failure
+        let memory_release = Memory::new(release, usage);
+        let buffer = device.raw.bind_buffer_memory(memory, offset, buffer)?;
+        Ok((buffer, memory_release))
+    }
+
+    fn allocate_image(&mut self,
+        device: &mut Device<B>,
+        _: image::Usage,
+        image: B::UnboundImage
+    ) -> Result<(B::Image, Memory), AllocationError> {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerRingAllocator<B> = &mut self.0;
+        let mut requirements = device.raw.get_image_requirements(&image);
+        // Keep images on their own pages, apart from buffers.
+        let granularity = max(inner.limits.buffer_image_granularity, 1);
+        requirements.alignment = max(requirements.alignment, granularity);
+        requirements.size = align(requirements.size, granularity);
+        let usage = inner.usage;
+        let (memory, offset, release) = inner.allocate(device, requirements, dependency)?;
+        // released on failure
+        let memory_release = Memory::new(release, usage);
+        let image = device.raw.bind_image_memory(memory, offset, image)?;
+        Ok((image, memory_release))
+    }
+
+    fn stats(&mut self) -> Stats {
+        let inner: &mut InnerRingAllocator<B> = &mut self.0;
+        inner.update_allocs();
+        Stats {
+            memory_types: inner.rings
+                .iter()
+                .map(|(&memory_type, list)| (memory_type, list.stats()))
+                .collect(),
+        }
+    }
+}
+
+struct Release {
+    memory_type: MemoryTypeId,
+    ring_id: u64,
+    alloc_id: u64,
+}
+
+fn align(offset: u64, alignment: u64) -> u64 {
+    let rem = offset % alignment;
+    if rem == 0 {
+        offset
+    } else {
+        offset - rem + alignment
+    }
+}
+
+struct RingAlloc {
+    beg: u64,
+    end: u64,
+    // number of the submission the allocation is made for
+    submission: usize,
+    released: bool,
+}
+
+struct Ring<B: Backend> {
+    id: u64,
+    memory: B::Memory,
+    size: u64,
+    // live allocations in allocation order
+    allocs: VecDeque<RingAlloc>,
+    // id of the front allocation, ids are consecutive
+    front_id: u64,
+}
+
+impl<B: Backend> Ring<B> {
+    fn new(id: u64, memory: B::Memory, size: u64) -> Self {
+        Ring {
+            id,
+            memory,
+            size,
+            allocs: VecDeque::new(),
+            front_id: 0,
+        }
+    }
+
+    /// Allocate behind the most recent allocation, wrapping around at the end.
+    /// Returns the allocation id and offset.
+    fn allocate(&mut self, req: &Requirements, submission: usize) -> Option<(u64, u64)> {
+        let beg = match (self.allocs.front(), self.allocs.back()) {
+            (Some(front), Some(back)) => {
+                let beg = align(back.end, req.alignment);
+                if back.beg >= front.beg {
+                    // live range doesn't wrap around
+                    if beg + req.size <= self.size {
+                        beg
+                    } else if req.size <= front.beg {
+                        0
+                    } else {
+                        return None;
+                    }
+                } else if beg + req.size <= front.beg {
+                    beg
+                } else {
+                    return None;
+                }
+            }
+            _ if req.size <= self.size => 0,
+            _ => return None,
+        };
+
+        let id = self.front_id + self.allocs.len() as u64;
+        self.allocs.push_back(RingAlloc {
+            beg,
+            end: beg + req.size,
+            submission,
+            released: false,
+        });
+        Some((id, beg))
+    }
+
+    fn release(&mut self, alloc_id: u64) {
+        let index = (alloc_id - self.front_id) as usize;
+        self.allocs[index].released = true;
+    }
+
+    /// Recycle the released allocations at the front whose submission
+    /// is among the first `completed` submissions.
+    fn retire(&mut self, completed: usize) {
+        while self.allocs.front().map_or(false, |a| a.released && a.submission < completed) {
+            self.allocs.pop_front();
+            self.front_id += 1;
+        }
+    }
+
+    fn used_bytes(&self) -> u64 {
+        self.allocs
+            .iter()
+            .filter(|a| !a.released)
+            .map(|a| a.end - a.beg)
+            .sum()
+    }
+}
+
+struct RingList<B: Backend> {
+    memory_type: MemoryTypeId,
+    // the last ring is the active one
+    rings: VecDeque<Ring<B>>,
+    next_ring_id: u64,
+}
+
+impl<B: Backend> RingList<B> {
+    fn new(memory_type: MemoryTypeId) -> Self {
+        RingList {
+            memory_type,
+            rings: VecDeque::new(),
+            next_ring_id: 0,
+        }
+    }
+
+    fn release(&mut self, ring_id: u64, alloc_id: u64) {
+        self.rings
+            .iter_mut()
+            .find(|ring| ring.id == ring_id)
+            .expect("released memory of an unknown ring")
+            .release(alloc_id);
+    }
+
+    fn retire(&mut self, completed: usize) {
+        for ring in &mut self.rings {
+            ring.retire(completed);
+        }
+    }
+
+    /// Free retired rings without live allocations, or all empty rings.
+    fn shrink(&mut self, device: &B::Device, budgets: &HeapBudgets, all: bool) {
+        let active = if all { None } else { self.rings.back().map(|ring| ring.id) };
+        let (empty, live): (Vec<_>, Vec<_>) = self.rings
+            .drain(..)
+            .partition(|ring| ring.allocs.is_empty() && Some(ring.id) != active);
+        for ring in empty {
+            budgets.free_memory::<B>(device, self.memory_type, ring.memory, ring.size);
+        }
+        self.rings = live.into_iter().collect();
+    }
+
+    fn stats(&self) -> MemoryTypeStats {
+        let (allocated, used) = self.rings.iter().fold((0, 0), |(allocated, used), ring| {
+            (allocated + ring.size, used + ring.used_bytes())
+        });
+        MemoryTypeStats::new(allocated, used)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_backend_empty::Backend as Empty;
+    use hal::memory::Requirements;
+    use super::Ring;
+
+    fn req(size: u64, alignment: u64) -> Requirements {
+        Requirements { size, alignment, type_mask: !0 }
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut ring = Ring::<Empty>::new(0, (), 256);
+        assert_eq!(ring.allocate(&req(100, 1), 0), Some((0, 0)));
+        assert_eq!(ring.allocate(&req(100, 64), 0), Some((1, 128)));
+        assert_eq!(ring.allocate(&req(100, 1), 1), None);
+
+        ring.release(0);
+        ring.retire(1);
+        // the end is full, wrap around into the recycled front
+        assert_eq!(ring.allocate(&req(100, 1), 1), Some((2, 0)));
+        // the next allocation would overlap the live one at 128
+        assert_eq!(ring.allocate(&req(32, 1), 1), None);
+        assert_eq!(ring.allocate(&req(28, 1), 1), Some((3, 100)));
+        assert_eq!(ring.used_bytes(), 228);
+    }
+
+    #[test]
+    fn recycle_after_submission() {
+        let mut ring = Ring::<Empty>::new(0, (), 256);
+        assert_eq!(ring.allocate(&req(256, 1), 0), Some((0, 0)));
+
+        // dropped by the user, but the GPU may still use it
+        ring.release(0);
+        ring.retire(0);
+        assert_eq!(ring.allocate(&req(1, 1), 1), None);
+
+        // the fence of submission 0 is reached
+        ring.retire(1);
+        assert!(ring.allocs.is_empty());
+        assert_eq!(ring.allocate(&req(256, 1), 1), Some((1, 0)));
+    }
+
+    #[test]
+    fn recycle_in_order() {
+        let mut ring = Ring::<Empty>::new(0, (), 256);
+        assert_eq!(ring.allocate(&req(128, 1), 0), Some((0, 0)));
+        assert_eq!(ring.allocate(&req(128, 1), 0), Some((1, 128)));
+
+        // a later release can't be recycled before the front
+        ring.release(1);
+        ring.retire(1);
+        assert_eq!(ring.allocs.len(), 2);
+        assert_eq!(ring.used_bytes(), 128);
+
+        ring.release(0);
+        ring.retire(1);
+        assert!(ring.allocs.is_empty());
+        assert_eq!(ring.allocate(&req(256, 1), 1), Some((2, 0)));
+    }
+}
@@ -3,7 +3,8 @@ use std::collections::HashMap;
 
 use hal::{Device as Device_, Limits, MemoryTypeId};
 use hal::memory::Requirements;
-use memory::{self, Allocator, Memory, ReleaseFn, Provider, Dependency};
+use memory::{self, AllocationError, Allocator, Memory, MemoryTypeStats, ReleaseFn, Provider, Dependency, Stats};
+use memory::HeapBudgets;
 use {buffer, image};
 use {Backend, Device};
 
@@ -37,6 +38,7 @@ pub struct InnerStackAllocator<B: Backend> {
     // TODO: VecMap ?
     stacks: HashMap<MemoryTypeId, ChunkStack<B>>,
     chunk_size: u64,
+    budgets: Arc<HeapBudgets>,
 }
 
 impl<B: Backend> Drop for InnerStackAllocator<B> {
@@ -63,6 +65,7 @@ impl<B: Backend> StackAllocator<B> {
             limits,
             stacks: HashMap::new(),
             chunk_size,
+            budgets: Arc::clone(device.budgets()),
         }))
     }
 
@@ -74,7 +77,7 @@ impl<B: Backend> StackAllocator<B> {
 impl<B: Backend> InnerStackAllocator<B> {
     fn shrink(&mut self) {
         for (_, stack) in &mut self.stacks {
-            stack.shrink(&self.device);
+            stack.shrink(&self.device, &self.budgets, self.chunk_size);
         }
     }
 }
@@ -84,7 +87,7 @@ impl<B: Backend> Allocator<B> for StackAllocator<B> {
         device: &Device<B>,
         usage: buffer::Usage,
         buffer: B::UnboundBuffer
-    ) -> (B::Buffer, Memory) {
+    ) -> Result<(B::Buffer, Memory), AllocationError> {
         let dependency = self.0.dependency();
         let inner: &mut InnerStackAllocator<B> = &mut self.0;
         let requirements = complete_requirements::<B>(
@@ -94,7 +97,7 @@ impl<B: Backend> Allocator<B> for StackAllocator<B> {
             usage,
         );
         let memory_type = device.find_usage_memory(inner.usage, requirements.type_mask)
-            .expect("could not find suitable memory");
+            .ok_or(AllocationError::NoSuitableMemory)?;
         let stack = inner.stacks.entry(memory_type)
             .or_insert_with(|| ChunkStack::new(memory_type));
         let (memory, offset, release) = stack.allocate(
@@ -102,23 +105,23 @@ impl<B: Backend> Allocator<B> for StackAllocator<B> {
             inner.chunk_size,
             requirements,
             dependency,
-        );
-        let buffer = device.raw
-            .bind_buffer_memory(memory, offset, buffer)
-            .unwrap();
-        (buffer, Memory::new(release, inner.usage))
+        )?;
+        // released on failure
+        let memory_release = Memory::new(release, inner.usage);
+        let buffer = device.raw.bind_buffer_memory(memory, offset, buffer)?;
+        Ok((buffer, memory_release))
     }
 
     fn allocate_image(&mut self,
         device: &mut Device<B>,
         _: image::Usage,
         image: B::UnboundImage
-    ) -> (B::Image, Memory) {
+    ) -> Result<(B::Image, Memory), AllocationError> {
         let dependency = self.0.dependency();
         let inner: &mut InnerStackAllocator<B> = &mut self.0;
         let requirements = device.raw.get_image_requirements(&image);
         let memory_type = device.find_usage_memory(inner.usage, requirements.type_mask)
-            .expect("could not find suitable memory");
+            .ok_or(AllocationError::NoSuitableMemory)?;
         let stack = inner.stacks.entry(memory_type)
             .or_insert_with(|| ChunkStack::new(memory_type));
         let (memory, offset, release) = stack.allocate(
@@ -126,11 +129,22 @@ impl<B: Backend> Allocator<B> for StackAllocator<B> {
             inner.chunk_size,
             requirements,
             dependency,
-        );
-        let image = device.raw
-            .bind_image_memory(memory, offset, image)
-            .unwrap();
-        (image, Memory::new(release, inner.usage))
+        )?;
+        // released on failure
+        let memory_release = Memory::new(release, inner.usage);
+        let image = device.raw.bind_image_memory(memory, offset, image)?;
+        Ok((image, memory_release))
+    }
+
+    fn stats(&mut self) -> Stats {
+        let inner: &mut InnerStackAllocator<B> = &mut self.0;
+        let chunk_size = inner.chunk_size;
+        Stats {
+            memory_types: inner.stacks
+                .iter_mut()
+                .map(|(&memory_type, stack)| (memory_type, stack.stats(chunk_size)))
+                .collect(),
+        }
     }
 }
 
@@ -145,6 +159,7 @@ struct ChunkStack<B: Backend> {
 struct StackAlloc {
     chunk_index: usize,
     end: u64,
+    size: u64,
     released: bool,
 }
 
@@ -166,7 +181,7 @@ impl<B: Backend> ChunkStack<B> {
         chunk_size: u64,
         req: Requirements,
         dependency: Dependency<InnerStackAllocator<B>>,
-    ) -> (&B::Memory, u64, ReleaseFn)
+    ) -> Result<(&B::Memory, u64, ReleaseFn), AllocationError>
     {
         self.update_allocs();
         assert!(req.size <= chunk_size);
@@ -190,36 +205,47 @@ impl<B: Backend> ChunkStack<B> {
             };
 
         if chunk_index == self.chunks.len() {
-            self.grow(device, chunk_size);
+            self.grow(device, chunk_size)?;
         }
 
         let alloc_index = self.allocs.len();
         self.allocs.push(StackAlloc {
             chunk_index,
             end,
+            size: req.size,
             released: false,
         });
 
         let sender = self.sender.clone();
-        (&self.chunks[chunk_index], beg, Box::new(move || {
+        Ok((&self.chunks[chunk_index], beg, Box::new(move || {
             let _ = dependency;
             sender.send(alloc_index).unwrap_or_else(|_| {
                 error!("could not release StackAllocator's memory")
             });
-        }))
+        })))
     }
 
     fn grow(&mut self,
         device: &Device<B>,
         chunk_size: u64,
-    ) {
-        let memory = device.raw
-            .allocate_memory(self.memory_type, chunk_size)
-            .unwrap();
+    ) -> Result<(), AllocationError> {
+        let memory = device.budgets()
+            .allocate_memory::<B>(&device.raw, self.memory_type, chunk_size)?;
         self.chunks.push(memory);
+        Ok(())
+    }
+
+    fn stats(&mut self, chunk_size: u64) -> MemoryTypeStats {
+        self.update_allocs();
+        let used = self.allocs
+            .iter()
+            .filter(|a| !a.released)
+            .map(|a| a.size)
+            .sum();
+        MemoryTypeStats::new(self.chunks.len() as u64 * chunk_size, used)
     }
 
-    fn shrink(&mut self, device: &B::Device) {
+    fn shrink(&mut self, device: &B::Device, budgets: &HeapBudgets, chunk_size: u64) {
         self.update_allocs();
 
         let drain_beg = self.allocs.last()
@@ -227,7 +253,7 @@ impl<B: Backend> ChunkStack<B> {
             .unwrap_or(0);
 
         for memory in self.chunks.drain(drain_beg..) {
-            device.free_memory(memory);
+            budgets.free_memory::<B>(device, self.memory_type, memory, chunk_size);
         }
     }
 
@@ -1,10 +1,52 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
 use std::sync::atomic::{self, AtomicBool, AtomicUsize};
 
 use {hal, handle};
-use memory::{Memory, Pod};
+use memory::{AllocationError, Memory, Pod};
 use Backend;
 
-pub use hal::buffer::{CreationError, Usage, ViewError};
+pub use hal::buffer::{Usage, ViewError};
+
+/// Error creating a buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// The device failed to create the buffer.
+    Device(hal::buffer::CreationError),
+    /// Allocating memory for the buffer failed.
+    Allocation(AllocationError),
+}
+
+impl From<hal::buffer::CreationError> for CreationError {
+    fn from(error: hal::buffer::CreationError) -> Self {
+        CreationError::Device(error)
+    }
+}
+
+impl From<AllocationError> for CreationError {
+    fn from(error: AllocationError) -> Self {
+        CreationError::Allocation(error)
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::Device(ref err) => write!(f, "{}", err),
+            CreationError::Allocation(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Device(ref err) => err.description(),
+            CreationError::Allocation(ref err) => err.description(),
+        }
+    }
+}
 
 /// An information block that is immutable and associated to each buffer.
 #[derive(Debug)]
@@ -93,3 +135,10 @@ impl<B: Backend> MaybeTyped<B> for handle::raw::Buffer<B> {
 impl<B: Backend, T: Pod> MaybeTyped<B> for handle::Buffer<B, T> {
     type Data = T;
 }
+
+impl<B: Backend, T> handle::Buffer<B, T> {
+    /// Number of elements of type `T` in the buffer.
+    pub fn len(&self) -> u64 {
+        self.as_ref().info().size / mem::size_of::<T>() as u64
+    }
+}
@@ -1,23 +1,33 @@
 use std::mem;
 use std::ops::Range;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use hal::{self, Device as CoreDevice, MemoryType, MemoryTypeId};
+use hal::{self, Device as CoreDevice, Features, Limits, MemoryType, MemoryTypeId};
 use hal::memory::{Properties};
 
-use memory::{self, Allocator, Typed};
+use encoder::MipmapError;
+use memory::{self, AllocationError, Allocator, HeapBudgets, Memory, QueueProgress, Typed};
+use mipmap::ComputeMipmap;
 use handle::{self, GarbageSender};
 use handle::inner::*;
 use {buffer, image, format, pso};
 use {Backend, Primitive, Extent};
 
-pub use hal::device::{FramebufferError};
+pub use hal::device::{FramebufferError, OutOfMemory};
 
 pub struct Device<B: Backend> {
     pub raw: Arc<B::Device>,
     // TODO: could be shared instead of cloned
     memory_types: Vec<MemoryType>,
     memory_heaps: Vec<u64>,
+    limits: Limits,
+    features: Features,
+    // indexed by `format as usize - 1`
+    format_properties: Arc<Vec<format::Properties>>,
+    // created on first use
+    compute_mipmap: Arc<Mutex<Option<ComputeMipmap<B>>>>,
+    budgets: Arc<HeapBudgets>,
+    progress: Arc<QueueProgress>,
     garbage: GarbageSender<B>,
 }
 
@@ -27,6 +37,12 @@ impl<B: Backend> Clone for Device<B> {
             raw: self.raw.clone(),
             memory_types: self.memory_types.clone(),
             memory_heaps: self.memory_heaps.clone(),
+            limits: self.limits,
+            features: self.features,
+            format_properties: self.format_properties.clone(),
+            compute_mipmap: self.compute_mipmap.clone(),
+            budgets: self.budgets.clone(),
+            progress: self.progress.clone(),
             garbage: self.garbage.clone(),
         }
     }
@@ -41,11 +57,27 @@ impl<B: Backend> Device<B> {
         raw: B::Device,
         memory_types: Vec<MemoryType>,
         memory_heaps: Vec<u64>,
+        limits: Limits,
+        features: Features,
+        format_properties: Vec<format::Properties>,
     ) -> (Self, handle::GarbageCollector<B>)
     {
         let arc = Arc::new(raw);
         let (garbage, collector) = handle::garbage(&arc);
-        (Device { raw: arc, memory_types, memory_heaps, garbage }, collector)
+        let budgets = HeapBudgets::new(&memory_types, &memory_heaps);
+        let device = Device {
+            raw: arc,
+            memory_types,
+            memory_heaps,
+            limits,
+            features,
+            format_properties: Arc::new(format_properties),
+            compute_mipmap: Arc::new(Mutex::new(None)),
+            budgets: Arc::new(budgets),
+            progress: Arc::new(QueueProgress::default()),
+            garbage,
+        };
+        (device, collector)
     }
 
     pub fn memory_types(&self) -> &[MemoryType] {
@@ -56,6 +88,64 @@ impl<B: Backend> Device<B> {
         &self.memory_heaps
     }
 
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn features(&self) -> Features {
+        self.features
+    }
+
+    /// Limit the memory all allocators of this device allocate from a heap.
+    /// Defaults to the size of the heap.
+    pub fn set_memory_budget(&mut self, heap_index: usize, budget: u64) {
+        self.budgets.set_budget(heap_index, budget);
+    }
+
+    /// Memory budget of a heap.
+    pub fn memory_budget(&self, heap_index: usize) -> u64 {
+        self.budgets.budget(heap_index)
+    }
+
+    /// Memory currently allocated from a heap by the allocators of this device,
+    /// including free space inside of their chunks.
+    pub fn heap_usage(&self, heap_index: usize) -> u64 {
+        self.budgets.used(heap_index)
+    }
+
+    pub(crate) fn budgets(&self) -> &Arc<HeapBudgets> {
+        &self.budgets
+    }
+
+    pub(crate) fn progress(&self) -> &Arc<QueueProgress> {
+        &self.progress
+    }
+
+    pub fn format_properties(&self, format: format::Format) -> format::Properties {
+        // `Format` starts at 1, values past the known formats support nothing
+        let index = (format as usize).checked_sub(1);
+        match index.and_then(|index| self.format_properties.get(index)) {
+            Some(&properties) => properties,
+            None => format::Properties {
+                linear_tiling: format::ImageFeature::empty(),
+                optimal_tiling: format::ImageFeature::empty(),
+                buffer_features: format::BufferFeature::empty(),
+            },
+        }
+    }
+
+    pub(crate) fn compute_mipmap(&mut self) -> Result<ComputeMipmap<B>, MipmapError> {
+        let cached = self.compute_mipmap.lock().unwrap().clone();
+        match cached {
+            Some(compute_mipmap) => Ok(compute_mipmap),
+            None => {
+                let compute_mipmap = ComputeMipmap::new(self)?;
+                *self.compute_mipmap.lock().unwrap() = Some(compute_mipmap.clone());
+                Ok(compute_mipmap)
+            }
+        }
+    }
+
     pub fn find_memory<P>(&self, type_mask: u64, predicate: P) -> Option<MemoryTypeId>
         where P: Fn(Properties) -> bool
     {
@@ -75,6 +165,7 @@ impl<B: Backend> Device<B> {
             Data => self.find_data_memory(type_mask),
             Upload => self.find_upload_memory(type_mask),
             Download => self.find_download_memory(type_mask),
+            Dynamic => self.find_dynamic_memory(type_mask),
         }
     }
 
@@ -111,6 +202,14 @@ impl<B: Backend> Device<B> {
         }))
     }
 
+    pub fn find_dynamic_memory(&self, type_mask: u64) -> Option<MemoryTypeId> {
+        self.find_memory(type_mask, |props| {
+            props.contains(Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | Properties::COHERENT)
+        }).or_else(|| self.find_memory(type_mask, |props| {
+            props.contains(Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE)
+        })).or_else(|| self.find_upload_memory(type_mask))
+    }
+
     pub fn create_buffer_raw<A>(
         &mut self,
         allocator: &mut A,
@@ -121,7 +220,7 @@ impl<B: Backend> Device<B> {
         where A: Allocator<B>
     {
         let buffer = self.raw.create_buffer(size, usage)?;
-        let (buffer, memory) = allocator.allocate_buffer(self, usage, buffer);
+        let (buffer, memory) = allocator.allocate_buffer(self, usage, buffer)?;
         let info = buffer::Info::new(usage, memory, size, stride);
         let handle = handle::raw::Buffer::from(
             Buffer::new(buffer, info, self.garbage.clone()));
@@ -129,6 +228,40 @@ impl<B: Backend> Device<B> {
         Ok((handle, token))
     }
 
+    /// Create a host visible buffer for staging transfers, bound to its own memory.
+    /// The memory is owned by the caller and has to outlive any use of the buffer.
+    /// `memory_usage` is either `Upload` or `Download`.
+    pub(crate) fn create_staging_buffer(
+        &mut self,
+        size: u64,
+        memory_usage: memory::Usage,
+    ) -> Result<(handle::raw::Buffer<B>, B::Memory), buffer::CreationError> {
+        let usage = match memory_usage {
+            memory::Usage::Upload => buffer::Usage::TRANSFER_SRC,
+            memory::Usage::Download => buffer::Usage::TRANSFER_DST,
+            _ => panic!("staging buffers are either for upload or download"),
+        };
+        let buffer = self.raw.create_buffer(size, usage)?;
+        let requirements = self.raw.get_buffer_requirements(&buffer);
+        let mem_type = self.find_usage_memory(memory_usage, requirements.type_mask)
+            .ok_or(AllocationError::NoSuitableMemory)?;
+        let memory = self.raw.allocate_memory(mem_type, requirements.size)
+            .map_err(AllocationError::from)?;
+        let buffer = match self.raw.bind_buffer_memory(&memory, 0, buffer) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                self.raw.free_memory(memory);
+                return Err(AllocationError::from(err).into());
+            }
+        };
+
+        let release = Memory::new(Box::new(|| {}), memory_usage);
+        let info = buffer::Info::new(usage, release, size, 1);
+        let handle = handle::raw::Buffer::from(
+            Buffer::new(buffer, info, self.garbage.clone()));
+        Ok((handle, memory))
+    }
+
     pub fn create_buffer<T, A>(
         &mut self,
         allocator: &mut A,
@@ -184,8 +317,15 @@ impl<B: Backend> Device<B> {
         use hal::image::ImageLayout;
 
         let aspects = format.aspects();
-        let image = self.raw.create_image(kind, mip_levels, format, usage)?;
-        let (image, memory) = allocator.allocate_image(self, usage, image);
+        let image = self.raw.create_image(
+            kind,
+            mip_levels,
+            format,
+            hal::image::Tiling::Optimal,
+            usage,
+            hal::image::StorageFlags::empty(),
+        )?;
+        let (image, memory) = allocator.allocate_image(self, usage, image)?;
         let origin = image::Origin::User(memory);
         let stable_access = hal::image::Access::empty();
         let stable_layout = match usage {
@@ -235,10 +375,10 @@ impl<B: Backend> Device<B> {
         format: format::Format,
         range: image::SubresourceRange,
     ) -> Result<handle::raw::ImageView<B>, image::ViewError> {
-        self.raw.create_image_view(image.resource(), format, format::Swizzle::NO, range)
+        self.raw.create_image_view(image.resource(), format, format::Swizzle::NO, range.clone())
             .map(|view| ImageView::new(
                 view,
-                image.clone(),
+                handle::ImageViewInfo { image: image.clone(), range },
                 self.garbage.clone()
             ).into())
     }
@@ -255,20 +395,34 @@ impl<B: Backend> Device<B> {
             .map(Typed::new)
     }
 
-    pub fn create_sampler(&mut self, info: image::SamplerInfo) -> handle::Sampler<B> {
-        handle::inner::Sampler::new(
-            self.raw.create_sampler(info.clone()), info, self.garbage.clone()
-        ).into()
+    pub fn create_sampler(
+        &mut self, info: image::SamplerInfo
+    ) -> Result<handle::Sampler<B>, OutOfMemory> {
+        let sampler = self.raw.create_sampler(info.clone())?;
+        Ok(handle::inner::Sampler::new(sampler, info, self.garbage.clone()).into())
     }
 
     // TODO: smarter allocation
-    pub fn create_descriptors<D>(&mut self, count: usize) -> Vec<(D, D::Data)>
+    pub fn create_descriptors<D>(
+        &mut self, count: usize
+    ) -> Result<Vec<(D, D::Data)>, OutOfMemory>
         where D: pso::Descriptors<B>
     {
-        use hal::pso::DescriptorPool as CDP;
-
         let bindings = &D::layout_bindings()[..];
         let layout = self.create_descriptor_set_layout(bindings);
+        let sets = self.create_descriptor_sets_raw(&layout, bindings, count)?;
+        Ok(sets.into_iter().map(|set| D::from_raw(layout.clone(), set)).collect())
+    }
+
+    /// Allocate `count` sets of `layout` from a new pool.
+    pub(crate) fn create_descriptor_sets_raw(
+        &mut self,
+        layout: &handle::raw::DescriptorSetLayout<B>,
+        bindings: &[hal::pso::DescriptorSetLayoutBinding],
+        count: usize,
+    ) -> Result<Vec<pso::RawDescriptorSet<B>>, OutOfMemory> {
+        use hal::pso::DescriptorPool as CDP;
+
         let ranges = bindings.iter().map(|binding| {
             hal::pso::DescriptorRangeDesc {
                 ty: binding.ty,
@@ -276,27 +430,43 @@ impl<B: Backend> Device<B> {
             }
         }).collect::<Vec<_>>();
 
-        let mut pool = self.raw.create_descriptor_pool(count, &ranges[..]);
+        let mut pool = self.raw.create_descriptor_pool(
+            count,
+            &ranges[..],
+            hal::pso::DescriptorPoolCreateFlags::empty(),
+        )?;
         let sets = {
             let layout_refs = (0..count).map(|_| layout.resource());
-            pool.allocate_sets(layout_refs)
+            // The pool has been sized for exactly these sets.
+            match pool.allocate_sets(layout_refs) {
+                Ok(sets) => sets,
+                Err(hal::pso::AllocationError::OutOfHostMemory) => {
+                    self.raw.destroy_descriptor_pool(pool);
+                    return Err(OutOfMemory::OutOfHostMemory);
+                }
+                Err(hal::pso::AllocationError::OutOfDeviceMemory) => {
+                    self.raw.destroy_descriptor_pool(pool);
+                    return Err(OutOfMemory::OutOfDeviceMemory);
+                }
+                Err(err) => panic!("Unexpected descriptor set allocation failure: {}", err),
+            }
         };
 
         let pool = handle::raw::DescriptorPool::from(
             DescriptorPool::new(pool, (), self.garbage.clone()));
-        sets.into_iter().map(|set| {
-            D::from_raw(layout.clone(), pso::RawDescriptorSet {
+        Ok(sets.into_iter().map(|set| {
+            pso::RawDescriptorSet {
                 resource: set,
                 pool: pool.clone()
-            })
-        }).collect()
+            }
+        }).collect())
     }
 
-    fn create_descriptor_set_layout(
+    pub(crate) fn create_descriptor_set_layout(
         &mut self,
         bindings: &[hal::pso::DescriptorSetLayoutBinding],
     ) -> handle::raw::DescriptorSetLayout<B> {
-        let layout = self.raw.create_descriptor_set_layout(bindings);
+        let layout = self.raw.create_descriptor_set_layout(bindings, &[]);
         DescriptorSetLayout::new(layout, (), self.garbage.clone()).into()
     }
 
@@ -330,10 +500,19 @@ impl<B: Backend> Device<B> {
         &mut self,
         desc: hal::pso::GraphicsPipelineDesc<B>,
     ) -> Result<handle::raw::GraphicsPipeline<B>, pso::CreationError> {
-        let pipeline = self.raw.create_graphics_pipelines(&[desc]).pop().unwrap()?;
+        let pipeline = self.raw.create_graphics_pipelines(&[desc], None).pop().unwrap()?;
         Ok(GraphicsPipeline::new(pipeline, (), self.garbage.clone()).into())
     }
 
+    #[doc(hidden)]
+    pub fn create_compute_pipeline_raw(
+        &mut self,
+        desc: hal::pso::ComputePipelineDesc<B>,
+    ) -> Result<handle::raw::ComputePipeline<B>, pso::CreationError> {
+        let pipeline = self.raw.create_compute_pipeline(&desc, None)?;
+        Ok(ComputePipeline::new(pipeline, (), self.garbage.clone()).into())
+    }
+
     pub fn create_graphics_pipeline<I>(
         &mut self,
         shader_entries: hal::pso::GraphicsShaderSet<B>,
@@ -346,6 +525,16 @@ impl<B: Backend> Device<B> {
         init.create(self, shader_entries, primitive, rasterizer)
     }
 
+    pub fn create_compute_pipeline<I>(
+        &mut self,
+        shader: hal::pso::EntryPoint<B>,
+        init: I,
+    ) -> Result<I::Pipeline, pso::CreationError>
+        where I: pso::ComputePipelineInit<B>
+    {
+        init.create(self, shader)
+    }
+
     // TODO?: typed
     pub fn create_framebuffer<P>(
         &mut self,
@@ -0,0 +1,123 @@
+//! Reading buffer and image data back from the device.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::{mem, ptr};
+
+use hal::{mapping, Device as Device_};
+
+use memory::Pod;
+use handle;
+use Backend;
+
+/// Host visible copy of buffer or image data, recorded with
+/// `Encoder::download_buffer` or `Encoder::download_image`.
+///
+/// The data can be read once the submission of the encoder completed,
+/// e.g. after `Context::wait` returned for its `SubmitToken`.
+///
+/// ```no_run
+/// # extern crate gfx_render as gfx;
+/// # use gfx::{Backend, Context, Graphics};
+/// # fn sample<B: Backend>(
+/// #     context: &mut Context<B, Graphics>,
+/// #     buffer: &gfx::handle::Buffer<B, u32>,
+/// # ) {
+/// let mut device = context.ref_device().clone();
+/// let mut pool = context.acquire_encoder_pool().unwrap();
+/// let mut encoder = pool.acquire_encoder();
+/// let download = encoder.download_buffer(&mut device, buffer, 0 .. 16).unwrap();
+/// let token = context.submit(vec![encoder.finish()]).unwrap();
+/// context.wait(token).unwrap();
+/// let data: Vec<u32> = download.read().unwrap();
+/// # }
+/// # fn main() {}
+/// ```
+pub struct Download<B: Backend, T> {
+    device: Arc<B::Device>,
+    // `memory` outlives `buffer`, which is destroyed by the garbage collector
+    buffer: handle::raw::Buffer<B>,
+    memory: Option<B::Memory>,
+    // in bytes, the rows of images are padded to `row_pitch`
+    row_size: u64,
+    row_pitch: u64,
+    rows: u64,
+    phantom: PhantomData<T>,
+}
+
+impl<B: Backend, T: Pod> Download<B, T> {
+    pub(crate) fn new(
+        device: Arc<B::Device>,
+        buffer: handle::raw::Buffer<B>,
+        memory: B::Memory,
+        row_size: u64,
+        row_pitch: u64,
+        rows: u64,
+    ) -> Self {
+        debug_assert_eq!(row_size % mem::size_of::<T>() as u64, 0,
+            "rows are not a whole number of elements");
+        Download {
+            device,
+            buffer,
+            memory: Some(memory),
+            row_size,
+            row_pitch,
+            rows,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Number of elements of type `T` read by `read`.
+    pub fn len(&self) -> usize {
+        (self.rows * self.row_size) as usize / mem::size_of::<T>()
+    }
+
+    /// Read the downloaded data, rows are tightly packed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a submission using the download is still pending.
+    pub fn read(&self) -> Result<Vec<T>, mapping::Error> {
+        let access = &self.buffer.info().access;
+        assert!(access.acquire_exclusive(), "download still in use by the device");
+        let result = self.read_mapped();
+        access.release_exclusive();
+        result
+    }
+
+    fn read_mapped(&self) -> Result<Vec<T>, mapping::Error> {
+        let memory = self.memory.as_ref().unwrap();
+        let range = 0 .. self.rows * self.row_pitch;
+        let mapped = self.device.map_memory(memory, range.clone())?;
+        self.device.invalidate_mapped_memory_ranges(Some((memory, range)));
+
+        let len = self.len();
+        let mut data = Vec::<T>::with_capacity(len);
+        unsafe {
+            let dst = data.as_mut_ptr() as *mut u8;
+            for row in 0 .. self.rows {
+                ptr::copy_nonoverlapping(
+                    mapped.offset((row * self.row_pitch) as isize),
+                    dst.offset((row * self.row_size) as isize),
+                    self.row_size as usize,
+                );
+            }
+            data.set_len(len);
+        }
+        self.device.unmap_memory(memory);
+        Ok(data)
+    }
+}
+
+impl<B: Backend, T> Drop for Download<B, T> {
+    fn drop(&mut self) {
+        let memory = self.memory.take().unwrap();
+        if self.buffer.info().access.acquire_exclusive() {
+            // no use of the buffer is pending anymore
+            self.device.free_memory(memory);
+        } else {
+            error!("Download dropped while in use by the device, leaking its memory");
+            mem::forget(memory);
+        }
+    }
+}
@@ -1,21 +1,29 @@
 //! Commands encoder.
 
+use std::error::Error;
+use std::fmt;
 use std::mem;
 use std::ops::Range;
 use std::sync::mpsc;
 use std::collections::{HashMap, HashSet};
 
-use hal::{self, buffer as b, image as i, CommandPool};
-use hal::command::CommandBuffer;
-use hal::format::Aspects;
-use hal::memory::{Barrier, Dependencies};
-use hal::pso::PipelineStage;
-
-use memory::{Provider, Dependency, cast_slice};
-use device::InitToken;
+use hal::{self, buffer as b, image as i, CommandPool, Compute, Device as Device_, Features};
+use hal::command::{BlitFilter, CommandBuffer, ImageBlit};
+use hal::device::OutOfMemory;
+use hal::format::{Aspects, ChannelType, ImageFeature};
+use hal::memory::Dependencies;
+use hal::pso::{Descriptor, DescriptorSetWrite, PipelineStage};
+use hal::tracking::{BufferTracker, ImageTracker, Transitions};
+
+use memory::{self, Provider, Dependency, Pod, cast_slice};
+use device::{Device, InitToken};
+use download::Download;
+use mipmap;
+use upload::{align, lcm};
 use {handle, buffer, image, format, pso};
-use {Backend, Supports, Transfer, Graphics};
-use {VertexCount};
+use {Backend, Extent, Supports, Transfer, General, Graphics};
+use slice::IndexBuffer;
+use {VertexCount, VertexOffset, IndexCount, WorkGroupCount};
 
 pub use hal::command::{
     BufferCopy, ImageCopy, BufferImageCopy,
@@ -49,7 +57,6 @@ impl<B: Backend, C> Pool<B, C> {
             buffer: self.mut_inner().acquire_command_buffer(false),
             // raw_data: pso::RawDataSet::new(),
             handles: handle::Bag::new(),
-            pipeline_stage: PipelineStage::TOP_OF_PIPE,
             buffer_states: HashMap::new(),
             image_states: HashMap::new(),
         }
@@ -87,9 +94,8 @@ pub struct Encoder<'a, B: Backend, C> {
     handles: handle::Bag<B>,
     pool: PoolDependency<B, C>,
     // raw_data: pso::RawDataSet<B>,
-    pipeline_stage: PipelineStage,
-    buffer_states: HashMap<handle::raw::Buffer<B>, hal::buffer::State>,
-    image_states: HashMap<handle::raw::Image<B>, ImageStates>,
+    buffer_states: HashMap<handle::raw::Buffer<B>, BufferTracker>,
+    image_states: HashMap<handle::raw::Image<B>, ImageTracker>,
 }
 
 pub struct Submit<B: Backend, C> {
@@ -99,38 +105,127 @@ pub struct Submit<B: Backend, C> {
     pub(crate) pool: PoolDependency<B, C>
 }
 
-// TODO: coalescing?
-struct ImageStates {
-    states: Vec<hal::image::State>,
-    levels: usize,
+/// Error generating mipmaps.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MipmapError {
+    /// The image or its format supports neither blits nor the compute
+    /// fallback on this queue.
+    Unsupported,
+    /// The device or host ran out of memory.
+    OutOfMemory(OutOfMemory),
+    /// Creating the compute pipeline failed.
+    Pipeline(pso::CreationError),
+    /// Creating an image view failed.
+    View(image::ViewError),
 }
 
-impl ImageStates {
-    fn new(
-        state: hal::image::State,
-        levels: image::Level,
-        layers: image::Layer,
-    ) -> Self {
-        let size = layers as usize * levels as usize;
-        ImageStates {
-            states: (0..size).map(|_| state).collect(),
-            levels: levels as usize,
+impl From<OutOfMemory> for MipmapError {
+    fn from(error: OutOfMemory) -> Self {
+        MipmapError::OutOfMemory(error)
+    }
+}
+
+impl From<pso::CreationError> for MipmapError {
+    fn from(error: pso::CreationError) -> Self {
+        MipmapError::Pipeline(error)
+    }
+}
+
+impl From<image::ViewError> for MipmapError {
+    fn from(error: image::ViewError) -> Self {
+        MipmapError::View(error)
+    }
+}
+
+impl fmt::Display for MipmapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MipmapError::OutOfMemory(ref err) => write!(f, "{}: {}", self.description(), err),
+            MipmapError::Pipeline(ref err) => write!(f, "{}: {}", self.description(), err),
+            MipmapError::View(ref err) => write!(f, "{}: {:?}", self.description(), err),
+            MipmapError::Unsupported => write!(f, "{}", self.description()),
         }
     }
+}
 
-    fn get_mut(
-        &mut self,
-        level: image::Level,
-        layer: image::Layer,
-    ) -> &mut hal::image::State {
-        let index = layer as usize * self.levels + level as usize;
-        &mut self.states[index]
+impl Error for MipmapError {
+    fn description(&self) -> &str {
+        match *self {
+            MipmapError::Unsupported => "Mipmap generation is not supported for the image",
+            MipmapError::OutOfMemory(_) => "Out of memory",
+            MipmapError::Pipeline(_) => "Failed to create the mipmap pipeline",
+            MipmapError::View(_) => "Failed to create an image view",
+        }
+    }
+}
+
+/// Queue capabilities which can generate mipmaps.
+///
+/// Blits require `Graphics`, the compute fallback requires `Compute`.
+pub trait MipmapCapability: Sized {
+    #[doc(hidden)]
+    fn generate_mipmap<'a, B: Backend>(
+        encoder: &mut Encoder<'a, B, Self>,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError>;
+}
+
+impl MipmapCapability for General {
+    fn generate_mipmap<'a, B: Backend>(
+        encoder: &mut Encoder<'a, B, Self>,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError> {
+        if supports_blit_mipmap(device, image) {
+            encoder.blit_mipmap(image);
+            Ok(())
+        } else {
+            encoder.compute_mipmap(device, image)
+        }
+    }
+}
+
+impl MipmapCapability for Graphics {
+    fn generate_mipmap<'a, B: Backend>(
+        encoder: &mut Encoder<'a, B, Self>,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError> {
+        if supports_blit_mipmap(device, image) {
+            encoder.blit_mipmap(image);
+            Ok(())
+        } else {
+            Err(MipmapError::Unsupported)
+        }
     }
+}
 
-    fn ranges(&self) -> (Range<image::Level>, Range<image::Layer>) {
-        let levels = self.levels as image::Level;
-        let layers = (self.states.len() / self.levels) as image::Layer;
-        (0..levels, 0..layers)
+impl MipmapCapability for Compute {
+    fn generate_mipmap<'a, B: Backend>(
+        encoder: &mut Encoder<'a, B, Self>,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError> {
+        encoder.compute_mipmap(device, image)
+    }
+}
+
+fn supports_blit_mipmap<B: Backend>(device: &Device<B>, image: &handle::raw::Image<B>) -> bool {
+    let features = device.format_properties(image.info().format).optimal_tiling;
+    features.contains(ImageFeature::BLIT_SRC | ImageFeature::BLIT_DST | ImageFeature::SAMPLED_LINEAR)
+}
+
+fn level_bounds(kind: i::Kind, level: i::Level) -> Range<i::Offset> {
+    let (width, height, depth, _) = kind.level_dimensions(level);
+    let depth = match kind {
+        i::Kind::D3(..) => depth,
+        _ => 1,
+    };
+    i::Offset { x: 0, y: 0, z: 0 } .. i::Offset {
+        x: width as i32,
+        y: height as i32,
+        z: depth as i32,
     }
 }
 
@@ -202,109 +297,47 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
     }
 
     pub fn init_resources(&mut self, tokens: Vec<InitToken<B>>) {
-        let mut barriers = Vec::new();
-        for token in &tokens {
+        self.init_resources_ref(&tokens);
+    }
+
+    // Tokens are only consumed once the recorded commands are submitted.
+    pub(crate) fn init_resources_ref(&mut self, tokens: &[InitToken<B>]) {
+        let mut transitions = Transitions::new();
+        for token in tokens {
             match token.handle {
-                handle::Any::Image(ref image) =>
-                    barriers.push(self.init_image(image)),
+                handle::Any::Image(ref image) => {
+                    let creation_state = (hal::image::Access::empty(), i::ImageLayout::Undefined);
+                    let info = image.info();
+                    let mut tracker = ImageTracker::new(
+                        creation_state,
+                        info.aspects,
+                        info.mip_levels,
+                        info.kind.num_layers(),
+                    );
+                    let range = tracker.range();
+                    tracker.transition(
+                        image.resource(),
+                        range,
+                        info.stable_state,
+                        PipelineStage::TOP_OF_PIPE,
+                        &mut transitions,
+                    );
+                    self.image_states.insert(image.clone(), tracker);
+                }
                 _ => {}
             }
         }
-        let stage_transition = self.pipeline_stage..self.pipeline_stage;
-        self.buffer.pipeline_barrier(stage_transition, Dependencies::empty(), barriers);
-    }
-
-    fn init_image<'b>(
-        &mut self, image: &'b handle::raw::Image<B>
-    ) -> Barrier<'b, B> {
-        let creation_state = (hal::image::Access::empty(), i::ImageLayout::Undefined);
-        let num_levels = image.info().mip_levels;
-        let num_layers = image.info().kind.num_layers();
-        let stable_state = image.info().stable_state;
-        let states = ImageStates::new(stable_state, num_levels, num_layers);
-        self.image_states.insert(image.clone(), states);
-        Barrier::Image {
-            states: creation_state .. stable_state,
-            target: image.resource(),
-            range: i::SubresourceRange {
-                aspects: image.info().aspects,
-                levels: 0 .. num_levels,
-                layers: 0 .. num_layers,
-            },
-        }
-    }
-
-    fn require_buffer_state<'b>(
-        &mut self,
-        buffer: &'b handle::raw::Buffer<B>,
-        state: hal::buffer::State
-    ) -> Option<Barrier<'b, B>> {
-        if !self.buffer_states.contains_key(buffer) {
-            self.buffer_states.insert(buffer.clone(), buffer.info().stable_state);
-        }
-        Self::transition_buffer(
-            buffer,
-            self.buffer_states.get_mut(buffer).unwrap(),
-            state)
-    }
-
-    fn require_image_state<'b>(
-        &mut self,
-        image: &'b handle::raw::Image<B>,
-        level: image::Level,
-        layer: image::Layer,
-        state: hal::image::State,
-    ) -> Option<Barrier<'b, B>> {
-        if !self.image_states.contains_key(image) {
-            let levels = image.info().mip_levels;
-            let layers = image.info().kind.num_layers();
-            let states = ImageStates::new(image.info().stable_state, levels, layers);
-            self.image_states.insert(image.clone(), states);
-        }
-        Self::transition_image(
-            image,
-            level,
-            layer,
-            self.image_states.get_mut(image).unwrap().get_mut(level, layer),
-            state)
-    }
-
-    fn transition_buffer<'b>(
-        buffer: &'b handle::raw::Buffer<B>,
-        current: &mut hal::buffer::State,
-        next: hal::buffer::State
-    ) -> Option<Barrier<'b, B>> {
-        let state = mem::replace(current, next);
-        if state != next {
-            Some(Barrier::Buffer {
-                states: state..next,
-                target: buffer.resource(),
-            })
-        } else {
-            None
-        }
+        Self::pipeline_barrier(&mut self.buffer, &transitions);
     }
 
-    fn transition_image<'b>(
-        image: &'b handle::raw::Image<B>,
-        level: image::Level,
-        layer: image::Layer,
-        current: &mut hal::image::State,
-        next: hal::image::State,
-    ) -> Option<Barrier<'b, B>> {
-        let state = mem::replace(current, next);
-        if state != next {
-            Some(Barrier::Image {
-                states: state .. next,
-                target: image.resource(),
-                range: i::SubresourceRange {
-                    aspects: image.info().aspects,
-                    levels: level .. (level+1),
-                    layers: layer .. (layer+1),
-                },
-            })
-        } else {
-            None
+    // Takes the command buffer only, as the transitions may borrow from the trackers.
+    fn pipeline_barrier(buffer: &mut CommandBuffer<'a, B, C>, transitions: &Transitions<B>) {
+        if !transitions.is_empty() {
+            buffer.pipeline_barrier(
+                transitions.stages(),
+                Dependencies::empty(),
+                transitions.barriers(),
+            );
         }
     }
 
@@ -315,17 +348,28 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         buffer_states: &[(&handle::raw::Buffer<B>, hal::buffer::State)],
         image_states: &[(&handle::raw::Image<B>, image::Subresource, hal::image::State)],
     ) {
-        let mut barriers = Vec::new();
+        let mut transitions = Transitions::new();
         for &(buffer, state) in buffer_states {
-            barriers.extend(self.require_buffer_state(buffer, state));
+            self.buffer_states
+                .entry(buffer.clone())
+                .or_insert_with(|| BufferTracker::new(buffer.info().stable_state))
+                .transition(buffer.resource(), state, stage, &mut transitions);
         }
         for &(image, (level, layer), state) in image_states {
-            barriers.extend(self.require_image_state(image, level, layer, state));
-        }
-        let current_stage = mem::replace(&mut self.pipeline_stage, stage);
-        if (current_stage != stage) || !barriers.is_empty() {
-            self.buffer.pipeline_barrier(current_stage..stage, Dependencies::empty(), barriers);
+            let range = i::SubresourceRange {
+                aspects: image.info().aspects,
+                levels: level .. (level+1),
+                layers: layer .. (layer+1),
+            };
+            self.image_states
+                .entry(image.clone())
+                .or_insert_with(|| {
+                    let info = image.info();
+                    ImageTracker::new(info.stable_state, info.aspects, info.mip_levels, info.kind.num_layers())
+                })
+                .transition(image.resource(), range, state, stage, &mut transitions);
         }
+        Self::pipeline_barrier(&mut self.buffer, &transitions);
     }
 
     #[doc(hidden)]
@@ -333,25 +377,79 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         &mut self.handles
     }
 
+    /// Tracked state of a buffer used by the encoder.
+    #[cfg(test)]
+    pub(crate) fn buffer_state(&self, buffer: &handle::raw::Buffer<B>) -> Option<b::State> {
+        self.buffer_states.get(buffer).map(|tracker| tracker.state())
+    }
+
+    /// Tracked state of a subresource of an image used by the encoder.
+    #[cfg(test)]
+    pub(crate) fn image_state(
+        &self, image: &handle::raw::Image<B>, subresource: image::Subresource,
+    ) -> Option<i::State> {
+        self.image_states.get(image)
+            .map(|tracker| tracker.state(image.info().aspects, subresource))
+    }
+
     fn transition_to_stable_state(&mut self) {
-        let mut barriers = Vec::new();
-        for (buffer, state) in &mut self.buffer_states {
-            barriers.extend(Self::transition_buffer(buffer, state, buffer.info().stable_state));
+        let mut transitions = Transitions::new();
+        for (buffer, tracker) in &mut self.buffer_states {
+            tracker.transition(
+                buffer.resource(),
+                buffer.info().stable_state,
+                PipelineStage::BOTTOM_OF_PIPE,
+                &mut transitions,
+            );
         }
-        for (image, states) in &mut self.image_states {
-            let (levels, layers) = states.ranges();
-            for level in levels {
-                for layer in layers.clone() {
-                    let state = states.get_mut(level, layer);
-                    barriers.extend(Self::transition_image(image, level, layer, state, image.info().stable_state));
-                }
-            }
+        for (image, tracker) in &mut self.image_states {
+            let range = tracker.range();
+            tracker.transition(
+                image.resource(),
+                range,
+                image.info().stable_state,
+                PipelineStage::BOTTOM_OF_PIPE,
+                &mut transitions,
+            );
         }
-        let stage_transition = self.pipeline_stage..PipelineStage::BOTTOM_OF_PIPE;
-        self.buffer.pipeline_barrier(stage_transition, Dependencies::empty(), barriers);
+        Self::pipeline_barrier(&mut self.buffer, &transitions);
     }
 
-    // TODO: fill buffer
+    /// Fill the `range` of a buffer with the repeated `data` word.
+    /// `range` is expressed in elements and must start and end on 4 bytes boundaries.
+    pub fn fill_buffer<MTB>(
+        &mut self,
+        buffer: &MTB,
+        range: Range<u64>,
+        data: u32,
+    )
+        where MTB: buffer::MaybeTyped<B>
+    {
+        if range.start >= range.end { return; }
+        let buffer = buffer.as_ref();
+
+        debug_assert!(buffer.info().usage.contains(b::Usage::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+
+        let stride = mem::size_of::<MTB::Data>() as u64;
+        let start_bytes = range.start * stride;
+        let end_bytes = range.end * stride;
+        debug_assert!(start_bytes % 4 == 0 && end_bytes % 4 == 0,
+            "fill range is not 4 bytes aligned");
+        debug_assert!(end_bytes <= buffer.info().size,
+            "out of buffer bounds");
+
+        self.require_state(
+            PipelineStage::TRANSFER,
+            &[(buffer, b::Access::TRANSFER_WRITE)],
+            &[]);
+
+        self.handles.add(buffer.clone());
+        self.buffer.fill_buffer(
+            buffer.resource(),
+            start_bytes .. end_bytes,
+            data);
+    }
 
     /// Copy part of a buffer to another
     pub fn copy_buffer<MTB>(
@@ -584,18 +682,289 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
             dst.resource(),
             regions);
     }
+
+    /// Copy the `range` of elements of `buffer` to host visible memory.
+    pub fn download_buffer<MTB>(
+        &mut self,
+        device: &mut Device<B>,
+        buffer: &MTB,
+        range: Range<u64>,
+    ) -> Result<Download<B, MTB::Data>, buffer::CreationError>
+        where MTB: buffer::MaybeTyped<B>
+    {
+        let stride = mem::size_of::<MTB::Data>() as u64;
+        let size = (range.end - range.start) * stride;
+        let (staging, staging_memory) = device.create_staging_buffer(
+            size.max(1), memory::Usage::Download)?;
+        self.copy_buffer(buffer.as_ref(), &staging, &[BufferCopy {
+            src: range.start * stride,
+            dst: 0,
+            size,
+        }]);
+        Ok(Download::new(device.raw.clone(), staging, staging_memory, size, size, 1))
+    }
+
+    /// Copy a region of `image` to host visible memory.
+    /// Texel blocks are read row by row, array layers follow each other like depth slices.
+    pub fn download_image_raw<T: Pod>(
+        &mut self,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+        layers: i::SubresourceLayers,
+        offset: i::Offset,
+        extent: Extent,
+    ) -> Result<Download<B, T>, buffer::CreationError> {
+        let desc = image.info().format.base_format().0.desc();
+        let block_bytes = desc.bits as u64 / 8;
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let blocks = |texels: u32, block: u32| ((texels + block - 1) / block) as u64;
+
+        let row_bytes = blocks(extent.width, block_width) * block_bytes;
+        let rows = blocks(extent.height, block_height);
+        let slices = extent.depth as u64 * (layers.layers.end - layers.layers.start) as u64;
+        // staging rows have to be a whole number of texel blocks
+        let pitch_alignment = lcm(device.limits().min_buffer_copy_pitch_alignment.max(1), block_bytes);
+        let row_pitch = align(row_bytes, pitch_alignment);
+        let size = slices * rows * row_pitch;
+
+        let (staging, staging_memory) = device.create_staging_buffer(
+            size.max(1), memory::Usage::Download)?;
+        self.copy_image_to_buffer(image, &staging, &[BufferImageCopy {
+            buffer_offset: 0,
+            buffer_width: (row_pitch / block_bytes) as u32 * block_width,
+            buffer_height: rows as u32 * block_height,
+            image_layers: layers,
+            image_offset: offset,
+            image_extent: extent,
+        }]);
+        Ok(Download::new(device.raw.clone(), staging, staging_memory, row_bytes, row_pitch, slices * rows))
+    }
+
+    /// Copy a region of `image` to host visible memory.
+    pub fn download_image<F, T>(
+        &mut self,
+        device: &mut Device<B>,
+        image: &handle::Image<B, F>,
+        layers: i::SubresourceLayers,
+        offset: i::Offset,
+        extent: Extent,
+    ) -> Result<Download<B, T>, buffer::CreationError>
+    where
+        F: format::AsFormat,
+        T: Pod,
+    {
+        self.download_image_raw(device, image.as_ref(), layers, offset, extent)
+    }
+}
+
+impl<'a, B: Backend, C> Encoder<'a, B, C>
+    where C: Supports<Transfer> + MipmapCapability
+{
+    /// Generate the mipmap chain of `image` from its first level.
+    ///
+    /// Levels are downsampled with linear blits if the format supports them.
+    /// Otherwise a compute shader is used if the queue supports compute, which
+    /// requires a 2D image with `SAMPLED` and `STORAGE` usage and a
+    /// non-integer format supporting both.
+    pub fn generate_mipmap<F>(
+        &mut self,
+        device: &mut Device<B>,
+        image: &handle::Image<B, F>,
+    ) -> Result<(), MipmapError>
+        where F: format::AsFormat
+    {
+        self.generate_mipmap_raw(device, image.as_ref())
+    }
+
+    /// Untyped version of mipmap generation.
+    pub fn generate_mipmap_raw(
+        &mut self,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError> {
+        if image.info().mip_levels <= 1 {
+            return Ok(());
+        }
+        C::generate_mipmap(self, device, image)
+    }
+}
+
+impl<'a, B: Backend, C> Encoder<'a, B, C>
+    where C: Supports<Transfer> + Supports<Compute>
+{
+    /// Dispatch `count` work groups of the compute `pipeline`.
+    pub fn dispatch<D>(
+        &mut self,
+        count: WorkGroupCount,
+        pipeline: &D::Pipeline,
+        data: D
+    )
+        where D: pso::ComputePipelineData<B>
+    {
+        data.bind(self, pipeline);
+        self.buffer.dispatch(count);
+    }
+
+    /// Dispatch the compute `pipeline` with the work group count read from
+    /// `buffer` at `offset` bytes.
+    pub fn dispatch_indirect<D>(
+        &mut self,
+        buffer: &handle::raw::Buffer<B>,
+        offset: u64,
+        pipeline: &D::Pipeline,
+        data: D
+    )
+        where D: pso::ComputePipelineData<B>
+    {
+        debug_assert!(buffer.info().usage.contains(b::Usage::INDIRECT),
+            "missing INDIRECT usage flag");
+        self.require_state(
+            PipelineStage::DRAW_INDIRECT,
+            &[(buffer, b::Access::INDIRECT_COMMAND_READ)],
+            &[]);
+        self.handles.add(buffer.clone());
+
+        data.bind(self, pipeline);
+        self.buffer.dispatch_indirect(buffer.resource(), offset);
+    }
+
+    fn compute_mipmap(
+        &mut self,
+        device: &mut Device<B>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError> {
+        let info = image.info();
+        let features = device.format_properties(info.format).optimal_tiling;
+        // Each layer is downsampled separately, as 2D views of a single layer.
+        let is_2d = match info.kind {
+            i::Kind::D2(_, _, i::AaMode::Single) |
+            i::Kind::D2Array(_, _, _, i::AaMode::Single) |
+            i::Kind::Cube(..) |
+            i::Kind::CubeArray(..) => true,
+            _ => false,
+        };
+        let is_integer = match info.format.base_format().1 {
+            ChannelType::Uint | ChannelType::Int => true,
+            _ => false,
+        };
+        // The shader writes the destination without a format qualifier.
+        if !is_2d || is_integer
+            || !device.features().contains(Features::SHADER_STORAGE_IMAGE_WRITE_WITHOUT_FORMAT)
+            || !features.contains(ImageFeature::SAMPLED | ImageFeature::STORAGE)
+            || !info.usage.contains(i::Usage::SAMPLED | i::Usage::STORAGE)
+        {
+            return Err(MipmapError::Unsupported);
+        }
+        if info.mip_levels < 2 {
+            return Ok(());
+        }
+
+        let pipeline = device.compute_mipmap()?;
+        let levels = info.mip_levels as usize;
+        let layers = info.kind.num_layers() as usize;
+        // One view per subresource, each used as destination of one dispatch
+        // and as source of the next. All sets are allocated from a single pool.
+        let views = (0 .. layers)
+            .flat_map(|layer| (0 .. levels).map(move |level| (level, layer)))
+            .map(|(level, layer)| device.create_image_view_raw(image, info.format, i::SubresourceRange {
+                aspects: info.aspects,
+                levels: level as i::Level .. level as i::Level + 1,
+                layers: layer as i::Layer .. layer as i::Layer + 1,
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sets = device.create_descriptor_sets_raw(
+            &pipeline.set_layout,
+            &mipmap::bindings(),
+            layers * (levels - 1),
+        )?;
+        // Set of the dispatch writing `level` (> 0) of `layer`.
+        let set_index = |level: usize, layer: usize| layer * (levels - 1) + level - 1;
+        let view_index = |level: usize, layer: usize| layer * levels + level;
+
+        let src_layout = i::ImageLayout::ShaderReadOnlyOptimal;
+        let dst_layout = i::ImageLayout::General;
+        device.raw.write_descriptor_sets((0 .. layers)
+            .flat_map(|layer| (1 .. levels).map(move |level| (level, layer)))
+            .flat_map(|(level, layer)| {
+                let set = sets[set_index(level, layer)].resource();
+                vec![
+                    DescriptorSetWrite {
+                        set,
+                        binding: 0,
+                        array_offset: 0,
+                        descriptors: Some(Descriptor::CombinedImageSampler(
+                            views[view_index(level - 1, layer)].resource(),
+                            src_layout,
+                            pipeline.sampler.resource(),
+                        )),
+                    },
+                    DescriptorSetWrite {
+                        set,
+                        binding: 1,
+                        array_offset: 0,
+                        descriptors: Some(Descriptor::Image(
+                            views[view_index(level, layer)].resource(),
+                            dst_layout,
+                        )),
+                    },
+                ]
+            }));
+
+        self.buffer.bind_compute_pipeline(pipeline.pipeline.resource());
+        let groups = |size: i::Size| (size as u32 + mipmap::WORK_GROUP_SIZE - 1) / mipmap::WORK_GROUP_SIZE;
+        for level in 1 .. levels {
+            let mut image_states = Vec::new();
+            for layer in 0 .. layers {
+                let (level, layer) = (level as i::Level, layer as i::Layer);
+                image_states.push((image, (level - 1, layer), (i::Access::SHADER_READ, src_layout)));
+                image_states.push((image, (level, layer), (i::Access::SHADER_WRITE, dst_layout)));
+            }
+            self.require_state(
+                PipelineStage::COMPUTE_SHADER,
+                &[],
+                &image_states[..]);
+
+            let (width, height, _, _) = info.kind.level_dimensions(level as i::Level);
+            for layer in 0 .. layers {
+                self.buffer.bind_compute_descriptor_sets(
+                    pipeline.layout.resource(),
+                    0,
+                    Some(sets[set_index(level, layer)].resource()),
+                );
+                self.buffer.dispatch([groups(width), groups(height), 1]);
+            }
+        }
+
+        // all sets share the same pool
+        self.handles.add(sets[0].pool.clone());
+        self.handles.extend(views);
+        self.handles.add(pipeline.pipeline);
+        self.handles.add(pipeline.layout);
+        self.handles.add(pipeline.set_layout);
+        self.handles.add(pipeline.sampler);
+        Ok(())
+    }
 }
 
 impl<'a, B: Backend, C> Encoder<'a, B, C>
     where C: Supports<Transfer> + Supports<Graphics>
 {
-    fn require_clear_state(&mut self, image: &handle::raw::Image<B>) -> i::ImageLayout {
-        let levels = image.info().mip_levels;
-        let layers = image.info().kind.num_layers();
+    fn require_clear_state(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        range: &i::SubresourceRange,
+    ) -> i::ImageLayout {
+        let info = image.info();
+        debug_assert!(info.aspects.contains(range.aspects),
+            "clearing aspects missing from the image");
+        debug_assert!(range.levels.end <= info.mip_levels,
+            "out of image levels");
+        debug_assert!(range.layers.end <= info.kind.num_layers(),
+            "out of image layers");
         let state = (i::Access::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal);
         let mut image_states = Vec::new();
-        for level in 0..levels {
-            for layer in 0..layers {
+        for level in range.levels.clone() {
+            for layer in range.layers.clone() {
                 image_states.push((image, (level, layer), state));
             }
         }
@@ -607,24 +976,33 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         state.1
     }
 
-    /// Clears `image` to `value`.
+    /// Clears all levels and layers of `image` to `value`.
     pub fn clear_color_raw(
         &mut self,
         image: &handle::raw::Image<B>,
         value: ClearColor,
     ) {
-        let layout = self.require_clear_state(image);
-        //TODO
-        let range = i::SubresourceRange {
-            aspects: Aspects::COLOR,
-            levels: 0 .. 1,
-            layers: 0 .. 1,
-        };
+        let range = image.info().full_range();
+        self.clear_color_range_raw(image, range, value);
+    }
+
+    /// Clears the `range` subresources of `image` to `value`.
+    pub fn clear_color_range_raw(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        range: i::SubresourceRange,
+        value: ClearColor,
+    ) {
+        debug_assert_eq!(range.aspects, Aspects::COLOR,
+            "color clears only apply to the color aspect");
+        if range.levels.start >= range.levels.end ||
+            range.layers.start >= range.layers.end { return; }
+        let layout = self.require_clear_state(image, &range);
         self.handles.add(image.clone());
         self.buffer.clear_color_image(image.resource(), layout, range, value);
     }
 
-    /// Clears `image` to `value`.
+    /// Clears all levels and layers of `image` to `value`.
     pub fn clear_color<F>(
         &mut self,
         image: &handle::Image<B, F>,
@@ -635,23 +1013,95 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         self.clear_color_raw(image.as_ref(), value);
     }
 
-    /// Clears `image`'s depth/stencil with `value`
+    /// Clears the depth and/or stencil of all levels and layers of `image` with `value`.
+    /// Only the aspects present in the format of `image` are cleared.
     pub fn clear_depth_stencil_raw(
         &mut self,
         image: &handle::raw::Image<B>,
         value: ClearDepthStencil,
     ) {
-        let layout = self.require_clear_state(image);
-        //TODO
-        let range = i::SubresourceRange {
-            aspects: Aspects::DEPTH | Aspects::STENCIL,
-            levels: 0 .. 1,
-            layers: 0 .. 1,
-        };
+        let range = image.info().full_range();
+        self.clear_depth_stencil_range_raw(image, range, value);
+    }
+
+    /// Clears the depth and/or stencil of the `range` subresources of `image` with `value`.
+    pub fn clear_depth_stencil_range_raw(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        range: i::SubresourceRange,
+        value: ClearDepthStencil,
+    ) {
+        debug_assert!(!range.aspects.is_empty() &&
+            (Aspects::DEPTH | Aspects::STENCIL).contains(range.aspects),
+            "depth/stencil clears only apply to the depth and stencil aspects");
+        if range.levels.start >= range.levels.end ||
+            range.layers.start >= range.layers.end { return; }
+        let layout = self.require_clear_state(image, &range);
         self.handles.add(image.clone());
         self.buffer.clear_depth_stencil_image(image.resource(), layout, range, value);
     }
 
+    /// Clears the depth and/or stencil of all levels and layers of `image` with `value`.
+    pub fn clear_depth_stencil<F>(
+        &mut self,
+        image: &handle::Image<B, F>,
+        value: ClearDepthStencil,
+    ) where
+        F: format::AsFormat,
+    {
+        self.clear_depth_stencil_raw(image.as_ref(), value);
+    }
+
+    fn blit_mipmap(&mut self, image: &handle::raw::Image<B>) {
+        let info = image.info();
+        let layers = info.kind.num_layers();
+        let src_state = (i::Access::TRANSFER_READ, i::ImageLayout::TransferSrcOptimal);
+        let dst_state = (i::Access::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal);
+        for level in 1 .. info.mip_levels {
+            let mut image_states = Vec::new();
+            for layer in 0 .. layers {
+                image_states.push((image, (level - 1, layer), src_state));
+                image_states.push((image, (level, layer), dst_state));
+            }
+            self.require_state(
+                PipelineStage::TRANSFER,
+                &[],
+                &image_states[..]);
+
+            self.buffer.blit_image(
+                image.resource(), src_state.1,
+                image.resource(), dst_state.1,
+                BlitFilter::Linear,
+                &[ImageBlit {
+                    src_subresource: i::SubresourceLayers {
+                        aspects: info.aspects,
+                        level: level - 1,
+                        layers: 0 .. layers,
+                    },
+                    src_bounds: level_bounds(info.kind, level - 1),
+                    dst_subresource: i::SubresourceLayers {
+                        aspects: info.aspects,
+                        level,
+                        layers: 0 .. layers,
+                    },
+                    dst_bounds: level_bounds(info.kind, level),
+                }]);
+        }
+    }
+
+    fn require_index_buffer(&mut self, index_buffer: &IndexBuffer<B>) {
+        if let Some(buffer) = index_buffer.raw() {
+            debug_assert!(buffer.info().usage.contains(b::Usage::INDEX),
+                "missing INDEX usage flag");
+            self.require_state(
+                PipelineStage::VERTEX_INPUT,
+                &[(buffer, b::Access::INDEX_BUFFER_READ)],
+                &[]);
+        }
+    }
+
+    /// Draw `vertices` once for each of the instances of `data`.
+    /// The index buffer of `data` is ignored.
     pub fn draw<D>(
         &mut self,
         vertices: Range<VertexCount>,
@@ -660,20 +1110,75 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
     )
         where D: pso::GraphicsPipelineData<B>
     {
-        // TODO: instances
-        data.begin_renderpass(self, pipeline).draw(vertices, 0..1);
+        let instances = data.instances();
+        data.begin_renderpass(self, pipeline).draw(vertices, instances);
     }
 
-/*
-    /// Generate a mipmap chain for the given resource view.
-    pub fn generate_mipmap<T: format::BlendFormat>(&mut self, view: &handle::ShaderResourceView<B, T>) {
-        self.generate_mipmap_raw(view.raw())
+    /// Draw `indices` of the index buffer of `data` once for each of its instances,
+    /// `base_vertex` is added to every index.
+    /// Falls back to a non-indexed draw for `IndexBuffer::Auto`.
+    pub fn draw_indexed<D>(
+        &mut self,
+        indices: Range<IndexCount>,
+        base_vertex: VertexOffset,
+        pipeline: &D::Pipeline,
+        data: D
+    )
+        where D: pso::GraphicsPipelineData<B>
+    {
+        // `data` is consumed by the render pass
+        let index_buffer = data.index_buffer().clone();
+        let instances = data.instances();
+        self.require_index_buffer(&index_buffer);
+        let mut pass = data.begin_renderpass(self, pipeline);
+        match index_buffer.view() {
+            Some(view) => {
+                pass.bind_index_buffer(view);
+                pass.draw_indexed(indices, base_vertex, instances);
+            }
+            None => {
+                let base = base_vertex as VertexCount;
+                pass.draw(
+                    indices.start.wrapping_add(base) .. indices.end.wrapping_add(base),
+                    instances);
+            }
+        }
     }
 
-    /// Untyped version of mipmap generation.
-    pub fn generate_mipmap_raw(&mut self, view: &handle::RawShaderResourceView<B>) {
-        let srv = self.handles.ref_srv(view).clone();
-        self.command_buffer.generate_mipmap(srv);
+    /// Draw `draw_count` times with the parameters read from `buffer`,
+    /// starting at `offset` bytes and spaced by `stride` bytes.
+    /// Draws are indexed unless the index buffer of `data` is `IndexBuffer::Auto`.
+    /// The instances of `data` are ignored, they are read from `buffer`.
+    pub fn draw_indirect<D>(
+        &mut self,
+        buffer: &handle::raw::Buffer<B>,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+        pipeline: &D::Pipeline,
+        data: D
+    )
+        where D: pso::GraphicsPipelineData<B>
+    {
+        debug_assert!(buffer.info().usage.contains(b::Usage::INDIRECT),
+            "missing INDIRECT usage flag");
+        self.require_state(
+            PipelineStage::DRAW_INDIRECT,
+            &[(buffer, b::Access::INDIRECT_COMMAND_READ)],
+            &[]);
+        let index_buffer = data.index_buffer().clone();
+        self.require_index_buffer(&index_buffer);
+        self.handles.add(buffer.clone());
+
+        let mut pass = data.begin_renderpass(self, pipeline);
+        match index_buffer.view() {
+            Some(view) => {
+                pass.bind_index_buffer(view);
+                pass.draw_indexed_indirect(buffer.resource(), offset, draw_count, stride);
+            }
+            None => {
+                pass.draw_indirect(buffer.resource(), offset, draw_count, stride);
+            }
+        }
     }
-    */
 }
@@ -55,6 +55,7 @@ impl<B: Backend> InnerGarbageCollector<B> {
                 RenderPass(rp) => dev.destroy_render_pass(rp),
                 PipelineLayout(pl) => dev.destroy_pipeline_layout(pl),
                 GraphicsPipeline(pl) => dev.destroy_graphics_pipeline(pl),
+                ComputePipeline(pl) => dev.destroy_compute_pipeline(pl),
                 Framebuffer(fb) => dev.destroy_framebuffer(fb),
                 Buffer(b) => dev.destroy_buffer(b),
                 BufferView(bv) => dev.destroy_buffer_view(bv),
@@ -231,12 +232,12 @@ define_resources! {
     RenderPass: (),
     PipelineLayout: (),
     GraphicsPipeline: (),
-    // ComputePipeline
+    ComputePipeline: (),
     Framebuffer: ::handle::FramebufferInfo<B>,
     Buffer: ::buffer::Info,
     BufferView: ::handle::raw::Buffer<B>,
     Image: ::image::Info,
-    ImageView: ::handle::raw::Image<B>,
+    ImageView: ::handle::ImageViewInfo<B>,
     Sampler: ::image::SamplerInfo,
     DescriptorPool: (),
     DescriptorSetLayout: (),
@@ -251,6 +252,12 @@ pub type ImageView<B, F> = Typed<raw::ImageView<B>, F>;
 
 pub use self::raw::Sampler;
 
+#[derive(Debug, Clone)]
+pub struct ImageViewInfo<B: Backend> {
+    pub image: raw::Image<B>,
+    pub range: ::image::SubresourceRange,
+}
+
 #[derive(Debug, Clone)]
 pub struct FramebufferInfo<B: Backend> {
     pub attachments: Vec<raw::ImageView<B>>,
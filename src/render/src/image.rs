@@ -1,13 +1,55 @@
+use std::error::Error;
+use std::fmt;
+
 use hal;
-use memory::Memory;
+use memory::{AllocationError, Memory};
 
 pub use hal::format::Aspects;
 pub use hal::image::{
-    CreationError, Kind, AaMode, Size, Level, Layer, Dimensions,
+    Kind, AaMode, Size, Level, Layer, Dimensions, Offset,
     SamplerInfo, ViewError, Usage,
     Subresource, SubresourceLayers, SubresourceRange,
 };
 
+/// Error creating an image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreationError {
+    /// The device failed to create the image.
+    Device(hal::image::CreationError),
+    /// Allocating memory for the image failed.
+    Allocation(AllocationError),
+}
+
+impl From<hal::image::CreationError> for CreationError {
+    fn from(error: hal::image::CreationError) -> Self {
+        CreationError::Device(error)
+    }
+}
+
+impl From<AllocationError> for CreationError {
+    fn from(error: AllocationError) -> Self {
+        CreationError::Allocation(error)
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::Device(ref err) => write!(f, "{}", err),
+            CreationError::Allocation(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Device(ref err) => err.description(),
+            CreationError::Allocation(ref err) => err.description(),
+        }
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub struct Info {
@@ -20,6 +62,17 @@ pub struct Info {
     pub(crate) stable_state: hal::image::State,
 }
 
+impl Info {
+    /// The range covering all aspects, levels and layers of the image.
+    pub fn full_range(&self) -> SubresourceRange {
+        SubresourceRange {
+            aspects: self.aspects,
+            levels: 0 .. self.mip_levels,
+            layers: 0 .. self.kind.num_layers(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Origin {
     Backbuffer,
@@ -1,8 +1,7 @@
 // #![deny(missing_docs)] TODO
 
 // TODO(doc) clarify the different type of queues and what is accessible from the high-level API
-// vs what belongs to core-ll. There doesn't seem to be a "ComputeEncoder" can I submit something
-// built with a GraphicsEncoder to a ComputeQueue?
+// vs what belongs to core-ll.
 
 //! # gfx
 //!
@@ -62,6 +61,12 @@
 //!
 //! See [the documentation of the gfx::pso module](pso/index.html).
 //!
+//! ## Compute
+//!
+//! Compute pipelines are declared with `gfx_compute_pipeline!` and dispatched with
+//! `Encoder::dispatch` on queues supporting compute. Compute-only applications can
+//! create a `Context` without surface with `Context::init_headless`.
+//!
 //! ## Memory management
 //!
 //! Handles internally use atomically reference counted pointers to deal with memory management.
@@ -96,6 +101,9 @@ extern crate failure;
 
 pub extern crate gfx_hal as hal;
 
+#[cfg(test)]
+extern crate gfx_backend_empty;
+
 /// public re-exported traits
 pub mod traits {
     pub use hal::memory::Pod;
@@ -104,20 +112,27 @@ pub mod traits {
 // public re-exports
 pub use hal::format;
 pub use hal::{Backend, Frame, Primitive};
-pub use hal::queue::{Supports, Transfer, General, Graphics};
-pub use hal::{VertexCount, InstanceCount};
+pub use hal::queue::{Supports, Transfer, General, Graphics, Compute};
+pub use hal::{VertexCount, VertexOffset, IndexCount, InstanceCount, WorkGroupCount};
 pub use hal::device::Extent;
 // pub use hal::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
 pub use encoder::Encoder;
 pub use device::Device;
+pub use upload::Uploader;
+pub use download::Download;
+pub use slice::Slice;
 
 pub mod handle;
 mod device;
+mod mipmap;
 pub mod encoder;
 pub mod memory;
 pub mod allocators;
 pub mod buffer;
 pub mod image;
+pub mod upload;
+pub mod download;
+pub mod slice;
 /// Pipeline states
 pub mod pso;
 /*
@@ -128,14 +143,20 @@ pub mod shade;
 pub mod macros;
 
 use std::collections::VecDeque;
+use std::error::Error;
+use std::{fmt, mem};
 use hal::{
-    Capability, CommandQueue, PhysicalDevice, Surface, Swapchain,
+    Capability, CommandQueue, PhysicalDevice, QueueFamily, Surface, Swapchain,
     Device as Device_,
 };
+use hal::device::OutOfMemory;
 use hal::format::AsFormat;
 use hal::pool::CommandPoolCreateFlags;
 use memory::Typed;
 
+// Stage at which submissions to the main queue wait for pending transfers.
+const TRANSFER_WAIT_STAGE: hal::pso::PipelineStage = hal::pso::PipelineStage::TOP_OF_PIPE;
+
 struct Queue<B: Backend, C> {
     group: hal::QueueGroup<B, C>,
     pool_receiver: encoder::CommandPoolReceiver<B, C>,
@@ -151,33 +172,134 @@ impl<B: Backend, C: hal::Capability> Queue<B, C> {
 
     fn acquire_encoder_pool(
         &mut self, device: &B::Device
-    ) -> encoder::Pool<B, C> {
-        let pool = self.pool_receiver.try_recv()
-            .map(|mut recycled| {
+    ) -> Result<encoder::Pool<B, C>, OutOfMemory> {
+        let pool = match self.pool_receiver.try_recv() {
+            Ok(mut recycled) => {
                 recycled.reset();
                 recycled
-            })
-            .unwrap_or_else(|_| {
+            }
+            Err(_) => {
                 let initial_capacity = 4;
                 let flags = CommandPoolCreateFlags::empty();
-                device.create_command_pool_typed(&self.group, flags, initial_capacity)
-            });
-        encoder::Pool::new(pool, self.pool_sender.clone())
+                device.create_command_pool_typed(&self.group, flags, initial_capacity)?
+            }
+        };
+        Ok(encoder::Pool::new(pool, self.pool_sender.clone()))
     }
 }
 
 pub struct Context<B: Backend, C> {
-    surface: B::Surface,
+    // `None` for headless contexts
+    surface: Option<B::Surface>,
     device: Device<B>,
     queue: Queue<B, C>,
-    swapchain: B::Swapchain,
+    // dedicated transfer queue used for uploads, if the adapter has one
+    transfer: Option<Queue<B, Transfer>>,
+    // signalled by transfer submissions, waited for by the next submission to `queue`
+    transfer_waits: Vec<B::Semaphore>,
+    semaphores: Vec<B::Semaphore>,
+    swapchain: Option<B::Swapchain>,
     frame_bundles: VecDeque<FrameBundle<B, C>>,
     frame_acquired: Option<FrameBundle<B, C>>,
+    // pending submissions made without presenting, oldest first
+    submit_bundles: VecDeque<SubmitBundle<B, C>>,
+    fences: Vec<B::Fence>,
+    // id of the next submission made without presenting
+    next_submit: u64,
     garbage: handle::GarbageCollector<B>,
 }
 
-pub struct Backbuffer<B: Backend, Cf: AsFormat> {
-    pub color: handle::Image<B, Cf>,
+/// Identifies a submission, see `Context::submit` and `Context::wait`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SubmitToken(u64);
+
+/// A presentable target of the swapchain.
+pub enum Backbuffer<B: Backend, Cf: AsFormat> {
+    /// A color image.
+    Image(handle::Image<B, Cf>),
+    /// A framebuffer provided by the window system, e.g. the default framebuffer of OpenGL.
+    /// Its attachments are not accessible, their states are not tracked.
+    Framebuffer(handle::raw::Framebuffer<B>),
+}
+
+/// Framebuffer and color view targeting a `Backbuffer`, see `Backbuffer::create_target`.
+pub struct BackbufferTarget<B: Backend, Cf: AsFormat> {
+    pub framebuffer: handle::raw::Framebuffer<B>,
+    /// `None` for `Backbuffer::Framebuffer`.
+    pub color: Option<handle::ImageView<B, Cf>>,
+}
+
+/// Error creating a `BackbufferTarget`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TargetError {
+    /// Creating the view of the color image failed.
+    View(image::ViewError),
+    /// Creating the framebuffer failed.
+    Framebuffer(device::FramebufferError),
+}
+
+impl From<image::ViewError> for TargetError {
+    fn from(error: image::ViewError) -> Self {
+        TargetError::View(error)
+    }
+}
+
+impl From<device::FramebufferError> for TargetError {
+    fn from(error: device::FramebufferError) -> Self {
+        TargetError::Framebuffer(error)
+    }
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TargetError::View(ref err) => write!(f, "{}: {}", self.description(), err),
+            TargetError::Framebuffer(_) => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for TargetError {
+    fn description(&self) -> &str {
+        match *self {
+            TargetError::View(_) => "Failed to create the view of the backbuffer",
+            TargetError::Framebuffer(_) => "Failed to create the framebuffer of the backbuffer",
+        }
+    }
+}
+
+impl<B: Backend, Cf: AsFormat> Backbuffer<B, Cf> {
+    /// Create a framebuffer targeting the backbuffer in the render pass of `pipeline`.
+    /// Framebuffers provided by the window system are shared with any pipeline.
+    pub fn create_target<P>(
+        &self,
+        device: &mut Device<B>,
+        pipeline: &P,
+    ) -> Result<BackbufferTarget<B, Cf>, TargetError>
+        where P: pso::GraphicsPipelineMeta<B>
+    {
+        match *self {
+            Backbuffer::Image(ref image) => {
+                let range = image::SubresourceRange {
+                    aspects: format::Aspects::COLOR,
+                    levels: 0 .. 1,
+                    layers: 0 .. 1,
+                };
+                let view = device.create_image_view(image, range)?;
+                let extent = kind_extent(image.as_ref().info().kind);
+                let framebuffer = device.create_framebuffer(pipeline, &[view.as_ref()], extent)?;
+                Ok(BackbufferTarget { framebuffer, color: Some(view) })
+            }
+            Backbuffer::Framebuffer(ref framebuffer) => {
+                Ok(BackbufferTarget { framebuffer: framebuffer.clone(), color: None })
+            }
+        }
+    }
+}
+
+fn kind_extent(kind: image::Kind) -> Extent {
+    let (width, height, _, _) = kind.dimensions();
+    Extent { width: width as _, height: height as _, depth: 1 }
 }
 
 use self::Signal::*;
@@ -209,6 +331,21 @@ struct FrameBundle<B: Backend, C> {
     // signal when the frame is done
     signal_semaphore: B::Semaphore,
     signal_fence: Sync<B::Fence>,
+    // transfer semaphores waited for by the last submission
+    transfer_waits: Vec<B::Semaphore>,
+    // number of the last submission, see `memory::QueueProgress`
+    submission: usize,
+}
+
+struct SubmitBundle<B: Backend, C> {
+    id: u64,
+    handles: handle::Bag<B>,
+    access_info: encoder::AccessInfo<B>,
+    encoder_pools: Vec<encoder::PoolDependency<B, C>>,
+    fence: B::Fence,
+    transfer_waits: Vec<B::Semaphore>,
+    // see `memory::QueueProgress`
+    submission: usize,
 }
 
 impl<B: Backend, C> Context<B, C>
@@ -221,7 +358,10 @@ impl<B: Backend, C> Context<B, C>
         Cf: AsFormat,
     {
         let memory_properties = adapter.physical_device.memory_properties();
-        let (device, queues) = adapter.open_with(1, |family| {
+        let limits = adapter.physical_device.limits();
+        let features = adapter.physical_device.features();
+        let format_properties = query_format_properties::<B>(&adapter.physical_device);
+        let (device, queues, transfer) = open_queues(adapter, |family| {
             surface.supports_queue_family(family)
         })?;
 
@@ -231,96 +371,230 @@ impl<B: Backend, C> Context<B, C>
             .with_color(Cf::SELF); // TODO: check support
         let (swapchain, backbuffer) = device.create_swapchain(&mut surface, swap_config);
 
-        let backbuffer_images = match backbuffer {
-            hal::Backbuffer::Images(images) => images,
-            hal::Backbuffer::Framebuffer(_) => unimplemented!(), //TODO
+        let backbuffers: Vec<_> = match backbuffer {
+            hal::Backbuffer::Images(images) => images
+                .into_iter()
+                .map(|raw| {
+                    let stable_access = hal::image::Access::empty();
+                    let stable_layout = hal::image::ImageLayout::Present;
+                    let handle = handle::inner::Image::without_garbage(
+                        raw,
+                        image::Info {
+                            aspects: format::Aspects::COLOR,
+                            usage: image::Usage::TRANSFER_SRC | image::Usage::COLOR_ATTACHMENT,
+                            kind: surface.kind(),
+                            mip_levels: 1,
+                            format: Cf::SELF,
+                            origin: image::Origin::Backbuffer,
+                            stable_state: (stable_access, stable_layout),
+                        },
+                    );
+                    Backbuffer::Image(Typed::new(handle.into()))
+                }).collect(),
+            hal::Backbuffer::Framebuffer(raw) => {
+                let handle = handle::inner::Framebuffer::without_garbage(
+                    raw,
+                    handle::FramebufferInfo {
+                        attachments: Vec::new(),
+                        extent: kind_extent(surface.kind()),
+                    },
+                );
+                vec![Backbuffer::Framebuffer(handle.into())]
+            }
         };
 
-        let frame_bundles = backbuffer_images
+        let frame_bundles = backbuffers
             .iter()
-            .map(|_| FrameBundle {
+            .map(|_| Ok(FrameBundle {
                 handles: handle::Bag::new(),
                 access_info: encoder::AccessInfo::new(),
                 encoder_pools: Vec::new(),
-                wait_semaphore: device.create_semaphore(),
-                signal_semaphore: device.create_semaphore(),
+                wait_semaphore: device.create_semaphore()?,
+                signal_semaphore: device.create_semaphore()?,
                 signal_fence: Sync::reached(
-                    device.create_fence(true)),
-            }).collect();
-
-        let backbuffers = backbuffer_images
-            .into_iter()
-            .map(|raw| {
-                let stable_access = hal::image::Access::empty();
-                let stable_layout = hal::image::ImageLayout::Present;
-                let handle = handle::inner::Image::without_garbage(
-                    raw,
-                    image::Info {
-                        aspects: format::Aspects::COLOR,
-                        usage: image::Usage::TRANSFER_SRC | image::Usage::COLOR_ATTACHMENT,
-                        kind: surface.kind(),
-                        mip_levels: 1,
-                        format: Cf::SELF,
-                        origin: image::Origin::Backbuffer,
-                        stable_state: (stable_access, stable_layout),
-                    },
-                );
-                Backbuffer {
-                    color: Typed::new(handle.into()),
-                }
-            }).collect();
+                    device.create_fence(true)?),
+                transfer_waits: Vec::new(),
+                submission: 0,
+            }))
+            .collect::<Result<_, OutOfMemory>>()?;
 
         let (device, garbage) = Device::new(
             device,
             memory_properties.memory_types,
             memory_properties.memory_heaps,
+            limits,
+            features,
+            format_properties,
         );
 
         let context = Context {
-            surface,
+            surface: Some(surface),
             device,
             queue,
-            swapchain,
+            transfer: transfer.map(Queue::new),
+            transfer_waits: Vec::new(),
+            semaphores: Vec::new(),
+            swapchain: Some(swapchain),
             frame_bundles,
             frame_acquired: None,
+            submit_bundles: VecDeque::new(),
+            fences: Vec::new(),
+            next_submit: 0,
             garbage,
         };
 
         Ok((context, backbuffers))
     }
 
-    pub fn acquire_frame(&mut self) -> Frame {
+    /// Create a context without surface, e.g. for compute or offscreen rendering.
+    /// Frames can't be acquired nor presented, work is submitted with `submit`.
+    pub fn init_headless(adapter: hal::Adapter<B>) -> Result<Self, failure::Error> {
+        let memory_properties = adapter.physical_device.memory_properties();
+        let limits = adapter.physical_device.limits();
+        let features = adapter.physical_device.features();
+        let format_properties = query_format_properties::<B>(&adapter.physical_device);
+        let (device, queues, transfer) = open_queues(adapter, |_| true)?;
+
+        let (device, garbage) = Device::new(
+            device,
+            memory_properties.memory_types,
+            memory_properties.memory_heaps,
+            limits,
+            features,
+            format_properties,
+        );
+
+        Ok(Context {
+            surface: None,
+            device,
+            queue: Queue::new(queues),
+            transfer: transfer.map(Queue::new),
+            transfer_waits: Vec::new(),
+            semaphores: Vec::new(),
+            swapchain: None,
+            frame_bundles: VecDeque::new(),
+            frame_acquired: None,
+            submit_bundles: VecDeque::new(),
+            fences: Vec::new(),
+            next_submit: 0,
+            garbage,
+        })
+    }
+
+    /// Submit `submits` to the queue without presenting.
+    /// The resources they use are kept alive until the submission completed.
+    pub fn submit(
+        &mut self, submits: Vec<encoder::Submit<B, C>>
+    ) -> Result<SubmitToken, hal::SubmissionError> {
+        self.poll().map_err(|hal::device::DeviceLost| hal::SubmissionError::DeviceLost)?;
+
+        let fence = match self.fences.pop() {
+            Some(fence) => fence,
+            None => self.device.raw.create_fence(false)
+                .map_err(submission_error)?,
+        };
+        let mut bundle = SubmitBundle {
+            id: self.next_submit,
+            handles: handle::Bag::new(),
+            access_info: encoder::AccessInfo::new(),
+            encoder_pools: Vec::new(),
+            fence,
+            transfer_waits: mem::replace(&mut self.transfer_waits, Vec::new()),
+            submission: 0,
+        };
+
+        let inner_submits: Vec<_> = submits.into_iter()
+            .map(|mut submit| {
+                bundle.handles.append(&mut submit.handles);
+                bundle.access_info.append(&mut submit.access_info);
+                bundle.encoder_pools.push(submit.pool);
+                submit.inner
+            }).collect();
+
+        bundle.access_info.start_gpu_access();
+        let result = {
+            let submission = hal::Submission::new()
+                .wait_on(bundle.transfer_waits.iter().map(|semaphore| (semaphore, TRANSFER_WAIT_STAGE)))
+                .promote::<C>()
+                .submit(inner_submits);
+            self.queue.group.queues[0].submit::<C>(submission, Some(&bundle.fence))
+        };
+        if let Err(err) = result {
+            // nothing got submitted, the fence is still unsignalled
+            bundle.access_info.end_gpu_access();
+            self.transfer_waits.extend(bundle.transfer_waits);
+            self.fences.push(bundle.fence);
+            return Err(err);
+        }
+
+        bundle.submission = self.device.progress().submit();
+        self.submit_bundles.push_back(bundle);
+        self.next_submit += 1;
+        Ok(SubmitToken(self.next_submit - 1))
+    }
+
+    /// Submit `submits` to the queue and block until they are executed.
+    pub fn submit_and_wait(
+        &mut self, submits: Vec<encoder::Submit<B, C>>
+    ) -> Result<(), hal::SubmissionError> {
+        let token = self.submit(submits)?;
+        self.wait(token).map_err(|hal::device::DeviceLost| hal::SubmissionError::DeviceLost)
+    }
+
+    pub fn acquire_frame(&mut self) -> Result<Frame, hal::AcquireError> {
         assert!(self.frame_acquired.is_none());
 
+        // bundles are only destroyed when their semaphores can't be recreated
         let mut bundle = self.frame_bundles.pop_front()
-            .expect("no frame bundles");
+            .ok_or(hal::AcquireError::OutOfMemory(OutOfMemory::OutOfHostMemory))?;
 
         if bundle.signal_fence.signal == Pending {
-            self.device.raw.wait_for_fence(&bundle.signal_fence.inner, !0);
+            if let Err(hal::device::DeviceLost) =
+                self.device.raw.wait_for_fence(&bundle.signal_fence.inner, !0)
+            {
+                self.frame_bundles.push_front(bundle);
+                return Err(hal::AcquireError::DeviceLost);
+            }
+            self.device.progress().complete(bundle.submission);
         }
+        self.semaphores.extend(bundle.transfer_waits.drain(..));
         self.device.raw.reset_fence(&bundle.signal_fence.inner);
         bundle.signal_fence.signal = Reached;
 
+        // the GPU is done with this frame's resources,
+        // dropping them lets allocators reuse their memory
         bundle.handles.clear();
         bundle.access_info.end_gpu_access();
         bundle.access_info.clear();
         bundle.encoder_pools.clear();
 
-        let frame = self.swapchain.acquire_frame(
-            hal::FrameSync::Semaphore(&mut bundle.wait_semaphore)
-        );
+        let swapchain = self.swapchain.as_mut()
+            .expect("no swapchain in a headless context");
+        let frame = match swapchain.acquire_frame(
+            hal::FrameSync::Semaphore(&mut bundle.wait_semaphore),
+            !0,
+        ) {
+            Ok(frame) => frame,
+            Err(err) => {
+                // the semaphore is left unsignalled, the bundle can be reused
+                self.frame_bundles.push_front(bundle);
+                return Err(err);
+            }
+        };
         self.frame_acquired = Some(bundle);
 
         self.garbage.collect();
-        frame
+        Ok(frame)
     }
 
-    pub fn acquire_encoder_pool(&mut self) -> encoder::Pool<B, C> {
+    pub fn acquire_encoder_pool(&mut self) -> Result<encoder::Pool<B, C>, OutOfMemory> {
         self.queue.acquire_encoder_pool(&self.device.raw)
     }
 
     // TODO: allow submissions before present
-    pub fn present(&mut self, submits: Vec<encoder::Submit<B, C>>) {
+    pub fn present(
+        &mut self, submits: Vec<encoder::Submit<B, C>>
+    ) -> Result<hal::PresentStatus, hal::PresentError> {
         let mut bundle = self.frame_acquired.take()
             .expect("no acquired frame");
 
@@ -333,33 +607,221 @@ impl<B: Backend, C> Context<B, C>
             }).collect();
 
         bundle.access_info.start_gpu_access();
+        bundle.transfer_waits = mem::replace(&mut self.transfer_waits, Vec::new());
 
         {
             let submission = hal::Submission::new()
                 .wait_on(&[(&bundle.wait_semaphore, hal::pso::PipelineStage::BOTTOM_OF_PIPE)])
+                .wait_on(bundle.transfer_waits.iter().map(|semaphore| (semaphore, TRANSFER_WAIT_STAGE)))
                 .signal(&[&bundle.signal_semaphore])
                 .promote::<C>()
                 .submit(inner_submits);
             let fence = Some(&bundle.signal_fence.inner);
-            self.queue.group.queues[0].submit::<C>(submission, fence);
+            let result = self.queue.group.queues[0].submit::<C>(submission, fence);
+            if let Err(err) = result {
+                // nothing got submitted, but the acquisition signals the wait
+                // semaphore and nothing will ever wait on it, so replace it
+                bundle.access_info.end_gpu_access();
+                self.transfer_waits.extend(bundle.transfer_waits.drain(..));
+                self.discard_wait_semaphore(bundle);
+                return Err(match err {
+                    hal::SubmissionError::OutOfHostMemory =>
+                        hal::PresentError::OutOfMemory(OutOfMemory::OutOfHostMemory),
+                    hal::SubmissionError::OutOfDeviceMemory =>
+                        hal::PresentError::OutOfMemory(OutOfMemory::OutOfDeviceMemory),
+                    hal::SubmissionError::DeviceLost =>
+                        hal::PresentError::DeviceLost,
+                });
+            }
         }
         bundle.signal_fence.signal = Pending;
+        bundle.submission = self.device.progress().submit();
 
-        self.swapchain.present(
+        let swapchain = self.swapchain.as_mut()
+            .expect("no swapchain in a headless context");
+        let status = swapchain.present(
             &mut self.queue.group.queues[0],
             Some(&bundle.signal_semaphore),
         );
 
         self.frame_bundles.push_back(bundle);
+        status
+    }
+}
+
+impl<B: Backend, C> Context<B, C> {
+    /// Replace the signalled wait semaphore of a bundle which wasn't submitted.
+    /// The bundle is destroyed if no new semaphore can be created,
+    /// the remaining bundles keep the frames going.
+    fn discard_wait_semaphore(&mut self, mut bundle: FrameBundle<B, C>) {
+        match self.device.raw.create_semaphore() {
+            Ok(semaphore) => {
+                let signalled = mem::replace(&mut bundle.wait_semaphore, semaphore);
+                self.device.raw.destroy_semaphore(signalled);
+                self.frame_bundles.push_back(bundle);
+            }
+            Err(err) => {
+                error!("Could not recreate the semaphore of a frame: {}", err);
+                self.device.raw.destroy_semaphore(bundle.wait_semaphore);
+                self.device.raw.destroy_semaphore(bundle.signal_semaphore);
+                self.device.raw.destroy_fence(bundle.signal_fence.inner);
+            }
+        }
+    }
+}
+
+impl<B: Backend, C> Context<B, C> {
+    /// Acquire a command pool of the dedicated transfer queue, if there is one.
+    pub(crate) fn acquire_transfer_pool(
+        &mut self
+    ) -> Option<Result<encoder::Pool<B, Transfer>, OutOfMemory>> {
+        let device = &self.device.raw;
+        self.transfer.as_mut().map(|queue| queue.acquire_encoder_pool(device))
+    }
+
+    /// Submit to the dedicated transfer queue.
+    /// The next submission to the main queue waits for the transfer to complete.
+    pub(crate) fn submit_transfer(
+        &mut self,
+        inner: hal::command::Submit<B, Transfer, hal::command::OneShot, hal::command::Primary>,
+        fence: &B::Fence,
+    ) -> Result<(), hal::SubmissionError> {
+        let semaphore = match self.semaphores.pop() {
+            Some(semaphore) => semaphore,
+            None => self.device.raw.create_semaphore()
+                .map_err(submission_error)?,
+        };
+        let result = {
+            let queue = &mut self.transfer.as_mut()
+                .expect("no dedicated transfer queue")
+                .group.queues[0];
+            let submission = hal::Submission::new()
+                .signal(Some(&semaphore))
+                .submit(Some(inner));
+            queue.submit::<Transfer>(submission, Some(fence))
+        };
+        match result {
+            Ok(_) => {
+                self.transfer_waits.push(semaphore);
+                Ok(())
+            }
+            Err(err) => {
+                self.semaphores.push(semaphore);
+                Err(err)
+            }
+        }
+    }
+}
+
+fn submission_error(err: OutOfMemory) -> hal::SubmissionError {
+    match err {
+        OutOfMemory::OutOfHostMemory => hal::SubmissionError::OutOfHostMemory,
+        OutOfMemory::OutOfDeviceMemory => hal::SubmissionError::OutOfDeviceMemory,
     }
 }
 
+/// Open a device with a queue of the first family supporting `C` accepted by `selector`
+/// and a queue of the first other family dedicated to transfers, if there is one.
+fn open_queues<B, C, F>(
+    adapter: hal::Adapter<B>, selector: F
+) -> Result<
+    (B::Device, hal::QueueGroup<B, C>, Option<hal::QueueGroup<B, Transfer>>),
+    hal::error::DeviceCreationError,
+>
+where
+    B: Backend,
+    C: Capability,
+    F: Fn(&B::QueueFamily) -> bool,
+{
+    let main = adapter.queue_families.iter()
+        .find(|family| {
+            C::supported_by(family.queue_type()) &&
+                selector(family) &&
+                family.max_queues() >= 1
+        })
+        .ok_or(hal::error::DeviceCreationError::InitializationFailed)?;
+    let transfer = adapter.queue_families.iter()
+        .find(|family| {
+            family.queue_type() == hal::QueueType::Transfer &&
+                family.id() != main.id() &&
+                family.max_queues() >= 1
+        });
+
+    let mut families = vec![(main, vec![1.0])];
+    families.extend(transfer.map(|family| (family, vec![1.0])));
+    let hal::Gpu { device, mut queues } = adapter.physical_device.open(families)?;
+
+    let group = queues.take::<C>(main.id()).unwrap();
+    let transfer = transfer.map(|family| queues.take::<Transfer>(family.id()).unwrap());
+    Ok((device, group, transfer))
+}
+
+fn query_format_properties<B: Backend>(
+    physical_device: &B::PhysicalDevice
+) -> Vec<format::Properties> {
+    (1 .. format::NUM_FORMATS)
+        .map(|format| {
+            let format = unsafe { mem::transmute::<u32, format::Format>(format as u32) };
+            physical_device.format_properties(Some(format))
+        })
+        .collect()
+}
+
 impl<B: Backend, C> Context<B, C> {
+    /// Check if the submission of `token` completed.
+    pub fn is_complete(&mut self, token: SubmitToken) -> Result<bool, hal::device::DeviceLost> {
+        self.poll()?;
+        Ok(self.submit_bundles.front().map_or(true, |bundle| token.0 < bundle.id))
+    }
+
+    /// Block until the submission of `token` completed.
+    pub fn wait(&mut self, token: SubmitToken) -> Result<(), hal::device::DeviceLost> {
+        while self.submit_bundles.front().map_or(false, |bundle| bundle.id <= token.0) {
+            let bundle = self.submit_bundles.pop_front().unwrap();
+            if let Err(err) = self.device.raw.wait_for_fence(&bundle.fence, !0) {
+                self.submit_bundles.push_front(bundle);
+                return Err(err);
+            }
+            self.complete(bundle);
+        }
+        self.garbage.collect();
+        Ok(())
+    }
+
+    /// Release the resources of completed submissions.
+    pub fn poll(&mut self) -> Result<(), hal::device::DeviceLost> {
+        while let Some(bundle) = self.submit_bundles.pop_front() {
+            match self.device.raw.get_fence_status(&bundle.fence) {
+                Ok(true) => self.complete(bundle),
+                Ok(false) => {
+                    self.submit_bundles.push_front(bundle);
+                    break;
+                }
+                Err(err) => {
+                    self.submit_bundles.push_front(bundle);
+                    return Err(err);
+                }
+            }
+        }
+        self.garbage.collect();
+        Ok(())
+    }
+
+    fn complete(&mut self, bundle: SubmitBundle<B, C>) {
+        // dropping the handles lets the garbage collector destroy them
+        let SubmitBundle { access_info, fence, transfer_waits, submission, .. } = bundle;
+        access_info.end_gpu_access();
+        self.device.progress().complete(submission);
+        self.semaphores.extend(transfer_waits);
+        self.device.raw.reset_fence(&fence);
+        self.fences.push(fence);
+    }
+
     fn wait_idle(&mut self) {
         assert!(self.frame_acquired.is_none());
 
         // TODO?: WaitIdle on queue instead
-        let fences = self.frame_bundles.iter_mut()
+        let frame_fences = self.frame_bundles.iter_mut()
             .filter_map(|bundle| {
                 // self can drop the handles before waiting because
                 // self will be the one receiving the garbage afterwards
@@ -371,8 +833,24 @@ impl<B: Backend, C> Context<B, C> {
                     None
                 }
             });
+        let submit_fences = self.submit_bundles.iter_mut()
+            .map(|bundle| {
+                bundle.handles.clear();
+                bundle.encoder_pools.clear();
+                &bundle.fence
+            });
+        let fences = frame_fences.chain(submit_fences);
 
-        self.device.raw.wait_for_fences(fences, hal::device::WaitFor::All, !0);
+        if let Err(err) = self.device.raw.wait_for_fences(fences, hal::device::WaitFor::All, !0) {
+            error!("Could not wait for pending frames: {}", err);
+        }
+        if !self.transfer_waits.is_empty() {
+            // nothing waits for these transfers yet
+            if let Err(err) = self.device.raw.wait_idle() {
+                error!("Could not wait for pending transfers: {}", err);
+            }
+        }
+        self.device.progress().complete_all();
     }
 
     pub fn ref_device(&self) -> &Device<B> {
@@ -396,9 +874,22 @@ impl<B: Backend, C> Drop for Context<B, C> {
         self.garbage.collect();
 
         for bundle in self.frame_bundles.drain(..) {
+            self.semaphores.extend(bundle.transfer_waits);
             self.device.raw.destroy_semaphore(bundle.wait_semaphore);
             self.device.raw.destroy_semaphore(bundle.signal_semaphore);
             self.device.raw.destroy_fence(bundle.signal_fence.inner);
         }
+        for bundle in self.submit_bundles.drain(..) {
+            bundle.access_info.end_gpu_access();
+            self.semaphores.extend(bundle.transfer_waits);
+            self.device.raw.destroy_fence(bundle.fence);
+        }
+        let semaphores = self.semaphores.drain(..).chain(self.transfer_waits.drain(..));
+        for semaphore in semaphores {
+            self.device.raw.destroy_semaphore(semaphore);
+        }
+        for fence in self.fences.drain(..) {
+            self.device.raw.destroy_fence(fence);
+        }
     }
 }
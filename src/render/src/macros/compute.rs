@@ -0,0 +1,87 @@
+#[macro_export]
+macro_rules! gfx_compute_pipeline {
+    ($name:ident {
+        $( $cmp_name:ident: $cmp:ty, )*
+    }) => {
+        #[allow(missing_docs)]
+        pub mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+            use $crate::{pso, handle};
+            use $crate::{Backend, Supports, Transfer, Compute, Encoder, Device};
+            use $crate::hal::pso as cpso;
+
+            pub struct Meta<B: Backend> {
+                layout: handle::raw::PipelineLayout<B>,
+                pipeline: handle::raw::ComputePipeline<B>,
+            }
+
+            pub struct Init<'a, B: Backend> {
+                $( pub $cmp_name: <$cmp as pso::Component<'a, B>>::Init, )*
+            }
+
+            pub struct Data<'a, B: Backend> {
+                $( pub $cmp_name: <$cmp as pso::Component<'a, B>>::Data, )*
+            }
+
+            impl<'a, B: Backend> pso::ComputePipelineInit<B> for Init<'a, B> {
+                type Pipeline = Meta<B>;
+
+                fn create<'b>(
+                    self,
+                    device: &mut Device<B>,
+                    shader: cpso::EntryPoint<'b, B>,
+                ) -> Result<Self::Pipeline, pso::CreationError> {
+                    let mut desc_layouts = Vec::new();
+                    $( desc_layouts.extend(<$cmp as pso::Component<'a, B>>::descriptor_layout(&self.$cmp_name)); )*
+                    let layout = device.create_pipeline_layout_raw(&desc_layouts[..], &[]);
+                    let pipeline = device.create_compute_pipeline_raw(
+                        cpso::ComputePipelineDesc::new(shader, layout.resource())
+                    )?;
+                    Ok(Meta { layout, pipeline })
+                }
+            }
+
+            impl<B: Backend> pso::ComputePipelineMeta<B> for Meta<B> {
+                fn layout(&self) -> &B::PipelineLayout { self.layout.resource() }
+            }
+
+            impl<'a, B: Backend> pso::ComputePipelineData<B> for Data<'a, B> {
+                type Pipeline = Meta<B>;
+
+                fn bind<'b, 'c, C>(
+                    self,
+                    encoder: &'b mut Encoder<'c, B, C>,
+                    meta: &'b Self::Pipeline
+                )
+                    where Self: 'a, 'c: 'b, C: Supports<Transfer> + Supports<Compute>
+                {
+                    let mut buffer_states = Vec::new();
+                    let mut image_states = Vec::new();
+                    $(
+                        <$cmp as pso::Component<'a, B>>::require(
+                            &self.$cmp_name,
+                            &mut buffer_states,
+                            &mut image_states,
+                            encoder.handles());
+                    )*
+                    encoder.require_state(
+                        cpso::PipelineStage::COMPUTE_SHADER,
+                        &buffer_states[..],
+                        &image_states[..]
+                    );
+                    encoder.handles().add(meta.layout.clone());
+                    encoder.handles().add(meta.pipeline.clone());
+
+                    let cmd_buffer = encoder.mut_buffer();
+                    cmd_buffer.bind_compute_pipeline(meta.pipeline.resource());
+                    let mut descs = Vec::new();
+                    $(
+                        descs.extend(<$cmp as pso::Component<'a, B>>::descriptor_set(&self.$cmp_name));
+                    )*
+                    cmd_buffer.bind_compute_descriptor_sets(meta.layout.resource(), 0, descs);
+                }
+            }
+        }
+    }
+}
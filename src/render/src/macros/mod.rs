@@ -1,5 +1,7 @@
 //! Various helper macros.
 
+#[cfg_attr(test, macro_use)]
+mod compute;
 #[cfg_attr(test, macro_use)]
 mod descriptors;
 #[cfg_attr(test, macro_use)]
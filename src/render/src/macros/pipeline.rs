@@ -10,8 +10,9 @@ macro_rules! gfx_graphics_pipeline {
             use $crate::{pso, handle};
             use $crate::{
                 Backend, Supports, Transfer, Graphics, Encoder,
-                Device, Primitive
+                Device, Primitive, InstanceCount
             };
+            use $crate::slice::IndexBuffer;
             use $crate::hal::{pass as cpass, pso as cpso};
             use $crate::hal::command::{RenderPassInlineEncoder, Rect, Viewport, Primary};
 
@@ -30,6 +31,10 @@ macro_rules! gfx_graphics_pipeline {
                 pub viewports: &'a [Viewport],
                 pub scissors: &'a [Rect],
                 pub framebuffer: &'a handle::raw::Framebuffer<B>,
+                // `IndexBuffer::Auto` for non-indexed draws
+                pub index_buffer: IndexBuffer<B>,
+                // `0 .. 1` when instancing is not used
+                pub instances: ::std::ops::Range<InstanceCount>,
                 $( pub $cmp_name: <$cmp as pso::Component<'a, B>>::Data, )*
             }
 
@@ -104,6 +109,14 @@ macro_rules! gfx_graphics_pipeline {
             impl<'a, B: Backend> pso::GraphicsPipelineData<B> for Data<'a, B> {
                 type Pipeline = Meta<B>;
 
+                fn index_buffer(&self) -> &IndexBuffer<B> {
+                    &self.index_buffer
+                }
+
+                fn instances(&self) -> ::std::ops::Range<InstanceCount> {
+                    self.instances.clone()
+                }
+
                 fn begin_renderpass<'b, 'c, C>(
                     self,
                     encoder: &'b mut Encoder<'c, B, C>,
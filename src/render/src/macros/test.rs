@@ -1,6 +1,11 @@
 #![allow(dead_code)]
 
-use format;
+use gfx_backend_empty as empty;
+use hal::buffer::Access as BufferAccess;
+use hal::image::{Access as ImageAccess, ImageLayout};
+use allocators::BoxedAllocator;
+use {buffer, format, hal, image, memory};
+use {Context, Device, General};
 
 gfx_buffer_struct! {
     Vertex {
@@ -23,5 +28,123 @@ gfx_graphics_pipeline! {
     }
 }
 
+gfx_descriptors! {
+    storage {
+        image: pso::StorageImage,
+        buffer: pso::StorageBuffer,
+    }
+}
+
+gfx_compute_pipeline! {
+    comp {
+        storage: storage::Component,
+    }
+}
+
 #[test]
 fn test_macros() {}
+
+fn headless_context() -> Context<empty::Backend, General> {
+    let adapter = hal::Adapter {
+        info: hal::AdapterInfo {
+            name: "empty".into(),
+            vendor: 0,
+            device: 0,
+            software_rendering: true,
+        },
+        physical_device: empty::PhysicalDevice,
+        queue_families: vec![empty::QueueFamily],
+    };
+    Context::init_headless(adapter).unwrap()
+}
+
+fn create_comp(
+    device: &mut Device<empty::Backend>,
+) -> (storage::Set<empty::Backend>, storage::Data<empty::Backend>, comp::Meta<empty::Backend>) {
+    let (set, data) = device.create_descriptors(1).unwrap().pop().unwrap();
+    let shader = hal::pso::EntryPoint { entry: "main", module: &(), specialization: &[] };
+    let pipeline = device.create_compute_pipeline(shader, comp::Init { storage: &set }).unwrap();
+    (set, data, pipeline)
+}
+
+#[test]
+fn create_compute_pipeline() {
+    let mut context = headless_context();
+    let device = context.mut_device();
+    let (set, _) = device.create_descriptors::<storage::Set<_>>(1).unwrap().pop().unwrap();
+    let shader = hal::pso::EntryPoint { entry: "main", module: &(), specialization: &[] };
+    assert!(device.create_compute_pipeline(shader, comp::Init { storage: &set }).is_ok());
+}
+
+#[test]
+fn dispatch_requires_view_subresources() {
+    let mut context = headless_context();
+    let (image, buffer, tokens, set, data, pipeline) = {
+        let device = context.mut_device();
+        let (set, mut data, pipeline) = create_comp(device);
+        let mut allocator = BoxedAllocator::new(memory::Usage::Data, device);
+        let kind = image::Kind::D2Array(8, 8, 2, image::AaMode::Single);
+        let (image, image_token) = device.create_image::<format::Rgba8Unorm, _>(
+            &mut allocator, image::Usage::STORAGE, kind, 3).unwrap();
+        let (buffer, buffer_token) = device.create_buffer::<u32, _>(
+            &mut allocator, buffer::Usage::STORAGE, 16).unwrap();
+        // second level of the first layer
+        let range = image::SubresourceRange {
+            aspects: format::Aspects::COLOR,
+            levels: 1 .. 2,
+            layers: 0 .. 1,
+        };
+        let view = device.create_image_view(&image, range).unwrap();
+        device.update_descriptor_sets()
+            .write(data.image(&set), 0, &[view.as_ref()])
+            .write(data.buffer(&set), 0, &[buffer.as_ref()])
+            .finish();
+        (image, buffer, vec![image_token, buffer_token], set, data, pipeline)
+    };
+
+    let mut pool = context.acquire_encoder_pool().unwrap();
+    let submit = {
+        let mut encoder = pool.acquire_encoder();
+        encoder.init_resources(tokens);
+        encoder.dispatch([1, 1, 1], &pipeline, comp::Data { storage: (&set, &data) });
+
+        let storage = (ImageAccess::SHADER_READ | ImageAccess::SHADER_WRITE, ImageLayout::General);
+        let stable = image.as_ref().info().stable_state;
+        assert_eq!(encoder.image_state(image.as_ref(), (1, 0)), Some(storage));
+        assert_eq!(encoder.image_state(image.as_ref(), (0, 0)), Some(stable));
+        assert_eq!(encoder.image_state(image.as_ref(), (2, 0)), Some(stable));
+        assert_eq!(encoder.image_state(image.as_ref(), (1, 1)), Some(stable));
+        assert_eq!(encoder.buffer_state(buffer.as_ref()),
+            Some(BufferAccess::SHADER_READ | BufferAccess::SHADER_WRITE));
+        encoder.finish()
+    };
+    context.submit_and_wait(vec![submit]).unwrap();
+}
+
+#[test]
+fn dispatch_indirect_reads_arguments() {
+    let mut context = headless_context();
+    let (arguments, tokens, set, data, pipeline) = {
+        let device = context.mut_device();
+        let (set, mut data, pipeline) = create_comp(device);
+        let mut allocator = BoxedAllocator::new(memory::Usage::Data, device);
+        let (arguments, arguments_token) = device.create_buffer::<u32, _>(
+            &mut allocator, buffer::Usage::INDIRECT, 3).unwrap();
+        let (buffer, buffer_token) = device.create_buffer::<u32, _>(
+            &mut allocator, buffer::Usage::STORAGE, 16).unwrap();
+        device.update_descriptor_sets()
+            .write(data.buffer(&set), 0, &[buffer.as_ref()])
+            .finish();
+        (arguments, vec![arguments_token, buffer_token], set, data, pipeline)
+    };
+
+    let mut pool = context.acquire_encoder_pool().unwrap();
+    let submit = {
+        let mut encoder = pool.acquire_encoder();
+        encoder.init_resources(tokens);
+        encoder.dispatch_indirect(arguments.as_ref(), 0, &pipeline, comp::Data { storage: (&set, &data) });
+        assert_eq!(encoder.buffer_state(arguments.as_ref()), Some(BufferAccess::INDIRECT_COMMAND_READ));
+        encoder.finish()
+    };
+    context.submit_and_wait(vec![submit]).unwrap();
+}
@@ -1,11 +1,16 @@
 pub use hal::memory::{Pod, cast_slice};
 
+use std::collections::HashMap;
+use std::error::Error;
 use std::marker::PhantomData;
 use std::{ops, cmp, fmt, hash};
 //use std::convert::AsRef;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::cell::UnsafeCell;
 
+use hal::{Device as Device_, MemoryType, MemoryTypeId};
+use hal::device::{BindError, OutOfMemory};
 use {buffer, image};
 use {Backend, Device};
 
@@ -23,7 +28,9 @@ pub enum Usage {
     /// GPU to CPU data flow with mapping.
     /// Used for staging for download from GPU.
     Download,
-    // TODO: Hybrid,
+    /// CPU to GPU data flow with mapping, rewritten every frame.
+    /// Prefers memory with full speed GPU access, falls back to upload memory.
+    Dynamic,
 }
 
 bitflags!(
@@ -76,19 +83,246 @@ impl fmt::Debug for Memory {
     }
 }
 
-// TODO: errors
+/// Error allocating and binding memory for a resource.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AllocationError {
+    /// No memory type supports both the resource and the allocator usage.
+    NoSuitableMemory,
+    /// The allocation would exceed the memory budget of the heap.
+    BudgetExceeded(usize),
+    /// The device or host ran out of memory.
+    OutOfMemory(OutOfMemory),
+    /// Binding the resource to the allocated memory failed.
+    Bind(BindError),
+}
+
+impl From<OutOfMemory> for AllocationError {
+    fn from(error: OutOfMemory) -> Self {
+        AllocationError::OutOfMemory(error)
+    }
+}
+
+impl From<BindError> for AllocationError {
+    fn from(error: BindError) -> Self {
+        AllocationError::Bind(error)
+    }
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AllocationError::BudgetExceeded(heap) => write!(f, "{}: heap {}", self.description(), heap),
+            AllocationError::OutOfMemory(ref err) => write!(f, "{}: {}", self.description(), err),
+            AllocationError::Bind(ref err) => write!(f, "{}: {}", self.description(), err),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for AllocationError {
+    fn description(&self) -> &str {
+        match *self {
+            AllocationError::NoSuitableMemory => "No suitable memory type",
+            AllocationError::BudgetExceeded(_) => "Memory budget exceeded",
+            AllocationError::OutOfMemory(_) => "Out of memory",
+            AllocationError::Bind(_) => "Failed to bind memory",
+        }
+    }
+}
+
+/// Memory statistics of a single memory type, in bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryTypeStats {
+    /// Memory allocated from the device.
+    pub allocated: u64,
+    /// Memory occupied by live resources.
+    pub used: u64,
+    /// Allocated memory which isn't occupied by live resources,
+    /// e.g. free space in chunks and alignment padding.
+    pub wasted: u64,
+}
+
+impl MemoryTypeStats {
+    pub(crate) fn new(allocated: u64, used: u64) -> Self {
+        MemoryTypeStats {
+            allocated,
+            used,
+            wasted: allocated - used,
+        }
+    }
+}
+
+/// Progress of the queue of a `Context`, shared with its allocators.
+///
+/// Submissions are numbered in submission order and complete in that order,
+/// so memory used by submission `n` can be reused once `completed() > n`.
+#[derive(Debug, Default)]
+pub(crate) struct QueueProgress {
+    submitted: AtomicUsize,
+    completed: AtomicUsize,
+}
+
+impl QueueProgress {
+    /// Number of the next submission.
+    pub(crate) fn next(&self) -> usize {
+        self.submitted.load(Ordering::Acquire)
+    }
+
+    /// Record a successful submission and return its number.
+    pub(crate) fn submit(&self) -> usize {
+        self.submitted.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// Record that submission `id` and all earlier ones completed.
+    pub(crate) fn complete(&self, id: usize) {
+        let mut current = self.completed.load(Ordering::Acquire);
+        while current <= id {
+            match self.completed.compare_exchange(current, id + 1, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(previous) => current = previous,
+            }
+        }
+    }
+
+    /// Record that all submissions completed.
+    pub(crate) fn complete_all(&self) {
+        let submitted = self.submitted.load(Ordering::Acquire);
+        if submitted > 0 {
+            self.complete(submitted - 1);
+        }
+    }
+
+    /// Number of completed submissions.
+    pub(crate) fn completed(&self) -> usize {
+        self.completed.load(Ordering::Acquire)
+    }
+}
+
+/// Memory budgets of the heaps of a device, shared by all its allocators.
+#[derive(Debug)]
+pub(crate) struct HeapBudgets {
+    // heap index by memory type
+    type_heaps: Vec<usize>,
+    heaps: Mutex<Vec<HeapBudget>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HeapBudget {
+    budget: u64,
+    used: u64,
+}
+
+impl HeapBudgets {
+    pub(crate) fn new(memory_types: &[MemoryType], memory_heaps: &[u64]) -> Self {
+        HeapBudgets {
+            type_heaps: memory_types.iter().map(|ty| ty.heap_index).collect(),
+            heaps: Mutex::new(memory_heaps
+                .iter()
+                .map(|&size| HeapBudget { budget: size, used: 0 })
+                .collect()),
+        }
+    }
+
+    pub(crate) fn heap_index(&self, memory_type: MemoryTypeId) -> usize {
+        self.type_heaps[memory_type.0]
+    }
+
+    pub(crate) fn budget(&self, heap_index: usize) -> u64 {
+        self.heaps.lock().unwrap()[heap_index].budget
+    }
+
+    pub(crate) fn set_budget(&self, heap_index: usize, budget: u64) {
+        self.heaps.lock().unwrap()[heap_index].budget = budget;
+    }
+
+    pub(crate) fn used(&self, heap_index: usize) -> u64 {
+        self.heaps.lock().unwrap()[heap_index].used
+    }
+
+    /// Check if `size` more bytes fit into the budget of the heap of `memory_type`.
+    pub(crate) fn fits(&self, memory_type: MemoryTypeId, size: u64) -> bool {
+        let heap = self.heaps.lock().unwrap()[self.heap_index(memory_type)];
+        heap.used + size <= heap.budget
+    }
+
+    fn reserve(&self, memory_type: MemoryTypeId, size: u64) -> Result<(), AllocationError> {
+        let heap_index = self.heap_index(memory_type);
+        let mut heaps = self.heaps.lock().unwrap();
+        let heap = &mut heaps[heap_index];
+        if heap.used + size > heap.budget {
+            return Err(AllocationError::BudgetExceeded(heap_index));
+        }
+        heap.used += size;
+        Ok(())
+    }
+
+    fn release(&self, memory_type: MemoryTypeId, size: u64) {
+        let heap_index = self.heap_index(memory_type);
+        self.heaps.lock().unwrap()[heap_index].used -= size;
+    }
+
+    /// Allocate memory within the budget of its heap.
+    pub(crate) fn allocate_memory<B: Backend>(
+        &self,
+        device: &B::Device,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, AllocationError> {
+        self.reserve(memory_type, size)?;
+        device.allocate_memory(memory_type, size).map_err(|err| {
+            self.release(memory_type, size);
+            err.into()
+        })
+    }
+
+    /// Free memory allocated by `allocate_memory`.
+    pub(crate) fn free_memory<B: Backend>(
+        &self,
+        device: &B::Device,
+        memory_type: MemoryTypeId,
+        memory: B::Memory,
+        size: u64,
+    ) {
+        device.free_memory(memory);
+        self.release(memory_type, size);
+    }
+}
+
+/// Memory statistics of an allocator.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// Statistics of each memory type the allocator allocated from.
+    pub memory_types: HashMap<MemoryTypeId, MemoryTypeStats>,
+}
+
+impl Stats {
+    /// Statistics summed over all memory types.
+    pub fn total(&self) -> MemoryTypeStats {
+        self.memory_types.values().fold(MemoryTypeStats::default(), |total, stats| {
+            MemoryTypeStats {
+                allocated: total.allocated + stats.allocated,
+                used: total.used + stats.used,
+                wasted: total.wasted + stats.wasted,
+            }
+        })
+    }
+}
+
 pub trait Allocator<B: Backend> {
     fn allocate_buffer(&mut self,
         device: &Device<B>,
         usage: buffer::Usage,
         buffer: B::UnboundBuffer
-    ) -> (B::Buffer, Memory);
+    ) -> Result<(B::Buffer, Memory), AllocationError>;
 
     fn allocate_image(&mut self,
         device: &mut Device<B>,
         usage: image::Usage,
         image: B::UnboundImage
-    ) -> (B::Image, Memory);
+    ) -> Result<(B::Image, Memory), AllocationError>;
+
+    /// Current memory statistics of the allocator.
+    fn stats(&mut self) -> Stats;
 }
 
 #[derive(Debug)]
@@ -0,0 +1,76 @@
+//! Compute pipeline generating mipmaps for formats without blit support.
+
+use hal::{self, Device as Device_};
+use hal::pso::{DescriptorSetLayoutBinding, DescriptorType, ShaderStageFlags};
+
+use encoder::MipmapError;
+use {handle, image};
+use {Backend, Device};
+
+/// Source: `shader/mipmap.comp`, compiled with `glslangValidator -V mipmap.comp -o mipmap.spv`.
+const SHADER: &'static [u8] = include_bytes!("../shader/mipmap.spv");
+
+/// Size of the work groups of the shader.
+pub(crate) const WORK_GROUP_SIZE: u32 = 8;
+
+/// Downsamples a level of a 2D image into the next level, reading the source
+/// level through a combined image sampler (binding 0) and writing the
+/// destination level as a storage image (binding 1).
+#[derive(Clone)]
+pub(crate) struct ComputeMipmap<B: Backend> {
+    pub set_layout: handle::raw::DescriptorSetLayout<B>,
+    pub layout: handle::raw::PipelineLayout<B>,
+    pub pipeline: handle::raw::ComputePipeline<B>,
+    pub sampler: handle::raw::Sampler<B>,
+}
+
+pub(crate) fn bindings() -> [DescriptorSetLayoutBinding; 2] {
+    [
+        DescriptorSetLayoutBinding {
+            binding: 0,
+            ty: DescriptorType::CombinedImageSampler,
+            count: 1,
+            stage_flags: ShaderStageFlags::COMPUTE,
+            immutable_samplers: false,
+        },
+        DescriptorSetLayoutBinding {
+            binding: 1,
+            ty: DescriptorType::StorageImage,
+            count: 1,
+            stage_flags: ShaderStageFlags::COMPUTE,
+            immutable_samplers: false,
+        },
+    ]
+}
+
+impl<B: Backend> ComputeMipmap<B> {
+    pub fn new(device: &mut Device<B>) -> Result<Self, MipmapError> {
+        let set_layout = device.create_descriptor_set_layout(&bindings());
+        let layout = device.create_pipeline_layout_raw(&[set_layout.resource()], &[]);
+        let sampler = device.create_sampler(image::SamplerInfo::new(
+            hal::image::FilterMethod::Scale,
+            hal::image::WrapMode::Clamp,
+        ))?;
+
+        let module = device.raw.create_shader_module(SHADER)
+            .map_err(hal::pso::CreationError::Shader)?;
+        let pipeline = device.create_compute_pipeline_raw(hal::pso::ComputePipelineDesc {
+            shader: hal::pso::EntryPoint {
+                entry: "main",
+                module: &module,
+                specialization: &[],
+            },
+            layout: layout.resource(),
+            flags: hal::pso::PipelineCreationFlags::empty(),
+            parent: hal::pso::BasePipeline::None,
+        });
+        device.raw.destroy_shader_module(module);
+
+        Ok(ComputeMipmap {
+            set_layout,
+            layout,
+            pipeline: pipeline?,
+            sampler,
+        })
+    }
+}
@@ -3,12 +3,15 @@
 use std::borrow::Borrow;
 use std::mem;
 use std::marker::PhantomData;
+use std::ops::Range;
 
 use {hal, format, handle};
 use hal::image::ImageLayout;
 use hal::pass::{AttachmentOps, AttachmentLoadOp, AttachmentStoreOp};
 
-use {Backend, Device, Primitive, Supports, Transfer, Graphics, Encoder};
+use {Backend, Device, Primitive, Supports, Transfer, Compute, Graphics, Encoder};
+use {InstanceCount};
+use slice::IndexBuffer;
 
 pub use hal::pso::{DescriptorBinding, DescriptorArrayIndex, Rasterizer, CreationError, InstanceRate};
 
@@ -99,6 +102,8 @@ define_descriptors! {
     [ 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12 ]
     SampledImage,
     Sampler,
+    StorageImage,
+    StorageBuffer,
 }
 
 impl<B: Backend> Bind<B> for SampledImage {
@@ -124,13 +129,11 @@ impl<B: Backend> Bind<B> for SampledImage {
         images: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
         _: &mut handle::Bag<B>,
     ) {
-        let img = view.info();
-        let levels = img.info().mip_levels;
-        let layers = img.info().kind.num_layers();
+        let info = view.info();
         let state = (hal::image::Access::SHADER_READ, ImageLayout::ShaderReadOnlyOptimal);
-        for level in 0..levels {
-            for layer in 0..layers {
-                images.push((img, (level, layer), state));
+        for level in info.range.levels.clone() {
+            for layer in info.range.layers.clone() {
+                images.push((&info.image, (level, layer), state));
             }
         }
     }
@@ -162,6 +165,68 @@ impl<B: Backend> Bind<B> for Sampler {
     }
 }
 
+impl<B: Backend> Bind<B> for StorageImage {
+    type Handle = handle::raw::ImageView<B>;
+
+    fn write<'a, I>(views: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        views
+            .into_iter()
+            .map(|view| {
+                let view: &'a Self::Handle = *view.borrow();
+                hal::pso::Descriptor::Image(view.resource(), ImageLayout::General)
+            }).collect()
+    }
+
+    fn require<'a>(
+        view: &'a Self::Handle,
+        _: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        images: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        let info = view.info();
+        // TODO: READ or WRITE not always necessary
+        let state = (hal::image::Access::SHADER_READ | hal::image::Access::SHADER_WRITE,
+            ImageLayout::General);
+        // only the subresources of the view are accessed
+        for level in info.range.levels.clone() {
+            for layer in info.range.layers.clone() {
+                images.push((&info.image, (level, layer), state));
+            }
+        }
+    }
+}
+
+impl<B: Backend> Bind<B> for StorageBuffer {
+    type Handle = handle::raw::Buffer<B>;
+
+    fn write<'a, I>(buffers: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        buffers
+            .into_iter()
+            .map(|buffer| {
+                let buffer: &'a Self::Handle = *buffer.borrow();
+                hal::pso::Descriptor::Buffer(buffer.resource(), None .. None)
+            }).collect()
+    }
+
+    fn require<'a>(
+        buffer: &'a Self::Handle,
+        buffers: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        _: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        // TODO: READ or WRITE not always necessary
+        buffers.push((buffer, hal::buffer::Access::SHADER_READ | hal::buffer::Access::SHADER_WRITE));
+    }
+}
+
 pub struct DescriptorSetBindRef<'a, 'b, B: Backend, T: Bind<B>> {
     pub set: &'a B::DescriptorSet,
     pub binding: DescriptorBinding,
@@ -228,6 +293,12 @@ pub trait GraphicsPipelineMeta<B: Backend> {
 pub trait GraphicsPipelineData<B: Backend> {
     type Pipeline;
 
+    /// Index buffer of indexed draws, `IndexBuffer::Auto` for non-indexed data.
+    fn index_buffer(&self) -> &IndexBuffer<B>;
+
+    /// Instances to draw, `0 .. 1` when instancing is not used.
+    fn instances(&self) -> Range<InstanceCount>;
+
     fn begin_renderpass<'a, 'b, C>(
         self,
         encoder: &'a mut Encoder<'b, B, C>,
@@ -236,6 +307,31 @@ pub trait GraphicsPipelineData<B: Backend> {
         where Self: 'a, 'b: 'a, C: Supports<Transfer> + Supports<Graphics>;
 }
 
+pub trait ComputePipelineInit<B: Backend> {
+    type Pipeline;
+
+    fn create<'a>(
+        self,
+        &mut Device<B>,
+        hal::pso::EntryPoint<'a, B>,
+    ) -> Result<Self::Pipeline, CreationError>;
+}
+
+pub trait ComputePipelineMeta<B: Backend> {
+    fn layout(&self) -> &B::PipelineLayout;
+}
+
+pub trait ComputePipelineData<B: Backend> {
+    type Pipeline;
+
+    fn bind<'a, 'b, C>(
+        self,
+        encoder: &'a mut Encoder<'b, B, C>,
+        pipeline: &'a Self::Pipeline
+    )
+        where Self: 'a, 'b: 'a, C: Supports<Transfer> + Supports<Compute>;
+}
+
 pub trait Component<'a, B: Backend> {
     type Init: 'a;
     type Data: 'a;
@@ -290,7 +386,8 @@ where
     F: 'a + format::AsFormat,
 {
     type Init = hal::pso::ColorBlendDesc;
-    type Data = &'a handle::ImageView<B, F>;
+    /// `None` when targeting a framebuffer provided by the window system.
+    type Data = Option<&'a handle::ImageView<B, F>>;
 
     fn attachment(_: &Self::Init) -> Option<Attachment> {
         Some(Attachment {
@@ -315,15 +412,16 @@ where
         images: &mut Vec<(&'b handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
         _: &mut handle::Bag<B>,
     ) where 'a: 'b {
-        let img = data.as_ref().info();
-        let levels = img.info().mip_levels;
-        let layers = img.info().kind.num_layers();
+        let info = match *data {
+            Some(view) => view.as_ref().info(),
+            None => return,
+        };
         // TODO: READ not always necessary
         let state = (hal::image::Access::COLOR_ATTACHMENT_READ | hal::image::Access::COLOR_ATTACHMENT_WRITE,
             ImageLayout::ColorAttachmentOptimal);
-        for level in 0..levels {
-            for layer in 0..layers {
-                images.push((img, (level, layer), state));
+        for level in info.range.levels.clone() {
+            for layer in info.range.layers.clone() {
+                images.push((&info.image, (level, layer), state));
             }
         }
     }
@@ -2,13 +2,12 @@
 //!
 //! See `Slice`-structure documentation for more information on this module.
 
-use hal::{handle, buffer};
-use hal::{Primitive, Backend, VertexCount};
-use hal::command::InstanceParams;
-use hal::device::Device;
-use hal::memory::Bind;
-use format::Format;
-use pso;
+use std::ops::Range;
+
+use hal::{IndexType, Primitive, IndexCount, InstanceCount, VertexOffset};
+use hal::buffer::IndexBufferView;
+use {handle, pso};
+use Backend;
 
 /// A `Slice` dictates in which and in what order vertices get processed. It is required for
 /// processing a PSO.
@@ -38,25 +37,26 @@ use pso;
 /// vertex with this index.
 ///
 /// # Construction & Handling
-/// The `Slice` structure can be constructed automatically when using a `Device` to create a
-/// vertex buffer. If needed, it can also be created manually.
+/// A `Slice` matching a whole vertex buffer can be created with `Slice::new_match_vertex_buffer`.
+/// If needed, it can also be created manually.
 ///
 /// A `Slice` is required to process a PSO, as it contains the needed information on in what order
-/// to draw which vertices. As such, every `draw` call on an `Encoder` requires a `Slice`.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// to draw which vertices. Its `buffer` and `instances` go into the data of the pipeline, while
+/// `start .. end` and `base_vertex` are passed to `Encoder::draw_indexed`.
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub struct Slice<B: Backend> {
     /// The start index of the index-buffer. Processing will start at this location in the
     /// index-buffer.
-    pub start: VertexCount,
+    pub start: IndexCount,
     /// The end index in the index-buffer. Processing will stop at this location (exclusive) in
     /// the index buffer.
-    pub end: VertexCount,
+    pub end: IndexCount,
     /// This is the index of the first vertex in the `VertexBuffer`. This value will be added to
     /// every index in the index-buffer, effectively moving the start of the `VertexBuffer` to this
     /// base-vertex.
-    pub base_vertex: VertexCount,
-    /// Instancing configuration.
-    pub instances: Option<InstanceParams>,
+    pub base_vertex: VertexOffset,
+    /// The range of instances to draw, `0 .. 1` when instancing is not used.
+    pub instances: Range<InstanceCount>,
     /// Represents the type of index-buffer used.
     pub buffer: IndexBuffer<B>,
 }
@@ -64,16 +64,31 @@ pub struct Slice<B: Backend> {
 impl<B: Backend> Slice<B> {
     /// Creates a new `Slice` to match the supplied vertex buffer, from start to end, in order.
     pub fn new_match_vertex_buffer<V>(vbuf: &handle::Buffer<B, V>) -> Self
-                                      where V: pso::buffer::Structure<Format> {
+        where V: pso::Structure
+    {
         Slice {
             start: 0,
-            end: vbuf.len() as u32,
+            end: vbuf.len() as IndexCount,
             base_vertex: 0,
-            instances: None,
+            instances: 0 .. 1,
             buffer: IndexBuffer::Auto,
         }
     }
 
+    /// Creates a new `Slice` drawing all the indices of `ibuf`.
+    pub fn from_index_buffer<I>(ibuf: I) -> Self
+        where I: IntoIndexBuffer<B>
+    {
+        let buffer = ibuf.into_index_buffer();
+        Slice {
+            start: 0,
+            end: buffer.len(),
+            base_vertex: 0,
+            instances: 0 .. 1,
+            buffer,
+        }
+    }
+
     /// Calculates the number of primitives of the specified type in this `Slice`.
     pub fn get_prim_count(&self, prim: Primitive) -> u32 {
         use hal::Primitive as p;
@@ -81,11 +96,11 @@ impl<B: Backend> Slice<B> {
         match prim {
             p::PointList => nv,
             p::LineList => nv / 2,
-            p::LineStrip => (nv-1),
+            p::LineStrip => nv - 1,
             p::TriangleList => nv / 3,
             p::TriangleStrip => (nv-2) / 3,
             p::LineListAdjacency => nv / 4,
-            p::LineStripAdjacency => (nv-3),
+            p::LineStripAdjacency => nv - 3,
             p::TriangleListAdjacency => nv / 6,
             p::TriangleStripAdjacency => (nv-4) / 2,
             p::PatchList(num) => nv / (num as u32),
@@ -96,7 +111,7 @@ impl<B: Backend> Slice<B> {
     ///
     /// The first will contain the range in the index-buffer [self.start, mid) (excluding the index mid itself) and the
     /// second will contain the range [mid, self.end).
-    pub fn split_at(&self, mid: VertexCount) -> (Self, Self) {
+    pub fn split_at(&self, mid: IndexCount) -> (Self, Self) {
         let mut first = self.clone();
         let mut second = self.clone();
         first.end = mid;
@@ -114,17 +129,15 @@ impl<B: Backend> Slice<B> {
 /// `start` to 0, and `end` to the `VertexBuffer`'s length.
 ///
 /// The `Index*` variants represent an actual `Buffer` with a list of vertex-indices. The numeric
-/// suffix specifies the amount of bits to use per index. Each of these also contains a
-/// base-vertex. This is the index of the first vertex in the `VertexBuffer`. This value will be
-/// added to every index in the index-buffer, effectively moving the start of the `VertexBuffer` to
-/// this base-vertex.
+/// suffix specifies the amount of bits to use per index.
 ///
 /// # Construction & Handling
-/// A `IndexBuffer` can be constructed using the `IntoIndexBuffer` trait, from either a slice or a
-/// `Buffer` of integers, using a device.
+/// A `IndexBuffer` can be constructed using the `IntoIndexBuffer` trait, from a typed `Buffer`
+/// of `u16` or `u32` indices.
 ///
-/// An `IndexBuffer` is exclusively used to create `Slice`s.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// An `IndexBuffer` is used to create `Slice`s and is passed to draws as part of the
+/// pipeline data.
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub enum IndexBuffer<B: Backend> {
     /// Represents a hypothetical index-buffer from 0 to infinity. In other words, all vertices
     /// get processed in order.
@@ -135,25 +148,59 @@ pub enum IndexBuffer<B: Backend> {
     Index32(handle::Buffer<B, u32>),
 }
 
+impl<B: Backend> IndexBuffer<B> {
+    /// The underlying buffer, `None` for `Auto`.
+    pub fn raw(&self) -> Option<&handle::raw::Buffer<B>> {
+        match *self {
+            IndexBuffer::Auto => None,
+            IndexBuffer::Index16(ref buffer) => Some(buffer.as_ref()),
+            IndexBuffer::Index32(ref buffer) => Some(buffer.as_ref()),
+        }
+    }
+
+    /// The number of indices in the buffer, `0` for `Auto`.
+    pub fn len(&self) -> IndexCount {
+        match *self {
+            IndexBuffer::Auto => 0,
+            IndexBuffer::Index16(ref buffer) => buffer.len() as IndexCount,
+            IndexBuffer::Index32(ref buffer) => buffer.len() as IndexCount,
+        }
+    }
+
+    pub(crate) fn view(&self) -> Option<IndexBufferView<B>> {
+        let (buffer, index_type) = match *self {
+            IndexBuffer::Auto => return None,
+            IndexBuffer::Index16(ref buffer) => (buffer.as_ref(), IndexType::U16),
+            IndexBuffer::Index32(ref buffer) => (buffer.as_ref(), IndexType::U32),
+        };
+        Some(IndexBufferView {
+            buffer: buffer.resource(),
+            offset: 0,
+            index_type,
+        })
+    }
+}
+
 impl<B: Backend> Default for IndexBuffer<B> {
     fn default() -> Self {
         IndexBuffer::Auto
     }
 }
+
 /// A helper trait to create `IndexBuffers` from different kinds of data.
 pub trait IntoIndexBuffer<B: Backend> {
     /// Turns self into an `IndexBuffer`.
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, device: &mut F) -> IndexBuffer<B>;
+    fn into_index_buffer(self) -> IndexBuffer<B>;
 }
 
 impl<B: Backend> IntoIndexBuffer<B> for IndexBuffer<B> {
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+    fn into_index_buffer(self) -> IndexBuffer<B> {
         self
     }
 }
 
 impl<B: Backend> IntoIndexBuffer<B> for () {
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+    fn into_index_buffer(self) -> IndexBuffer<B> {
         IndexBuffer::Auto
     }
 }
@@ -161,16 +208,14 @@ impl<B: Backend> IntoIndexBuffer<B> for () {
 macro_rules! impl_index_buffer {
     ($prim_ty:ty, $buf_ty:ident) => (
         impl<B: Backend> IntoIndexBuffer<B> for handle::Buffer<B, $prim_ty> {
-            fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+            fn into_index_buffer(self) -> IndexBuffer<B> {
                 IndexBuffer::$buf_ty(self)
             }
         }
 
-        impl<'s, B: Backend> IntoIndexBuffer<B> for &'s [$prim_ty] {
-            fn into_index_buffer<F: Device<B> + ?Sized>(self, device: &mut F) -> IndexBuffer<B> {
-                device.create_buffer_immutable(self, buffer::Role::Index, Bind::empty())
-                      .unwrap()
-                      .into_index_buffer(device)
+        impl<'a, B: Backend> IntoIndexBuffer<B> for &'a handle::Buffer<B, $prim_ty> {
+            fn into_index_buffer(self) -> IndexBuffer<B> {
+                IndexBuffer::$buf_ty(self.clone())
             }
         }
     )
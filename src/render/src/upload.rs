@@ -0,0 +1,590 @@
+//! Staging uploads of buffer and image data.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::{fmt, mem, slice};
+
+use hal::{self, mapping, Capability, Device as Device_, Limits};
+use hal::device::OutOfMemory;
+
+use device::InitToken;
+use encoder::{self, AccessInfo, BufferCopy, BufferImageCopy, PoolDependency};
+use memory::{self, cast_slice, Pod};
+use {buffer, handle, image};
+use {Backend, Context, Device, Extent, Supports, Transfer};
+
+/// Error uploading data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UploadError {
+    /// Creating a staging buffer failed.
+    Staging(buffer::CreationError),
+    /// Mapping staging memory failed.
+    Mapping(mapping::Error),
+    /// The device or host ran out of memory.
+    OutOfMemory(OutOfMemory),
+    /// The device has been lost.
+    DeviceLost,
+    /// The uploaded region exceeds the bounds of the destination resource.
+    OutOfBounds,
+}
+
+impl From<buffer::CreationError> for UploadError {
+    fn from(error: buffer::CreationError) -> Self {
+        UploadError::Staging(error)
+    }
+}
+
+impl From<mapping::Error> for UploadError {
+    fn from(error: mapping::Error) -> Self {
+        UploadError::Mapping(error)
+    }
+}
+
+impl From<OutOfMemory> for UploadError {
+    fn from(error: OutOfMemory) -> Self {
+        UploadError::OutOfMemory(error)
+    }
+}
+
+impl From<hal::device::DeviceLost> for UploadError {
+    fn from(_: hal::device::DeviceLost) -> Self {
+        UploadError::DeviceLost
+    }
+}
+
+impl From<hal::SubmissionError> for UploadError {
+    fn from(error: hal::SubmissionError) -> Self {
+        match error {
+            hal::SubmissionError::OutOfHostMemory =>
+                UploadError::OutOfMemory(OutOfMemory::OutOfHostMemory),
+            hal::SubmissionError::OutOfDeviceMemory =>
+                UploadError::OutOfMemory(OutOfMemory::OutOfDeviceMemory),
+            hal::SubmissionError::DeviceLost =>
+                UploadError::DeviceLost,
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UploadError::Staging(ref err) => write!(f, "{}: {}", self.description(), err),
+            UploadError::Mapping(ref err) => write!(f, "{}: {}", self.description(), err),
+            UploadError::OutOfMemory(ref err) => write!(f, "{}: {}", self.description(), err),
+            UploadError::DeviceLost => write!(f, "{}", self.description()),
+            UploadError::OutOfBounds => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for UploadError {
+    fn description(&self) -> &str {
+        match *self {
+            UploadError::Staging(_) => "Failed to create a staging buffer",
+            UploadError::Mapping(_) => "Failed to map staging memory",
+            UploadError::OutOfMemory(_) => "Out of memory",
+            UploadError::DeviceLost => "Device lost",
+            UploadError::OutOfBounds => "Upload out of the bounds of the resource",
+        }
+    }
+}
+
+/// Identifies an upload, see `Uploader::is_resident` and `Uploader::wait`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UploadToken(u64);
+
+/// Uploads data into buffers and images through staging memory.
+///
+/// Uploads are batched until `flush`, which records the copies and layout
+/// transitions and submits them to the queue of a `Context`. Each upload
+/// returns an `UploadToken` which can be used to check whether the data is
+/// resident on the device. Staging memory is recycled once the submission
+/// of its batch completed.
+///
+/// ```no_run
+/// # extern crate gfx_render as gfx;
+/// # use gfx::{Backend, Context, Graphics};
+/// # use gfx::upload::Uploader;
+/// # fn sample<B: Backend>(
+/// #     context: &mut Context<B, Graphics>,
+/// #     uploader: &mut Uploader<B, Graphics>,
+/// #     buffer: &gfx::handle::Buffer<B, [f32; 4]>,
+/// # ) {
+/// let token = uploader.upload_buffer(buffer, 0, &[[0.0, 0.5, 1.0, 1.0]]).unwrap();
+/// uploader.flush(context).unwrap();
+/// uploader.wait(token).unwrap();
+/// # }
+/// # fn main() {}
+/// ```
+pub struct Uploader<B: Backend, C> {
+    device: Device<B>,
+    limits: Limits, // TODO: only store relevant data
+    chunk_size: u64,
+    // chunk currently being filled
+    current: Option<Staging<B>>,
+    // filled chunks of the current batch
+    filled: Vec<Staging<B>>,
+    free: Vec<Staging<B>>,
+    init_tokens: Vec<InitToken<B>>,
+    copies: Vec<Copy<B>>,
+    pending: VecDeque<Batch<B, C>>,
+    fences: Vec<B::Fence>,
+    // id of the batch being recorded
+    next_batch: u64,
+}
+
+struct Staging<B: Backend> {
+    // `memory` outlives `buffer`, which is destroyed by the garbage collector
+    buffer: handle::raw::Buffer<B>,
+    memory: B::Memory,
+    size: u64,
+    offset: u64,
+}
+
+enum Copy<B: Backend> {
+    Buffer {
+        staging: handle::raw::Buffer<B>,
+        dst: handle::raw::Buffer<B>,
+        region: BufferCopy,
+    },
+    Image {
+        staging: handle::raw::Buffer<B>,
+        dst: handle::raw::Image<B>,
+        region: BufferImageCopy,
+    },
+}
+
+struct Batch<B: Backend, C> {
+    id: u64,
+    fence: B::Fence,
+    staging: Vec<Staging<B>>,
+    access_info: AccessInfo<B>,
+    #[allow(dead_code)]
+    handles: handle::Bag<B>,
+    #[allow(dead_code)]
+    pool: BatchPool<B, C>,
+}
+
+// command pool of the queue a batch was submitted to
+#[allow(dead_code)]
+enum BatchPool<B: Backend, C> {
+    Main(PoolDependency<B, C>),
+    Transfer(PoolDependency<B, Transfer>),
+}
+
+// recorded command buffer of a batch and the queue it is submitted to
+enum BatchCommands<B: Backend, C> {
+    Main(hal::command::Submit<B, C, hal::command::OneShot, hal::command::Primary>),
+    Transfer(hal::command::Submit<B, Transfer, hal::command::OneShot, hal::command::Primary>),
+}
+
+fn record<B: Backend, C: Supports<Transfer>>(
+    pool: &mut encoder::Pool<B, C>,
+    init_tokens: &[InitToken<B>],
+    copies: &[Copy<B>],
+) -> encoder::Submit<B, C> {
+    let mut encoder = pool.acquire_encoder();
+    encoder.init_resources_ref(init_tokens);
+    for copy in copies {
+        match *copy {
+            Copy::Buffer { ref staging, ref dst, region } =>
+                encoder.copy_buffer(staging, dst, &[region]),
+            Copy::Image { ref staging, ref dst, ref region } =>
+                encoder.copy_buffer_to_image(staging, dst, &[region.clone()]),
+        }
+    }
+    encoder.finish()
+}
+
+pub(crate) fn align(offset: u64, alignment: u64) -> u64 {
+    let rem = offset % alignment;
+    if rem == 0 {
+        offset
+    } else {
+        offset - rem + alignment
+    }
+}
+
+/// Check that the region lies within the level and layers of the image.
+fn region_in_bounds(
+    info: &image::Info,
+    layers: &image::SubresourceLayers,
+    offset: image::Offset,
+    extent: Extent,
+) -> bool {
+    if layers.level >= info.mip_levels || layers.layers.end > info.kind.num_layers() {
+        return false;
+    }
+    let (width, height, depth, _) = info.kind.level_dimensions(layers.level);
+    let depth = match info.kind {
+        image::Kind::D3(..) => depth,
+        _ => 1,
+    };
+    let fits = |offset: i32, extent: u32, size: u16| {
+        offset >= 0 && offset as u64 + extent as u64 <= size as u64
+    };
+    fits(offset.x, extent.width, width) &&
+        fits(offset.y, extent.height, height) &&
+        fits(offset.z, extent.depth, depth)
+}
+
+pub(crate) fn lcm(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let t = x % y;
+        x = y;
+        y = t;
+    }
+    a / x * b
+}
+
+impl<B: Backend, C> Uploader<B, C> {
+    pub fn new(device: &Device<B>, limits: Limits) -> Self {
+        let mega = 1 << 20;
+        Self::with_chunk_size(device, limits, 4 * mega)
+    }
+
+    pub fn with_chunk_size(device: &Device<B>, limits: Limits, chunk_size: u64) -> Self {
+        Uploader {
+            device: device.clone(),
+            limits,
+            // whole chunks can be flushed
+            chunk_size: align(chunk_size, limits.non_coherent_atom_size.max(1)),
+            current: None,
+            filled: Vec::new(),
+            free: Vec::new(),
+            init_tokens: Vec::new(),
+            copies: Vec::new(),
+            pending: VecDeque::new(),
+            fences: Vec::new(),
+            next_batch: 0,
+        }
+    }
+
+    /// Transition newly created resources out of their undefined initial
+    /// state before any upload of the next batch.
+    pub fn init_resources(&mut self, tokens: Vec<InitToken<B>>) {
+        self.init_tokens.extend(tokens);
+    }
+
+    /// Upload `data` into `buffer`, starting at the element `offset`.
+    pub fn upload_buffer<MTB>(
+        &mut self,
+        buffer: &MTB,
+        offset: u64,
+        data: &[MTB::Data],
+    ) -> Result<UploadToken, UploadError>
+        where MTB: buffer::MaybeTyped<B>
+    {
+        let dst = buffer.as_ref();
+        let bytes: &[u8] = cast_slice(data);
+        let stride = mem::size_of::<MTB::Data>() as u64;
+        let size = bytes.len() as u64;
+
+        debug_assert!(dst.info().usage.contains(buffer::Usage::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+        if offset * stride + size > dst.info().size {
+            return Err(UploadError::OutOfBounds);
+        }
+        if bytes.is_empty() {
+            return Ok(UploadToken(self.next_batch));
+        }
+
+        let (staging, staging_offset) = self.stage(size, 4, |mapped| {
+            mapped.copy_from_slice(bytes);
+        })?;
+        self.copies.push(Copy::Buffer {
+            staging,
+            dst: dst.clone(),
+            region: BufferCopy {
+                src: staging_offset,
+                dst: offset * stride,
+                size,
+            },
+        });
+        Ok(UploadToken(self.next_batch))
+    }
+
+    /// Upload `data` into a region of the subresources `layers` of `image`.
+    ///
+    /// Like in `BufferImageCopy`, `data_width` and `data_height` describe the
+    /// layout of `data` in texels: rows are `data_width` texels apart and
+    /// image slices `data_height` rows. Zero means tightly packed according
+    /// to `extent`. Array layers follow each other like depth slices.
+    pub fn upload_image_raw<T: Pod>(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        layers: image::SubresourceLayers,
+        offset: image::Offset,
+        extent: Extent,
+        data: &[T],
+        data_width: u32,
+        data_height: u32,
+    ) -> Result<UploadToken, UploadError> {
+        let data: &[u8] = cast_slice(data);
+        let info = image.info();
+        debug_assert!(info.usage.contains(image::Usage::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+        if !region_in_bounds(info, &layers, offset, extent) {
+            return Err(UploadError::OutOfBounds);
+        }
+
+        let desc = info.format.base_format().0.desc();
+        let block_bytes = desc.bits as u64 / 8;
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let blocks = |texels: u32, block: u32| ((texels + block - 1) / block) as u64;
+
+        let data_width = if data_width == 0 { extent.width } else { data_width };
+        let data_height = if data_height == 0 { extent.height } else { data_height };
+        let row_bytes = blocks(extent.width, block_width) * block_bytes;
+        let rows = blocks(extent.height, block_height);
+        let slices = extent.depth as u64 * (layers.layers.end - layers.layers.start) as u64;
+        let data_row_pitch = blocks(data_width, block_width) * block_bytes;
+        let data_slice_pitch = blocks(data_height, block_height) * data_row_pitch;
+        if row_bytes == 0 || rows == 0 || slices == 0 {
+            return Ok(UploadToken(self.next_batch));
+        }
+        assert!((slices - 1) * data_slice_pitch + (rows - 1) * data_row_pitch + row_bytes
+            <= data.len() as u64, "not enough image data");
+
+        // staging rows have to be a whole number of texel blocks
+        let pitch_alignment = lcm(self.limits.min_buffer_copy_pitch_alignment.max(1), block_bytes);
+        let offset_alignment = lcm(self.limits.min_buffer_copy_offset_alignment.max(1), lcm(block_bytes, 4));
+        let row_pitch = align(row_bytes, pitch_alignment);
+        let size = slices * rows * row_pitch;
+
+        let (staging, staging_offset) = self.stage(size, offset_alignment, |mapped| {
+            for slice in 0 .. slices {
+                for row in 0 .. rows {
+                    let src = (slice * data_slice_pitch + row * data_row_pitch) as usize;
+                    let dst = ((slice * rows + row) * row_pitch) as usize;
+                    mapped[dst .. dst + row_bytes as usize]
+                        .copy_from_slice(&data[src .. src + row_bytes as usize]);
+                }
+            }
+        })?;
+        self.copies.push(Copy::Image {
+            staging,
+            dst: image.clone(),
+            region: BufferImageCopy {
+                buffer_offset: staging_offset,
+                buffer_width: (row_pitch / block_bytes) as u32 * block_width,
+                buffer_height: rows as u32 * block_height,
+                image_layers: layers,
+                image_offset: offset,
+                image_extent: extent,
+            },
+        });
+        Ok(UploadToken(self.next_batch))
+    }
+
+    pub fn upload_image<F, T>(
+        &mut self,
+        image: &handle::Image<B, F>,
+        layers: image::SubresourceLayers,
+        offset: image::Offset,
+        extent: Extent,
+        data: &[T],
+        data_width: u32,
+        data_height: u32,
+    ) -> Result<UploadToken, UploadError>
+    where
+        T: Pod,
+    {
+        self.upload_image_raw(image.as_ref(), layers, offset, extent, data, data_width, data_height)
+    }
+
+    /// Write `size` bytes into staging memory.
+    fn stage<W>(
+        &mut self,
+        size: u64,
+        alignment: u64,
+        write: W,
+    ) -> Result<(handle::raw::Buffer<B>, u64), UploadError>
+        where W: FnOnce(&mut [u8])
+    {
+        let fits = |staging: &Staging<B>| align(staging.offset, alignment) + size <= staging.size;
+        if !self.current.as_ref().map_or(false, &fits) {
+            if let Some(full) = self.current.take() {
+                self.filled.push(full);
+            }
+            let reused = self.free.iter().position(|staging| fits(staging));
+            self.current = Some(match reused {
+                Some(index) => self.free.swap_remove(index),
+                None => {
+                    let atom_size = self.limits.non_coherent_atom_size.max(1);
+                    let size = align(size, atom_size).max(self.chunk_size);
+                    let (buffer, memory) = self.device.create_staging_buffer(size, memory::Usage::Upload)?;
+                    Staging { buffer, memory, size, offset: 0 }
+                }
+            });
+        }
+
+        let staging = self.current.as_mut().unwrap();
+        let offset = align(staging.offset, alignment);
+        // non-coherent memory is flushed in whole atoms, staging sizes are multiples of atoms
+        let atom_size = self.limits.non_coherent_atom_size.max(1);
+        let range = offset / atom_size * atom_size .. align(offset + size, atom_size);
+        let raw = &self.device.raw;
+        let mapped = raw.map_memory(&staging.memory, range.clone())?;
+        write(unsafe {
+            let start = (offset - range.start) as isize;
+            slice::from_raw_parts_mut(mapped.offset(start), size as usize)
+        });
+        raw.flush_mapped_memory_ranges(Some((&staging.memory, range)));
+        raw.unmap_memory(&staging.memory);
+        staging.offset = offset + size;
+
+        Ok((staging.buffer.clone(), offset))
+    }
+
+    /// Check if the uploads of the batch of `token` completed.
+    /// The upload has to be flushed to ever complete.
+    pub fn is_resident(&mut self, token: UploadToken) -> Result<bool, UploadError> {
+        self.poll()?;
+        Ok(token.0 < self.next_batch && self.pending.front().map_or(true, |b| token.0 < b.id))
+    }
+
+    /// Block until the uploads of the batch of `token` completed.
+    pub fn wait(&mut self, token: UploadToken) -> Result<(), UploadError> {
+        assert!(token.0 < self.next_batch, "waiting for an upload which was not flushed");
+        while self.pending.front().map_or(false, |b| b.id <= token.0) {
+            let batch = self.pending.pop_front().unwrap();
+            if let Err(err) = self.device.raw.wait_for_fence(&batch.fence, !0) {
+                self.pending.push_front(batch);
+                return Err(err.into());
+            }
+            self.complete(batch);
+        }
+        Ok(())
+    }
+
+    /// Recycle the staging memory of completed batches.
+    pub fn poll(&mut self) -> Result<(), UploadError> {
+        while let Some(batch) = self.pending.pop_front() {
+            match self.device.raw.get_fence_status(&batch.fence) {
+                Ok(true) => self.complete(batch),
+                Ok(false) => {
+                    self.pending.push_front(batch);
+                    break;
+                }
+                Err(err) => {
+                    self.pending.push_front(batch);
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self, batch: Batch<B, C>) {
+        batch.access_info.end_gpu_access();
+        self.device.raw.reset_fence(&batch.fence);
+        self.fences.push(batch.fence);
+        for mut staging in batch.staging {
+            if staging.size == self.chunk_size {
+                staging.offset = 0;
+                self.free.push(staging);
+            } else {
+                self.destroy_staging(staging);
+            }
+        }
+    }
+
+    fn destroy_staging(&mut self, staging: Staging<B>) {
+        let Staging { buffer, memory, .. } = staging;
+        // no use of the buffer is pending anymore
+        drop(buffer);
+        self.device.raw.free_memory(memory);
+    }
+}
+
+impl<B: Backend, C> Uploader<B, C>
+    where C: Capability + Supports<Transfer>
+{
+    /// Submit all batched uploads to the dedicated transfer queue of `context`
+    /// if it has one, to its main queue otherwise.
+    /// The next submission to the main queue waits for the uploads.
+    pub fn flush(&mut self, context: &mut Context<B, C>) -> Result<(), UploadError> {
+        self.poll()?;
+        if self.copies.is_empty() && self.init_tokens.is_empty() {
+            return Ok(());
+        }
+
+        // the batch stays recorded in `self` until it is submitted,
+        // a failed flush can be retried without losing uploads
+        let (commands, access_info, handles, pool) = match context.acquire_transfer_pool() {
+            Some(pool) => {
+                let submit = record(&mut pool?, &self.init_tokens, &self.copies);
+                let commands = BatchCommands::Transfer(submit.inner);
+                (commands, submit.access_info, submit.handles, BatchPool::Transfer(submit.pool))
+            }
+            None => {
+                let submit = record(&mut context.acquire_encoder_pool()?, &self.init_tokens, &self.copies);
+                let commands = BatchCommands::Main(submit.inner);
+                (commands, submit.access_info, submit.handles, BatchPool::Main(submit.pool))
+            }
+        };
+
+        let fence = match self.fences.pop() {
+            Some(fence) => fence,
+            None => self.device.raw.create_fence(false)?,
+        };
+        access_info.start_gpu_access();
+        let result = match commands {
+            BatchCommands::Transfer(inner) => context.submit_transfer(inner, &fence),
+            BatchCommands::Main(inner) => {
+                let submission = hal::Submission::new()
+                    .promote::<C>()
+                    .submit(Some(inner));
+                context.queue.group.queues[0].submit::<C>(submission, Some(&fence))
+                    .map(|_| ())
+            }
+        };
+        if let Err(err) = result {
+            access_info.end_gpu_access();
+            self.fences.push(fence);
+            return Err(err.into());
+        }
+        self.init_tokens.clear();
+        self.copies.clear();
+
+        let mut staging = mem::replace(&mut self.filled, Vec::new());
+        staging.extend(self.current.take());
+        self.pending.push_back(Batch {
+            id: self.next_batch,
+            fence,
+            staging,
+            access_info,
+            handles,
+            pool,
+        });
+        self.next_batch += 1;
+        Ok(())
+    }
+}
+
+impl<B: Backend, C> Drop for Uploader<B, C> {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            let fences = self.pending.iter().map(|batch| &batch.fence);
+            if let Err(err) = self.device.raw.wait_for_fences(fences, hal::device::WaitFor::All, !0) {
+                error!("Could not wait for pending uploads: {}", err);
+            }
+        }
+        while let Some(batch) = self.pending.pop_front() {
+            self.complete(batch);
+        }
+        self.copies.clear();
+        let staging: Vec<_> = self.current.take().into_iter()
+            .chain(self.filled.drain(..))
+            .chain(self.free.drain(..))
+            .collect();
+        for staging in staging {
+            self.destroy_staging(staging);
+        }
+        for fence in self.fences.drain(..) {
+            self.device.raw.destroy_fence(fence);
+        }
+    }
+}
@@ -161,7 +161,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             &queue_group,
             hal::pool::CommandPoolCreateFlags::empty(),
             1 + raw.jobs.len(),
-        );
+        ).expect("Failed to create command pool");
 
         // create resources
         let mut resources = Resources::<B> {
@@ -285,7 +285,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                     }
                     raw::Resource::Image { kind, num_levels, format, usage, ref data } => {
                         // allocate memory
-                        let unbound = device.create_image(kind, num_levels, format, usage)
+                        let unbound = device.create_image(kind, num_levels, format, i::Tiling::Optimal, usage, i::StorageFlags::empty())
                             .unwrap();
                         let requirements = device.get_image_requirements(&unbound);
                         let memory_type = memory_types
@@ -516,12 +516,16 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                         resources.shaders.insert(name.clone(), module);
                     }
                     raw::Resource::DescriptorSetLayout { ref bindings } => {
-                        let layout = device.create_descriptor_set_layout(bindings);
+                        let layout = device.create_descriptor_set_layout(bindings, &[]);
                         let binding_indices = bindings.iter().map(|dsb| dsb.binding).collect();
                         resources.desc_set_layouts.insert(name.clone(), (binding_indices, layout));
                     }
                     raw::Resource::DescriptorPool { capacity, ref ranges } => {
-                        let pool = device.create_descriptor_pool(capacity, ranges);
+                        let pool = device.create_descriptor_pool(
+                            capacity,
+                            ranges,
+                            hal::pso::DescriptorPoolCreateFlags::empty(),
+                        ).expect("Failed to create descriptor pool");
                         resources.desc_pools.insert(name.clone(), pool);
                     }
                     _ => {}
@@ -543,7 +547,8 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                         let desc_set = resources.desc_pools
                             .get_mut(pool)
                             .expect(&format!("Missing descriptor pool: {}", pool))
-                            .allocate_set(set_layout);
+                            .allocate_set(set_layout)
+                            .expect("Failed to allocate descriptor set");
                         resources.desc_sets.insert(name.clone(), desc_set);
                         // fill it up
                         let set = &resources.desc_sets[name];
@@ -651,7 +656,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             flags: pso::PipelineCreationFlags::empty(),
                             parent: pso::BasePipeline::None,
                         };
-                        let pso = device.create_graphics_pipelines(&[desc])
+                        let pso = device.create_graphics_pipelines(&[desc], None)
                             .swap_remove(0)
                             .unwrap();
                         resources.graphics_pipelines.insert(name.clone(), pso);
@@ -671,7 +676,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             flags: pso::PipelineCreationFlags::empty(),
                             parent: pso::BasePipeline::None,
                         };
-                        let pso = device.create_compute_pipelines(&[desc])
+                        let pso = device.create_compute_pipelines(&[desc], None)
                             .swap_remove(0)
                             .unwrap();
                         resources.compute_pipelines.insert(name.clone(), (layout.clone(), pso));
@@ -861,7 +866,8 @@ impl<B: hal::Backend> Scene<B, hal::General> {
         let submission = hal::queue::Submission::new()
             .submit(Some(&self.init_submit))
             .submit(submits);
-        self.queue_group.queues[0].submit(submission, None);
+        self.queue_group.queues[0].submit(submission, None)
+            .expect("Failed to submit jobs");
     }
 
     pub fn fetch_buffer(&mut self, name: &str) -> FetchGuard<B> {
@@ -885,7 +891,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             &self.queue_group,
             hal::pool::CommandPoolCreateFlags::empty(),
             1,
-        );
+        ).expect("Failed to create command pool");
         let copy_submit = {
             let mut cmd_buffer = command_pool.acquire_command_buffer(false);
             let pre_barrier = memory::Barrier::Buffer {
@@ -921,12 +927,15 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             cmd_buffer.finish()
         };
 
-        let copy_fence = self.device.create_fence(false);
+        let copy_fence = self.device.create_fence(false)
+            .expect("Failed to create fence");
         let submission = hal::queue::Submission::new()
             .submit(Some(copy_submit));
-        self.queue_group.queues[0].submit(submission, Some(&copy_fence));
+        self.queue_group.queues[0].submit(submission, Some(&copy_fence))
+            .expect("Failed to submit copy");
         //queue.destroy_command_pool(command_pool);
-        self.device.wait_for_fence(&copy_fence, !0);
+        self.device.wait_for_fence(&copy_fence, !0)
+            .expect("Device lost while waiting for copy");
         self.device.destroy_fence(copy_fence);
 
         let mapping = self
@@ -979,7 +988,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             &self.queue_group,
             hal::pool::CommandPoolCreateFlags::empty(),
             1,
-        );
+        ).expect("Failed to create command pool");
         let copy_submit = {
             let mut cmd_buffer = command_pool.acquire_command_buffer(false);
             let pre_barrier = memory::Barrier::Image {
@@ -1029,12 +1038,15 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             cmd_buffer.finish()
         };
 
-        let copy_fence = self.device.create_fence(false);
+        let copy_fence = self.device.create_fence(false)
+            .expect("Failed to create fence");
         let submission = hal::queue::Submission::new()
             .submit(Some(copy_submit));
-        self.queue_group.queues[0].submit(submission, Some(&copy_fence));
+        self.queue_group.queues[0].submit(submission, Some(&copy_fence))
+            .expect("Failed to submit copy");
         //queue.destroy_command_pool(command_pool);
-        self.device.wait_for_fence(&copy_fence, !0);
+        self.device.wait_for_fence(&copy_fence, !0)
+            .expect("Device lost while waiting for copy");
         self.device.destroy_fence(copy_fence);
 
         let mapping = self